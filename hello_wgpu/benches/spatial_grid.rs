@@ -0,0 +1,49 @@
+//! `cargo bench --bench spatial_grid`: radius queries on one dense chunk,
+//! linear scan vs `ChunkGrid`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use hello_wgpu::spatial::ChunkGrid;
+
+const SIZE: f32 = 220.0;    // chunk side (m)
+const PITCH: f32 = 3.4;     // lot pitch (m) = grid cell
+const QUERIES: u32 = 2_000;
+const RADIUS: f32 = 12.0;
+
+fn main() {
+    // one building per lot over the whole chunk: ~4k placements
+    let n = (SIZE / PITCH) as u32;
+    let pts: Vec<(f32, f32)> = (0..n * n)
+        .map(|i| (((i % n) as f32 + 0.5) * PITCH, ((i / n) as f32 + 0.5) * PITCH))
+        .collect();
+    let centers: Vec<(f32, f32)> = (0..QUERIES)
+        .map(|q| { let a = q as f32 * 0.618_034; ((a.fract()) * SIZE, ((a * 7.0).fract()) * SIZE) })
+        .collect();
+    let r2 = RADIUS * RADIUS;
+    let inside = |&(x, z): &(f32, f32), (cx, cz): (f32, f32)| (x - cx) * (x - cx) + (z - cz) * (z - cz) <= r2;
+
+    let t = Instant::now();
+    let grid = ChunkGrid::build((0.0, 0.0), (SIZE, SIZE), PITCH, pts.iter().copied());
+    let build = t.elapsed();
+
+    let t = Instant::now();
+    let mut linear = 0usize;
+    for &c in &centers { linear += pts.iter().filter(|p| inside(p, c)).count(); }
+    let t_linear = t.elapsed();
+
+    let t = Instant::now();
+    let mut gridded = 0usize;
+    for &(cx, cz) in &centers {
+        gridded += grid.query_rect((cx - RADIUS, cz - RADIUS), (cx + RADIUS, cz + RADIUS))
+            .filter(|&i| inside(&pts[i], (cx, cz))).count();
+    }
+    let t_grid = t.elapsed();
+
+    assert_eq!(black_box(linear), black_box(gridded));
+    let per = |d: std::time::Duration| d.as_secs_f64() * 1e6 / QUERIES as f64;
+    println!("{} placements, {QUERIES} queries of r = {RADIUS} m ({} hits)", pts.len(), linear);
+    println!("grid build: {:.1} µs", build.as_secs_f64() * 1e6);
+    println!("linear:     {:.2} µs/query", per(t_linear));
+    println!("grid:       {:.2} µs/query ({:.1}x)", per(t_grid), t_linear.as_secs_f64() / t_grid.as_secs_f64().max(1e-12));
+}
@@ -4,6 +4,7 @@
 
 use cgmath::Vector3;
 
+use crate::facade::{LAYER_CHECKER, LAYER_LATTICE, LAYER_PLAIN, LAYER_WINDOWS};
 use crate::mesh;
 
 // ───────────────────────── Categories & lookup ──────────────────────────
@@ -26,10 +27,20 @@ pub struct Archetype {
     pub name: &'static str,
     pub category: BuildingCategory,
     pub base_half: Vector3<f32>,          // for culling / billboard footprint
-    pub mesh: Option<mesh::Mesh>,         // None ⇒ use category rep mesh
+    pub lod_meshes: [Option<mesh::Mesh>; 3], // LOD0, LOD1, billboard; None ⇒ the category's (`mesh_for_lod`)
+    pub mesh_data: mesh::MeshData,        // CPU copy of the drawn geometry (baking)
     pub rep_category_mesh: CategoryMesh,  // which shared VA to draw
+    pub texture: u32,                     // facade layer (facade::LAYER_*)
+    pub weight: f32,                      // relative spawn odds within the category (≤ 0 ⇒ never)
+    pub tint: [f32; 4],                   // multiplies the category colour (rgb; a unused)
 }
 
+/// LOD levels `lod_meshes` / `mesh_for_lod` cover: LOD0, LOD1, billboard.
+pub const LOD_LEVELS: usize = 3;
+
+/// Edge length (m) of the shared ground mesh; instances scale it.
+pub const GROUND_PLANE_SIZE: f32 = 512.0;
+
 // ───────────────────────── AssetLibrary struct ─────────────────────────
 pub struct AssetLibrary {
     pub archetypes: Vec<Archetype>,
@@ -37,25 +48,52 @@ pub struct AssetLibrary {
     idx_highrise: Vec<usize>,
     idx_landmark: Vec<usize>,
 
-    // shared meshes (one VA per category + billboard + ground)
-    pub mesh_lowrise:   mesh::Mesh,
-    pub mesh_highrise:  mesh::Mesh,
-    pub mesh_landmark:  mesh::Mesh,
-    pub mesh_billboard: mesh::Mesh,
-    pub mesh_ground:    mesh::Mesh,
+    // None for `data_only` libraries (no device)
+    meshes: Option<SharedMeshes>,
+
+    tint_rev: u32, // bumped by `set_tint` so the renderer re-uploads
+}
+
+/// Shared meshes: one VA per category and LOD + billboard + ground; LOD1
+/// is the simplified model drawn in the second ring.
+pub struct SharedMeshes {
+    pub lowrise:   mesh::Mesh,
+    pub highrise:  mesh::Mesh,
+    pub landmark:  mesh::Mesh,
+    pub lowrise_lod1:  mesh::Mesh,
+    pub highrise_lod1: mesh::Mesh,
+    pub landmark_lod1: mesh::Mesh,
+    pub billboard: mesh::Mesh,
+    pub ground:    mesh::Mesh,
 }
 
 impl AssetLibrary {
     pub fn new(device: &wgpu::Device) -> Self {
-        // ---------- shared representative meshes ----------
-        let mesh_lowrise   = mesh::make_timber_gable(device);
-        let mesh_highrise  = mesh::make_block_tower(device);
-        let mesh_landmark  = mesh::make_pyramid(device);
-        let mesh_billboard = mesh::make_billboard(device);
-        let mesh_ground    = mesh::make_ground_plane(device, 512.0);
+        let mut lib = Self::data_only();
+        lib.meshes = Some(SharedMeshes {
+            lowrise:   mesh::make_timber_gable(device),
+            highrise:  mesh::make_block_tower(device),
+            landmark:  mesh::make_pyramid(device),
+            lowrise_lod1:  mesh::create_block_lowrise_lod1(device),
+            highrise_lod1: mesh::create_tower_highrise_lod1(device),
+            landmark_lod1: mesh::create_pyramid_tower_lod1(device),
+            billboard: mesh::make_billboard(device),
+            ground:    mesh::make_ground_plane(device, GROUND_PLANE_SIZE),
+        });
+        // ---------- optional per-archetype meshes (timber_house_b) ----------
+        lib.archetypes[1].lod_meshes = [Some(mesh::make_timber_gable_alt(device)), Some(mesh::make_timber_gable_alt_lod1(device)), None];
+        lib
+    }
 
-        // ---------- optional per-archetype mesh ----------
-        let timber_alt_mesh = mesh::make_timber_gable_alt(device);
+    /// The archetype table (categories, footprints, CPU geometry) without
+    /// any GPU meshes: enough to generate, cull and bucket a city with no
+    /// adapter.  Drawing from it (`mesh_of`, `mesh_for`, …) panics.
+    pub fn data_only() -> Self {
+        // ---------- CPU copies (for chunk baking) ----------
+        let data_lowrise  = mesh::timber_gable_data();
+        let data_alt      = mesh::timber_gable_alt_data();
+        let data_highrise = mesh::tower_highrise_data();
+        let data_landmark = mesh::pyramid_tower_data();
 
         // ---------- build archetype table ----------
         let mut archetypes = Vec::<Archetype>::new();
@@ -67,59 +105,89 @@ impl AssetLibrary {
         let mut push = |name:&'static str,
                         category:BuildingCategory,
                         half:Vector3<f32>,
-                        mesh_opt:Option<mesh::Mesh>,
+                        lod_meshes:[Option<mesh::Mesh>; LOD_LEVELS],
+                        mesh_data:&mesh::MeshData,
                         rep:CategoryMesh,
+                        texture:u32,
                         catlist:&mut Vec<usize>| {
             archetypes.push(Archetype{ name, category, base_half:half,
-                                       mesh:mesh_opt, rep_category_mesh:rep});
+                                       lod_meshes, mesh_data:mesh_data.clone(),
+                                       rep_category_mesh:rep, texture, weight:1.0, tint:[1.0;4]});
             catlist.push(archetypes.len()-1);
         };
 
         // ---- Low-rise variants ----
         let h_low = Vector3::new(0.9,0.9,0.9);
-        push("timber_house_a", BuildingCategory::Lowrise, h_low, None,
-             CategoryMesh::Lowrise, &mut idx_low);
+        push("timber_house_a", BuildingCategory::Lowrise, h_low, Default::default(),
+             &data_lowrise, CategoryMesh::Lowrise, LAYER_WINDOWS, &mut idx_low);
         push("timber_house_b", BuildingCategory::Lowrise, h_low,
-             Some(timber_alt_mesh), CategoryMesh::Lowrise, &mut idx_low);
-        push("workshop_neon" , BuildingCategory::Lowrise, h_low, None,
-             CategoryMesh::Lowrise, &mut idx_low);
+             Default::default(), &data_alt, CategoryMesh::Lowrise, LAYER_WINDOWS, &mut idx_low);
+        push("workshop_neon" , BuildingCategory::Lowrise, h_low, Default::default(),
+             &data_lowrise, CategoryMesh::Lowrise, LAYER_CHECKER, &mut idx_low);
 
         // ---- High-rise variants ----
         let h_high = Vector3::new(0.7,1.6,0.7);
-        push("block_tower_a", BuildingCategory::Highrise, h_high, None,
-             CategoryMesh::Highrise, &mut idx_high);
-        push("block_tower_b", BuildingCategory::Highrise, h_high, None,
-             CategoryMesh::Highrise, &mut idx_high);
+        push("block_tower_a", BuildingCategory::Highrise, h_high, Default::default(),
+             &data_highrise, CategoryMesh::Highrise, LAYER_WINDOWS, &mut idx_high);
+        push("block_tower_b", BuildingCategory::Highrise, h_high, Default::default(),
+             &data_highrise, CategoryMesh::Highrise, LAYER_WINDOWS, &mut idx_high);
         let h_cyl = Vector3::new(0.55,1.5,0.55);
-        push("cyl_tower_12", BuildingCategory::Highrise, h_cyl, None,
-             CategoryMesh::Highrise, &mut idx_high);
+        push("cyl_tower_12", BuildingCategory::Highrise, h_cyl, Default::default(),
+             &data_highrise, CategoryMesh::Highrise, LAYER_PLAIN, &mut idx_high);
 
         // ---- Landmarks ----
         let h_pyr = Vector3::new(1.2,1.2,1.2);
-        push("pyramid_citadel", BuildingCategory::Landmark, h_pyr, None,
-             CategoryMesh::Landmark, &mut idx_land);
+        push("pyramid_citadel", BuildingCategory::Landmark, h_pyr, Default::default(),
+             &data_landmark, CategoryMesh::Landmark, LAYER_PLAIN, &mut idx_land);
         let h_gate = Vector3::new(1.1,1.1,0.8);
-        push("gate_arch", BuildingCategory::Landmark, h_gate, None,
-             CategoryMesh::Landmark, &mut idx_land);
+        push("gate_arch", BuildingCategory::Landmark, h_gate, Default::default(),
+             &data_landmark, CategoryMesh::Landmark, LAYER_LATTICE, &mut idx_land);
 
         Self {
             archetypes,
             idx_lowrise:  idx_low,
             idx_highrise: idx_high,
             idx_landmark: idx_land,
-            mesh_lowrise, mesh_highrise, mesh_landmark, mesh_billboard, mesh_ground,
+            meshes: None,
+            tint_rev: 0,
         }
     }
 
+    // ---------- per-archetype colour ----------
+    /// Colour archetype `id` on top of its category's palette colour, so
+    /// variants sharing a mesh can still look different.  White = unchanged.
+    pub fn set_tint(&mut self, id: usize, color: [f32; 4]) {
+        self.archetypes[id].tint = color;
+        self.tint_rev = self.tint_rev.wrapping_add(1);
+    }
+    /// Changes on every `set_tint`.
+    #[inline] pub fn tint_revision(&self) -> u32 { self.tint_rev }
+
     // ---------- quick lookups ----------
     #[inline] pub fn base_half(&self, id: usize) -> Vector3<f32> {
         self.archetypes[id].base_half
     }
+    /// Design-space centre height that stands archetype `id` at `scale` on
+    /// the ground.  Every path that sets `center.y` (designer, mutations,
+    /// edits, network) goes through this.
+    #[inline] pub fn ground_y_for(&self, id: usize, scale: Vector3<f32>) -> f32 {
+        self.base_half(id).y * scale.y
+    }
     #[inline] pub fn category_of(&self, id: usize) -> BuildingCategory {
         self.archetypes[id].category
     }
-    #[inline] pub fn mesh_of(&self, id: usize) -> Option<&mesh::Mesh> {
-        self.archetypes[id].mesh.as_ref()
+    /// What archetype `id` draws at `lod` (0, 1, 2+ = billboard): its own
+    /// model if it has one, else its category's.
+    #[inline] pub fn mesh_of(&self, id: usize, lod: usize) -> &mesh::Mesh {
+        let a = &self.archetypes[id];
+        a.lod_meshes.get(lod.min(LOD_LEVELS - 1)).and_then(Option::as_ref)
+            .unwrap_or_else(|| self.mesh_for_lod(a.rep_category_mesh, lod))
+    }
+    #[inline] pub fn data_of(&self, id: usize) -> &mesh::MeshData {
+        &self.archetypes[id].mesh_data
+    }
+    #[inline] pub fn texture_of(&self, id: usize) -> u32 {
+        self.archetypes[id].texture
     }
     #[inline] pub fn indices_by_category(&self, cat: BuildingCategory) -> &[usize] {
         match cat {
@@ -128,13 +196,32 @@ impl AssetLibrary {
             BuildingCategory::Landmark => &self.idx_landmark,
         }
     }
+    /// The shared GPU meshes; panics on a `data_only` library.
+    #[inline] pub fn meshes(&self) -> &SharedMeshes {
+        self.meshes.as_ref().expect("data-only AssetLibrary has no meshes")
+    }
+    #[inline] pub fn has_meshes(&self) -> bool { self.meshes.is_some() }
     #[inline] pub fn mesh_for(&self, cm: CategoryMesh) -> &mesh::Mesh {
+        let m = self.meshes();
         match cm {
-            CategoryMesh::Lowrise   => &self.mesh_lowrise,
-            CategoryMesh::Highrise  => &self.mesh_highrise,
-            CategoryMesh::Landmark  => &self.mesh_landmark,
-            CategoryMesh::Billboard => &self.mesh_billboard,
-            CategoryMesh::Ground    => &self.mesh_ground,
+            CategoryMesh::Lowrise   => &m.lowrise,
+            CategoryMesh::Highrise  => &m.highrise,
+            CategoryMesh::Landmark  => &m.landmark,
+            CategoryMesh::Billboard => &m.billboard,
+            CategoryMesh::Ground    => &m.ground,
+        }
+    }
+    /// The shared mesh of `cm` at `lod`; building categories turn into the
+    /// billboard from LOD2 on, which (like the ground) has a single level.
+    pub fn mesh_for_lod(&self, cm: CategoryMesh, lod: usize) -> &mesh::Mesh {
+        let m = self.meshes();
+        match (cm, lod) {
+            (CategoryMesh::Ground, _) => &m.ground,
+            (_, 0) => self.mesh_for(cm),
+            (CategoryMesh::Lowrise, 1)  => &m.lowrise_lod1,
+            (CategoryMesh::Highrise, 1) => &m.highrise_lod1,
+            (CategoryMesh::Landmark, 1) => &m.landmark_lod1,
+            _ => &m.billboard,
         }
     }
 }
@@ -0,0 +1,32 @@
+// Axis gizmo: three coloured unit lines, rotated by the camera only.
+struct Gizmo {
+    rot_proj : mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> GIZMO : Gizmo;
+
+struct VSIn {
+    @location(0) position : vec3<f32>,
+    @location(1) color    : vec3<f32>,
+};
+
+struct VSOut {
+    @builtin(position) pos : vec4<f32>,
+    @location(0) color : vec3<f32>,
+};
+
+fn srgb_to_linear(c : vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+@vertex
+fn vs_gizmo(v : VSIn) -> VSOut {
+    var out : VSOut;
+    out.pos = GIZMO.rot_proj * vec4<f32>(v.position, 1.0);
+    out.color = srgb_to_linear(v.color);   // authored sRGB, the frame re-encodes
+    return out;
+}
+
+@fragment
+fn fs_gizmo(in : VSOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,32 @@
+// Impostor atlas bake (impostor.rs): a category mesh seen orthographically
+// from one side into one tile.  The colour is a fixed per-face shade in
+// linear light, so faces stay apart once tinted; alpha marks coverage.
+struct Bake {
+    view_proj : mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> BAKE : Bake;
+
+struct VSIn {
+    @location(0) position : vec3<f32>,
+    @location(5) normal   : vec3<f32>,
+};
+
+struct VSOut {
+    @builtin(position) pos : vec4<f32>,
+    @location(0) normal : vec3<f32>,
+};
+
+@vertex
+fn vs_bake(v : VSIn) -> VSOut {
+    var out : VSOut;
+    out.pos = BAKE.view_proj * vec4<f32>(v.position, 1.0);
+    out.normal = v.normal;
+    return out;
+}
+
+@fragment
+fn fs_bake(in : VSOut) -> @location(0) vec4<f32> {
+    let n = normalize(in.normal);
+    let shade = 0.75 + 0.25 * max(dot(n, normalize(vec3<f32>(0.3, 1.0, 0.5))), 0.0);
+    return vec4<f32>(vec3<f32>(shade), 1.0);
+}
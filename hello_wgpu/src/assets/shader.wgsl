@@ -1,51 +1,349 @@
+// ---------- colour ----------
+// Colours are authored in sRGB: palette, tints, vertex colours, the clear
+// colour (Engine converts that one).  Light quantities (key light, ambient,
+// exposure) are linear.  Shading happens in linear light; the frame is an
+// sRGB view (or the tone-map pass encodes), so fs_* output linear colour.
+fn srgb_to_linear(c : vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
 // ---------- shared structs ----------
 struct Camera {
     view_proj : mat4x4<f32>,
+    eye       : vec4<f32>,   // .xyz = camera position  .w = impostors on (Engine::set_impostors)
 };
 @group(0) @binding(0) var<uniform> CAMERA : Camera;
 
 struct Palette {
-    col_low   : vec3<f32>,
-    col_high  : vec3<f32>,
-    col_land  : vec3<f32>,
+    col_low    : vec3<f32>,
+    col_high   : vec3<f32>,
+    col_land   : vec3<f32>,
+    col_ground : vec3<f32>,
+    col_ground_alt : vec3<f32>,
+    ground_grid    : vec4<f32>,   // .xy = checker tile (m, 0 = off)  .zw = phase added to local xz
+    ground_span    : vec4<f32>,   // .xy = chunk span the squares restart at (m, 0 = never)
 };
 @group(1) @binding(0) var<uniform> PAL : Palette;
 
+// directional key light + hemispheric ambient (sky above, ground below)
+struct Light {
+    dir    : vec4<f32>,   // .xyz towards the light
+    sky    : vec4<f32>,
+    ground : vec4<f32>,
+    windows : vec4<f32>,  // .x = windows per metre (0 = off)  .y = lit probability
+};
+@group(1) @binding(1) var<uniform> LIGHT : Light;
+
+// per-archetype colour (Archetype::tint), multiplied into the palette colour
+const MAX_TINTS : u32 = 64u;
+@group(1) @binding(2) var<uniform> TINTS : array<vec4<f32>, MAX_TINTS>;
+
+// per-chunk density heatmap over the ground (Engine::set_heatmap)
+const HEAT_SIDE : i32 = 16;
+struct Heat {
+    ramp  : array<vec4<f32>, 4>,    // colour stops at 0, 1/3, 2/3, 1
+    grid  : vec4<f32>,              // .xy = local xz of the grid corner  .zw = cell size (m, 0 = off)
+    cells : array<vec4<f32>, 64>,   // HEAT_SIDE² values, four per vec4; < 0 = no data
+};
+@group(1) @binding(3) var<uniform> HEAT : Heat;
+
+// impostor atlas (see impostor.rs): a column per view angle, a row per
+// category; billboard quads (vertex color.w < 0) sample it when enabled
+const IMPOSTOR_ANGLES : f32 = 8.0;
+const IMPOSTOR_ROWS   : f32 = 3.0;
+const IMPOSTOR_SPAN_W : f32 = 1.4849242;   // √2 · IMPOSTOR_PAD
+const IMPOSTOR_SPAN_H : f32 = 1.05;        // IMPOSTOR_PAD
+@group(1) @binding(4) var ATLAS      : texture_2d<f32>;
+@group(1) @binding(5) var ATLAS_SAMP : sampler;
+// per row, the mesh bounds its tiles frame: [2r] = half extents, [2r+1] =
+// centre (ImpostorFrame)
+@group(1) @binding(6) var<uniform> IMPOSTOR_FRAMES : array<vec4<f32>, 6>;
+
+// shadow map from the key light (see shadow.rs)
+struct Shadow {
+    light_vp : mat4x4<f32>,
+    params   : vec4<f32>,   // .x = texel (uv)  .y = bias  .z = enabled
+};
+@group(2) @binding(0) var<uniform> SHADOW : Shadow;
+@group(2) @binding(1) var SHADOW_MAP  : texture_depth_2d;
+@group(2) @binding(2) var SHADOW_SAMP : sampler_comparison;
+
+// facade texture array (see facade.rs); layer 0 = plain white
+@group(3) @binding(0) var FACADE      : texture_2d_array<f32>;
+@group(3) @binding(1) var FACADE_SAMP : sampler;
+
 struct VSIn {
     @location(0) position : vec3<f32>,
-    @location(1) normal   : vec3<f32>,
+    @location(1) color    : vec4<f32>,
+    @location(5) normal   : vec3<f32>,
+    @location(6) uv       : vec2<f32>,
     // instance
     @location(2) i_pos   : vec3<f32>,
     @location(3) i_scale : vec3<f32>,
-    @location(4) i_misc  : vec3<f32>,   // .x = category (0,1,2)   .y = archetypeId (0..65535 in uint bits)
+    @location(4) i_misc  : vec4<f32>,   // .x = tint (0 low, 1 high, 2 landmark, 3 ground)   .y = archetypeId   .z = facade layer   .w = window seed
+    @location(7) i_pos_w   : f32,       // LOD2: building scale.x
+    @location(8) i_scale_w : f32,       // LOD2: building scale.y
 };
 
 struct VSOut {
-    @builtin(position) pos : vec4<f32>,
+    @builtin(position) @invariant pos : vec4<f32>,   // identical in prepass + main
     @location(0) worldN    : vec3<f32>,
     @location(1) tint_idx  : f32,
     @location(2) arche_id  : f32,
+    @location(3) world_pos : vec3<f32>,
+    @location(4) uv        : vec2<f32>,
+    @location(5) tex_layer : f32,
+    @location(6) face_pos  : vec3<f32>,  // metres from the building centre
+    @location(7) win_seed  : f32,
 };
 
+const IMPOSTOR_LAYER : f32 = -1.0;   // VSOut.tex_layer of impostor fragments
+
+// horizontal unit direction from `p` to the eye (+Z when right above it);
+// a quad turned to face it has right = (d.y, 0, -d.x)
+fn toward_eye(p : vec3<f32>) -> vec2<f32> {
+    let to_eye = CAMERA.eye.xz - p.xz;
+    return select(vec2<f32>(0.0, 1.0), normalize(to_eye), dot(to_eye, to_eye) > 1e-6);
+}
+
+// LOD2 quad turned about +Y to face the eye, textured with the atlas tile
+// nearest the view direction.  The quad frames the row's mesh bounds times
+// the building's scale, as the tile was baked; padded by SPAN
+// (impostor::impostor_quad).
+fn vs_impostor(v : VSIn) -> VSOut {
+    let d = toward_eye(v.i_pos);
+    let step = 6.2831853 / IMPOSTOR_ANGLES;
+    let k = (i32(round(atan2(d.x, d.y) / step)) % i32(IMPOSTOR_ANGLES) + i32(IMPOSTOR_ANGLES)) % i32(IMPOSTOR_ANGLES);
+    let row = clamp(round(v.i_misc.x), 0.0, IMPOSTOR_ROWS - 1.0);
+    let s = vec3<f32>(v.i_pos_w, v.i_scale_w, v.i_scale.z);
+    let half = IMPOSTOR_FRAMES[2u * u32(row)].xyz * s;
+    let centre = v.i_pos + IMPOSTOR_FRAMES[2u * u32(row) + 1u].xyz * s;
+    let right = vec3<f32>(d.y, 0.0, -d.x);
+    // quad vertices span ±BILLBOARD_W/2 × ±BILLBOARD_H/2 (mesh.rs)
+    let local = right * (v.position.x / 0.75 * max(half.x, half.z) * IMPOSTOR_SPAN_W)
+              + vec3<f32>(0.0, v.position.y / 1.25 * half.y * IMPOSTOR_SPAN_H, 0.0);
+    let world_pos = centre + local;
+    var out : VSOut;
+    out.pos = CAMERA.view_proj * vec4<f32>(world_pos, 1.0);
+    out.worldN = vec3<f32>(d.x, 0.0, d.y);
+    out.tint_idx = v.i_misc.x;
+    out.arche_id = v.i_misc.y;
+    out.world_pos = world_pos;
+    out.uv = (vec2<f32>(f32(k), row) + v.uv) / vec2<f32>(IMPOSTOR_ANGLES, IMPOSTOR_ROWS);
+    out.tex_layer = IMPOSTOR_LAYER;
+    out.face_pos = local;
+    out.win_seed = v.i_misc.w;
+    return out;
+}
+
 @vertex
 fn vs_main(v : VSIn) -> VSOut {
+    if (CAMERA.eye.w > 0.5 && v.color.w < 0.0) { return vs_impostor(v); }
     let world_pos = v.i_pos + v.position * v.i_scale;
-    let world_n   = v.normal;
+    let world_n   = v.normal / v.i_scale;   // inverse-scale keeps normals perpendicular
     var out : VSOut;
     out.pos = CAMERA.view_proj * vec4<f32>(world_pos, 1.0);
     out.worldN  = world_n;
     out.tint_idx = v.i_misc.x;
     out.arche_id = v.i_misc.y;
+    out.world_pos = world_pos;
+    out.uv = v.uv;
+    out.tex_layer = v.i_misc.z;
+    out.face_pos = v.position * v.i_scale;
+    out.win_seed = v.i_misc.w;
     return out;
 }
 
-@fragment
-fn fs_main(in : VSOut) -> @location(0) vec4<f32> {
+// 1 = lit, 0 = fully shadowed; 3×3 PCF on the comparison sampler
+fn shadow_factor(world_pos : vec3<f32>) -> f32 {
+    if (SHADOW.params.z < 0.5) { return 1.0; }
+    let lp  = SHADOW.light_vp * vec4<f32>(world_pos, 1.0);
+    let ndc = lp.xyz / lp.w;
+    let uv  = vec2<f32>(ndc.x * 0.5 + 0.5, -ndc.y * 0.5 + 0.5);
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 || ndc.z > 1.0) { return 1.0; }
+    var sum = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let o = vec2<f32>(f32(x), f32(y)) * SHADOW.params.x;
+            sum += textureSampleCompareLevel(SHADOW_MAP, SHADOW_SAMP, uv + o, ndc.z - SHADOW.params.y);
+        }
+    }
+    return sum / 9.0;
+}
+
+// ramp colour of the heatmap cell under xz, or `base` outside it / without data
+fn heat_tint(xz : vec2<f32>, base : vec3<f32>) -> vec3<f32> {
+    if (HEAT.grid.z <= 0.0 || HEAT.grid.w <= 0.0) { return base; }
+    let c = vec2<i32>(floor((xz - HEAT.grid.xy) / HEAT.grid.zw));
+    if (any(c < vec2<i32>(0)) || any(c >= vec2<i32>(HEAT_SIDE))) { return base; }
+    let i = c.y * HEAT_SIDE + c.x;
+    let v = HEAT.cells[i / 4][i % 4];
+    if (v < 0.0) { return base; }
+    let t = clamp(v, 0.0, 1.0) * 3.0;
+    let k = min(i32(t), 2);
+    return mix(HEAT.ramp[k].rgb, HEAT.ramp[k + 1].rgb, t - f32(k));
+}
+
+// ground checkerboard aligned to the city blocks (Engine::set_ground_checker)
+fn checker_tint(xz : vec2<f32>) -> vec3<f32> {
+    let tile = PAL.ground_grid.xy;
+    if (tile.x <= 0.0 || tile.y <= 0.0) { return PAL.col_ground; }
+    let q = xz + PAL.ground_grid.zw;
+    var c = vec2<i32>(floor(q / tile));
+    // per chunk: squares start again at each seam, numbered on from the
+    // previous chunk's count so its last (partial) square still alternates
+    let span = PAL.ground_span.xy;
+    if (span.x > 0.0 && span.y > 0.0) {
+        let k = floor(q / span);
+        c = vec2<i32>(floor((q - k * span) / tile)) + vec2<i32>(k) * vec2<i32>(ceil(span / tile));
+    }
+    return select(PAL.col_ground, PAL.col_ground_alt, ((c.x + c.y) & 1) == 1);
+}
+
+fn ground_tint(xz : vec2<f32>) -> vec3<f32> { return heat_tint(xz, checker_tint(xz)); }
+
+// procedural windows on plain walls: 1 inside a pane, 2 inside a lit one.
+// Panes sit in a grid of LIGHT.windows.x per metre on the face; which are
+// lit is hashed from the cell, the face and the building's seed.
+fn window(in : VSOut, n : vec3<f32>) -> f32 {
+    let density = LIGHT.windows.x;
+    if (density <= 0.0 || in.tint_idx > 2.5 || abs(in.tex_layer) > 0.5 || abs(n.y) > 0.5) { return 0.0; }
+    let across = select(in.face_pos.x, in.face_pos.z, abs(n.x) > abs(n.z));
+    let g = vec2<f32>(across, in.face_pos.y) * density;
+    let f = abs(fract(g) - 0.5);
+    if (f.x > 0.3 || f.y > 0.25) { return 0.0; }   // wall between panes
+    let side = dot(sign(n), vec3<f32>(1.0, 2.0, 3.0));
+    let h = fract(sin(dot(vec3<f32>(floor(g), in.win_seed * 1e-3 + side), vec3<f32>(12.9898, 78.233, 37.719))) * 43758.5453);
+    return select(1.0, 2.0, h < LIGHT.windows.y);
+}
+
+// facade texel, or the atlas texel for impostors; both sampled so the
+// derivatives stay in uniform control flow (sRGB textures: already linear)
+fn surface_texel(in : VSOut) -> vec4<f32> {
+    let facade = textureSample(FACADE, FACADE_SAMP, in.uv, i32(max(in.tex_layer, 0.0) + 0.5));
+    let atlas  = textureSample(ATLAS, ATLAS_SAMP, in.uv);
+    return select(facade, atlas, in.tex_layer < -0.5);
+}
+
+// lit, tinted, alpha-tested surface colour (fs_main / fs_billboard)
+fn shade(in : VSOut) -> vec3<f32> {
     // pick tint
     var tint : vec3<f32>;
     if     (in.tint_idx < 0.5) { tint = PAL.col_low;  }
     else if(in.tint_idx < 1.5) { tint = PAL.col_high; }
-    else                       { tint = PAL.col_land; }
-    let light = clamp(dot(normalize(in.worldN), vec3<f32>(0.4,0.9,0.1)), 0.15, 1.0);
-    return vec4<f32>(tint * light, 1.0);
+    else if(in.tint_idx < 2.5) { tint = PAL.col_land; }
+    else                       { tint = ground_tint(in.world_pos.xz); }
+    let arche = u32(in.arche_id + 0.5);
+    if (in.tint_idx < 2.5 && arche < MAX_TINTS) { tint = tint * TINTS[arche].rgb; }
+    tint = srgb_to_linear(tint);
+    // alpha-tested facade (or impostor tile), tinted by the palette
+    let texel = surface_texel(in);
+    if (texel.a < 0.5) { discard; }
+    tint = tint * texel.rgb;
+    let n = normalize(in.worldN);
+    let diffuse = max(dot(n, normalize(LIGHT.dir.xyz)), 0.0) * shadow_factor(in.world_pos);
+    let ambient = mix(LIGHT.ground.rgb, LIGHT.sky.rgb, n.y * 0.5 + 0.5);
+    let w = window(in, n);
+    if (w > 1.5) { return srgb_to_linear(vec3<f32>(1.0, 0.85, 0.55)); }   // lit: emissive, ignores the light
+    if (w > 0.5) { return tint * (diffuse + ambient) * 0.35; }
+    return tint * (diffuse + ambient);
+}
+
+@fragment
+fn fs_main(in : VSOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(in), 1.0);
+}
+
+// billboards (quad uv 0..1): alpha fades to 0 over the outermost pixel so
+// alpha-to-coverage (MSAA) or blending (no MSAA) smooths the silhouette;
+// impostors are cut out by the atlas alpha instead
+@fragment
+fn fs_billboard(in : VSOut) -> @location(0) vec4<f32> {
+    let px = min(in.uv, 1.0 - in.uv) / max(fwidth(in.uv), vec2<f32>(1e-6));
+    let edge = clamp(min(px.x, px.y) + 0.5, 0.0, 1.0);
+    return vec4<f32>(shade(in), select(edge, 1.0, in.tex_layer < -0.5));
+}
+
+// selection highlight: flat emissive tint over the scaled shell (alpha-blended)
+@fragment
+fn fs_highlight(in : VSOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(srgb_to_linear(vec3<f32>(1.0, 0.8, 0.2)), 0.35);
+}
+
+// depth prepass: only the facade alpha test, so cut-outs don't occlude
+@fragment
+fn fs_prepass(in : VSOut) {
+    if (surface_texel(in).a < 0.5) { discard; }
+}
+
+// ---------- smoke ----------
+// Puffs from smoke.rs on the billboard quad, turned to the eye like the
+// impostors; i_misc.x = opacity, .y = age (0..1), .w = seed.
+struct VSSmokeOut {
+    @builtin(position) pos : vec4<f32>,
+    @location(0) uv    : vec2<f32>,
+    @location(1) alpha : f32,
+    @location(2) age   : f32,
+    @location(3) seed  : f32,
+};
+
+@vertex
+fn vs_smoke(v : VSIn) -> VSSmokeOut {
+    let d = toward_eye(v.i_pos);
+    let right = vec3<f32>(d.y, 0.0, -d.x);
+    let world_pos = v.i_pos + right * (v.position.x * v.i_scale.x) + vec3<f32>(0.0, v.position.y * v.i_scale.y, 0.0);
+    var out : VSSmokeOut;
+    out.pos = CAMERA.view_proj * vec4<f32>(world_pos, 1.0);
+    out.uv = v.uv;
+    out.alpha = v.i_misc.x;
+    out.age = v.i_misc.y;
+    out.seed = v.i_misc.w;
+    return out;
+}
+
+// soft disc, lumpy per puff, greying and thinning as it ages; lit by the
+// ambient and the key light's height only (no normals to speak of)
+@fragment
+fn fs_smoke(in : VSSmokeOut) -> @location(0) vec4<f32> {
+    let p = in.uv * 2.0 - 1.0;
+    let lump = 0.12 * sin(atan2(p.y, p.x) * 5.0 + in.seed);
+    let a = (1.0 - smoothstep(0.35, 1.0, length(p) + lump)) * in.alpha * 0.6;
+    if (a <= 0.0) { discard; }
+    let base = srgb_to_linear(mix(vec3<f32>(0.32, 0.31, 0.30), vec3<f32>(0.62, 0.62, 0.64), in.age));
+    let light = LIGHT.sky.rgb + max(normalize(LIGHT.dir.xyz).y, 0.0) * 0.5;
+    return vec4<f32>(base * light, a);
+}
+
+// ---------- baked chunks ----------
+// Whole-chunk mesh pre-transformed on the CPU; one instance carries the
+// floating-origin offset and vertex `color.w` carries
+// category + 4 * layer + 4 * LAYER_COUNT * archetype; `color.xyz` is the
+// face position across and up, and the window seed (see
+// mesh::bake_chunk_data).  LAYER_COUNT is prepended from facade.rs.
+struct VSBakedIn {
+    @location(0) position : vec3<f32>,
+    @location(1) color    : vec4<f32>,
+    @location(5) normal   : vec3<f32>,
+    @location(6) uv       : vec2<f32>,
+    @location(2) i_pos    : vec3<f32>,
+    @location(3) i_scale  : vec3<f32>,
+    @location(4) i_misc   : vec4<f32>,
+};
+
+@vertex
+fn vs_baked(v : VSBakedIn) -> VSOut {
+    var out : VSOut;
+    out.pos = CAMERA.view_proj * vec4<f32>(v.i_pos + v.position, 1.0);
+    out.worldN  = v.normal;
+    let arche = floor(v.color.w / (4.0 * LAYER_COUNT));
+    let rest  = v.color.w - 4.0 * LAYER_COUNT * arche;
+    let layer = floor(rest / 4.0);
+    out.tint_idx = rest - 4.0 * layer;
+    out.arche_id = arche;
+    out.world_pos = v.i_pos + v.position;
+    out.uv = v.uv;
+    out.tex_layer = layer;
+    out.face_pos = vec3<f32>(v.color.x, v.color.y, v.color.x);  // `window` reads x or z
+    out.win_seed = v.color.z;
+    return out;
 }
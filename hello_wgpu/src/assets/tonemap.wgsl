@@ -0,0 +1,25 @@
+// Tone mapping: the linear HDR scene → the frame, one full-screen triangle.
+struct ToneMap {
+    params : vec4<f32>,   // .x = exposure  .y = 1 to gamma-encode (frame format isn't sRGB)
+};
+@group(0) @binding(0) var HDR      : texture_2d<f32>;
+@group(0) @binding(1) var<uniform> TM : ToneMap;
+
+@vertex
+fn vs_tonemap(@builtin(vertex_index) i : u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// ACES filmic fit (Narkowicz 2015)
+fn aces(x : vec3<f32>) -> vec3<f32> {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_tonemap(@builtin(position) pos : vec4<f32>) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(HDR, vec2<i32>(pos.xy), 0).rgb;
+    var c = aces(hdr * TM.params.x);
+    if (TM.params.y > 0.5) { c = pow(c, vec3<f32>(1.0 / 2.2)); }
+    return vec4<f32>(c, 1.0);
+}
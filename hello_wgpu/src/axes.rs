@@ -0,0 +1,93 @@
+//! World-axis conventions: which axis is up and the handedness of the
+//! coordinate system.  `Camera` builds its basis and view matrix from a
+//! `WorldAxes`, and culling asks it which extents are horizontal, so an
+//! embedder with a Z-up world sets one value instead of patching the math.
+//! Yaw turns about `up` starting from +X (positive = towards the camera's
+//! right), pitch tilts towards `up`.  The bundled city (chunk grid, meshes,
+//! ground plane) is laid out Y-up and stays on the default.
+
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis { #[default] Y, Z }
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Handedness { #[default] Right, Left }
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorldAxes {
+    pub up: UpAxis,
+    pub handedness: Handedness,
+}
+
+impl WorldAxes {
+    pub const Y_UP_RH: WorldAxes = WorldAxes { up: UpAxis::Y, handedness: Handedness::Right };
+    pub const Z_UP_RH: WorldAxes = WorldAxes { up: UpAxis::Z, handedness: Handedness::Right };
+
+    /// Unit world up.
+    pub fn up(&self) -> Vector3<f32> {
+        match self.up { UpAxis::Y => Vector3::unit_y(), UpAxis::Z => Vector3::unit_z() }
+    }
+
+    /// `a × b` in this handedness: for `(forward, up)` it gives right, for
+    /// `(right, forward)` up.
+    pub fn cross(&self, a: Vector3<f32>, b: Vector3<f32>) -> Vector3<f32> {
+        match self.handedness { Handedness::Right => a.cross(b), Handedness::Left => b.cross(a) }
+    }
+
+    /// Horizontal direction at yaw 0 (+X) and the one yaw turns it towards
+    /// (the camera's right at yaw 0): Y-up RH gives (+X, +Z).
+    pub fn horizontal(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let x = Vector3::unit_x();
+        (x, self.cross(x, self.up()).normalize())
+    }
+
+    /// Unit forward for `yaw`/`pitch` (radians).
+    pub fn forward(&self, yaw: f32, pitch: f32) -> Vector3<f32> {
+        let (h0, h1) = self.horizontal();
+        ((h0 * yaw.cos() + h1 * yaw.sin()) * pitch.cos() + self.up() * pitch.sin()).normalize()
+    }
+
+    /// Component of `v` along up.
+    pub fn height(&self, v: Vector3<f32>) -> f32 { v.dot(self.up()) }
+
+    /// `v` with its up component replaced by `h`.
+    pub fn with_height(&self, v: Vector3<f32>, h: f32) -> Vector3<f32> {
+        match self.up { UpAxis::Y => Vector3::new(v.x, h, v.z), UpAxis::Z => Vector3::new(v.x, v.y, h) }
+    }
+
+    /// `v` with the up component removed.
+    pub fn flatten(&self, v: Vector3<f32>) -> Vector3<f32> { self.with_height(v, 0.0) }
+
+    /// A vector given in the default Y-up RH axes, in these: x along +X,
+    /// y along up, z along the yaw-90° direction.
+    pub fn from_y_up(&self, v: Vector3<f32>) -> Vector3<f32> {
+        let (h0, h1) = self.horizontal();
+        h0 * v.x + self.up() * v.y + h1 * v.z
+    }
+
+    /// Half-extent `h` grown by `horizontal` on the two horizontal axes and
+    /// `vertical` along up.
+    pub fn grow(&self, h: Vector3<f32>, horizontal: f32, vertical: f32) -> Vector3<f32> {
+        let up = self.up();
+        let along = |u: f32| if u != 0.0 { vertical } else { horizontal };
+        h + Vector3::new(along(up.x), along(up.y), along(up.z))
+    }
+
+    /// The larger horizontal component of the half-extent `h`.
+    pub fn horizontal_extent(&self, h: Vector3<f32>) -> f32 {
+        match self.up { UpAxis::Y => h.x.abs().max(h.z.abs()), UpAxis::Z => h.x.abs().max(h.y.abs()) }
+    }
+
+    /// World → view for an eye at `eye` looking along `forward` with
+    /// camera `up`, into wgpu's right-handed view space (−Z forward); a
+    /// left-handed world is mirrored on the way in, so screen right stays
+    /// the camera's right.
+    pub fn view(&self, eye: Point3<f32>, forward: Vector3<f32>, up: Vector3<f32>) -> Matrix4<f32> {
+        let rh = Matrix4::look_to_rh(eye, forward, up);
+        match self.handedness {
+            Handedness::Right => rh,
+            Handedness::Left => Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0) * rh,
+        }
+    }
+}
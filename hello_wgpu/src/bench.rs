@@ -0,0 +1,151 @@
+//! `bench` subcommand: render a fixed number of frames headless along a
+//! seed-derived camera path and report frame-time / draw statistics.
+//! Simulation time advances by a fixed step, so two runs with the same
+//! options do the same work regardless of how fast the machine is.
+
+use std::fmt;
+
+use log::info;
+
+use crate::camera::CameraState;
+use crate::rng::hash2;
+use crate::flythrough::CameraPath;
+use crate::hello_wgpu::{App, init_logging};
+use crate::render::Engine;
+
+/// Fixed simulation step (s).
+pub const BENCH_DT: f32 = 1.0 / 60.0;
+
+#[derive(Clone, Debug)]
+pub struct BenchOptions {
+    pub frames: u32,
+    pub seed:   u64,
+    pub width:  u32,
+    pub height: u32,
+    pub depth_prepass: bool,
+    pub render_bundles: bool,
+    pub hdr: bool,
+    pub backends: wgpu::Backends,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self { Self { frames: 600, seed: 42, width: 1280, height: 720, depth_prepass: false, render_bundles: true, hdr: true, backends: wgpu::Backends::all() } }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BenchReport {
+    pub frames:  u32,
+    pub mean_ms: f32,
+    pub p99_ms:  f32,
+    pub max_ms:  f32,
+    /// Mean GPU time of the (prepass +) main pass, when timestamps exist.
+    pub gpu_mean_ms: Option<f32>,
+    /// Mean CPU time recording + submitting a frame (`FrameStats::encode_ms`).
+    pub encode_mean_ms: f32,
+    pub avg_draw_calls: f32,
+    pub avg_instances:  f32,
+    pub avg_triangles:  f32,
+    pub adapter: String,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "adapter: {}", self.adapter)?;
+        writeln!(f, "frames:  {}", self.frames)?;
+        writeln!(f, "frame:   mean {:.3} ms  p99 {:.3} ms  max {:.3} ms", self.mean_ms, self.p99_ms, self.max_ms)?;
+        if let Some(g) = self.gpu_mean_ms { writeln!(f, "gpu:     mean {g:.3} ms (scene passes)")?; }
+        writeln!(f, "encode:  mean {:.3} ms (cpu)", self.encode_mean_ms)?;
+        write!(f,   "draws:   {:.1} calls  {:.0} instances  {:.0} triangles (avg/frame)",
+               self.avg_draw_calls, self.avg_instances, self.avg_triangles)
+    }
+}
+
+/// Deterministic loop over the city: radius, height and phase come from
+/// `seed`; the camera looks slightly inwards along the direction of travel.
+pub fn bench_path(seed: u64, duration: f32) -> CameraPath {
+    let unit = |k: i32| (hash2(seed as i32 ^ (seed >> 32) as i32, k) % 10_000) as f32 / 10_000.0;
+    let radius = 60.0 + 120.0 * unit(1);
+    let height = 8.0 + 20.0 * unit(2);
+    let phase  = std::f32::consts::TAU * unit(3);
+    let laps   = 0.5 + unit(4);
+    let interval = 0.25;
+    let n = (duration / interval).ceil() as usize + 2;
+    let samples = (0..n).map(|i| {
+        let a = phase + std::f32::consts::TAU * laps * (i as f32 * interval / duration.max(1e-3));
+        let (x, z) = (radius * a.cos(), radius * a.sin());
+        // tangent is (-sin, cos); bias 20° toward the centre
+        let yaw = z.atan2(x) + std::f32::consts::FRAC_PI_2 + 20f32.to_radians();
+        CameraState { position: [x, height, z], yaw, pitch: -10f32.to_radians() }
+    }).collect();
+    CameraPath { interval, samples }
+}
+
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() { return 0.0; }
+    let i = ((sorted.len() as f32 - 1.0) * p).round() as usize;
+    sorted[i.min(sorted.len() - 1)]
+}
+
+/// Create a headless device + engine and run the benchmark.
+pub fn run_bench(opts: &BenchOptions) -> BenchReport {
+    init_logging(false);
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends: opts.backends, ..Default::default() });
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+        force_fallback_adapter: false,
+    })).expect("no GPU adapter");
+    let info = adapter.get_info();
+    let desc = wgpu::DeviceDescriptor {
+        required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+        ..Default::default()
+    };
+    let (device, queue) = pollster::block_on(adapter.request_device(&desc)).expect("request_device");
+    let mut engine = Engine::new_headless(device, queue, opts.width, opts.height);
+    engine.set_depth_prepass(opts.depth_prepass);
+    engine.set_render_bundles(opts.render_bundles);
+    engine.set_hdr(opts.hdr);
+
+    let duration = opts.frames as f32 * BENCH_DT;
+    let mut app = App::new_headless(engine, opts.seed, bench_path(opts.seed, duration + 1.0));
+    let size = winit::dpi::PhysicalSize::new(opts.width.max(1), opts.height.max(1));
+    info!("bench: {} frames at {}x{}, seed {}, prepass {}", opts.frames, size.width, size.height, opts.seed, opts.depth_prepass);
+
+    let mut times = Vec::with_capacity(opts.frames as usize);
+    let (mut draws, mut inst, mut tris) = (0u64, 0u64, 0u64);
+    let (mut gpu_sum, mut gpu_n) = (0.0f32, 0u32);
+    let mut encode_sum = 0.0f32;
+    for _ in 0..opts.frames {
+        let t0 = instant::Instant::now();
+        app.advance_camera(BENCH_DT);
+        app.step_frame(BENCH_DT, size, opts.seed)
+            .expect("headless frame");
+        let e = app.engine().expect("engine");
+        // wait for the GPU so frame time covers the whole frame
+        let _ = e.device.poll(wgpu::PollType::Wait);
+        times.push(t0.elapsed().as_secs_f32() * 1000.0);
+
+        let s = e.frame_stats();
+        draws += s.draw_calls as u64;
+        inst  += s.instances as u64;
+        tris  += s.triangles;
+        if let Some(g) = s.gpu_ms { gpu_sum += g; gpu_n += 1; }
+        encode_sum += s.encode_ms;
+    }
+
+    let n = times.len().max(1) as f32;
+    let mean = times.iter().sum::<f32>() / n;
+    times.sort_by(f32::total_cmp);
+    BenchReport {
+        frames: opts.frames,
+        mean_ms: mean,
+        p99_ms: percentile(&times, 0.99),
+        max_ms: times.last().copied().unwrap_or(0.0),
+        gpu_mean_ms: (gpu_n > 0).then(|| gpu_sum / gpu_n as f32),
+        encode_mean_ms: encode_sum / n,
+        avg_draw_calls: draws as f32 / n,
+        avg_instances:  inst as f32 / n,
+        avg_triangles:  tris as f32 / n,
+        adapter: format!("{} ({:?})", info.name, info.backend),
+    }
+}
@@ -1,22 +1,73 @@
 use std::collections::HashSet;
 
 use cgmath::{
-    Deg, InnerSpace, Matrix4, Point3, Vector3,
+    Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3,
     perspective,
 };
-use winit::keyboard::KeyCode;
+use winit::keyboard::{KeyCode, ModifiersState};
 
+use crate::axes::WorldAxes;
+
+#[derive(Default)]
 pub struct KeyboardInput {
     pressed: HashSet<KeyCode>,
+    modifiers: ModifiersState,
 }
 
 impl KeyboardInput {
     pub fn new() -> Self {
-        Self { pressed: HashSet::new() }
+        Self { pressed: HashSet::new(), modifiers: ModifiersState::empty() }
     }
     pub fn key_press(&mut self, code: KeyCode)   { self.pressed.insert(code); }
     pub fn key_release(&mut self, code: KeyCode) { self.pressed.remove(&code); }
     pub fn is_pressed(&self, code: KeyCode) -> bool { self.pressed.contains(&code) }
+
+    /// Is any key `Camera::update` moves or turns with held?
+    pub fn steering(&self) -> bool {
+        const KEYS: [KeyCode; 11] = [
+            KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::Space,
+            KeyCode::ShiftLeft, KeyCode::ShiftRight,
+            KeyCode::ArrowLeft, KeyCode::ArrowRight, KeyCode::ArrowUp, KeyCode::ArrowDown,
+        ];
+        KEYS.iter().any(|k| self.pressed.contains(k))
+    }
+
+    /// Forget every held key: releases that happen while the window is
+    /// unfocused are never delivered, so call this on focus loss.
+    pub fn release_all(&mut self) {
+        self.pressed.clear();
+        self.modifiers = ModifiersState::empty();
+    }
+
+    /// `WindowEvent::ModifiersChanged`: a modifier reported as up also drops
+    /// its left/right key codes, in case their release went missing.
+    pub fn set_modifiers(&mut self, state: ModifiersState) {
+        self.modifiers = state;
+        let groups = [
+            (state.shift_key(),   [KeyCode::ShiftLeft,   KeyCode::ShiftRight]),
+            (state.control_key(), [KeyCode::ControlLeft, KeyCode::ControlRight]),
+            (state.alt_key(),     [KeyCode::AltLeft,     KeyCode::AltRight]),
+            (state.super_key(),   [KeyCode::SuperLeft,   KeyCode::SuperRight]),
+        ];
+        for (held, codes) in groups {
+            if !held { for c in codes { self.pressed.remove(&c); } }
+        }
+    }
+    pub fn modifiers(&self) -> ModifiersState { self.modifiers }
+}
+
+/// Serializable camera pose; orientation as yaw/pitch (radians).
+#[derive(Copy, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CameraState {
+    pub position: [f32; 3],
+    pub yaw:      f32,
+    pub pitch:    f32,
+}
+
+impl CameraState {
+    /// Where `Camera::new` starts, in design space: 10 m behind the origin
+    /// looking down +Z.
+    pub const SPAWN: CameraState = CameraState { position: [0.0, 5.0, -10.0], yaw: 0.0, pitch: 0.0 };
 }
 
 pub struct Camera {
@@ -24,15 +75,56 @@ pub struct Camera {
     pub forward:  Vector3<f32>,
     pub right:    Vector3<f32>,
     pub up:       Vector3<f32>,
-    pub speed:    f32,   // movement units per second
+    pub speed:    f32,   // movement units per second (max speed when smooth)
     pub yaw:      f32,   // radians, left/right
     pub pitch:    f32,   // radians, up/down (clamped)
+    // mouse look: radians per pixel on each axis; invert_y makes mouse-up look down
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    pub invert_y: bool,
+    // eased movement: held keys pull `velocity` towards `speed` at the rate
+    // `acceleration` (1/s, ~63% of the way in `1/acceleration` s) and it
+    // decays at `damping` (1/s) once released; off moves at `speed` instantly
+    pub smooth: bool,
+    pub acceleration: f32,
+    pub damping: f32,
+    pub velocity: Vector3<f32>,
+    // walk mode (`set_walk`): W/S stay level, Space/Shift do nothing, and the
+    // eye never drops below `walk_height` above the ground plane `ground_y`
+    pub walk: bool,
+    pub walk_height: f32,
+    pub ground_y: f32,
+    // altitude-scaled speed (`set_altitude_speed`): movement runs at
+    // `speed * (1 + height * altitude_factor)`, height above `ground_y`, the
+    // multiplier capped at `max_speed_scale`; `speed` stays the base
+    pub altitude_speed: bool,
+    pub altitude_factor: f32,
+    pub max_speed_scale: f32,
+    // up axis / handedness the basis, heights and view matrix follow
+    // (`set_axes`); `ground_y` and walk heights are measured along its up
+    pub axes: WorldAxes,
+    // vertical field of view (degrees); `set_fov` keeps it in `FOV_RANGE`
+    pub fov_deg: f32,
 }
 
+/// Default mouse-look sensitivity (radians per pixel).
+pub const DEFAULT_SENSITIVITY: f32 = 0.002;
+
+/// Range `scale_speed` keeps the base speed in (units per second).
+pub const SPEED_RANGE: (f32, f32) = (0.5, 500.0);
+
+/// Default vertical field of view, `Camera::fov_deg` (degrees).
+pub const FOV_Y_DEG: f32 = 60.0;
+
+/// Range the field of view is kept in (degrees): narrower turns the
+/// frustum into a needle, wider tears the edges of the image apart.
+pub const FOV_RANGE: (f32, f32) = (20.0, 120.0);
+
+
 impl Camera {
     pub fn new() -> Self {
         // Start at +Z forward. If you want -Z forward, set forward.z = -1.0 and yaw = PI.
-        let position = Point3::new(0.0, 5.0, -10.0);
+        let position = Point3::from(CameraState::SPAWN.position);
         let forward  = Vector3::new(0.0, 0.0, 1.0).normalize();
         let up       = Vector3::new(0.0, 1.0, 0.0);
         let right    = forward.cross(up).normalize();
@@ -45,14 +137,118 @@ impl Camera {
             speed: 5.0,
             yaw:   0.0,
             pitch: 0.0,
+            sensitivity_x: DEFAULT_SENSITIVITY,
+            sensitivity_y: DEFAULT_SENSITIVITY,
+            invert_y: false,
+            smooth: false,
+            acceleration: 4.0,
+            damping: 4.0,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            walk: false,
+            walk_height: 1.7,
+            ground_y: 0.0,
+            altitude_speed: false,
+            altitude_factor: 0.05,
+            max_speed_scale: 20.0,
+            axes: WorldAxes::default(),
+            fov_deg: FOV_Y_DEG,
         }
     }
 
-    /// Apply mouse delta (in pixels) to yaw/pitch. Call from WindowEvent::CursorMoved.
-    pub fn process_mouse_delta(&mut self, delta_x: f32, delta_y: f32, sensitivity: f32) {
+    /// Switch world axes, keeping yaw/pitch (now about the new up) and the
+    /// position as is; see `reset_to_spawn` to start over in the new world.
+    pub fn set_axes(&mut self, axes: WorldAxes) {
+        self.axes = axes;
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+        self.update_axes_from_angles();
+    }
+
+    /// Height of the eye along the world up.
+    pub fn height(&self) -> f32 { self.axes.height(self.position.to_vec()) }
+
+    fn set_height(&mut self, h: f32) {
+        self.position = Point3::from_vec(self.axes.with_height(self.position.to_vec(), h));
+    }
+
+    pub fn set_smooth(&mut self, on: bool) {
+        self.smooth = on;
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    /// Same mouse-look sensitivity on both axes (radians per pixel, kept positive).
+    pub fn set_sensitivity(&mut self, s: f32) {
+        self.sensitivity_x = s.abs();
+        self.sensitivity_y = s.abs();
+    }
+
+    pub fn set_invert_y(&mut self, invert: bool) { self.invert_y = invert; }
+
+    pub fn set_altitude_speed(&mut self, on: bool) { self.altitude_speed = on; }
+
+    /// Multiply the base `speed` (scroll wheel), kept within `SPEED_RANGE`;
+    /// altitude scaling still applies on top.
+    pub fn scale_speed(&mut self, factor: f32) {
+        self.speed = (self.speed * factor).clamp(SPEED_RANGE.0, SPEED_RANGE.1);
+    }
+
+    /// Set the vertical field of view, kept within `FOV_RANGE`; NaN or
+    /// infinite leaves it as it was.
+    pub fn set_fov(&mut self, deg: f32) {
+        if deg.is_finite() { self.fov_deg = deg.clamp(FOV_RANGE.0, FOV_RANGE.1); }
+    }
+
+    /// Field of view actually projected with: `fov_deg` within `FOV_RANGE`
+    /// (the field is public), the default if it isn't a number.
+    pub fn fov(&self) -> f32 {
+        if self.fov_deg.is_finite() { self.fov_deg.clamp(FOV_RANGE.0, FOV_RANGE.1) } else { FOV_Y_DEG }
+    }
+
+    /// Multiplier on `speed` this frame: 1 unless `altitude_speed`.
+    pub fn speed_scale(&self) -> f32 {
+        if !self.altitude_speed { return 1.0; }
+        let height = (self.height() - self.ground_y).max(0.0);
+        (1.0 + height * self.altitude_factor).clamp(1.0, self.max_speed_scale.max(1.0))
+    }
+
+    /// Movement speed actually used: `speed * speed_scale()`.
+    pub fn effective_speed(&self) -> f32 { self.speed * self.speed_scale() }
+
+    /// Switch walk mode; turning it on stands the camera on the ground at
+    /// `walk_height`.  Free fly (off) is the default.
+    pub fn set_walk(&mut self, on: bool) {
+        self.walk = on;
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+        if on { self.set_height(self.ground_y + self.walk_height); }
+    }
+
+    pub fn state(&self) -> CameraState {
+        CameraState { position: self.position.into(), yaw: self.yaw, pitch: self.pitch }
+    }
+
+    pub fn apply_state(&mut self, s: &CameraState) {
+        self.position = Point3::from(s.position);
+        self.yaw = s.yaw;
+        self.pitch = s.pitch;
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+        self.clamp_pitch();
+        self.update_axes_from_angles();
+    }
+
+    /// Back to `CameraState::SPAWN` (local = design only once the origin
+    /// shift is undone, see `ChunkManager::reset_origin`), its position
+    /// carried over into `axes`; walking stays on the ground.
+    pub fn reset_to_spawn(&mut self) {
+        let p = self.axes.from_y_up(Vector3::from(CameraState::SPAWN.position));
+        self.apply_state(&CameraState { position: p.into(), ..CameraState::SPAWN });
+        if self.walk { self.set_height(self.ground_y + self.walk_height); }
+    }
+
+    /// Apply mouse delta (in pixels) to yaw/pitch. Call with mouse-motion deltas.
+    pub fn process_mouse_delta(&mut self, delta_x: f32, delta_y: f32) {
         // Typical: add yaw with +dx, subtract pitch with +dy (so moving mouse up looks up)
-        self.yaw   += delta_x * sensitivity;
-        self.pitch -= delta_y * sensitivity;
+        let dy = if self.invert_y { -delta_y } else { delta_y };
+        self.yaw   += delta_x * self.sensitivity_x;
+        self.pitch -= dy * self.sensitivity_y;
         self.clamp_pitch();
         self.update_axes_from_angles();
     }
@@ -71,30 +267,59 @@ impl Camera {
         self.update_axes_from_angles();
 
         // ----- Movement along the rotated axes -----
-        let movement = self.speed * delta_time;
-
-        if input.is_pressed(KeyCode::KeyW) { self.position += self.forward * movement; }
-        if input.is_pressed(KeyCode::KeyS) { self.position -= self.forward * movement; }
-        if input.is_pressed(KeyCode::KeyA) { self.position -= self.right   * movement; }
-        if input.is_pressed(KeyCode::KeyD) { self.position += self.right   * movement; }
+        // walking looks up/down without climbing or diving
+        let forward = if self.walk { self.axes.flatten(self.forward).normalize() } else { self.forward };
+        let mut wish = Vector3::new(0.0, 0.0, 0.0);
+        if input.is_pressed(KeyCode::KeyW) { wish += forward; }
+        if input.is_pressed(KeyCode::KeyS) { wish -= forward; }
+        if input.is_pressed(KeyCode::KeyA) { wish -= self.right; }
+        if input.is_pressed(KeyCode::KeyD) { wish += self.right; }
 
         // Vertical (noclip) movement
-        if input.is_pressed(KeyCode::Space) {
-            self.position += self.up * movement;
+        if !self.walk {
+            if input.is_pressed(KeyCode::Space) { wish += self.up; }
+            if input.is_pressed(KeyCode::ShiftLeft) || input.is_pressed(KeyCode::ShiftRight) { wish -= self.up; }
         }
-        if input.is_pressed(KeyCode::ShiftLeft) || input.is_pressed(KeyCode::ShiftRight) {
-            self.position -= self.up * movement;
+
+        if self.smooth { self.integrate_velocity(wish, delta_time); }
+        else { self.position += wish * self.effective_speed() * delta_time; }
+        if self.walk { self.clamp_to_ground(); }
+    }
+
+    /// Walk mode: keep the eye `walk_height` above `ground_y` (later also
+    /// building tops), stopping any downward drift.
+    fn clamp_to_ground(&mut self) {
+        let min = self.ground_y + self.walk_height;
+        if self.height() < min {
+            self.set_height(min);
+            self.velocity = self.axes.with_height(self.velocity, self.axes.height(self.velocity).max(0.0));
         }
     }
 
-    /// View matrix (right-handed). `look_at_rh` expects `Point3` for eye/center, `Vector3` for up.
+    /// Ease the velocity towards `wish` at `effective_speed`, integrated
+    /// exactly over `delta_time` so the easing and the distance covered are
+    /// the same at any frame rate.  The velocity only ever approaches the
+    /// top speed, so it needs no cap.
+    fn integrate_velocity(&mut self, wish: Vector3<f32>, delta_time: f32) {
+        let held = wish.magnitude2() > 0.0;
+        let dir = if held { wish.normalize() } else { wish };
+        let k = if held { self.acceleration } else { self.damping }.max(1e-3);
+        let terminal = dir * self.effective_speed();
+        let decay = (-k * delta_time).exp();
+        let dv = self.velocity - terminal;
+        self.position += terminal * delta_time + dv * ((1.0 - decay) / k);
+        self.velocity = terminal + dv * decay;
+        if !held && self.velocity.magnitude() < 1e-3 { self.velocity = Vector3::new(0.0, 0.0, 0.0); }
+    }
+
+    /// View matrix into right-handed view space (see `WorldAxes::view`).
     pub fn view_matrix(&self) -> Matrix4<f32> {
-        Matrix4::look_at_rh(self.position, self.position + self.forward, self.up)
+        self.axes.view(self.position, self.forward, self.up)
     }
 
     /// Basic perspective projection. Pass your swapchain aspect (width/height).
     pub fn projection_matrix(&self, aspect: f32) -> Matrix4<f32> {
-        perspective(Deg(60.0), aspect, 0.1, 1_000.0)
+        perspective(Deg(self.fov()), aspect, 0.1, 1_000.0)
     }
 
     /// Combined view-projection matrix.
@@ -102,6 +327,25 @@ impl Camera {
         self.projection_matrix(aspect) * self.view_matrix()
     }
 
+    /// Unit direction of the view ray through `(ndc_x, ndc_y)`: −1…1 with
+    /// +y up, `(0, 0)` is the screen centre (= `forward`).
+    pub fn view_ray(&self, ndc_x: f32, ndc_y: f32, aspect: f32) -> Vector3<f32> {
+        let t = (self.fov().to_radians() * 0.5).tan();
+        (self.forward + self.right * (ndc_x * t * aspect) + self.up * (ndc_y * t)).normalize()
+    }
+
+    /// Where the view ray through `(ndc_x, ndc_y)` meets the ground plane,
+    /// height `ground_y` along up; `None` if it runs parallel or points away.  Like
+    /// `position`, everything is in local space (design − origin shift), so
+    /// the design-space ground `y = 0` is `ground_y = -origin_shift.y`.
+    pub fn ground_hit(&self, ndc_x: f32, ndc_y: f32, aspect: f32, ground_y: f32) -> Option<Vector3<f32>> {
+        let dir = self.view_ray(ndc_x, ndc_y, aspect);
+        let rise = self.axes.height(dir);
+        if rise.abs() < 1e-6 { return None; }
+        let t = (ground_y - self.height()) / rise;
+        (t > 0.0).then(|| self.position.to_vec() + dir * t)
+    }
+
     // --- internals ---
 
     fn clamp_pitch(&mut self) {
@@ -112,21 +356,19 @@ impl Camera {
     }
 
     fn update_axes_from_angles(&mut self) {
-        // Forward from yaw/pitch (right-handed, +Z forward at yaw=0).
-        // If you want -Z forward at yaw=0, use x =  cos(yaw)*cos(pitch), z = -sin(yaw)*cos(pitch)
-        let cy = self.yaw.cos();
-        let sy = self.yaw.sin();
-        let cp = self.pitch.cos();
-        let sp = self.pitch.sin();
-
-        self.forward = Vector3::new(cy * cp, sp, sy * cp).normalize();
+        // Forward from yaw/pitch (+X at yaw 0, see `WorldAxes::forward`)
+        self.forward = self.axes.forward(self.yaw, self.pitch);
 
         // Derive right and up to keep an orthonormal basis
-        self.right = self.forward.cross(Vector3::unit_y()).normalize();
-        self.up    = self.right.cross(self.forward).normalize();
+        self.right = self.axes.cross(self.forward, self.axes.up()).normalize();
+        self.up    = self.axes.cross(self.right, self.forward).normalize();
     }
 }
 
+impl Default for Camera {
+    fn default() -> Self { Self::new() }
+}
+
 /// Distance-based culling helper
 pub fn should_render(building_pos: Point3<f32>, camera_pos: Point3<f32>, max_distance: f32) -> bool {
     (building_pos - camera_pos).magnitude() < max_distance
@@ -1,27 +1,165 @@
-use std::collections::HashMap;
-use cgmath::Vector3;
+use std::collections::{HashMap, HashSet, VecDeque};
+use cgmath::{InnerSpace, Vector3};
+use log::warn;
 
-use crate::designer_ml::{CityDesigner, DesignContext, Placement};
-use crate::assets::{AssetLibrary, BuildingCategory};
+use crate::designer_ml::{CityDesigner, DesignContext, DistrictMap, Placement, RuleDesigner};
+use crate::assets::AssetLibrary;
+use crate::city_store::{ChunkFile, PlacementDisk, StoreBackend, StoreLoader};
+use crate::culling::ray_aabb;
+use crate::rng::hash2;
+use crate::spatial::ChunkGrid;
 
 pub type ViewerId = u32;
 
+/// Most extra boxes one placement may stack on its main box.
+pub const MAX_STACK: usize = 4;
+
+/// An extra box of a multi-part building (podium + tower …): centred at the
+/// placement's `center + offset`, with its own absolute `scale`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SubBox {
+    pub offset: Vector3<f32>,
+    pub scale:  Vector3<f32>,
+    pub archetype_id: u16,
+}
+
 /// Runtime placement (what renderer reads)
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct RuntimePlacement {
     pub center: Vector3<f32>,
     pub scale:  Vector3<f32>,
     pub archetype_id: u16,
+    /// Extra boxes drawn with this one; `None` (no allocation) for the usual
+    /// single box, else at most `MAX_STACK`.
+    pub stack: Option<Box<[SubBox]>>,
+}
+
+impl RuntimePlacement {
+    pub fn single(center: Vector3<f32>, scale: Vector3<f32>, archetype_id: u16) -> Self {
+        Self { center, scale, archetype_id, stack: None }
+    }
+
+    /// Every box to draw as `(center, scale, archetype)`: the main one, then the stack.
+    pub fn boxes(&self) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>, u16)> + '_ {
+        std::iter::once((self.center, self.scale, self.archetype_id))
+            .chain(self.stack.iter().flatten().map(|s| (self.center + s.offset, s.scale, s.archetype_id)))
+    }
+}
+
+/// `parts` as a placement stack: empty → `None`, past `MAX_STACK` truncated.
+pub fn bounded_stack(parts: &[SubBox]) -> Option<Box<[SubBox]>> {
+    if parts.len() > MAX_STACK { warn!("stack of {} boxes cut to {MAX_STACK}", parts.len()); }
+    (!parts.is_empty()).then(|| parts[..parts.len().min(MAX_STACK)].into())
 }
-#[derive(Clone, Debug)]
+/// Serde form is the TOML city file (`to_toml_str` / `from_toml_str`):
+/// missing fields keep their `Default`, unknown ones are an error.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CityGenParams {
     pub lots_x: usize, pub lots_z: usize,
     pub lot_w: f32, pub lot_d: f32, pub lot_gap: f32,
     pub road_w_minor: f32, pub road_w_major: f32, pub major_every: usize,
     pub blocks_per_chunk_x: usize, pub blocks_per_chunk_z: usize,
+    #[serde(with = "seed_serde")]
     pub seed: u64,
 }
 
+/// TOML integers are i64: seeds past `i64::MAX` are written as a `"0x…"`
+/// string, and a string (hex or decimal) is read back as well as an integer.
+mod seed_serde {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, s: S) -> Result<S::Ok, S::Error> {
+        match i64::try_from(*seed) {
+            Ok(v) => s.serialize_i64(v),
+            Err(_) => s.serialize_str(&format!("{seed:#x}")),
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seed { Int(u64), Text(String) }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+        match Seed::deserialize(d).map_err(|_| D::Error::custom("seed must be a non-negative integer or a \"0x…\" string"))? {
+            Seed::Int(v) => Ok(v),
+            Seed::Text(t) => {
+                let t = t.replace('_', "");
+                match t.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => t.parse(),
+                }.map_err(|e| D::Error::custom(format!("seed {t:?}: {e}")))
+            }
+        }
+    }
+}
+
+/// The demo city: 3×3 lots per block, 8×8 blocks per chunk, a major road
+/// every 6 blocks.
+impl Default for CityGenParams {
+    fn default() -> Self {
+        Self {
+            lots_x: 3, lots_z: 3,
+            lot_w: 3.0, lot_d: 3.0, lot_gap: 0.4,
+            road_w_minor: 3.0, road_w_major: 8.0, major_every: 6,
+            blocks_per_chunk_x: 8, blocks_per_chunk_z: 8,
+            seed: 0xA11CE,
+        }
+    }
+}
+
+impl CityGenParams {
+    /// Reject layouts the designer can't build: no lots or blocks, lots
+    /// without area, negative or non-finite gaps and road widths.
+    pub fn validate(&self) -> Result<(), String> {
+        let counts = [("lots_x", self.lots_x), ("lots_z", self.lots_z),
+                      ("blocks_per_chunk_x", self.blocks_per_chunk_x), ("blocks_per_chunk_z", self.blocks_per_chunk_z)];
+        if let Some((name, _)) = counts.iter().find(|(_, n)| *n == 0) {
+            return Err(format!("{name} must be at least 1"));
+        }
+        for (name, v) in [("lot_w", self.lot_w), ("lot_d", self.lot_d)] {
+            if !(v.is_finite() && v > 0.0) { return Err(format!("{name} must be a positive size, got {v}")); }
+        }
+        for (name, v) in [("lot_gap", self.lot_gap), ("road_w_minor", self.road_w_minor), ("road_w_major", self.road_w_major)] {
+            if !(v.is_finite() && v >= 0.0) { return Err(format!("{name} must be finite and not negative, got {v}")); }
+        }
+        Ok(())
+    }
+
+    /// The params as a TOML table (what `from_toml_str` reads back).
+    pub fn to_toml_str(&self) -> String {
+        toml::to_string(self).expect("a table of numbers always serializes")
+    }
+
+    /// Parse a TOML city file; missing keys keep their `Default`, unknown
+    /// keys are an error, and the result must pass `validate`.
+    pub fn from_toml_str(s: &str) -> Result<Self, String> {
+        let p: Self = toml::from_str(s).map_err(|e| e.to_string())?;
+        p.validate()?;
+        Ok(p)
+    }
+
+    /// `from_toml_str` on the contents of `path`.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::from_toml_str(&text).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// Stable FNV-1a hash of every field (floats by bit pattern).
+    pub fn fingerprint(&self) -> u64 {
+        let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut eat = |v: u64| for b in v.to_le_bytes() { h = (h ^ b as u64).wrapping_mul(0x0100_0000_01b3); };
+        for u in [self.lots_x, self.lots_z, self.major_every, self.blocks_per_chunk_x, self.blocks_per_chunk_z] {
+            eat(u as u64);
+        }
+        for f in [self.lot_w, self.lot_d, self.lot_gap, self.road_w_minor, self.road_w_major] {
+            eat(f.to_bits() as u64);
+        }
+        eat(self.seed);
+        h
+    }
+}
+
 // world metric sizes
 pub fn block_world_span(p: &CityGenParams) -> (f32,f32) {
     let block_w = p.lots_x as f32 * p.lot_w + (p.lots_x-1) as f32 * p.lot_gap + p.road_w_minor;
@@ -40,8 +178,100 @@ pub fn chunk_world_span(p: &CityGenParams) -> (f32,f32) {
     (w, d)
 }
 
-#[derive(Hash, Eq, PartialEq, Copy, Clone)]
-pub(crate) struct ChunkKey(pub i32, pub i32);
+/// Smallest chunk radius along one axis (chunks of `span` meters) that keeps
+/// everything within `cull` meters of the viewer loaded.  The viewer can stand
+/// anywhere inside its own chunk, so `ceil(cull / span)` chunks are needed on
+/// each side.
+pub fn min_chunk_radius(cull: f32, span: f32) -> i32 {
+    ((cull.max(0.0) / span.max(1e-3)).ceil() as i32).max(1)
+}
+
+/// Lot centres along one axis of a chunk, relative to the chunk centre, in
+/// the order `RuleDesigner` lays them out (major-road blocks left out).
+fn axis_lot_centers(p: &CityGenParams, blocks: usize, lots: usize, lot: f32, block: f32, span: f32) -> Vec<f32> {
+    let mut out = Vec::with_capacity(blocks * lots);
+    for bi in 0..blocks {
+        let mut start = -0.5 * span + bi as f32 * block + p.road_w_minor * 0.5;
+        if p.major_every > 0 {
+            if bi % p.major_every == 0 { continue; }
+            start += (p.road_w_major - p.road_w_minor) * (bi / p.major_every) as f32;
+        }
+        out.extend((0..lots).map(|l| start + l as f32 * (lot + p.lot_gap) + lot * 0.5));
+    }
+    out
+}
+
+/// Nearest lot centre to the design-space `pos` (x/z; `y` is kept), so
+/// edited buildings line up with the designed ones.  Lots are laid out the
+/// same in every chunk, and major-road blocks have none.
+pub fn snap_to_grid(p: &CityGenParams, pos: Vector3<f32>) -> Vector3<f32> {
+    let (bw, bd) = block_world_span(p);
+    let (cw, cd) = chunk_world_span(p);
+    let snap = |v: f32, span: f32, centers: &[f32]| {
+        let c = (v / span).round();
+        // the nearest lot may be across the chunk seam
+        (-1..=1).flat_map(|k| centers.iter().map(move |&o| (c + k as f32) * span + o))
+            .min_by(|a, b| (a - v).abs().total_cmp(&(b - v).abs()))
+            .unwrap_or(v)
+    };
+    let xs = axis_lot_centers(p, p.blocks_per_chunk_x, p.lots_x, p.lot_w, bw, cw);
+    let zs = axis_lot_centers(p, p.blocks_per_chunk_z, p.lots_z, p.lot_d, bd, cd);
+    Vector3::new(snap(pos.x, cw, &xs), pos.y, snap(pos.z, cd, &zs))
+}
+
+/// Most store reads in flight at once when `async_store` is on.
+pub const MAX_PENDING_LOADS: usize = 64;
+
+/// Gap (m) `resolve_collision` leaves between the camera sphere and a wall.
+pub const COLLISION_SKIN: f32 = 1e-3;
+
+/// Upper bound on mutation ticks simulated by one `mutate_near` call.
+pub const MAX_MUTATION_TICKS: u32 = 10;
+
+/// Chunks around each viewer that live mutations reach (`step`, the app).
+pub const MUTATION_RADIUS: i32 = 1;
+
+#[derive(Hash, Eq, PartialEq, Copy, Clone, Debug)]
+pub struct ChunkKey(pub i32, pub i32);
+
+/// `mutation_snapshot` layout: magic, params fingerprint (u64), delta count
+/// (u32), then per delta cx, cz (i32), index (u32), archetype (u16), scale
+/// (3×f32) and design-space centre y (f32); then a whole-chunk count (u32)
+/// and per chunk cx, cz (i32), byte length (u32) and its buildings as
+/// bincode `Vec<PlacementDisk>`.  Integers are little-endian.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"CDIF";
+const SNAPSHOT_HEADER: usize = 4 + 8 + 4;
+const DELTA_LEN: usize = 4 + 4 + 4 + 2 + 12 + 4;
+const WHOLE_HEADER: usize = 4 + 4 + 4;
+/// How far (m) a building may sit from the designer's footprint before its
+/// chunk counts as rearranged; covers rounding through the origin shift.
+const MOVED_EPS: f32 = 0.01;
+
+/// One building's mutated state from a snapshot, for a chunk not loaded yet.
+#[derive(Copy, Clone, Debug)]
+struct Delta {
+    idx: usize,
+    archetype_id: u16,
+    scale: Vector3<f32>,
+    y: f32, // design space
+}
+
+/// Where a newly loaded chunk came from (passed to `on_chunk_loaded`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChunkSource { Designed, Store, Override }
+
+pub type ChunkLoadedHook  = Box<dyn FnMut(ChunkKey, ChunkSource, &[RuntimePlacement])>;
+pub type ChunkEvictedHook = Box<dyn FnMut(ChunkKey)>;
+pub type ChunkMutatedHook = Box<dyn FnMut(ChunkKey, usize)>;
+
+/// Snapshot of what the world keeps in memory (`ChunkManager::stats`).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorldStats {
+    pub loaded_chunks: usize,
+    pub placements:    usize,
+    /// Placement storage (by capacity) plus the `loaded` table's own slots.
+    pub estimated_bytes: usize,
+}
 
 fn wrap_coord(c: i32, min_c: i32, max_c: i32) -> i32 {
     let size = max_c - min_c + 1;
@@ -60,41 +290,227 @@ fn wrap_key(cx: i32, cz: i32, bounds: (i32,i32,i32,i32)) -> ChunkKey {
 
 pub struct ChunkManager {
     pub params: CityGenParams,
-    pub chunk_radius: i32,
+    // load window half-size in chunks along x / z; must cover the render
+    // cull (see `cover_cull`); `ensure_for_viewers` evicts outside it
+    pub chunk_radius_x: i32,
+    pub chunk_radius_z: i32,
     pub bounds: (i32,i32,i32,i32), // inclusive [minx..maxx]x[minz..maxz]
+    // torus world: windows past `bounds` wrap around; when false the city
+    // ends at `bounds` and nothing is loaded beyond it
+    pub wrap: bool,
     pub loaded: HashMap<ChunkKey, Vec<RuntimePlacement>>,
+    // designs every chunk that is neither overridden nor in the store
+    // (`RuleDesigner` on `params` until `set_designer`)
+    designer: Box<dyn CityDesigner>,
+    // district layout handed to the designer with every chunk
+    pub districts: DistrictMap,
     viewers: HashMap<ViewerId, (f32,f32)>, // x,z in meters
+    velocities: HashMap<ViewerId, (f32,f32)>, // x,z in m/s (prefetch)
+
+    // at most this many chunks are loaded/designed per `ensure_for_viewers`
+    pub gen_budget: usize,
+    // ...and none once this much wall-clock time (ms) went into loading in
+    // that call (`set_generation_budget_ms`)
+    gen_budget_ms: Option<f32>,
+    // look-ahead (s): the window is also ensured around `pos + vel * prefetch_secs`
+    pub prefetch_secs: f32,
 
-    // baked store path or in-browser storage key prefix
-    pub store_prefix: String,
+    // where chunks persist; `new` picks the platform store for its prefix
+    pub store: StoreBackend,
+    // write freshly designed chunks to the store right away so later loads
+    // hit disk; when false designed chunks are ephemeral until edited+flushed
+    pub bake_on_miss: bool,
+    // read the store off the frame through a `StoreLoader`; at most
+    // `store_budget` finished reads are applied per `ensure_for_viewers`
+    pub async_store: bool,
+    pub store_budget: usize,
+    loader: Option<StoreLoader>,
+    // requested from `loader` but not applied yet: neither loaded nor designed meanwhile
+    pending: HashSet<ChunkKey>,
+    // store misses still `pending`, waiting for generation budget to design them
+    misses: VecDeque<ChunkKey>,
 
     // torus world span (meters)
     world_span_x: f32,
     world_span_z: f32,
+
+    // accumulated floating-origin shift (local = design - origin_shift)
+    origin_shift: Vector3<f32>,
+
+    // chunks whose nearest edge is farther than this are drawn as one baked
+    // mesh instead of per-instance; `f32::INFINITY` disables baking
+    pub bake_distance: f32,
+    // debug: only this chunk is drawn and picked (see `shows`); with
+    // `isolate_loads` it is also the only chunk `ensure_for_viewers` loads
+    // (wherever the viewers are), while chunks already loaded just stay hidden
+    pub isolated: Option<ChunkKey>,
+    pub isolate_loads: bool,
+    // bumped on every mutation so a stale bake can be detected
+    revisions: HashMap<ChunkKey, u32>,
+    // spatial index per loaded chunk, built when it is loaded (chunks put
+    // straight into `loaded` have none and are scanned linearly)
+    grids: HashMap<ChunkKey, ChunkGrid>,
+    // where each loaded chunk came from (same caveat as `grids`)
+    sources: HashMap<ChunkKey, ChunkSource>,
+    // hand-authored chunks (design space), used instead of the store and
+    // the designer whenever the chunk is (re)loaded; kept across eviction
+    overrides: HashMap<ChunkKey, Vec<RuntimePlacement>>,
+    // `apply_snapshot` deltas for chunks not loaded yet, applied on load
+    held_deltas: HashMap<ChunkKey, Vec<Delta>>,
+    held_chunks: HashMap<ChunkKey, Vec<PlacementDisk>>,
+    // loaded chunks edited since they were last written to the store
+    dirty: HashSet<ChunkKey>,
+
+    // live mutations run on a fixed clock (ticks per second, see `mutate_near`)
+    pub mutation_hz: f32,
+    mutation_acc:  f32,
+    mutation_tick: u64,
+    mutation_debt: f32,   // fractional buildings owed to the next tick
+
+    // embedder hooks: fired after a chunk enters / leaves `loaded`, and
+    // after a live or network mutation edits placement `idx` of a chunk
+    pub on_chunk_loaded:  Option<ChunkLoadedHook>,
+    pub on_chunk_evicted: Option<ChunkEvictedHook>,
+    pub on_mutated:       Option<ChunkMutatedHook>,
 }
 
 impl ChunkManager {
     pub fn new(params: CityGenParams, chunk_radius: i32, bounds: (i32,i32,i32,i32), bake_on_miss: bool, store_prefix: &str) -> Self {
+        Self { bake_on_miss, ..Self::with_store(params, chunk_radius, bounds, StoreBackend::platform(store_prefix)) }
+    }
+
+    /// Like `new`, reading and writing chunks through `store`; generated
+    /// chunks are saved on a miss unless it is `StoreBackend::None`.
+    pub fn with_store(params: CityGenParams, chunk_radius: i32, bounds: (i32,i32,i32,i32), store: StoreBackend) -> Self {
         let (cw, cd) = chunk_world_span(&params);
         Self {
+            designer: Box::new(RuleDesigner::new(params.clone())),
+            districts: DistrictMap::default(),
             params,
-            chunk_radius: chunk_radius.max(1),
+            chunk_radius_x: chunk_radius.max(1),
+            chunk_radius_z: chunk_radius.max(1),
             bounds,
+            wrap: true,
             loaded: HashMap::new(),
             viewers: HashMap::new(),
-            store_prefix: store_prefix.to_string(),
+            velocities: HashMap::new(),
+            gen_budget: usize::MAX,
+            gen_budget_ms: None,
+            prefetch_secs: 0.0,
+            bake_on_miss: store != StoreBackend::None,
+            store,
+            async_store: false,
+            store_budget: 8,
+            loader: None,
+            pending: HashSet::new(),
+            misses: VecDeque::new(),
             world_span_x: cw * ((bounds.1 - bounds.0 + 1) as f32),
             world_span_z: cd * ((bounds.3 - bounds.2 + 1) as f32),
+            origin_shift: Vector3::new(0.0, 0.0, 0.0),
+            bake_distance: f32::INFINITY,
+            isolated: None,
+            isolate_loads: false,
+            revisions: HashMap::new(),
+            grids: HashMap::new(),
+            sources: HashMap::new(),
+            overrides: HashMap::new(),
+            held_deltas: HashMap::new(),
+            held_chunks: HashMap::new(),
+            dirty: HashSet::new(),
+            mutation_hz: 10.0,
+            mutation_acc: 0.0,
+            mutation_tick: 0,
+            mutation_debt: 0.0,
+            on_chunk_loaded: None,
+            on_chunk_evicted: None,
+            on_mutated: None,
         }
     }
 
     #[inline]
     pub fn world_span(&self) -> (f32,f32) { (self.world_span_x, self.world_span_z) }
 
+    /// Design-space rectangle covered by `bounds`: `(min_x, max_x, min_z, max_z)` in meters.
+    pub fn world_rect(&self) -> (f32,f32,f32,f32) {
+        let (cw, cd) = chunk_world_span(&self.params);
+        let (minx, maxx, minz, maxz) = self.bounds;
+        ((minx as f32 - 0.5) * cw, (maxx as f32 + 0.5) * cw, (minz as f32 - 0.5) * cd, (maxz as f32 + 0.5) * cd)
+    }
+
+    /// Key for window cell `(cx, cz)`: wrapped on a torus, `None` past the
+    /// edge of a finite world.
+    fn window_key(&self, cx: i32, cz: i32) -> Option<ChunkKey> {
+        if self.wrap { return Some(wrap_key(cx, cz, self.bounds)); }
+        let (minx, maxx, minz, maxz) = self.bounds;
+        ((minx..=maxx).contains(&cx) && (minz..=maxz).contains(&cz)).then_some(ChunkKey(cx, cz))
+    }
+
+    /// Square load window: sets both `chunk_radius_x` and `chunk_radius_z`.
+    pub fn set_chunk_radius(&mut self, r: i32) {
+        self.chunk_radius_x = r.max(1);
+        self.chunk_radius_z = r.max(1);
+    }
+
+    /// Farthest distance (m) from the viewer that is guaranteed to be loaded.
+    pub fn loaded_distance(&self) -> f32 {
+        let (cw, cd) = chunk_world_span(&self.params);
+        (self.chunk_radius_x as f32 * cw).min(self.chunk_radius_z as f32 * cd)
+    }
+
+    /// Make sure the render `cull` range never reaches unloaded chunks: an
+    /// axis radius that is too small is bumped (with a warning).
+    pub fn cover_cull(&mut self, cull: f32) {
+        let (cw, cd) = chunk_world_span(&self.params);
+        let (nx, nz) = (min_chunk_radius(cull, cw), min_chunk_radius(cull, cd));
+        if self.chunk_radius_x < nx || self.chunk_radius_z < nz {
+            warn!("chunk radius {}x{} only loads {:.0} m but cull is {:.0} m; raising to {}x{}",
+                  self.chunk_radius_x, self.chunk_radius_z, self.loaded_distance(), cull,
+                  self.chunk_radius_x.max(nx), self.chunk_radius_z.max(nz));
+            self.chunk_radius_x = self.chunk_radius_x.max(nx);
+            self.chunk_radius_z = self.chunk_radius_z.max(nz);
+        }
+    }
+
+    /// Smallest window covering `cull`; unlike `cover_cull` this also shrinks it.
+    pub fn fit_cull(&mut self, cull: f32) {
+        let (cw, cd) = chunk_world_span(&self.params);
+        self.chunk_radius_x = min_chunk_radius(cull, cw);
+        self.chunk_radius_z = min_chunk_radius(cull, cd);
+    }
+
+    pub fn stats(&self) -> WorldStats {
+        let placements = self.loaded.values().map(Vec::len).sum();
+        let heap: usize = self.loaded.values().map(|l| {
+            let stacks: usize = l.iter().filter_map(|p| p.stack.as_ref()).map(|s| s.len()).sum();
+            l.capacity() * std::mem::size_of::<RuntimePlacement>() + stacks * std::mem::size_of::<SubBox>()
+        }).sum();
+        // hashbrown: one (key, value) slot + one control byte per bucket
+        let table = self.loaded.capacity() * (std::mem::size_of::<(ChunkKey, Vec<RuntimePlacement>)>() + 1);
+        let grids: usize = self.grids.values().map(ChunkGrid::heap_bytes).sum::<usize>()
+            + self.grids.capacity() * (std::mem::size_of::<(ChunkKey, ChunkGrid)>() + 1);
+        WorldStats { loaded_chunks: self.loaded.len(), placements, estimated_bytes: heap + table + grids }
+    }
+
+    /// Short hash of `(seed, params)` prefixed to every stored chunk key.
+    pub fn store_namespace(&self) -> String {
+        let fp = self.params.fingerprint() as u32;
+        match self.designer.name() {
+            "" => format!("{fp:08x}"),
+            name => format!("{fp:08x}-{name}"),
+        }
+    }
+
     pub fn set_viewer(&mut self, id: ViewerId, world_x: f32, world_z: f32) {
         self.viewers.insert(id, (world_x, world_z));
     }
 
+    /// Viewer motion (m/s) used to prefetch chunks ahead of it; non-finite
+    /// input is dropped (the last velocity stays).
+    pub fn set_viewer_velocity(&mut self, id: ViewerId, vx: f32, vz: f32) {
+        if !vx.is_finite() || !vz.is_finite() { return; }
+        self.velocities.insert(id, (vx, vz));
+    }
+
     pub fn apply_shift(&mut self, off: Vector3<f32>) {
         // shift all loaded placements (keep camera-centered continuity)
         for list in self.loaded.values_mut() {
@@ -107,56 +523,672 @@ impl ChunkManager {
             v.0 -= off.x;
             v.1 -= off.z;
         }
+        self.origin_shift += off;
+    }
+
+    /// Undo every `apply_shift` so local = design again; returns the offset
+    /// applied.  The shift is snapped to exactly zero afterwards, dropping
+    /// the rounding the shifts accumulated.
+    pub fn reset_origin(&mut self) -> Vector3<f32> {
+        let off = -self.origin_shift;
+        self.apply_shift(off);
+        self.origin_shift = Vector3::new(0.0, 0.0, 0.0);
+        off
+    }
+
+    /// Switch to a new world seed: drops every loaded chunk so the next
+    /// `ensure_for_viewers` designs the new city around the viewers.
+    pub fn reseed(&mut self, seed: u64) {
+        self.drop_world();
+        self.params.seed = seed;
+    }
+
+    /// Design new chunks with `designer` (same seed, via `DesignContext`):
+    /// every loaded chunk is dropped so the next `ensure_for_viewers`
+    /// builds the city around the viewers again.  Overrides still win, and
+    /// so do chunks the store saved under this designer's namespace.
+    pub fn set_designer(&mut self, designer: Box<dyn CityDesigner>) {
+        self.drop_world();
+        self.designer = designer;
+    }
+
+    /// Evict everything loaded and forget per-world state keyed to it.
+    /// Edits are flushed first, so call this while the old seed and
+    /// designer (the store namespace) are still set; if the store fails
+    /// they are lost, with a warning.
+    fn drop_world(&mut self) {
+        if let Err(e) = self.flush() { warn!("saving edits before switching worlds failed: {e}"); }
+        let keys: Vec<ChunkKey> = self.loaded.keys().copied().collect();
+        for key in keys { self.unload(key); }
+        self.revisions.clear();
+        self.held_deltas.clear();
+        self.held_chunks.clear();
+        self.pending.clear(); // replies for the old namespace are dropped
+        self.misses.clear();
+    }
+
+    /// Author chunk `(cx, cz)` by hand: from now on it loads as exactly
+    /// `placements` (design-space centres) instead of coming from the store
+    /// or the designer.  A loaded copy is replaced right away; edits to it
+    /// last until it is evicted, then the override is applied again.
+    pub fn set_chunk_override(&mut self, cx: i32, cz: i32, placements: Vec<RuntimePlacement>) {
+        let key = wrap_key(cx, cz, self.bounds);
+        self.overrides.insert(key, placements);
+        self.pending.remove(&key); // a late store reply is dropped
+        if self.evict(key) { self.insert_override(key); }
+    }
+
+    /// Return `(cx, cz)` to procedural generation; a loaded copy is evicted
+    /// so the next `ensure_for_viewers` loads or designs it.  False if it
+    /// had no override.
+    pub fn clear_chunk_override(&mut self, cx: i32, cz: i32) -> bool {
+        let key = wrap_key(cx, cz, self.bounds);
+        if self.overrides.remove(&key).is_none() { return false; }
+        self.evict(key);
+        true
+    }
+
+    #[inline]
+    pub fn has_chunk_override(&self, key: ChunkKey) -> bool { self.overrides.contains_key(&key) }
+
+    /// How loaded chunk `key` got into `loaded`; None if it isn't loaded or
+    /// was inserted there directly.
+    #[inline]
+    pub fn chunk_source(&self, key: ChunkKey) -> Option<ChunkSource> { self.sources.get(&key).copied() }
+
+    fn insert_override(&mut self, key: ChunkKey) {
+        let shift = self.origin_shift;
+        let rt = self.overrides[&key].iter().map(|p| RuntimePlacement { center: p.center - shift, ..p.clone() }).collect();
+        self.insert_loaded(key, ChunkSource::Override, rt);
+    }
+
+    /// Drop a loaded chunk (fires `on_chunk_evicted`); false if it wasn't
+    /// loaded.  Unflushed edits are saved first, and if that fails the chunk
+    /// stays loaded (false, with a warning).  Edits to an overridden chunk
+    /// are dropped: it loads as its override again.
+    pub fn evict(&mut self, key: ChunkKey) -> bool {
+        if !self.loaded.contains_key(&key) { return false; }
+        if self.dirty.contains(&key) && self.sources.get(&key) != Some(&ChunkSource::Override)
+            && let Err(e) = self.save_loaded(key) {
+            warn!("keeping chunk {key:?} loaded: saving its edits failed: {e}");
+            return false;
+        }
+        self.unload(key)
+    }
+
+    /// `evict` without saving: unflushed edits go with the chunk.
+    fn unload(&mut self, key: ChunkKey) -> bool {
+        if self.loaded.remove(&key).is_none() { return false; }
+        self.grids.remove(&key);
+        self.sources.remove(&key);
+        self.dirty.remove(&key);
+        if let Some(hook) = self.on_chunk_evicted.as_mut() { hook(key); }
+        true
+    }
+
+    fn insert_loaded(&mut self, key: ChunkKey, source: ChunkSource, rt: Vec<RuntimePlacement>) {
+        if let Some(hook) = self.on_chunk_loaded.as_mut() { hook(key, source, &rt); }
+        let grid = self.build_grid(key, &rt);
+        self.grids.insert(key, grid);
+        self.sources.insert(key, source);
+        self.loaded.insert(key, rt);
+        if source == ChunkSource::Override { return; }
+        if let Some(disk) = self.held_chunks.remove(&key) { self.replace_chunk(key, &disk); }
+        if let Some(deltas) = self.held_deltas.remove(&key) {
+            for d in deltas { self.apply_delta(key, d); }
+        }
     }
 
+    /// Lot-pitch grid over the chunk's design-space footprint.
+    fn build_grid(&self, key: ChunkKey, list: &[RuntimePlacement]) -> ChunkGrid {
+        let (cw, cd) = chunk_world_span(&self.params);
+        let origin = ((key.0 as f32 - 0.5) * cw, (key.1 as f32 - 0.5) * cd);
+        let cell = self.params.lot_w.max(self.params.lot_d) + self.params.lot_gap;
+        let shift = self.origin_shift;
+        ChunkGrid::build(origin, (cw, cd), cell, list.iter().map(|p| (p.center.x + shift.x, p.center.z + shift.z)))
+    }
+
+    /// The grid of `key`, if it still matches the placements.
+    fn grid(&self, key: ChunkKey) -> Option<&ChunkGrid> {
+        let g = self.grids.get(&key)?;
+        (g.len() == self.loaded.get(&key)?.len()).then_some(g)
+    }
+
+    #[inline]
+    pub fn origin_shift(&self) -> Vector3<f32> { self.origin_shift }
+
+    /// The chunk under local-space `(x, z)`; None past the edge of a finite world.
+    pub fn chunk_at(&self, x: f32, z: f32) -> Option<ChunkKey> {
+        let (cx, cz) = self.world_to_chunk(x, z);
+        self.window_key(cx, cz)
+    }
+
+    /// False for every chunk but `isolated`, when set.
+    #[inline]
+    pub fn shows(&self, key: ChunkKey) -> bool { self.isolated.is_none_or(|k| k == key) }
+
+    // ---------- baking ----------
+    /// Invalidate any bake of `key` and mark it for the next `flush` (call
+    /// after editing its placements).
+    pub fn mark_mutated(&mut self, key: ChunkKey) {
+        *self.revisions.entry(key).or_insert(0) += 1;
+        self.dirty.insert(key);
+    }
+    /// Tell `on_mutated` that placement `idx` of `key` changed (call after
+    /// `mark_mutated` when editing a single building).
+    pub fn notify_mutated(&mut self, key: ChunkKey, idx: usize) {
+        if let Some(hook) = self.on_mutated.as_mut() { hook(key, idx); }
+    }
+
+    #[inline]
+    pub fn revision(&self, key: ChunkKey) -> u32 {
+        self.revisions.get(&key).copied().unwrap_or(0)
+    }
+
+    #[inline]
+    pub fn is_dirty(&self, key: ChunkKey) -> bool { self.dirty.contains(&key) }
+
+    /// Write every dirty loaded chunk to `store` and clear its flag.  Stops at the first failure; chunks
+    /// not yet written stay dirty.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        let mut keys: Vec<ChunkKey> = self.dirty.iter().copied().collect();
+        keys.sort_by_key(|k| (k.0, k.1));
+        for key in keys {
+            if self.loaded.contains_key(&key) { self.save_loaded(key)?; }
+            self.dirty.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Write one loaded chunk to the store under the current namespace.
+    fn save_loaded(&self, key: ChunkKey) -> std::io::Result<()> {
+        if self.store == StoreBackend::None { return Ok(()); }
+        let Some(list) = self.loaded.get(&key) else { return Ok(()) };
+        let file = ChunkFile {
+            namespace: self.store_namespace(),
+            cx: key.0, cz: key.1,
+            buildings: list.iter().map(|p| PlacementDisk::from_runtime(p, self.origin_shift)).collect(),
+        };
+        self.store.save_chunk(&file)
+    }
+
+    /// Local-space AABB `(center, half)` of a loaded chunk; height from its tallest building.
+    pub fn chunk_aabb(&self, key: ChunkKey) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let list = self.loaded.get(&key)?;
+        let (cw, cd) = chunk_world_span(&self.params);
+        // 2·centre bounds the top of any box standing on or above the ground
+        let top = list.iter().flat_map(|p| p.boxes()).fold(0.0f32, |m, (c, _, _)| m.max(c.y * 2.0));
+        let center = Vector3::new(key.0 as f32 * cw, top * 0.5, key.1 as f32 * cd) - self.origin_shift;
+        Some((Vector3::new(center.x, top * 0.5, center.z), Vector3::new(cw * 0.5, top * 0.5, cd * 0.5)))
+    }
+
+    /// Buildings whose centre lies within `radius` m of `center` on the
+    /// ground plane (local space), sorted by chunk then index.  Chunks whose
+    /// footprint misses the circle are skipped without looking at them.
+    pub fn buildings_in_radius(&self, center: Vector3<f32>, radius: f32) -> Vec<(ChunkKey, usize)> {
+        let mut keys: Vec<ChunkKey> = self.loaded.keys().copied().filter(|k| {
+            let Some((c, h)) = self.chunk_aabb(*k) else { return false };
+            let dx = ((center.x - c.x).abs() - h.x).max(0.0);
+            let dz = ((center.z - c.z).abs() - h.z).max(0.0);
+            dx * dx + dz * dz <= radius * radius
+        }).collect();
+        keys.sort_by_key(|k| (k.0, k.1));
+        let r2 = radius * radius;
+        let inside = |p: &RuntimePlacement| {
+            let (dx, dz) = (p.center.x - center.x, p.center.z - center.z);
+            dx * dx + dz * dz <= r2
+        };
+        let (sx, sz) = (center.x + self.origin_shift.x, center.z + self.origin_shift.z);
+        let mut out = Vec::new();
+        for k in keys {
+            let list = &self.loaded[&k];
+            match self.grid(k) {
+                Some(g) => {
+                    let start = out.len();
+                    out.extend(g.query_rect((sx - radius, sz - radius), (sx + radius, sz + radius))
+                        .filter(|&i| inside(&list[i])).map(|i| (k, i)));
+                    out[start..].sort_by_key(|e| e.1);
+                }
+                None => out.extend(list.iter().enumerate().filter(|(_, p)| inside(p)).map(|(i, _)| (k, i))),
+            }
+        }
+        out
+    }
+
+    // ---------- collision ----------
+    /// Move a sphere of `radius` from `prev` to `next` (local space) without
+    /// entering any building box: each axis is swept on its own, so a
+    /// blocked move slides along the wall instead of stopping.  Starting
+    /// inside a box first pushes out through its nearest side.  Boxes are
+    /// found through the spatial grid within two lot pitches of the path.
+    pub fn resolve_collision(&self, prev: Vector3<f32>, next: Vector3<f32>, radius: f32, assets: &AssetLibrary) -> Vector3<f32> {
+        let pitch = self.params.lot_w.max(self.params.lot_d) + self.params.lot_gap;
+        let reach = (next - prev).magnitude() * 0.5 + radius + 2.0 * pitch;
+        let r = Vector3::new(radius, radius, radius);
+        let boxes: Vec<(Vector3<f32>, Vector3<f32>)> = self.buildings_in_radius((prev + next) * 0.5, reach).into_iter()
+            .flat_map(|(k, i)| self.loaded[&k][i].boxes())
+            .map(|(c, s, id)| {
+                let b = assets.base_half(id as usize);
+                let half = Vector3::new(b.x * s.x, b.y * s.y, b.z * s.z) + r;
+                (c - half, c + half)
+            })
+            .collect();
+
+        let inside = |p: Vector3<f32>, (lo, hi): (Vector3<f32>, Vector3<f32>)| (0..3).all(|a| p[a] > lo[a] && p[a] < hi[a]);
+        let mut p = prev;
+        // spawned inside: out through the nearest face, a few rounds for overlapping boxes
+        for _ in 0..4 {
+            let Some(&(lo, hi)) = boxes.iter().find(|b| inside(p, **b)) else { break };
+            let (axis, to) = (0..3).flat_map(|a| [(a, lo[a] - COLLISION_SKIN), (a, hi[a] + COLLISION_SKIN)])
+                .min_by(|x, y| (x.1 - p[x.0]).abs().total_cmp(&(y.1 - p[y.0]).abs())).unwrap();
+            p[axis] = to;
+        }
+        for axis in [0, 2, 1] {
+            let (from, mut to) = (p[axis], p[axis] + next[axis] - prev[axis]);
+            for &(lo, hi) in &boxes {
+                let across = (0..3).filter(|&a| a != axis).all(|a| p[a] > lo[a] && p[a] < hi[a]);
+                if !across { continue; }
+                if to > from && from <= lo[axis] && to > lo[axis] { to = to.min(lo[axis] - COLLISION_SKIN); }
+                if to < from && from >= hi[axis] && to < hi[axis] { to = to.max(hi[axis] + COLLISION_SKIN); }
+            }
+            p[axis] = to;
+        }
+        p
+    }
+
+    // ---------- editing ----------
+    /// Nearest building hit by the local-space ray `origin + t·dir` (`dir`
+    /// unit length) within `max_dist`: `(chunk, index, t)`.
+    pub fn pick_ray(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_dist: f32, assets: &AssetLibrary)
+        -> Option<(ChunkKey, usize, f32)> {
+        let mut best: Option<(ChunkKey, usize, f32)> = None;
+        let mut keys: Vec<ChunkKey> = self.loaded.keys().copied().filter(|k| self.shows(*k)).collect();
+        keys.sort_by_key(|k| (k.0, k.1));
+        for key in keys {
+            let Some((c, h)) = self.chunk_aabb(key) else { continue };
+            let Some(t) = ray_aabb(origin, dir, c, h) else { continue };
+            if t > max_dist.min(best.map_or(f32::INFINITY, |b| b.2)) { continue; }
+            for (i, p) in self.loaded[&key].iter().enumerate() {
+                // any of its boxes picks the building
+                for (c, s, id) in p.boxes() {
+                    let b = assets.base_half(id as usize);
+                    let half = Vector3::new(b.x * s.x, b.y * s.y, b.z * s.z);
+                    if let Some(t) = ray_aabb(origin, dir, c, half)
+                        && t <= max_dist && best.is_none_or(|b| t < b.2) {
+                        best = Some((key, i, t));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Append `p` to the loaded chunk `key`; returns its index, `None` if
+    /// `key` isn't loaded.  `p.center` must lie in that chunk (local space).
+    pub fn insert_building(&mut self, key: ChunkKey, p: RuntimePlacement) -> Option<usize> {
+        let list = self.loaded.get_mut(&key)?;
+        list.push(p);
+        let idx = list.len() - 1;
+        self.edited(key, idx);
+        Some(idx)
+    }
+
+    /// Add a building at its local-space `center` to whichever chunk that
+    /// falls in, loading the chunk first if needed (synchronously, even with
+    /// `async_store`).  `None` only past the edge of a finite world.
+    pub fn place_building(&mut self, mut p: RuntimePlacement, assets: &AssetLibrary)
+        -> Option<(ChunkKey, usize)> {
+        let (cx, cz) = self.world_to_chunk(p.center.x, p.center.z);
+        let key = self.window_key(cx, cz)?;
+        // a wrapped image of `key`: move the building into its canonical copy
+        let (cw, cd) = chunk_world_span(&self.params);
+        p.center.x += (key.0 - cx) as f32 * cw;
+        p.center.z += (key.1 - cz) as f32 * cd;
+        if !self.loaded.contains_key(&key) {
+            self.pending.remove(&key); // a late store reply is dropped
+            self.ensure_chunk(key.0, key.1, assets);
+        }
+        Some((key, self.insert_building(key, p)?))
+    }
+
+    /// Remove building `idx` of `key` (later indices shift down by one).
+    pub fn remove_building(&mut self, key: ChunkKey, idx: usize) -> Option<RuntimePlacement> {
+        let list = self.loaded.get_mut(&key)?;
+        if idx >= list.len() { return None; }
+        let p = list.remove(idx);
+        self.edited(key, idx);
+        Some(p)
+    }
+
+    /// After adding/removing placements: re-index, rebake, save, notify.
+    fn edited(&mut self, key: ChunkKey, idx: usize) {
+        let grid = self.build_grid(key, &self.loaded[&key]);
+        self.grids.insert(key, grid);
+        self.mark_mutated(key);
+        self.notify_mutated(key, idx);
+    }
+
+    // ---------- join sync ----------
+    /// Every loaded building whose archetype or scale differs from what
+    /// the designer makes for its chunk (i.e. live / network mutations), for a
+    /// client joining mid-session to `apply_snapshot` after regenerating.
+    /// Chunks with buildings added, removed or moved (which index deltas
+    /// can't express) are sent whole; overridden chunks are left out.
+    pub fn mutation_snapshot(&mut self, assets: &AssetLibrary) -> Vec<u8> {
+        let mut keys: Vec<ChunkKey> = self.loaded.keys().copied().filter(|k| !self.overrides.contains_key(k)).collect();
+        keys.sort_by_key(|k| (k.0, k.1));
+        let mut out = Vec::with_capacity(SNAPSHOT_HEADER);
+        out.extend_from_slice(SNAPSHOT_MAGIC);
+        out.extend_from_slice(&self.params.fingerprint().to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        let mut count = 0u32;
+        let mut whole = Vec::new();
+        let shift = self.origin_shift;
+        for key in keys {
+            let ctx = DesignContext { districts: self.districts, ..DesignContext::new(key.0, key.1, self.params.seed) };
+            let base = self.designer.design_chunk(&ctx, assets);
+            let list = &self.loaded[&key];
+            let same_lot = |p: &RuntimePlacement, b: &Placement| {
+                let c = p.center + shift;
+                (c.x - b.center.x).abs() <= MOVED_EPS && (c.z - b.center.z).abs() <= MOVED_EPS && p.stack == b.stack
+            };
+            if base.len() != list.len() || !list.iter().zip(&base).all(|(p, b)| same_lot(p, b)) {
+                let disk: Vec<PlacementDisk> = list.iter().map(|p| PlacementDisk::from_runtime(p, shift)).collect();
+                whole.push((key, bincode::serialize(&disk).expect("bincode serialize")));
+                continue;
+            }
+            for (i, (p, b)) in list.iter().zip(&base).enumerate() {
+                if p.archetype_id == b.archetype_id && p.scale == b.scale { continue; }
+                out.extend_from_slice(&key.0.to_le_bytes());
+                out.extend_from_slice(&key.1.to_le_bytes());
+                out.extend_from_slice(&(i as u32).to_le_bytes());
+                out.extend_from_slice(&p.archetype_id.to_le_bytes());
+                for v in [p.scale.x, p.scale.y, p.scale.z, p.center.y + self.origin_shift.y] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                count += 1;
+            }
+        }
+        out[12..16].copy_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&(whole.len() as u32).to_le_bytes());
+        for (key, body) in whole {
+            out.extend_from_slice(&key.0.to_le_bytes());
+            out.extend_from_slice(&key.1.to_le_bytes());
+            out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            out.extend(body);
+        }
+        out
+    }
+
+    /// Apply a peer's `mutation_snapshot`: deltas and whole chunks for
+    /// loaded chunks now (returns how many buildings changed), the rest when
+    /// their chunk loads.  Rejects a snapshot of another seed / layout or
+    /// with a bad length before applying any of it; deltas and chunks naming
+    /// an archetype `assets` doesn't have are skipped.
+    pub fn apply_snapshot(&mut self, assets: &AssetLibrary, buf: &[u8]) -> Result<usize, String> {
+        if buf.len() < SNAPSHOT_HEADER || &buf[..4] != SNAPSHOT_MAGIC { return Err("not a world snapshot".into()); }
+        let u32_at = |o: usize| u32::from_le_bytes(buf[o..o + 4].try_into().unwrap());
+        let f32_at = |o: usize| f32::from_le_bytes(buf[o..o + 4].try_into().unwrap());
+        if u64::from_le_bytes(buf[4..12].try_into().unwrap()) != self.params.fingerprint() {
+            return Err("snapshot is of a different city (seed or params)".into());
+        }
+        let count = u32_at(12) as usize;
+        let short = || format!("snapshot length {} doesn't fit its {count} deltas and chunks", buf.len());
+        let deltas_end = count.checked_mul(DELTA_LEN).and_then(|n| n.checked_add(SNAPSHOT_HEADER + 4))
+            .filter(|&end| end <= buf.len()).ok_or_else(short)?;
+        let mut whole = Vec::new();
+        let mut o = deltas_end;
+        for _ in 0..u32_at(deltas_end - 4) {
+            if buf.len() - o < WHOLE_HEADER { return Err(short()); }
+            let key = ChunkKey(u32_at(o) as i32, u32_at(o + 4) as i32);
+            let body = buf.get(o + WHOLE_HEADER..).and_then(|b| b.get(..u32_at(o + 8) as usize)).ok_or_else(short)?;
+            let disk: Vec<PlacementDisk> = bincode::deserialize(body).map_err(|e| format!("corrupt chunk in snapshot: {e}"))?;
+            whole.push((key, disk));
+            o += WHOLE_HEADER + body.len();
+        }
+        if o != buf.len() { return Err(short()); }
+
+        let mut applied = 0;
+        let known = |id: u16| (id as usize) < assets.archetypes.len();
+        for (key, disk) in whole {
+            let ok = disk.iter().all(|p| known(p.archetype_id) && p.stack.iter().all(|s| known(s.archetype_id)));
+            if self.overrides.contains_key(&key) || !ok { continue; }
+            if self.loaded.contains_key(&key) {
+                applied += disk.len();
+                self.replace_chunk(key, &disk);
+            } else {
+                self.held_chunks.insert(key, disk);
+            }
+        }
+        for o in (SNAPSHOT_HEADER..deltas_end - 4).step_by(DELTA_LEN) {
+            let key = ChunkKey(u32_at(o) as i32, u32_at(o + 4) as i32);
+            let d = Delta {
+                idx: u32_at(o + 8) as usize,
+                archetype_id: u16::from_le_bytes([buf[o + 12], buf[o + 13]]),
+                scale: Vector3::new(f32_at(o + 14), f32_at(o + 18), f32_at(o + 22)),
+                y: f32_at(o + 26),
+            };
+            if self.overrides.contains_key(&key) || !known(d.archetype_id) { continue; }
+            if self.loaded.contains_key(&key) {
+                applied += self.apply_delta(key, d) as usize;
+            } else {
+                self.held_deltas.entry(key).or_default().push(d);
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Swap loaded chunk `key`'s buildings for a snapshot's `disk` copy.
+    fn replace_chunk(&mut self, key: ChunkKey, disk: &[PlacementDisk]) {
+        let rt: Vec<RuntimePlacement> = disk.iter().map(|p| p.to_runtime(self.origin_shift)).collect();
+        let grid = self.build_grid(key, &rt);
+        self.grids.insert(key, grid);
+        self.loaded.insert(key, rt);
+        self.mark_mutated(key);
+        for idx in 0..disk.len() { self.notify_mutated(key, idx); }
+    }
+
+    fn apply_delta(&mut self, key: ChunkKey, d: Delta) -> bool {
+        let shift_y = self.origin_shift.y;
+        let Some(p) = self.loaded.get_mut(&key).and_then(|l| l.get_mut(d.idx)) else { return false };
+        p.archetype_id = d.archetype_id;
+        p.scale = d.scale;
+        p.center.y = d.y - shift_y;
+        self.mark_mutated(key);
+        self.notify_mutated(key, d.idx);
+        true
+    }
+
+    /// Is `key` far enough from `cam` (nearest point of its footprint) to be baked?
+    pub fn is_baked(&self, key: ChunkKey, cam: Vector3<f32>) -> bool {
+        if !self.bake_distance.is_finite() { return false; }
+        let Some((c, h)) = self.chunk_aabb(key) else { return false };
+        let dx = ((cam.x - c.x).abs() - h.x).max(0.0);
+        let dz = ((cam.z - c.z).abs() - h.z).max(0.0);
+        dx.hypot(dz) > self.bake_distance
+    }
+
+    /// Chunk under the local-space point `(x, z)`.  Chunk `c` is centred on
+    /// `c * span` in design space, so the floating-origin shift is added back.
     fn world_to_chunk(&self, x: f32, z: f32) -> (i32, i32) {
         let (cw, cd) = chunk_world_span(&self.params);
-        let cx = (x / cw).floor() as i32;
-        let cz = (z / cd).floor() as i32;
+        let cx = ((x + self.origin_shift.x) / cw + 0.5).floor() as i32;
+        let cz = ((z + self.origin_shift.z) / cd + 0.5).floor() as i32;
         (cx, cz)
     }
 
     fn ensure_chunk(
         &mut self,
         cx: i32, cz: i32,
-        designer: &mut dyn CityDesigner,
         assets: &AssetLibrary,
     ) {
         let key = wrap_key(cx, cz, self.bounds);
         if self.loaded.contains_key(&key) { return; }
+        if self.overrides.contains_key(&key) { return self.insert_override(key); }
 
-        // Try load from baked store (omitted for brevity; can add your existing file/localStorage)
-        // If not found, design now:
-        let ctx = DesignContext { cx: key.0, cz: key.1, seed: self.params.seed };
-        let placements = designer.design_chunk(&ctx, assets);
+        // Try the store first (namespace mismatch ⇒ miss)
+        match self.store.load_chunk(&self.store_namespace(), key.0, key.1) {
+            Some(file) => self.insert_stored(key, file),
+            None => self.design(key, assets),
+        }
+    }
+
+    fn insert_stored(&mut self, key: ChunkKey, file: ChunkFile) {
+        let rt = file.buildings.iter().map(|d| d.to_runtime(self.origin_shift)).collect();
+        self.insert_loaded(key, ChunkSource::Store, rt);
+    }
+
+    fn design(&mut self, key: ChunkKey, assets: &AssetLibrary) {
+        let ctx = DesignContext { districts: self.districts, ..DesignContext::new(key.0, key.1, self.params.seed) };
+        let placements = self.designer.design_chunk(&ctx, assets);
 
-        // Convert to runtime
+        // Convert to runtime (design space → local, like the store and overrides)
         let mut rt: Vec<RuntimePlacement> = Vec::with_capacity(placements.len());
         for p in placements {
-            rt.push(RuntimePlacement { center: p.center, scale: p.scale, archetype_id: p.archetype_id });
+            let stack = p.stack.as_deref().and_then(bounded_stack);
+            rt.push(RuntimePlacement { center: p.center - self.origin_shift, scale: p.scale, archetype_id: p.archetype_id, stack });
         }
 
-        self.loaded.insert(key, rt);
+        self.insert_loaded(key, ChunkSource::Designed, rt);
+        // a failed bake is retried by the next `flush`
+        if self.bake_on_miss && let Err(e) = self.save_loaded(key) {
+            warn!("baking chunk {key:?} failed: {e}");
+            self.dirty.insert(key);
+        }
     }
 
-    pub fn ensure_for_viewers(
-        &mut self,
-        designer: &mut dyn CityDesigner,
-        assets: &AssetLibrary,
-    ) {
-        for (_vid, (wx, wz)) in self.viewers.clone() {
+    /// Chunks requested from the store and not applied yet (`async_store`).
+    #[inline]
+    pub fn pending_loads(&self) -> usize { self.pending.len() }
+
+    /// Cap the time `ensure_for_viewers` spends loading and designing chunks
+    /// per call, on top of `gen_budget`; `None` (the default) lifts it.  At
+    /// least one chunk is still loaded per call, and the rest wait for later
+    /// calls in the same nearest-first order.
+    pub fn set_generation_budget_ms(&mut self, ms: Option<f32>) {
+        self.gen_budget_ms = ms.filter(|ms| ms.is_finite()).map(|ms| ms.max(0.0));
+    }
+
+    pub fn generation_budget_ms(&self) -> Option<f32> { self.gen_budget_ms }
+
+    /// Ask the loader for `key`, (re)starting it if `store` changed.
+    fn request_load(&mut self, key: ChunkKey) {
+        if self.loader.as_ref().is_none_or(|l| *l.backend() != self.store) {
+            self.loader = Some(StoreLoader::new(self.store.clone()));
+            self.pending.clear();
+            self.misses.clear();
+        }
+        let ns = self.store_namespace();
+        if let Some(loader) = self.loader.as_mut() { loader.request(&ns, key.0, key.1); }
+        self.pending.insert(key);
+    }
+
+    /// Has this `ensure_for_viewers` call, `done` chunks in since `t0`, used
+    /// up `gen_budget` or `generation_budget_ms`?  The first chunk is free.
+    fn over_budget(&self, done: usize, t0: instant::Instant) -> bool {
+        done >= self.gen_budget
+            || done > 0 && self.gen_budget_ms.is_some_and(|ms| t0.elapsed().as_secs_f32() * 1000.0 >= ms)
+    }
+
+    /// Apply up to `store_budget` finished reads: hits are inserted, misses
+    /// queued and designed while the generation budget lasts (`done` chunks
+    /// since `t0` so far), the rest on later calls.  Replies for another
+    /// namespace (reseeded since) or for chunks no longer pending are dropped.
+    fn drain_store(&mut self, assets: &AssetLibrary, mut done: usize, t0: instant::Instant) {
+        let ns = self.store_namespace();
+        for _ in 0..self.store_budget {
+            let Some((file_ns, cx, cz, file)) = self.loader.as_mut().and_then(StoreLoader::poll) else { break };
+            let key = ChunkKey(cx, cz);
+            if file_ns != ns || !self.pending.contains(&key) || self.loaded.contains_key(&key) { continue; }
+            match file {
+                Some(file) => { self.pending.remove(&key); self.insert_stored(key, file); }
+                None => self.misses.push_back(key),
+            }
+        }
+        while let Some(&key) = self.misses.front() {
+            if self.pending.contains(&key) && !self.loaded.contains_key(&key) {
+                if self.over_budget(done, t0) { break; }
+                self.pending.remove(&key);
+                self.design(key, assets);
+                done += 1;
+            } else {
+                self.pending.remove(&key);
+            }
+            self.misses.pop_front();
+        }
+    }
+
+    /// Load the window around every viewer, plus the same window around the
+    /// point each viewer reaches in `prefetch_secs`, after evicting loaded
+    /// chunks outside all of them (edits are saved first).  Missing chunks are
+    /// ordered by distance to that lead point (so the leading edge goes first,
+    /// window before prefetch) and at most `gen_budget` are loaded per call,
+    /// fewer once `generation_budget_ms` is spent.
+    /// With `async_store` they are requested from the store loader instead
+    /// and arrive over later calls (`store_budget` per call); misses are
+    /// designed under the same budgets.
+    pub fn ensure_for_viewers(&mut self, assets: &AssetLibrary) {
+        if self.isolate_loads && let Some(key) = self.isolated {
+            // earlier async requests are still applied (the target may be one)
+            if self.async_store { self.drain_store(assets, 0, instant::Instant::now()); }
+            if !self.loaded.contains_key(&key) && !self.pending.contains(&key) {
+                self.ensure_chunk(key.0, key.1, assets);
+            }
+            return;
+        }
+        let (cw, cd) = chunk_world_span(&self.params);
+        let mut want: Vec<(bool, i64, ChunkKey, i32, i32)> = Vec::new();
+        let mut keep: HashSet<ChunkKey> = HashSet::new();
+        let mut viewers: Vec<_> = self.viewers.iter().map(|(id, p)| (*id, *p)).collect();
+        viewers.sort_by_key(|v| v.0);
+        for (vid, (wx, wz)) in viewers {
+            let (vx, vz) = self.velocities.get(&vid).copied().unwrap_or((0.0, 0.0));
+            let (lx, lz) = (wx + vx * self.prefetch_secs, wz + vz * self.prefetch_secs);
             let (vcx, vcz) = self.world_to_chunk(wx, wz);
-            for dz in -self.chunk_radius..=self.chunk_radius {
-                for dx in -self.chunk_radius..=self.chunk_radius {
-                    let cx = vcx + dx;
-                    let cz = vcz + dz;
-                    self.ensure_chunk(cx, cz, designer, assets);
+            let (lcx, lcz) = self.world_to_chunk(lx, lz);
+            for (prefetch, ox, oz) in [(false, vcx, vcz), (true, lcx, lcz)] {
+                if prefetch && (lcx, lcz) == (vcx, vcz) { continue; }
+                for dz in -self.chunk_radius_z..=self.chunk_radius_z {
+                    for dx in -self.chunk_radius_x..=self.chunk_radius_x {
+                        let (cx, cz) = (ox + dx, oz + dz);
+                        let Some(key) = self.window_key(cx, cz) else { continue };
+                        keep.insert(key);
+                        if self.loaded.contains_key(&key) { continue; }
+                        let ex = cx as f32 * cw - self.origin_shift.x - lx;
+                        let ez = cz as f32 * cd - self.origin_shift.z - lz;
+                        want.push((prefetch, (ex * ex + ez * ez) as i64, key, cx, cz));
+                    }
                 }
             }
         }
+        // no viewers, no windows: chunks inserted by hand are left alone
+        if !self.viewers.is_empty() {
+            let mut out: Vec<ChunkKey> = self.loaded.keys().copied().filter(|k| !keep.contains(k)).collect();
+            out.sort_by_key(|k| (k.0, k.1));
+            for key in out { self.evict(key); }
+        }
+        // deterministic: ties broken by key
+        want.sort_by_key(|w| (w.0, w.1, w.2.0, w.2.1));
+        let mut done = 0;
+        let t0 = instant::Instant::now();
+        for (_, _, key, cx, cz) in want {
+            if self.loaded.contains_key(&key) || self.pending.contains(&key) { continue; }
+            if self.async_store && !self.overrides.contains_key(&key) {
+                if self.pending.len() >= MAX_PENDING_LOADS { break; }
+                self.request_load(key);
+                continue;
+            }
+            if self.over_budget(done, t0) { break; }
+            self.ensure_chunk(cx, cz, assets);
+            done += 1;
+        }
+        if self.async_store { self.drain_store(assets, done, t0); }
     }
 
     /// Randomly change a few buildings near viewers (rate: fraction of placements per second).
+    /// `dt` only feeds a fixed `mutation_hz` clock; whole ticks are simulated,
+    /// so which buildings change depends on elapsed time and `seed_add`
+    /// alone, not on the frame rate, and clients with the same seed agree.
     pub fn mutate_near(
         &mut self,
         assets: &AssetLibrary,
@@ -165,42 +1197,85 @@ impl ChunkManager {
         radius_chunks: i32,
         seed_add: u64,
     ) {
-        if rate_per_sec <= 0.0 { return; }
-        let mut want_mut = 0.0;
+        if rate_per_sec <= 0.0 || self.mutation_hz <= 0.0 { return; }
+        let step = 1.0 / self.mutation_hz;
+        for t in self.due_ticks(dt) {
+            self.mutate_tick(assets, rate_per_sec * step, radius_chunks, seed_add ^ hash2(t as i32, (t >> 32) as i32));
+        }
+    }
 
-        let (cw, cd) = chunk_world_span(&self.params);
+    /// Advance the world by `dt`: `mutate_near` around the viewers
+    /// (`MUTATION_RADIUS`, ticks mixed with `mutation_seed`) at
+    /// `mutation_rate`, or with a rate of 0 just the clock.  The app's
+    /// frame loop goes through here too, so a headless run with its seed
+    /// (0 for the live app, the bench's `--seed`) reproduces it.  Like
+    /// `mutate_near` the result depends only on the ticks elapsed.
+    pub fn step(&mut self, dt: f32, assets: &AssetLibrary, mutation_rate: f32, mutation_seed: u64) {
+        if mutation_rate > 0.0 { self.mutate_near(assets, mutation_rate, dt, MUTATION_RADIUS, mutation_seed); }
+        else { self.skip_mutations(dt); }
+    }
+
+    /// Run the mutation clock for `dt` without changing any building, so
+    /// once `mutate_near` is called again its ticks are numbered as if it
+    /// had never stopped (and still match peers on the same seed).
+    pub fn skip_mutations(&mut self, dt: f32) {
+        if self.mutation_hz > 0.0 { self.due_ticks(dt); }
+    }
 
-        for (_id, (wx, wz)) in self.viewers.iter() {
-            let (vcx, vcz) = self.world_to_chunk(*wx, *wz);
+    /// Mutation ticks `dt` brings due (consumed from the clock).
+    fn due_ticks(&mut self, dt: f32) -> std::ops::Range<u64> {
+        let step = 1.0 / self.mutation_hz;
+        // after a long stall, catch up at most MAX_MUTATION_TICKS
+        self.mutation_acc = (self.mutation_acc + dt.max(0.0)).min(step * MAX_MUTATION_TICKS as f32);
+        let first = self.mutation_tick;
+        while self.mutation_acc >= step {
+            self.mutation_acc -= step;
+            self.mutation_tick += 1;
+        }
+        first..self.mutation_tick
+    }
+
+    /// One mutation tick: `fraction` of the placements around each viewer
+    /// (carried over between ticks when below one building).
+    fn mutate_tick(&mut self, assets: &AssetLibrary, fraction: f32, radius_chunks: i32, seed: u64) {
+        let mut viewers: Vec<_> = self.viewers.iter().map(|(id, p)| (*id, *p)).collect();
+        viewers.sort_by_key(|v| v.0);
+        let mut n = 0i32;
+        let shift_y = self.origin_shift.y;
+        for (_id, (wx, wz)) in viewers {
+            let (vcx, vcz) = self.world_to_chunk(wx, wz);
             for dz in -radius_chunks..=radius_chunks {
                 for dx in -radius_chunks..=radius_chunks {
-                    let key = wrap_key(vcx + dx, vcz + dz, self.bounds);
+                    let Some(key) = self.window_key(vcx + dx, vcz + dz) else { continue };
                     if let Some(list) = self.loaded.get_mut(&key) {
                         // decide how many to mutate
-                        want_mut += (list.len() as f32) * rate_per_sec * dt;
-                        while want_mut >= 1.0 && !list.is_empty() {
-                            want_mut -= 1.0;
+                        self.mutation_debt += (list.len() as f32) * fraction;
+                        while self.mutation_debt >= 1.0 && !list.is_empty() {
+                            self.mutation_debt -= 1.0;
+                            *self.revisions.entry(key).or_insert(0) += 1;
+                            self.dirty.insert(key);
                             // pick random placement and re-roll archetype within same category
-                            let idx = (hash2(key.0 ^ key.1, list.len() as i32) ^ seed_add) as usize % list.len();
+                            n += 1;
+                            let idx = (hash2(key.0 ^ key.1, n) ^ seed) as usize % list.len();
                             let cat = assets.category_of(list[idx].archetype_id as usize);
                             let ids = assets.indices_by_category(cat);
                             if ids.is_empty() { continue; }
                             // pick different id if possible
-                            let mut new_id = ids[(seed_add as usize ^ idx) % ids.len()];
+                            let mut new_id = ids[(seed as usize ^ idx) % ids.len()];
                             if ids.len() > 1 && new_id == list[idx].archetype_id as usize {
                                 new_id = ids[(idx + 1) % ids.len()];
                             }
                             list[idx].archetype_id = new_id as u16;
 
                             // small scale jitter
-                            let j = ((seed_add as f32) * 0.000123).sin().abs() * 0.12;
+                            let j = ((seed % 10_000) as f32 / 10_000.0) * 0.12;
                             list[idx].scale.x = (list[idx].scale.x * (0.95 + j)).clamp(0.7, 1.8);
                             list[idx].scale.y = (list[idx].scale.y * (0.95 + j)).clamp(0.7, 2.5);
                             list[idx].scale.z = (list[idx].scale.z * (0.95 + j)).clamp(0.7, 1.8);
 
-                            // adjust Y to keep on “ground” by base_half
-                            let base = assets.base_half(new_id);
-                            list[idx].center.y = base.y * list[idx].scale.y;
+                            // keep it standing on the ground at the new size
+                            list[idx].center.y = assets.ground_y_for(new_id, list[idx].scale) - shift_y;
+                            if let Some(hook) = self.on_mutated.as_mut() { hook(key, idx); }
                         }
                     }
                 }
@@ -209,12 +1284,3 @@ impl ChunkManager {
     }
 }
 
-
-
-// simple hash (same as in designer)
-fn hash2(a: i32, b: i32) -> u64 {
-    let mut x = (a as i64 as i128) as u128 ^ (((b as i64 as i128) << 1) as u128) ^ 0x9E37_79B9_7F4A_7C15u128;
-    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9u128);
-    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EBu128);
-    (x ^ (x >> 31)) as u64
-}
@@ -1,15 +1,220 @@
 //! Finite-world chunk persistence.
-//! Native: ./city_chunks/{cx}_{cz}.bin (bincode).
-//! Web   : window.localStorage["city_chunk_{cx}_{cz}"] = base64(bincode).
+//! Native: ./city_chunks/{ns}_{cx}_{cz}.bin (`encode_chunk`: header + bincode).
+//! Web   : window.localStorage["city_chunk_{ns}_{cx}_{cz}"] = base64(same bytes).
+//! `ns` is `ChunkManager::store_namespace()` (hash of seed + params), so
+//! different worlds never read each other's chunks.
+//! `StoreBackend` picks one of the two (or none) per `ChunkManager`.
 
-use crate::mesh::{BuildingDisk, BuildingRecord};
+use cgmath::Vector3;
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize)]
+use crate::chunking::{bounded_stack, RuntimePlacement, SubBox};
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChunkFile {
+    pub namespace: String,
     pub cx: i32,
     pub cz: i32,
-    pub buildings: Vec<BuildingDisk>,
+    pub buildings: Vec<PlacementDisk>,
+}
+
+/// Disk form of a `RuntimePlacement`; `pos` is in world (un-shifted) space.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlacementDisk {
+    pub pos:   [f32; 3],
+    pub scale: [f32; 3],
+    pub archetype_id: u16,
+    pub stack: Vec<SubBoxDisk>,
+}
+
+/// Disk form of a `SubBox` (offset is relative, so needs no shift).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubBoxDisk {
+    pub offset: [f32; 3],
+    pub scale:  [f32; 3],
+    pub archetype_id: u16,
+}
+
+// ---------- format versions ----------
+
+/// First bytes of a chunk file, followed by its `CHUNK_FORMAT` (u32 LE).
+/// Files from before the header (v1, no stacks) start with the bincode
+/// length of their namespace instead, which can't spell this.
+pub const CHUNK_MAGIC: [u8; 4] = *b"CHNK";
+/// Layout of `ChunkFile`; bump it with any change to the disk structs.
+pub const CHUNK_FORMAT: u32 = 2;
+
+/// v1 layout: placements without `stack`.
+#[derive(Deserialize)]
+struct ChunkFileV1 { namespace: String, cx: i32, cz: i32, buildings: Vec<PlacementDiskV1> }
+#[derive(Deserialize)]
+struct PlacementDiskV1 { pos: [f32; 3], scale: [f32; 3], archetype_id: u16 }
+
+/// Bytes of `chunk` as stored: header, then bincode.
+pub fn encode_chunk(chunk: &ChunkFile) -> Vec<u8> {
+    let mut out = [CHUNK_MAGIC.as_slice(), &CHUNK_FORMAT.to_le_bytes()].concat();
+    out.extend(bincode::serialize(chunk).expect("bincode serialize"));
+    out
+}
+
+/// Read stored bytes: the current format, or v1 migrated (no stacks).
+/// Newer or unknown versions and corrupt data are errors, never guesses.
+pub fn decode_chunk(bytes: &[u8]) -> Result<ChunkFile, String> {
+    let Some(body) = bytes.strip_prefix(CHUNK_MAGIC.as_slice()) else {
+        let v1: ChunkFileV1 = bincode::deserialize(bytes).map_err(|e| format!("not a chunk file: {e}"))?;
+        return Ok(ChunkFile {
+            namespace: v1.namespace, cx: v1.cx, cz: v1.cz,
+            buildings: v1.buildings.into_iter()
+                .map(|p| PlacementDisk { pos: p.pos, scale: p.scale, archetype_id: p.archetype_id, stack: Vec::new() }).collect(),
+        });
+    };
+    let (version, body) = body.split_first_chunk::<4>().ok_or("truncated chunk header")?;
+    match u32::from_le_bytes(*version) {
+        CHUNK_FORMAT => bincode::deserialize(body).map_err(|e| format!("corrupt v{CHUNK_FORMAT} chunk: {e}")),
+        v => Err(format!("chunk format v{v}; this build reads v{CHUNK_FORMAT} and v1")),
+    }
+}
+
+/// `decode_chunk` for a load: a file that can't be read is reported and
+/// treated as a miss (regenerated, then overwritten when saved).
+fn read_chunk(bytes: &[u8], ns: &str, cx: i32, cz: i32) -> Option<ChunkFile> {
+    match decode_chunk(bytes) {
+        Ok(c) => Some(c).filter(|c| c.namespace == ns),
+        Err(e) => { log::warn!("ignoring stored chunk {ns} ({cx},{cz}): {e}"); None }
+    }
+}
+
+impl PlacementDisk {
+    pub fn from_runtime(p: &RuntimePlacement, origin_shift: Vector3<f32>) -> Self {
+        let c = p.center + origin_shift;
+        let stack = p.stack.iter().flatten().map(|s| SubBoxDisk {
+            offset: s.offset.into(), scale: s.scale.into(), archetype_id: s.archetype_id,
+        }).collect();
+        Self { pos: [c.x, c.y, c.z], scale: [p.scale.x, p.scale.y, p.scale.z], archetype_id: p.archetype_id, stack }
+    }
+    pub fn to_runtime(&self, origin_shift: Vector3<f32>) -> RuntimePlacement {
+        let stack: Vec<SubBox> = self.stack.iter().map(|s| SubBox {
+            offset: Vector3::from(s.offset), scale: Vector3::from(s.scale), archetype_id: s.archetype_id,
+        }).collect();
+        RuntimePlacement {
+            center: Vector3::from(self.pos) - origin_shift,
+            scale:  Vector3::from(self.scale),
+            archetype_id: self.archetype_id,
+            stack: bounded_stack(&stack),
+        }
+    }
+}
+
+/// Where a `ChunkManager` persists chunks.  `None` never touches disk or
+/// browser storage: loads miss and saves succeed without writing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreBackend {
+    None,
+    /// Directory of chunk files (native only; misses on the web).
+    Disk(String),
+    /// `window.localStorage` (web only; misses natively).
+    Web,
+}
+
+impl StoreBackend {
+    /// The platform store: `Disk(dir)` natively, `Web` in the browser.
+    pub fn platform(dir: &str) -> Self {
+        if cfg!(target_arch = "wasm32") { Self::Web } else { Self::Disk(dir.to_string()) }
+    }
+
+    pub fn load_chunk(&self, ns: &str, cx: i32, cz: i32) -> Option<ChunkFile> {
+        match self {
+            Self::None => None,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Disk(dir) => native::load_chunk(dir, ns, cx, cz),
+            #[cfg(target_arch = "wasm32")]
+            Self::Web => web::load_chunk("", ns, cx, cz),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    pub fn save_chunk(&self, chunk: &ChunkFile) -> std::io::Result<()> {
+        match self {
+            Self::None => Ok(()),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Disk(dir) => native::save_chunk(dir, chunk),
+            #[cfg(target_arch = "wasm32")]
+            Self::Web => web::save_chunk("", chunk).map_err(|e| std::io::Error::other(format!("{e:?}"))),
+            #[allow(unreachable_patterns)]
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("{self:?} store is not available here"))),
+        }
+    }
+}
+
+/// Answer to `StoreLoader::request`: `(namespace, cx, cz, chunk or miss)`.
+pub type StoreReply = (String, i32, i32, Option<ChunkFile>);
+
+#[cfg(not(target_arch = "wasm32"))]
+type StoreWorker = (std::sync::mpsc::Sender<(String, i32, i32)>, std::sync::mpsc::Receiver<StoreReply>);
+
+/// Batched store reads that don't block the frame.  `Disk` stores are read
+/// by a worker thread that takes every queued request at once; the other
+/// backends (localStorage is synchronous anyway) read lazily, one request
+/// per `poll`, so the caller's poll budget is what spreads them over frames.
+pub struct StoreLoader {
+    backend: StoreBackend,
+    #[cfg(not(target_arch = "wasm32"))]
+    worker: Option<StoreWorker>,
+    queued: std::collections::VecDeque<(String, i32, i32)>,
+}
+
+impl StoreLoader {
+    pub fn new(backend: StoreBackend) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let worker = match &backend {
+            StoreBackend::Disk(dir) => Some(Self::spawn(dir.clone())),
+            _ => None,
+        };
+        Self {
+            backend,
+            #[cfg(not(target_arch = "wasm32"))]
+            worker,
+            queued: Default::default(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn(dir: String) -> StoreWorker {
+        let (req_tx, req_rx) = std::sync::mpsc::channel::<(String, i32, i32)>();
+        let (rep_tx, rep_rx) = std::sync::mpsc::channel();
+        std::thread::Builder::new().name("chunk store".into()).spawn(move || {
+            // block for the first request, then take whatever else is queued
+            while let Ok(first) = req_rx.recv() {
+                for (ns, cx, cz) in std::iter::once(first).chain(req_rx.try_iter()) {
+                    let file = native::load_chunk(&dir, &ns, cx, cz);
+                    if rep_tx.send((ns, cx, cz, file)).is_err() { return; }
+                }
+            }
+        }).expect("spawn chunk store thread");
+        (req_tx, rep_rx)
+    }
+
+    #[inline]
+    pub fn backend(&self) -> &StoreBackend { &self.backend }
+
+    pub fn request(&mut self, ns: &str, cx: i32, cz: i32) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((tx, _)) = &self.worker {
+            if tx.send((ns.to_string(), cx, cz)).is_ok() { return; }
+            self.worker = None; // worker gone: fall back to reading inline
+        }
+        self.queued.push_back((ns.to_string(), cx, cz));
+    }
+
+    /// Next finished read, if any (never waits on the worker).
+    pub fn poll(&mut self) -> Option<StoreReply> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((_, rx)) = &self.worker && let Ok(reply) = rx.try_recv() { return Some(reply); }
+        let (ns, cx, cz) = self.queued.pop_front()?;
+        let file = self.backend.load_chunk(&ns, cx, cz);
+        Some((ns, cx, cz, file))
+    }
 }
 
 // ---------- Native FS impl ----------
@@ -23,22 +228,21 @@ pub mod native {
     fn dir_path(dir: &str) -> PathBuf {
         Path::new(dir).to_path_buf()
     }
-    fn file_path(dir: &str, cx: i32, cz: i32) -> PathBuf {
-        dir_path(dir).join(format!("{}_{}.bin", cx, cz))
+    fn file_path(dir: &str, ns: &str, cx: i32, cz: i32) -> PathBuf {
+        dir_path(dir).join(format!("{}_{}_{}.bin", ns, cx, cz))
     }
 
-    pub fn load_chunk(dir: &str, cx: i32, cz: i32) -> Option<ChunkFile> {
-        let p = file_path(dir, cx, cz);
+    pub fn load_chunk(dir: &str, ns: &str, cx: i32, cz: i32) -> Option<ChunkFile> {
+        let p = file_path(dir, ns, cx, cz);
         let bytes = fs::read(p).ok()?;
-        bincode::deserialize::<ChunkFile>(&bytes).ok()
+        read_chunk(&bytes, ns, cx, cz)
     }
 
     pub fn save_chunk(dir: &str, chunk: &ChunkFile) -> std::io::Result<()> {
         let d = dir_path(dir);
         if !d.exists() { std::fs::create_dir_all(&d)?; }
-        let p = file_path(dir, chunk.cx, chunk.cz);
-        let bytes = bincode::serialize(chunk).expect("bincode serialize");
-        std::fs::write(p, bytes)
+        let p = file_path(dir, &chunk.namespace, chunk.cx, chunk.cz);
+        std::fs::write(p, encode_chunk(chunk))
     }
 }
 
@@ -49,25 +253,24 @@ pub mod web {
     use super::*;
     use wasm_bindgen::JsValue;
 
-    fn key(cx: i32, cz: i32) -> String {
-        format!("city_chunk_{}_{}", cx, cz)
+    fn key(ns: &str, cx: i32, cz: i32) -> String {
+        format!("city_chunk_{}_{}_{}", ns, cx, cz)
     }
 
-    pub fn load_chunk(_dir_unused: &str, cx: i32, cz: i32) -> Option<ChunkFile> {
+    pub fn load_chunk(_dir_unused: &str, ns: &str, cx: i32, cz: i32) -> Option<ChunkFile> {
         let window = web_sys::window()?;
         let storage = window.local_storage().ok()??;
-        let k = key(cx, cz);
+        let k = key(ns, cx, cz);
         let s = storage.get_item(&k).ok()??;
         let bytes = base64::decode(s).ok()?;
-        bincode::deserialize::<ChunkFile>(&bytes).ok()
+        read_chunk(&bytes, ns, cx, cz)
     }
 
     pub fn save_chunk(_dir_unused: &str, chunk: &ChunkFile) -> Result<(), JsValue> {
         let window = web_sys::window().ok_or(JsValue::from_str("no window"))?;
         let storage = window.local_storage()?.ok_or(JsValue::from_str("no localStorage"))?;
-        let k = key(chunk.cx, chunk.cz);
-        let bytes = bincode::serialize(chunk).map_err(|e| JsValue::from_str(&format!("{e}")))?;
-        let s = base64::encode(bytes);
+        let k = key(&chunk.namespace, chunk.cx, chunk.cz);
+        let s = base64::encode(encode_chunk(chunk));
         storage.set_item(&k, &s)
     }
 }
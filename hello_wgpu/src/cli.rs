@@ -0,0 +1,100 @@
+//! Command line of the native binary: what to run and with which
+//! `EngineConfig` / `BenchOptions`.  No arguments is `run` with the
+//! defaults; errors are one-line messages for `main` to print.
+
+use crate::bench::BenchOptions;
+use crate::chunking::CityGenParams;
+use crate::hello_wgpu::EngineConfig;
+
+pub const USAGE: &str = "\
+usage: hello_wgpu_native [--seed S] [--params FILE] [--width W] [--height H] [--fullscreen] [--backend LIST]
+       hello_wgpu_native [--seed S] [--width W] [--height H] [--backend LIST] --bench [bench options]
+       hello_wgpu_native bench [--frames N] [--seed S] [--size WxH] [--backend LIST] [--prepass] [--no-bundles] [--no-hdr]
+LIST is comma-separated: vulkan, metal, dx12, gl (or all)";
+
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)] // parsed once, then consumed
+pub enum Command {
+    Run(EngineConfig),
+    Bench(BenchOptions),
+    Help,
+}
+
+/// `--backend`: wgpu's comma list (`vulkan,gl`), or `all`.
+pub fn parse_backends(s: &str) -> Result<wgpu::Backends, String> {
+    if s.eq_ignore_ascii_case("all") { return Ok(wgpu::Backends::all()); }
+    let known = ["vulkan", "vk", "dx12", "d3d12", "metal", "mtl", "opengl", "gles", "gl"];
+    if let Some(b) = s.split(',').map(str::trim).find(|b| !known.contains(&b.to_lowercase().as_str())) {
+        return Err(format!("--backend: unknown backend {b:?}"));
+    }
+    Ok(wgpu::Backends::from_comma_list(s))
+}
+
+fn number<T: std::str::FromStr<Err: std::fmt::Display>>(flag: &str, v: &str) -> Result<T, String> {
+    v.parse().map_err(|e| format!("{flag}: {e}"))
+}
+
+/// Options after `bench` (or `--bench`), on top of `o`.
+pub fn parse_bench(args: &[String], mut o: BenchOptions) -> Result<BenchOptions, String> {
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        let mut val = || it.next().ok_or(format!("{a} needs a value"));
+        match a.as_str() {
+            "--frames" => o.frames = number(a, val()?)?,
+            "--seed"   => o.seed   = number(a, val()?)?,
+            "--backend" => o.backends = parse_backends(val()?)?,
+            "--prepass" => o.depth_prepass = true,
+            "--no-bundles" => o.render_bundles = false,
+            "--no-hdr" => o.hdr = false,
+            "--size"   => {
+                let v = val()?;
+                let (w, h) = v.split_once('x').ok_or(format!("--size expects WxH, got {v}"))?;
+                o.width  = number(a, w)?;
+                o.height = number(a, h)?;
+            }
+            _ => return Err(format!("unknown bench option {a}")),
+        }
+    }
+    Ok(o)
+}
+
+/// Arguments without the program name.  `--seed` wins over the seed in
+/// `--params` whatever the order; `--bench` hands the rest of the line to
+/// `parse_bench`, keeping the seed, size and backends given before it.
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    if args.first().map(String::as_str) == Some("bench") {
+        return parse_bench(&args[1..], BenchOptions::default()).map(Command::Bench);
+    }
+    let mut config = EngineConfig::default();
+    let (mut seed, mut params) = (None, false);
+    let mut it = args.iter().enumerate();
+    while let Some((i, a)) = it.next() {
+        let mut val = || it.next().map(|(_, v)| v).ok_or(format!("{a} needs a value"));
+        match a.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--seed"   => seed = Some(number(a, val()?)?),
+            "--params" | "--city" => {
+                config.city = CityGenParams::load(val()?)?;
+                params = true;
+            }
+            "--width"  => config.width  = number(a, val()?)?,
+            "--height" => config.height = number(a, val()?)?,
+            "--fullscreen" => config.fullscreen = true,
+            "--backend" => config.backends = parse_backends(val()?)?,
+            "--bench" => {
+                if params { return Err("--params has no effect with --bench (the bench city is fixed)".into()); }
+                if config.fullscreen { return Err("--fullscreen has no effect with --bench (it renders headless)".into()); }
+                let base = BenchOptions {
+                    seed: seed.unwrap_or(BenchOptions::default().seed),
+                    width: config.width, height: config.height,
+                    backends: config.backends,
+                    ..Default::default()
+                };
+                return parse_bench(&args[i + 1..], base).map(Command::Bench);
+            }
+            _ => return Err(format!("unknown option {a}")),
+        }
+    }
+    if let Some(seed) = seed { config.city.seed = seed; }
+    Ok(Command::Run(config))
+}
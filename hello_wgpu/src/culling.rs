@@ -1,5 +1,13 @@
 // src/culling.rs
-use cgmath::{Matrix4, Vector3, InnerSpace};
+use std::collections::HashMap;
+
+use cgmath::{Matrix4, Vector3, Vector4, InnerSpace, SquareMatrix};
+
+use crate::assets::{AssetLibrary, BuildingCategory};
+use crate::axes::WorldAxes;
+use crate::chunking::{ChunkKey, RuntimePlacement};
+use crate::mesh;
+use crate::types::{InstanceRaw, TINT_HIGHRISE, TINT_LANDMARK, TINT_LOWRISE};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Plane {
@@ -12,6 +20,28 @@ pub struct Frustum {
     pub planes: [Plane; 6], // left, right, bottom, top, near, far
 }
 
+/// Where an AABB lies relative to a frustum (`Frustum::classify_aabb`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Containment { Inside, Intersect, Outside }
+
+impl Frustum {
+    /// Classify the AABB `center ± half`.  Per plane, `s + r` / `s - r` are
+    /// the signed distances of the positive / negative vertex: the box is
+    /// outside if some positive vertex is behind its plane, inside if no
+    /// negative vertex is.  Like `aabb_intersects_frustum` this is
+    /// conservative: a box near a frustum corner may report `Intersect`.
+    pub fn classify_aabb(&self, center: Vector3<f32>, half: Vector3<f32>) -> Containment {
+        let mut out = Containment::Inside;
+        for p in &self.planes {
+            let r = half.x * p.n.x.abs() + half.y * p.n.y.abs() + half.z * p.n.z.abs();
+            let s = p.n.dot(center) + p.d;
+            if s + r < 0.0 { return Containment::Outside; }
+            if s - r < 0.0 { out = Containment::Intersect; }
+        }
+        out
+    }
+}
+
 fn normalize_plane(mut p: Plane) -> Plane {
     let len = (p.n.x * p.n.x + p.n.y * p.n.y + p.n.z * p.n.z).sqrt().max(1e-6);
     p.n.x /= len; p.n.y /= len; p.n.z /= len;
@@ -48,6 +78,36 @@ pub fn frustum_from_vp(vp: &Matrix4<f32>) -> Frustum {
     Frustum { planes }
 }
 
+/// The eight corners of `vp`'s frustum (same clip convention as
+/// `frustum_from_vp`): the near quad, then the far quad, each going
+/// bottom-left, bottom-right, top-right, top-left.  `None` if `vp` is singular.
+pub fn frustum_corners(vp: &Matrix4<f32>) -> Option<[Vector3<f32>; 8]> {
+    let inv = vp.invert()?;
+    Some(std::array::from_fn(|i| {
+        let (x, y) = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)][i % 4];
+        let z = if i < 4 { -1.0 } else { 1.0 };
+        let p = inv * Vector4::new(x, y, z, 1.0);
+        p.truncate() / p.w
+    }))
+}
+
+/// Ray vs AABB (slab test): distance along `dir` (unit length) to the
+/// first hit at or after `origin`, `None` on a miss.
+pub fn ray_aabb(origin: Vector3<f32>, dir: Vector3<f32>, center: Vector3<f32>, half: Vector3<f32>) -> Option<f32> {
+    let (mut t0, mut t1) = (0.0f32, f32::INFINITY);
+    for (o, d, c, h) in [(origin.x, dir.x, center.x, half.x), (origin.y, dir.y, center.y, half.y), (origin.z, dir.z, center.z, half.z)] {
+        if d.abs() < 1e-8 {
+            if (o - c).abs() > h { return None; }
+            continue;
+        }
+        let (a, b) = ((c - h - o) / d, (c + h - o) / d);
+        t0 = t0.max(a.min(b));
+        t1 = t1.min(a.max(b));
+        if t0 > t1 { return None; }
+    }
+    Some(t0)
+}
+
 /// AABB vs frustum test (positive-vertex radius trick).
 /// center = AABB center; half = half-extents. Returns true if intersects.
 pub fn aabb_intersects_frustum(center: Vector3<f32>, half: Vector3<f32>, fr: &Frustum) -> bool {
@@ -62,3 +122,366 @@ pub fn aabb_intersects_frustum(center: Vector3<f32>, half: Vector3<f32>, fr: &Fr
     }
     true
 }
+
+/// One frame's instance lists: LOD0 / LOD1 per archetype group, then
+/// billboards for everything past LOD1.
+#[derive(Default)]
+pub struct Buckets {
+    pub v0_low_common: Vec<InstanceRaw>,
+    pub v0_low_alt:    Vec<InstanceRaw>,
+    pub v0_high:       Vec<InstanceRaw>,
+    pub v0_land:       Vec<InstanceRaw>,
+    pub v1_low_common: Vec<InstanceRaw>,
+    pub v1_low_alt:    Vec<InstanceRaw>,
+    pub v1_high:       Vec<InstanceRaw>,
+    pub v1_land:       Vec<InstanceRaw>,
+    pub v2_bill:       Vec<InstanceRaw>,
+}
+
+fn dist2(i: &InstanceRaw, cam: Vector3<f32>) -> f32 {
+    (Vector3::new(i.pos[0], i.pos[1], i.pos[2]) - cam).magnitude2()
+}
+
+/// Field names of the `Buckets` lists, in `lists` order.
+pub const BUCKET_NAMES: [&str; 9] = [
+    "v0_low_common", "v0_low_alt", "v0_high", "v0_land",
+    "v1_low_common", "v1_low_alt", "v1_high", "v1_land",
+    "v2_bill",
+];
+
+impl Buckets {
+    fn lists_mut(&mut self) -> [&mut Vec<InstanceRaw>; 9] {
+        [&mut self.v0_low_common, &mut self.v0_low_alt, &mut self.v0_high, &mut self.v0_land,
+         &mut self.v1_low_common, &mut self.v1_low_alt, &mut self.v1_high, &mut self.v1_land,
+         &mut self.v2_bill]
+    }
+
+    /// Every bucket, named by `BUCKET_NAMES`.
+    pub fn lists(&self) -> [&Vec<InstanceRaw>; 9] {
+        [&self.v0_low_common, &self.v0_low_alt, &self.v0_high, &self.v0_land,
+         &self.v1_low_common, &self.v1_low_alt, &self.v1_high, &self.v1_land,
+         &self.v2_bill]
+    }
+
+    /// Instances over every bucket.
+    pub fn total(&self) -> usize {
+        self.lists().iter().map(|v| v.len()).sum()
+    }
+
+    /// One CSV row per instance: its bucket, position, scale, category
+    /// (from the tint code), archetype and facade layer.  Billboard scales
+    /// are quad stretches, not building scales.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("bucket,x,y,z,sx,sy,sz,category,archetype,layer\n");
+        for (name, list) in BUCKET_NAMES.iter().zip(self.lists()) {
+            for i in list {
+                let cat = match i.misc[0] {
+                    TINT_LOWRISE => "lowrise", TINT_HIGHRISE => "highrise", TINT_LANDMARK => "landmark", _ => "other",
+                };
+                out += &format!("{name},{},{},{},{},{},{},{cat},{},{}\n",
+                                i.pos[0], i.pos[1], i.pos[2], i.scale[0], i.scale[1], i.scale[2], i.misc[1], i.misc[2]);
+            }
+        }
+        out
+    }
+
+    /// Order `v2_bill` far-to-near from `cam`, as blending needs.
+    pub fn sort_billboards_back_to_front(&mut self, cam: Vector3<f32>) {
+        self.v2_bill.sort_by(|a, b| dist2(b, cam).total_cmp(&dist2(a, cam)));
+    }
+
+    /// Keep at most `max` instances per bucket, the ones nearest `cam`
+    /// (partial sort; order within a bucket is not kept).  Returns how many
+    /// were dropped, so huge view distances degrade instead of growing the
+    /// instance buffers without bound.
+    pub fn cap_nearest(&mut self, cam: Vector3<f32>, max: usize) -> usize {
+        let mut dropped = 0;
+        for list in self.lists_mut() {
+            if list.len() <= max { continue; }
+            dropped += list.len() - max;
+            if max > 0 { list.select_nth_unstable_by(max - 1, |a, b| dist2(a, cam).total_cmp(&dist2(b, cam))); }
+            list.truncate(max);
+        }
+        dropped
+    }
+}
+
+/// LOD ring radii for `bucket_instances`, or, with `lod_override` set
+/// (0 = LOD0, 1 = LOD1, 2+ = billboard), rings that route every building
+/// within `cull` into that one level.  Culling itself is unaffected.
+pub fn lod_rings(lod_override: Option<u8>, lod0: f32, lod1: f32, cull: f32) -> (f32, f32) {
+    match lod_override {
+        None => (lod0, lod1),
+        Some(0) => (cull, cull),
+        Some(1) => (f32::NEG_INFINITY, cull),
+        Some(_) => (f32::NEG_INFINITY, f32::NEG_INFINITY),
+    }
+}
+
+/// Boxes (centre, half-extent) within `cull` m of `cam` that `fr` rejects,
+/// at most `max` of them: what `bucket_instances` drops as off-screen.
+pub fn frustum_culled<'a>(
+    placements: impl IntoIterator<Item = &'a RuntimePlacement>,
+    cam: Vector3<f32>, fr: &Frustum, cull: f32, max: usize,
+    assets: &AssetLibrary,
+) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    placements.into_iter().flat_map(|p| p.boxes())
+        .filter(|(center, _, _)| (center - cam).magnitude() <= cull)
+        .map(|(center, scale, id)| {
+            let base = assets.base_half(id as usize);
+            (center, Vector3::new(base.x * scale.x, base.y * scale.y, base.z * scale.z))
+        })
+        .filter(|&(center, half)| !aabb_intersects_frustum(center, half, fr))
+        .take(max)
+        .collect()
+}
+
+/// LOD of a building `dist` m away: 0 within `lod0`, 1 within `lod1`,
+/// else 2 (billboard).
+#[inline]
+fn ring_level(dist: f32, (lod0, lod1): (f32, f32)) -> u8 {
+    if dist<=lod0 { 0 } else if dist<=lod1 { 1 } else { 2 }
+}
+
+/// Box `b` of placement `index` in a chunk: what `LodHysteresis` remembers.
+type BoxKey = (ChunkKey, u32, u8);
+
+/// LOD chosen per building box last frame, so a building hovering at a ring
+/// (a bobbing camera) doesn't swap meshes every frame: it moves to a finer
+/// level as soon as it is inside the ring, but back to a coarser one only
+/// once it is `margin` m past it.  Boxes not bucketed in a frame (culled,
+/// out of range, unloaded) are forgotten.
+#[derive(Clone, Debug, Default)]
+pub struct LodHysteresis {
+    pub margin: f32,
+    levels: HashMap<BoxKey, u8>,
+    next:   HashMap<BoxKey, u8>,
+}
+
+impl LodHysteresis {
+    /// Negative or NaN `margin` is taken as 0 (no hysteresis).
+    pub fn new(margin: f32) -> Self { Self { margin: margin.max(0.0), ..Self::default() } }
+
+    /// Boxes remembered from the last `bucket`.
+    #[inline]
+    pub fn len(&self) -> usize { self.levels.len() }
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.levels.is_empty() }
+
+    /// Last frame's level for `key` held between the plain ring level and
+    /// the level `margin` m closer.  `margin` is public, so a negative one
+    /// set afterwards is ordered here rather than trusted.
+    fn select(&mut self, key: BoxKey, dist: f32, rings: (f32, f32)) -> u8 {
+        let raw=ring_level(dist, rings);
+        let near=ring_level(dist - self.margin, rings);
+        let level=match self.levels.get(&key) {
+            Some(&prev) => prev.clamp(near.min(raw), near.max(raw)),
+            None => raw,
+        };
+        self.next.insert(key, level);
+        level
+    }
+
+    /// `bucket_instances` over whole chunks with `rings` = (`lod0`, `lod1`)
+    /// (see `lod_rings`), keeping each box's level across calls; call once
+    /// per frame.  A chunk flagged `true` lies wholly inside `fr`
+    /// (`Containment::Inside`), so its boxes skip the frustum test.
+    pub fn bucket<'a>(
+        &mut self,
+        chunks: impl IntoIterator<Item = (ChunkKey, bool, &'a [RuntimePlacement])>,
+        cam: Vector3<f32>, fr: &Frustum, rings: (f32, f32), cull: f32,
+        assets: &AssetLibrary,
+    ) -> Buckets {
+        let boxes=chunks.into_iter().flat_map(|(key, inside, list)| list.iter().enumerate().flat_map(move |(i, p)| {
+            p.boxes().enumerate().map(move |(b, bx)| ((key, i as u32, b as u8), inside, bx))
+        }));
+        self.next.clear();
+        let out=bucket_boxes(boxes, cam, fr, cull, assets, |key, dist| self.select(key, dist, rings));
+        std::mem::swap(&mut self.levels, &mut self.next);
+        out
+    }
+}
+
+/// Camera pose quantised to `ChunkCullCache::pos_step` metres and
+/// `angle_step` radians, plus the exact aspect ratio and field of view of
+/// the projection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CameraCell { pos: [i32; 3], yaw: i32, pitch: i32, aspect: u32, fov: u32 }
+
+/// Per-chunk `Containment` kept while the camera stays in one
+/// `CameraCell`, so loitering doesn't re-classify every chunk each frame.
+/// Each chunk is classified with enough slack to hold for any pose in the
+/// cell: `Outside` if its box (grown by how far its buildings reach past
+/// it) misses the frustum by more than the slack, `Inside` if the box is
+/// that far inside, else `Intersect`, whose buildings are tested one by
+/// one as usual.  An entry is redone when its chunk's box or revision
+/// changes (mutations, origin shifts); a new cell forgets them all.
+/// `axes` says which box extents the horizontal reach applies to.
+#[derive(Clone, Debug)]
+pub struct ChunkCullCache {
+    pub pos_step:   f32,
+    pub angle_step: f32,
+    pub axes:       WorldAxes,
+    cell: Option<CameraCell>,
+    chunks: HashMap<ChunkKey, CachedChunk>,
+    misses: usize,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct CachedChunk { revision: u32, aabb: (Vector3<f32>, Vector3<f32>), containment: Containment }
+
+impl Default for ChunkCullCache {
+    fn default() -> Self { Self::new(4.0, 2f32.to_radians()) }
+}
+
+impl ChunkCullCache {
+    pub fn new(pos_step: f32, angle_step: f32) -> Self {
+        Self { pos_step, angle_step, axes: WorldAxes::default(), cell: None, chunks: HashMap::new(), misses: 0 }
+    }
+
+    /// The cell of a camera at `pos` (local space) looking along `yaw`/`pitch`
+    /// through a `fov`-degree projection.
+    pub fn cell_for(&self, pos: Vector3<f32>, yaw: f32, pitch: f32, aspect: f32, fov: f32) -> CameraCell {
+        let q = |v: f32, step: f32| (v / step.max(1e-6)).floor() as i32;
+        CameraCell {
+            pos: [q(pos.x, self.pos_step), q(pos.y, self.pos_step), q(pos.z, self.pos_step)],
+            yaw: q(yaw, self.angle_step), pitch: q(pitch, self.angle_step),
+            aspect: aspect.to_bits(), fov: fov.to_bits(),
+        }
+    }
+
+    /// Start a frame in `cell`; `None` (e.g. a frozen debug frustum)
+    /// classifies exactly and caches nothing.
+    pub fn begin(&mut self, cell: Option<CameraCell>) {
+        if cell.is_none() || cell != self.cell { self.chunks.clear(); }
+        self.cell = cell;
+        self.misses = 0;
+    }
+
+    /// Chunks classified afresh since `begin` (the rest came from the cache).
+    pub fn misses(&self) -> usize { self.misses }
+
+    /// Containment of chunk `key` with box `aabb` (`ChunkManager::chunk_aabb`)
+    /// at `revision`; `reach` is how far its building boxes stick out of
+    /// the box horizontally, only asked on a miss.
+    pub fn classify(&mut self, fr: &Frustum, key: ChunkKey, revision: u32, aabb: (Vector3<f32>, Vector3<f32>),
+                    cam: Vector3<f32>, reach: impl FnOnce() -> f32) -> Containment {
+        if let Some(e) = self.chunks.get(&key) && e.revision == revision && e.aabb == aabb { return e.containment; }
+        self.misses += 1;
+        let (c, h) = aabb;
+        let reach = reach();
+        let slack = if self.cell.is_none() { 0.0 } else {
+            // any pose in the cell: ≤ one step each way, and a turn moves
+            // a point r m away by up to r·θ
+            let r = (c - cam).magnitude() + h.magnitude() + reach;
+            self.pos_step * 3f32.sqrt() + r * self.angle_step * 2f32.sqrt()
+        };
+        let grow = |reach: f32| self.axes.grow(h, reach + slack, slack);
+        let containment = if fr.classify_aabb(c, grow(reach)) == Containment::Outside { Containment::Outside }
+            else if fr.classify_aabb(c, grow(0.0)) == Containment::Inside { Containment::Inside }
+            else { Containment::Intersect };
+        if self.cell.is_some() { self.chunks.insert(key, CachedChunk { revision, aabb, containment }); }
+        containment
+    }
+}
+
+/// How far the boxes of `list` reach past the footprint their centres
+/// span: the widest horizontal half-extent (horizontal per `axes`).
+pub fn horizontal_reach(list: &[RuntimePlacement], assets: &AssetLibrary, axes: &WorldAxes) -> f32 {
+    list.iter().flat_map(|p| p.boxes()).fold(0.0f32, |m, (_, scale, id)| {
+        let b = assets.base_half(id as usize);
+        m.max(axes.horizontal_extent(Vector3::new(b.x * scale.x, b.y * scale.y, b.z * scale.z)))
+    })
+}
+
+/// Per-building seed (`misc.w`) picking which procedural windows are lit:
+/// hashed from the jittered scale, which the designer draws per building
+/// and which, unlike the centre, survives floating-origin shifts.  Kept
+/// below 2^24 so it is exact in an f32.
+pub fn window_seed(scale: Vector3<f32>) -> f32 {
+    let h = crate::rng::hash2(scale.x.to_bits() as i32, (scale.y.to_bits() ^ scale.z.to_bits().rotate_left(16)) as i32);
+    (h >> 40) as f32
+}
+
+/// Sort placements within `cull` m of `cam` and inside `fr` into LOD0
+/// (≤ `lod0`), LOD1 (≤ `lod1`) or billboard buckets.  Each box of a stacked
+/// placement is culled and bucketed on its own.
+pub fn bucket_instances<'a>(
+    placements: impl IntoIterator<Item = &'a RuntimePlacement>,
+    cam: Vector3<f32>, fr: &Frustum, lod0: f32, lod1: f32, cull: f32,
+    assets: &AssetLibrary,
+) -> Buckets {
+    let boxes=placements.into_iter().flat_map(|p| p.boxes()).map(|b| ((), false, b));
+    bucket_boxes(boxes, cam, fr, cull, assets, |_, dist| ring_level(dist, (lod0, lod1)))
+}
+
+/// Shared body of `bucket_instances` / `LodHysteresis::bucket`: `level`
+/// picks the LOD (see `ring_level`) of each visible box; boxes flagged
+/// `true` are known to be inside `fr`.
+fn bucket_boxes<K>(
+    boxes: impl Iterator<Item = (K, bool, (Vector3<f32>, Vector3<f32>, u16))>,
+    cam: Vector3<f32>, fr: &Frustum, cull: f32, assets: &AssetLibrary,
+    mut level: impl FnMut(K, f32) -> u8,
+) -> Buckets {
+    let mut out=Buckets::default();
+
+    // alt low-rise archetype id (timber_house_b = id 1)
+    let alt_id:usize = 1;
+
+    for (key,inside,(center,scale,archetype_id)) in boxes {
+        let dist=(center-cam).magnitude();
+        if dist>cull { continue; }
+
+        let base=assets.base_half(archetype_id as usize);
+        let half=Vector3::new(
+            base.x*scale.x, base.y*scale.y, base.z*scale.z);
+        if !inside && !aabb_intersects_frustum(center,half,fr){continue;}
+
+        let cat=assets.category_of(archetype_id as usize);
+        let inst=InstanceRaw{
+            pos:[center.x,center.y,center.z,0.0],
+            scale:[scale.x,scale.y,scale.z,0.0],
+            misc:[match cat{
+                BuildingCategory::Lowrise =>TINT_LOWRISE,
+                BuildingCategory::Highrise=>TINT_HIGHRISE,
+                BuildingCategory::Landmark=>TINT_LANDMARK,
+            }, archetype_id as f32,
+                assets.texture_of(archetype_id as usize) as f32, window_seed(scale)],
+        };
+
+        let lod=level(key,dist);
+        if lod==0 {
+            match cat {
+                BuildingCategory::Lowrise=>{
+                    if archetype_id as usize==alt_id {
+                        out.v0_low_alt.push(inst)
+                    } else { out.v0_low_common.push(inst) }
+                }
+                BuildingCategory::Highrise => out.v0_high.push(inst),
+                BuildingCategory::Landmark => out.v0_land.push(inst),
+            }
+        } else if lod==1 {
+            match cat {
+                BuildingCategory::Lowrise=>{
+                    if archetype_id as usize==alt_id {
+                        out.v1_low_alt.push(inst)
+                    } else { out.v1_low_common.push(inst) }
+                }
+                BuildingCategory::Highrise => out.v1_high.push(inst),
+                BuildingCategory::Landmark => out.v1_land.push(inst),
+            }
+        } else {
+            // quad is BILLBOARD_W×BILLBOARD_H: stretch it to the building's
+            // world footprint/height and keep its category tint (no facade).
+            // The flat quad has no depth, so scale.z and the w slots carry
+            // the building's scale for the impostor (`impostor_quad`).
+            let w=2.0*half.x.max(half.z);
+            out.v2_bill.push(InstanceRaw{
+                pos:[center.x,center.y,center.z,scale.x],
+                scale:[w/mesh::BILLBOARD_W, 2.0*half.y/mesh::BILLBOARD_H,scale.z,scale.y],
+                misc:[inst.misc[0],inst.misc[1],0.0,inst.misc[3]],
+            });
+        }
+    }
+    out
+}
@@ -0,0 +1,146 @@
+//! World-space debug lines (frozen culling frustum, culled building boxes,
+//! chunk borders)
+//! drawn as one line list with the scene camera after everything else in
+//! the main pass.  Depth is ignored so the lines show through buildings.
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::Vector3;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LineVertex {
+    pub pos:   [f32; 3],
+    pub color: [f32; 3],
+}
+
+/// The 12 edges of a box over corners indexed by bit 0 = +x, 1 = +y, 2 = +z.
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7), // along x
+    (0, 2), (1, 3), (4, 6), (5, 7), // along y
+    (0, 4), (1, 5), (2, 6), (3, 7), // along z
+];
+
+fn push_edges(out: &mut Vec<LineVertex>, corners: &[Vector3<f32>; 8], edges: &[(usize, usize)], color: [f32; 3]) {
+    for &(a, b) in edges {
+        out.push(LineVertex { pos: corners[a].into(), color });
+        out.push(LineVertex { pos: corners[b].into(), color });
+    }
+}
+
+/// Wireframe of the axis-aligned box `center ± half`.
+pub fn push_box(out: &mut Vec<LineVertex>, center: Vector3<f32>, half: Vector3<f32>, color: [f32; 3]) {
+    let corners = std::array::from_fn(|i| {
+        let s = |bit: usize| if i & bit != 0 { 1.0 } else { -1.0 };
+        center + Vector3::new(half.x * s(1), half.y * s(2), half.z * s(4))
+    });
+    push_edges(out, &corners, &BOX_EDGES, color);
+}
+
+/// Horizontal rectangle `center ± (half_x, 0, half_z)`.
+pub fn push_rect(out: &mut Vec<LineVertex>, center: Vector3<f32>, half_x: f32, half_z: f32, color: [f32; 3]) {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .map(|(sx, sz)| center + Vector3::new(half_x * sx, 0.0, half_z * sz));
+    for i in 0..4 {
+        out.push(LineVertex { pos: corners[i].into(), color });
+        out.push(LineVertex { pos: corners[(i + 1) % 4].into(), color });
+    }
+}
+
+/// Wireframe of a frustum from `culling::frustum_corners` (near quad, far
+/// quad, then the four side edges).
+pub fn push_frustum(out: &mut Vec<LineVertex>, corners: &[Vector3<f32>; 8], color: [f32; 3]) {
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1), (1, 2), (2, 3), (3, 0),
+        (4, 5), (5, 6), (6, 7), (7, 4),
+        (0, 4), (1, 5), (2, 6), (3, 7),
+    ];
+    push_edges(out, corners, &EDGES, color);
+}
+
+pub struct LineOverlay {
+    pipeline: wgpu::RenderPipeline,
+    vbuf: wgpu::Buffer,
+    count: u32,
+}
+
+impl LineOverlay {
+    /// `camera_bgl` is the scene camera layout; its bind group is set at draw.
+    pub fn new(device: &wgpu::Device, camera_bgl: &wgpu::BindGroupLayout, color_format: wgpu::TextureFormat,
+               depth_format: wgpu::TextureFormat, samples: u32) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("debug lines shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/lines.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("debug lines pipe layout"),
+            bind_group_layouts: &[camera_bgl],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("debug lines pipe"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_lines"),
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_lines"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format, blend: None, write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState { topology: wgpu::PrimitiveTopology::LineList, ..Default::default() },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState { count: samples, ..Default::default() },
+            multiview: None,
+            cache: None,
+        });
+        let vbuf = Self::buffer(device, 2);
+        Self { pipeline, vbuf, count: 0 }
+    }
+
+    fn buffer(device: &wgpu::Device, verts: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("debug lines vb"),
+            size: (verts * std::mem::size_of::<LineVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Replace the line list (pairs of vertices); empty draws nothing.
+    pub fn set(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, verts: &[LineVertex]) {
+        self.count = (verts.len() & !1) as u32;
+        if self.count == 0 { return; }
+        let bytes = std::mem::size_of_val(verts) as u64;
+        if bytes > self.vbuf.size() { self.vbuf = Self::buffer(device, verts.len().next_power_of_two()); }
+        queue.write_buffer(&self.vbuf, 0, bytemuck::cast_slice(verts));
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize { self.count as usize }
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.count == 0 }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, camera_bg: &'a wgpu::BindGroup) {
+        if self.count == 0 { return; }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera_bg, &[]);
+        pass.set_vertex_buffer(0, self.vbuf.slice(..));
+        pass.draw(0..self.count, 0..1);
+    }
+}
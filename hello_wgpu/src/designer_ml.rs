@@ -1,45 +1,195 @@
 use cgmath::Vector3;
 use crate::assets::{AssetLibrary, BuildingCategory};
 
-use crate::chunking::{block_world_span, chunk_world_span, CityGenParams};
-#[derive(Copy, Clone)]
+use crate::chunking::{block_world_span, chunk_world_span, CityGenParams, SubBox};
+use crate::rng::{hash2, Rng};
+#[derive(Clone)]
 pub struct Placement {
     pub center: Vector3<f32>,
     pub scale:  Vector3<f32>,
     pub archetype_id: u16,
+    /// Extra boxes for multi-part buildings (see `chunking::SubBox`); more
+    /// than `chunking::MAX_STACK` are dropped.
+    pub stack: Option<Box<[SubBox]>>,
 }
 
 pub struct DesignContext {
     pub cx: i32,
     pub cz: i32,
     pub seed: u64,
+    /// World-space district layout (`district_at`), the same for every chunk.
+    pub districts: DistrictMap,
+}
+
+impl DesignContext {
+    /// Chunk `(cx, cz)` of the city seeded `seed`, default districts.
+    pub fn new(cx: i32, cz: i32, seed: u64) -> Self {
+        Self { cx, cz, seed, districts: DistrictMap::default() }
+    }
+
+    /// District of the design-space point `(x, z)`.
+    pub fn district_at(&self, x: f32, z: f32) -> District { self.districts.sample(self.seed, x, z) }
 }
 
 pub trait CityDesigner {
     fn design_chunk(&mut self, ctx: &DesignContext, assets: &AssetLibrary) -> Vec<Placement>;
+
+    /// Tag appended to `ChunkManager::store_namespace`, so chunks saved while
+    /// one designer was active don't load under another.  Empty keeps the
+    /// plain params namespace.
+    fn name(&self) -> &str { "" }
 }
 
-// RNG
-struct XorShift64(u64);
-impl XorShift64 {
-    fn new(seed: u64) -> Self { Self(seed | 1) }
-    fn next(&mut self) -> u64 { let mut x=self.0; x^=x<<13; x^=x>>7; x^=x<<17; self.0=x; x }
-    fn unit_f32(&mut self) -> f32 { (self.next() as f64 / u64::MAX as f64) as f32 }
+// ---------------- Rule designer with techno-medieval flavor ----------------
+
+/// How buildings sit inside their lots.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LotLayout {
+    /// Centred on the lot grid.
+    Grid,
+    /// Each building is moved by a world-space hash anywhere its footprint
+    /// still fits inside its lot cell (lot + gap) minus `min_gap / 2` per side
+    /// (stratified / jittered-grid sampling), so neighbours stay at least
+    /// `min_gap` apart and never line up exactly.
+    Jittered { min_gap: f32 },
 }
-pub(crate) fn hash2(a: i32, b: i32) -> u64 {
-    let mut x = (a as i64 as i128) as u128 ^ (((b as i64 as i128) << 1) as u128) ^ 0x9E37_79B9_7F4A_7C15u128;
-    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9u128);
-    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EBu128);
-    (x ^ (x >> 31)) as u64
+
+/// Smooth world-space multiplier on building height: value noise with
+/// features roughly `scale` metres across, swinging heights by ±`amplitude`.
+/// Sampled at the lot centre after the category's random `sy`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeightField {
+    pub scale:     f32,
+    pub amplitude: f32,
 }
 
-// ---------------- Rule designer with techno-medieval flavor ----------------
+impl HeightField {
+    /// No shaping (multiplier 1 everywhere).
+    pub const FLAT: Self = Self { scale: 1.0, amplitude: 0.0 };
+
+    /// Multiplier at world `(x, z)`, in `[1 - amplitude, 1 + amplitude]`
+    /// (never below 0.1).
+    pub fn sample(&self, seed: u64, x: f32, z: f32) -> f32 {
+        if self.amplitude == 0.0 { return 1.0; }
+        let lattice = |ix: i32, iz: i32| (hash2(ix, iz) ^ seed).wrapping_mul(0x2545_F491_4F6C_DD1D) >> 40;
+        let value = |x: f32, z: f32| {
+            let (fx, fz) = (x.floor(), z.floor());
+            let (tx, tz) = (x - fx, z - fz);
+            let (sx, sz) = (tx * tx * (3.0 - 2.0 * tx), tz * tz * (3.0 - 2.0 * tz));
+            let (ix, iz) = (fx as i32, fz as i32);
+            let v = |dx, dz| lattice(ix + dx, iz + dz) as f32 / (1u64 << 24) as f32;
+            let a = v(0, 0) + (v(1, 0) - v(0, 0)) * sx;
+            let b = v(0, 1) + (v(1, 1) - v(0, 1)) * sx;
+            a + (b - a) * sz
+        };
+        // two octaves, renormalised to 0..1
+        let s = self.scale.max(1e-3);
+        let n = (value(x / s, z / s) * 2.0 + value(x * 2.0 / s + 17.5, z * 2.0 / s - 9.25)) / 3.0;
+        (1.0 + self.amplitude * (2.0 * n - 1.0)).max(0.1)
+    }
+}
+
+// ---------------- districts ----------------
+
+/// Coarse named quarter of the city a lot belongs to (`DistrictMap`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum District { Market, Industrial, Citadel }
+
+impl District {
+    pub const ALL: [District; 3] = [District::Market, District::Industrial, District::Citadel];
+
+    /// Multipliers on the (lowrise, highrise, landmark) zone weights.
+    pub fn category_bias(self) -> (f32, f32, f32) {
+        match self {
+            District::Market     => (2.0, 0.5, 0.6),
+            District::Industrial => (0.6, 2.5, 0.4),
+            District::Citadel    => (0.8, 0.6, 3.0),
+        }
+    }
+
+    /// Multiplier on `Archetype::weight` for the archetype called `name`.
+    pub fn archetype_bias(self, name: &str) -> f32 {
+        match (self, name) {
+            (District::Market, "timber_house_a" | "timber_house_b") => 2.0,
+            (District::Industrial, "workshop_neon" | "block_tower_a" | "block_tower_b") => 3.0,
+            (District::Citadel, "pyramid_citadel") => 3.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Districts as a Voronoi diagram in design space: one site per square of
+/// `cell` metres, jittered inside it, its district hashed from the seed
+/// with the square.  A point belongs to the nearest site, so the layout is
+/// seamless and doesn't depend on which chunk asks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DistrictMap {
+    pub cell: f32,
+}
+
+impl Default for DistrictMap {
+    fn default() -> Self { Self { cell: 320.0 } }
+}
+
+impl DistrictMap {
+    /// Site of square `(ix, iz)`: design-space position and district.
+    pub fn site(&self, seed: u64, ix: i32, iz: i32) -> ((f32, f32), District) {
+        let h = (hash2(ix, iz) ^ seed.rotate_left(29)).wrapping_mul(0x2545_F491_4F6C_DD1D);
+        let u = ((h >> 40) & 0xFFFF) as f32 / 65535.0;
+        let v = ((h >> 24) & 0xFFFF) as f32 / 65535.0;
+        let c = self.cell.max(1.0);
+        (((ix as f32 + u) * c, (iz as f32 + v) * c), District::ALL[(h >> 8) as usize % District::ALL.len()])
+    }
+
+    /// District of the design-space point `(x, z)`.
+    pub fn sample(&self, seed: u64, x: f32, z: f32) -> District {
+        let c = self.cell.max(1.0);
+        let (ix, iz) = ((x / c).floor() as i32, (z / c).floor() as i32);
+        // a jittered site stays in its square, but one two squares away can
+        // still beat the 3×3 around, so search the 5×5
+        let mut best = (f32::INFINITY, District::Market);
+        for dz in -2..=2 {
+            for dx in -2..=2 {
+                let ((sx, sz), d) = self.site(seed, ix + dx, iz + dz);
+                let d2 = (sx - x) * (sx - x) + (sz - z) * (sz - z);
+                if d2 < best.0 { best = (d2, d); }
+            }
+        }
+        best.1
+    }
+}
 
 pub struct RuleDesigner {
     pub params: CityGenParams,
+    pub layout: LotLayout,
+    pub height: HeightField,
+    /// How much the lot's district (`DesignContext::district_at`) skews the
+    /// category and archetype odds: its biases are raised to this power, so
+    /// 0 ignores districts and 1 applies them in full.
+    pub district_strength: f32,
+    /// `CityDesigner::name`; set it when several configurations share a store.
+    pub name: &'static str,
 }
 
 impl RuleDesigner {
+    pub fn new(params: CityGenParams) -> Self {
+        Self { params, layout: LotLayout::Grid, height: HeightField::FLAT, district_strength: 0.0, name: "" }
+    }
+
+    /// Offset of a building with footprint half-size `(fx, fz)` on the lot at
+    /// world `(x, z)`; hashed from the position so it is the same whichever
+    /// chunk designs it.
+    fn lot_jitter(&self, seed: u64, x: f32, z: f32, fx: f32, fz: f32) -> (f32, f32) {
+        let LotLayout::Jittered { min_gap } = self.layout else { return (0.0, 0.0) };
+        let p = &self.params;
+        let slack_x = (0.5 * (p.lot_w + p.lot_gap) - fx - 0.5 * min_gap).max(0.0);
+        let slack_z = (0.5 * (p.lot_d + p.lot_gap) - fz - 0.5 * min_gap).max(0.0);
+        let h = hash2((x * 8.0).round() as i32, (z * 8.0).round() as i32) ^ seed.rotate_left(17);
+        let u = (h & 0xFFFF) as f32 / 65535.0;
+        let v = ((h >> 16) & 0xFFFF) as f32 / 65535.0;
+        ((2.0 * u - 1.0) * slack_x, (2.0 * v - 1.0) * slack_z)
+    }
+
     fn zone_weights(&self, x: f32, z: f32) -> (f32,f32,f32) {
         // Medieval “old town” near center, tech ring farther out.
         let dist = x.hypot(z);
@@ -54,11 +204,24 @@ impl RuleDesigner {
         (w_low/s, w_high/s, w_land/s)
     }
 
-    fn pick_archetype(assets: &AssetLibrary, cat: BuildingCategory, rng: &mut XorShift64) -> Option<usize> {
+    /// One archetype of `cat`, chosen by `Archetype::weight` × `bias`.  Equal
+    /// weights keep the plain modulo pick, so existing seeds design the same city.
+    fn pick_archetype(assets: &AssetLibrary, cat: BuildingCategory, rng: &mut Rng, bias: impl Fn(usize) -> f32) -> Option<usize> {
         let ids = assets.indices_by_category(cat);
-        if ids.is_empty() { return None; }
-        let k = (rng.next() as usize) % ids.len();
-        Some(ids[k])
+        let weight = |id: usize| assets.archetypes[id].weight.max(0.0) * bias(id);
+        let first = weight(*ids.first()?);
+        let total: f32 = ids.iter().map(|&id| weight(id)).sum();
+        if total <= 0.0 || ids.iter().all(|&id| weight(id) == first) {
+            let k = (rng.next_u64() as usize) % ids.len();
+            return Some(ids[k]);
+        }
+        let mut r = rng.next_f32() * total;
+        for &id in ids {
+            r -= weight(id);
+            if r < 0.0 { return Some(id); }
+        }
+        // rounding left r ≥ 0: the last archetype that can spawn
+        ids.iter().rev().copied().find(|&id| weight(id) > 0.0)
     }
 }
 
@@ -69,7 +232,7 @@ impl CityDesigner for RuleDesigner {
         let chunk_org_x = ctx.cx as f32 * sx;
         let chunk_org_z = ctx.cz as f32 * sz;
 
-        let mut rng = XorShift64::new(self.params.seed ^ hash2(ctx.cx, ctx.cz));
+        let mut rng = Rng::new(ctx.seed ^ hash2(ctx.cx, ctx.cz));
 
         let mut out = Vec::with_capacity(
             self.params.blocks_per_chunk_x * self.params.blocks_per_chunk_z
@@ -84,10 +247,10 @@ impl CityDesigner for RuleDesigner {
 
                 let mut block_x = -0.5*sx + bxi as f32 * bx + self.params.road_w_minor * 0.5;
                 let mut block_z = -0.5*sz + bzi as f32 * bz + self.params.road_w_minor * 0.5;
-                if (bxi % self.params.major_every) > 0 && ((bxi / self.params.major_every) > 0) {
+                if self.params.major_every > 0 && (bxi % self.params.major_every) > 0 && ((bxi / self.params.major_every) > 0) {
                     block_x += (self.params.road_w_major - self.params.road_w_minor) * ((bxi / self.params.major_every) as f32);
                 }
-                if (bzi % self.params.major_every) > 0 && ((bzi / self.params.major_every) > 0) {
+                if self.params.major_every > 0 && (bzi % self.params.major_every) > 0 && ((bzi / self.params.major_every) > 0) {
                     block_z += (self.params.road_w_major - self.params.road_w_minor) * ((bzi / self.params.major_every) as f32);
                 }
 
@@ -97,6 +260,12 @@ impl CityDesigner for RuleDesigner {
                         let z = chunk_org_z + block_z + (lz as f32) * (self.params.lot_d + self.params.lot_gap) + self.params.lot_d * 0.5;
 
                         let (mut w_low, mut w_high, mut w_land) = self.zone_weights(x, z);
+                        let district = ctx.district_at(x, z);
+                        let k = self.district_strength;
+                        if k != 0.0 {
+                            let (bl, bh, bm) = district.category_bias();
+                            w_low *= bl.powf(k); w_high *= bh.powf(k); w_land *= bm.powf(k);
+                        }
 
                         // Occasionally inject a landmark “gate” near grid seams to suggest walls.
                         if ((x / 60.0).sin().abs() < 0.02) || ((z / 60.0).cos().abs() < 0.02) {
@@ -106,7 +275,7 @@ impl CityDesigner for RuleDesigner {
                         w_low /= s; w_high /= s; w_land /= s;
 
                         // category pick
-                        let pick = rng.unit_f32();
+                        let pick = rng.next_f32();
                         let cat = if pick < w_low {
                             BuildingCategory::Lowrise
                         } else if pick < (w_low + w_high) {
@@ -115,23 +284,26 @@ impl CityDesigner for RuleDesigner {
                             BuildingCategory::Landmark
                         };
 
-                        let id = Self::pick_archetype(assets, cat, &mut rng).unwrap_or(0);
+                        let bias = |id: usize| if k == 0.0 { 1.0 } else { district.archetype_bias(assets.archetypes[id].name).powf(k) };
+                        let id = Self::pick_archetype(assets, cat, &mut rng, bias).unwrap_or(0);
 
-                        let sx = 0.85 + 0.35 * rng.unit_f32();
-                        let sz = 0.85 + 0.35 * rng.unit_f32();
+                        let sx = 0.85 + 0.35 * rng.next_f32();
+                        let sz = 0.85 + 0.35 * rng.next_f32();
                         let sy = match cat {
-                            BuildingCategory::Lowrise  => 0.8 + 0.7 * rng.unit_f32(),
-                            BuildingCategory::Highrise => 1.2 + 1.3 * rng.unit_f32(),
-                            BuildingCategory::Landmark => 1.0 + 1.2 * rng.unit_f32(),
-                        };
+                            BuildingCategory::Lowrise  => 0.8 + 0.7 * rng.next_f32(),
+                            BuildingCategory::Highrise => 1.2 + 1.3 * rng.next_f32(),
+                            BuildingCategory::Landmark => 1.0 + 1.2 * rng.next_f32(),
+                        } * self.height.sample(ctx.seed, x, z);
 
                         let base = assets.base_half(id);
-                        let center_y = base.y * sy;
+                        let scale = Vector3::new(sx, sy, sz);
+                        let (jx, jz) = self.lot_jitter(ctx.seed, x, z, base.x * sx, base.z * sz);
 
                         out.push(Placement {
-                            center: Vector3::new(x, center_y, z),
-                            scale:  Vector3::new(sx, sy, sz),
+                            center: Vector3::new(x + jx, assets.ground_y_for(id, scale), z + jz),
+                            scale,
                             archetype_id: id as u16,
+                            stack: None,
                         });
                     }
                 }
@@ -139,4 +311,31 @@ impl CityDesigner for RuleDesigner {
         }
         out
     }
+
+    fn name(&self) -> &str { self.name }
 }
+
+// ---------------- built-in designers ----------------
+
+/// A designer `ChunkManager::set_designer` can switch to, built for a city.
+pub type DesignerCtor = fn(CityGenParams) -> Box<dyn CityDesigner>;
+
+/// Every built-in designer by display name, the app's default first.
+pub const BUILTIN_DESIGNERS: [(&str, DesignerCtor); 4] = [
+    // jittered lots, skyline rising and falling over ~400 m (classic namespace)
+    ("skyline", |p| Box::new(RuleDesigner {
+        layout: LotLayout::Jittered { min_gap: 0.4 },
+        height: HeightField { scale: 400.0, amplitude: 0.45 },
+        ..RuleDesigner::new(p)
+    })),
+    ("grid", |p| Box::new(RuleDesigner { name: "grid", ..RuleDesigner::new(p) })),
+    ("jittered", |p| Box::new(RuleDesigner { layout: LotLayout::Jittered { min_gap: 0.4 }, name: "jittered", ..RuleDesigner::new(p) })),
+    // the skyline city split into market / industrial / citadel quarters
+    ("districts", |p| Box::new(RuleDesigner {
+        layout: LotLayout::Jittered { min_gap: 0.4 },
+        height: HeightField { scale: 400.0, amplitude: 0.45 },
+        district_strength: 1.0,
+        name: "districts",
+        ..RuleDesigner::new(p)
+    })),
+];
@@ -0,0 +1,140 @@
+//! Facade textures: a small procedurally generated 2D texture array (group 3)
+//! that `fs_main` samples with the box-unwrapped vertex UVs and tints by the
+//! palette.  Texels with alpha < 0.5 are discarded (alpha test), which is how
+//! the lattice layer gets its openings.  Layer 0 is plain white so an
+//! archetype without a facade keeps the flat-shaded look.
+
+pub const FACADE_SIZE: u32 = 64;
+
+pub const LAYER_PLAIN:   u32 = 0;
+pub const LAYER_CHECKER: u32 = 1;
+pub const LAYER_WINDOWS: u32 = 2;
+pub const LAYER_LATTICE: u32 = 3;
+pub const LAYER_COUNT:   u32 = 4;
+
+pub struct FacadeTextures {
+    pub bgl: wgpu::BindGroupLayout,
+    pub bg:  wgpu::BindGroup,
+    texture: wgpu::Texture,
+}
+
+/// RGBA8 texels of one `size`×`size` layer (u right, v down).
+fn layer_pixels(layer: u32, size: u32) -> Vec<u8> {
+    let mut px = Vec::with_capacity((size * size * 4) as usize);
+    let cell = (size / 8).max(1);
+    for y in 0..size {
+        for x in 0..size {
+            let (cx, cy) = (x % cell, y % cell);
+            let rgba: [u8; 4] = match layer {
+                LAYER_CHECKER => {
+                    if ((x / cell) + (y / cell)).is_multiple_of(2) { [255, 255, 255, 255] } else { [200, 200, 200, 255] }
+                }
+                LAYER_WINDOWS => {
+                    // 4×4 windows per tile, a frame of wall around each pane
+                    let (wx, wy) = (x % (size / 4), y % (size / 4));
+                    let pane = wx >= 3 && wx < size / 4 - 3 && wy >= 4 && wy < size / 4 - 2;
+                    if pane { [70, 90, 120, 255] } else { [235, 230, 220, 255] }
+                }
+                LAYER_LATTICE => {
+                    let bar = cx < 2 || cy < 2;
+                    if bar { [230, 225, 215, 255] } else { [0, 0, 0, 0] }
+                }
+                _ => [255, 255, 255, 255],
+            };
+            px.extend_from_slice(&rgba);
+        }
+    }
+    px
+}
+
+/// 2×2 box filter for the next mip level.
+fn downsample(src: &[u8], size: u32) -> Vec<u8> {
+    let half = (size / 2).max(1);
+    let mut out = Vec::with_capacity((half * half * 4) as usize);
+    for y in 0..half {
+        for x in 0..half {
+            for c in 0..4 {
+                let at = |dx: u32, dy: u32| src[(((y*2 + dy) * size + x*2 + dx) * 4 + c) as usize] as u32;
+                out.push(((at(0,0) + at(1,0) + at(0,1) + at(1,1)) / 4) as u8);
+            }
+        }
+    }
+    out
+}
+
+impl FacadeTextures {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let mips = FACADE_SIZE.ilog2() + 1;
+        let tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("facade array"),
+            size: wgpu::Extent3d { width: FACADE_SIZE, height: FACADE_SIZE, depth_or_array_layers: LAYER_COUNT },
+            mip_level_count: mips, sample_count: 1, dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for layer in 0..LAYER_COUNT {
+            let mut px = layer_pixels(layer, FACADE_SIZE);
+            let mut size = FACADE_SIZE;
+            for mip in 0..mips {
+                queue.write_texture(
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &tex, mip_level: mip,
+                        origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &px,
+                    wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(4 * size), rows_per_image: Some(size) },
+                    wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+                );
+                if size > 1 { px = downsample(&px, size); size /= 2; }
+            }
+        }
+        let view = tex.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("facade sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("facade bgl"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("facade bg"),
+            layout: &bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) },
+            ],
+        });
+        Self { bgl, bg, texture: tex }
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture { &self.texture }
+}
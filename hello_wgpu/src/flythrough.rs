@@ -0,0 +1,133 @@
+//! Camera flythrough recording & deterministic playback.
+//! Samples are taken at a fixed interval in *world* space (local position +
+//! floating-origin offset) so a path survives origin shifts and torus wraps.
+
+use cgmath::{InnerSpace, Vector3};
+use serde::{Serialize, Deserialize};
+
+use crate::camera::CameraState;
+
+/// Recorded path: `samples[i]` taken at `i * interval` seconds.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub interval: f32,
+    pub samples:  Vec<CameraState>,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.samples.len().saturating_sub(1) as f32 * self.interval
+    }
+    pub fn to_bytes(&self) -> Vec<u8> { bincode::serialize(self).expect("bincode serialize") }
+    pub fn from_bytes(b: &[u8]) -> Option<Self> { bincode::deserialize(b).ok() }
+
+    /// Pose at time `t` (clamped): lerp position, slerp the look direction.
+    pub fn sample(&self, t: f32) -> Option<CameraState> {
+        let first = *self.samples.first()?;
+        if self.samples.len() == 1 || self.interval <= 0.0 { return Some(first); }
+        let f = (t / self.interval).clamp(0.0, (self.samples.len() - 1) as f32);
+        let i = (f.floor() as usize).min(self.samples.len() - 2);
+        let u = f - i as f32;
+        let (a, b) = (self.samples[i], self.samples[i + 1]);
+
+        let pa = Vector3::from(a.position);
+        let pb = Vector3::from(b.position);
+        let p = pa + (pb - pa) * u;
+
+        let d = slerp(dir_of(&a), dir_of(&b), u);
+        Some(CameraState {
+            position: p.into(),
+            yaw:   d.z.atan2(d.x),
+            pitch: d.y.clamp(-1.0, 1.0).asin(),
+        })
+    }
+}
+
+// same convention as Camera::update_axes_from_angles
+fn dir_of(s: &CameraState) -> Vector3<f32> {
+    let (cy, sy, cp, sp) = (s.yaw.cos(), s.yaw.sin(), s.pitch.cos(), s.pitch.sin());
+    Vector3::new(cy * cp, sp, sy * cp)
+}
+
+fn slerp(a: Vector3<f32>, b: Vector3<f32>, u: f32) -> Vector3<f32> {
+    let dot = a.dot(b).clamp(-1.0, 1.0);
+    let th = dot.acos();
+    if th < 1e-4 { return (a + (b - a) * u).normalize(); }
+    let s = th.sin();
+    (a * ((1.0 - u) * th).sin() / s + b * (u * th).sin() / s).normalize()
+}
+
+// ───────────────────────── recorder ─────────────────────────
+pub struct CameraRecorder {
+    path: CameraPath,
+    acc:  f32,
+}
+
+impl CameraRecorder {
+    pub fn new(interval: f32) -> Self {
+        Self { path: CameraPath { interval: interval.max(1e-3), samples: Vec::new() }, acc: 0.0 }
+    }
+    /// Feed elapsed time and the current world-space pose; samples on the
+    /// interval grid so the output doesn't depend on frame rate.
+    pub fn tick(&mut self, dt: f32, world: CameraState) {
+        if self.path.samples.is_empty() { self.path.samples.push(world); return; }
+        self.acc += dt;
+        while self.acc >= self.path.interval {
+            self.acc -= self.path.interval;
+            self.path.samples.push(world);
+        }
+    }
+    pub fn finish(self) -> CameraPath { self.path }
+}
+
+// ───────────────────────── player ─────────────────────────
+pub struct CameraPlayer {
+    path: CameraPath,
+    t:    f32,
+}
+
+impl CameraPlayer {
+    pub fn new(path: CameraPath) -> Self { Self { path, t: 0.0 } }
+    /// Advance and return the world-space pose; `None` once the path ends.
+    pub fn tick(&mut self, dt: f32) -> Option<CameraState> {
+        if self.t > self.path.duration() { return None; }
+        let s = self.path.sample(self.t);
+        self.t += dt;
+        s
+    }
+}
+
+// ───────────────────────── click-to-move ─────────────────────────
+/// Eased (smoothstep) flight of the camera position from `from` to `to`;
+/// orientation stays with the user.  Positions are local space, so origin
+/// shifts must be passed on through `shift`.
+#[derive(Clone, Debug)]
+pub struct FlyTo {
+    from: Vector3<f32>,
+    to:   Vector3<f32>,
+    duration: f32,
+    t:    f32,
+}
+
+impl FlyTo {
+    pub fn new(from: Vector3<f32>, to: Vector3<f32>, duration: f32) -> Self {
+        Self { from, to, duration: duration.max(0.0), t: 0.0 }
+    }
+    pub fn target(&self) -> Vector3<f32> { self.to }
+
+    /// The world moved by `-off` (floating origin / torus wrap).
+    pub fn shift(&mut self, off: Vector3<f32>) {
+        self.from -= off;
+        self.to -= off;
+    }
+
+    /// Advance and return the position; the last one is exactly `to`, then
+    /// `None`.
+    pub fn tick(&mut self, dt: f32) -> Option<Vector3<f32>> {
+        if self.t > self.duration { return None; }
+        let u = if self.duration > 0.0 { (self.t / self.duration).min(1.0) } else { 1.0 };
+        let s = u * u * (3.0 - 2.0 * u);
+        self.t = if u >= 1.0 { f32::INFINITY } else { self.t + dt };
+        Some(self.from + (self.to - self.from) * s)
+    }
+}
@@ -0,0 +1,136 @@
+//! Orientation gizmo: an XYZ axis tripod (R=+X, G=+Y, B=+Z) drawn as a line
+//! list into a small viewport in the bottom-left corner, rotated by the
+//! camera's view matrix with the translation stripped.  It is drawn last in
+//! the main pass and ignores the scene depth.
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix4, Vector4};
+use wgpu::util::DeviceExt;
+
+use crate::shadow::OPENGL_TO_WGPU;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GizmoVertex {
+    pos:   [f32; 3],
+    color: [f32; 3],
+}
+
+const AXES: [GizmoVertex; 6] = [
+    GizmoVertex { pos: [0.0, 0.0, 0.0], color: [1.0, 0.2, 0.2] },
+    GizmoVertex { pos: [1.0, 0.0, 0.0], color: [1.0, 0.2, 0.2] },
+    GizmoVertex { pos: [0.0, 0.0, 0.0], color: [0.2, 1.0, 0.2] },
+    GizmoVertex { pos: [0.0, 1.0, 0.0], color: [0.2, 1.0, 0.2] },
+    GizmoVertex { pos: [0.0, 0.0, 0.0], color: [0.3, 0.4, 1.0] },
+    GizmoVertex { pos: [0.0, 0.0, 1.0], color: [0.3, 0.4, 1.0] },
+];
+
+pub struct AxisGizmo {
+    pub enabled: bool,
+    /// Side of the square corner viewport (px) and its distance from the edges.
+    pub size_px:   u32,
+    pub margin_px: u32,
+    pipeline: wgpu::RenderPipeline,
+    vbuf: wgpu::Buffer,
+    ubuf: wgpu::Buffer,
+    bg:   wgpu::BindGroup,
+}
+
+impl AxisGizmo {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat, depth_format: wgpu::TextureFormat, samples: u32) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gizmo shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/gizmo.wgsl").into()),
+        });
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gizmo bgl"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+        let ubuf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gizmo uniform"), size: 64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gizmo bg"),
+            layout: &bgl,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: ubuf.as_entire_binding() }],
+        });
+        let vbuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("gizmo vb"),
+            contents: bytemuck::cast_slice(&AXES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gizmo pipe layout"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("gizmo pipe"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_gizmo"),
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<GizmoVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_gizmo"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format, blend: None, write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState { topology: wgpu::PrimitiveTopology::LineList, ..Default::default() },
+            // the pass has the scene depth attached; never test or write it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState { count: samples, ..Default::default() },
+            multiview: None,
+            cache: None,
+        });
+        Self { enabled: false, size_px: 96, margin_px: 12, pipeline, vbuf, ubuf, bg }
+    }
+
+    /// Upload the camera rotation (translation of `view` is ignored).
+    pub fn update(&self, queue: &wgpu::Queue, view: &Matrix4<f32>) {
+        let mut rot = *view;
+        rot.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let proj = cgmath::ortho(-1.2, 1.2, -1.2, 1.2, -2.0, 2.0);
+        let m: [[f32; 4]; 4] = (OPENGL_TO_WGPU * proj * rot).into();
+        queue.write_buffer(&self.ubuf, 0, bytemuck::bytes_of(&m));
+    }
+
+    /// Draw into the bottom-left corner of a `width`×`height` target.
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, width: u32, height: u32) {
+        if !self.enabled { return; }
+        let size = self.size_px.min(width).min(height);
+        let margin = self.margin_px.min(width - size).min(height - size);
+        if size == 0 { return; }
+        pass.set_viewport(margin as f32, (height - size - margin) as f32, size as f32, size as f32, 0.0, 1.0);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bg, &[]);
+        pass.set_vertex_buffer(0, self.vbuf.slice(..));
+        pass.draw(0..AXES.len() as u32, 0..1);
+    }
+}
@@ -3,15 +3,15 @@
 
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}, Mutex};
 
-use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Vector3};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3, Zero};
 use instant::Instant;
 use log::{info, warn, error};
 use winit::{
     application::ApplicationHandler,
-    dpi::PhysicalPosition,
-    event::{ElementState, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, DeviceId, ElementState, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    keyboard::PhysicalKey,
+    keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowAttributes, WindowId},
 };
 
@@ -20,25 +20,34 @@ use {
     wasm_bindgen::prelude::*,
     wasm_bindgen::JsCast,
     web_sys::HtmlCanvasElement,
-    winit::platform::web::WindowAttributesExtWebSys,
+    winit::platform::web::{WindowAttributesExtWebSys, WindowExtWebSys},
 };
 
 use crate::{
-    assets::{AssetLibrary, BuildingCategory},
+    assets::{AssetLibrary, BuildingCategory, GROUND_PLANE_SIZE},
     camera,
-    chunking::{ChunkManager, ViewerId},
+    chunking::{self, ChunkKey, ChunkManager, ChunkSource, CityGenParams, RuntimePlacement, ViewerId},
+    city_store::StoreBackend,
     culling,
-    designer_ml::{RuleDesigner, CityDesigner},
-    mesh,
+    debug_lines::{self, LineVertex},
+    designer_ml::BUILTIN_DESIGNERS,
+    flythrough::{CameraPath, CameraPlayer, CameraRecorder, FlyTo},
     net_mutations,
-    render::Engine,
-    types::InstanceRaw,
+    quality::QualityScaler,
+    rng,
+    render::{Engine, HeatGrid, HEAT_SIDE},
+    smoke::{smoke_emitters, SmokeParams, SmokeSystem},
+    types::{InstanceRaw, TINT_GROUND},
 };
 
 // ───────────────────────── logging ─────────────────────────
-fn init_logging(web: bool) {
-    #[cfg(target_arch = "wasm32")]
-    { let _ = console_log::init_with_level(log::Level::Info); }
+pub(crate) fn init_logging(web: bool) {
+    // web: Info unless the page URL carries `?log=<spec>` (see `parse_log_level`);
+    // JS can change it later with `set_log_level`
+    #[cfg(target_arch = "wasm32")] {
+        let _ = console_log::init_with_level(log::Level::Trace);
+        log::set_max_level(query_log_level().unwrap_or(log::LevelFilter::Info));
+    }
 
     #[cfg(not(target_arch = "wasm32"))] {
         let env = env_logger::Env::default()
@@ -48,21 +57,329 @@ fn init_logging(web: bool) {
     info!("logging ready (web={})", web);
 }
 
+/// Level from a `RUST_LOG`-style spec: a bare level (`debug`) or this
+/// crate's directive (`hello_wgpu=trace`, which wins).  Directives for other
+/// modules are ignored because the web console logger has one global level.
+pub fn parse_log_level(spec: &str) -> Option<log::LevelFilter> {
+    let mut level = None;
+    for d in spec.split(',').map(str::trim) {
+        match d.split_once('=') {
+            Some(("hello_wgpu", l)) => return l.parse().ok(),
+            Some(_) => {}
+            None => level = d.parse().ok().or(level),
+        }
+    }
+    level
+}
+
+#[cfg(target_arch = "wasm32")]
+fn query_log_level() -> Option<log::LevelFilter> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let value = search.trim_start_matches('?').split('&').find_map(|kv| kv.strip_prefix("log="))?;
+    parse_log_level(&value.replace("%3D", "=").replace("%3d", "=").replace("%2C", ",").replace("%2c", ","))
+}
+
+/// JS: change the console log level at runtime (`"debug"`, `"hello_wgpu=trace"`, `"off"` …).
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+    let filter = parse_log_level(level).ok_or_else(|| JsValue::from_str(&format!("unknown log level {level:?}")))?;
+    log::set_max_level(filter);
+    Ok(())
+}
+
 // ───────────────────────── public entry ─────────────────────
+/// Everything an embedder configures: window, renderer and world.
+/// `Default` is the demo city as `run` shows it.
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub title:  String,
+    /// Initial inner size in logical pixels (native).  On the web the
+    /// canvas's CSS size wins and these are only the fallback.
+    pub width:  u32,
+    pub height: u32,
+    /// Native: open borderless fullscreen on the current monitor.
+    pub fullscreen: bool,
+    /// Native graphics backends to pick the adapter from; the web always
+    /// tries WebGPU, then WebGL.
+    pub backends: wgpu::Backends,
+
+    /// Scene MSAA samples: 1 (off) or 4.
+    pub sample_count: u32,
+    /// Falls back to `Fifo` when the surface doesn't offer it.
+    pub present_mode: wgpu::PresentMode,
+    /// Native frame cap: the loop sleeps until the next frame is due; 0 =
+    /// uncapped (only the present mode paces it).  The browser paces itself.
+    pub target_fps: f32,
+    /// sRGB, like the palette and tints.
+    pub clear_color:  wgpu::Color,
+    /// Light the scene in linear HDR and tone-map it into the frame when the
+    /// adapter can render a float target; otherwise (or when false) render LDR.
+    pub hdr: bool,
+
+    /// LOD ring radii and building render range (m); `lod0 ≤ lod1 ≤
+    /// billboard_cull`.  Past `mesh_cull` (≤ `billboard_cull`) buildings are
+    /// only drawn as billboards, whatever their ring.
+    pub lod0: f32,
+    pub lod1: f32,
+    pub mesh_cull: f32,
+    pub billboard_cull: f32,
+    /// Half-width (m) of the ground plane kept under the camera.
+    pub ground_extent: f32,
+    /// Chunks kept loaded around the viewer; raised if needed to cover `billboard_cull`.
+    pub chunk_radius: i32,
+    /// Upper bound per instance bucket (LOD × archetype group); the nearest
+    /// are kept and the rest dropped for the frame.
+    pub max_instances_per_bucket: usize,
+    /// Click-to-move (C): flight time (s) and eye height above the clicked
+    /// ground point (m).
+    pub fly_to_secs:   f32,
+    pub fly_to_height: f32,
+    /// A building drops to a coarser LOD only this far (m) past its ring,
+    /// so a bobbing camera doesn't flicker it between meshes; 0 disables.
+    pub lod_margin: f32,
+    /// Draw LOD2 billboards as impostors (`Engine::set_impostors`), from an
+    /// atlas of `impostor_tile`-texel views baked at startup; the tile is
+    /// shrunk to fit the device's largest texture.
+    pub impostors: bool,
+    pub impostor_tile: u32,
+    /// Chimney smoke over landmarks and industrial quarters (off by default;
+    /// see `SmokeParams` for the density and caps).
+    pub smoke: SmokeParams,
+    /// City layout; `city.seed` seeds the world.
+    pub city:  CityGenParams,
+    /// Chunks the world spans, inclusive `(min_x, max_x, min_z, max_z)`;
+    /// with `wrap` it repeats as a torus, else the city ends there.
+    pub bounds: (i32, i32, i32, i32),
+    pub wrap:   bool,
+    pub store: StoreBackend,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            title: "Techno-Medieval".into(), width: 1280, height: 720,
+            fullscreen: false, backends: wgpu::Backends::all(),
+            sample_count: 1,
+            present_mode: wgpu::PresentMode::Fifo,
+            target_fps: 60.0,
+            clear_color: wgpu::Color { r: 0.27, g: 0.27, b: 0.31, a: 1.0 },
+            hdr: true,
+            lod0: 90.0, lod1: 190.0, mesh_cull: 380.0, billboard_cull: 380.0,
+            ground_extent: GROUND_PLANE_SIZE * 0.5,
+            chunk_radius: 3,
+            max_instances_per_bucket: 65_536,
+            fly_to_secs: 1.5, fly_to_height: 12.0,
+            lod_margin: 8.0,
+            impostors: false, impostor_tile: crate::impostor::IMPOSTOR_TILE,
+            smoke: SmokeParams::default(),
+            city: CityGenParams::default(),
+            bounds: (-4, 4, -4, 4), wrap: true,
+            store: StoreBackend::platform("./city_chunks"),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// First problem that would stop `run_with`, if any.
+    pub fn validate(&self) -> Result<(), String> {
+        self.city.validate()?;
+        self.smoke.validate()?;
+        if !(0.0 < self.lod0 && self.lod0 <= self.lod1 && self.lod1 <= self.billboard_cull) {
+            return Err(format!("need 0 < lod0 ≤ lod1 ≤ billboard_cull (got {}, {}, {})", self.lod0, self.lod1, self.billboard_cull));
+        }
+        if !(0.0 < self.mesh_cull && self.mesh_cull <= self.billboard_cull) {
+            return Err(format!("need 0 < mesh_cull ≤ billboard_cull (got {}, {})", self.mesh_cull, self.billboard_cull));
+        }
+        if !(self.ground_extent.is_finite() && self.ground_extent > 0.0) {
+            return Err(format!("ground_extent must be positive (got {})", self.ground_extent));
+        }
+        if self.chunk_radius < 1 { return Err(format!("chunk_radius must be ≥ 1 (got {})", self.chunk_radius)); }
+        let (minx, maxx, minz, maxz) = self.bounds;
+        if minx > maxx || minz > maxz { return Err(format!("bounds need min ≤ max on both axes (got {:?})", self.bounds)); }
+        if self.backends.is_empty() { return Err("backends must include at least one backend".into()); }
+        if self.max_instances_per_bucket == 0 { return Err("max_instances_per_bucket must be ≥ 1".into()); }
+        if !matches!(self.sample_count, 1 | 4) { return Err(format!("sample_count must be 1 or 4 (got {})", self.sample_count)); }
+        if !(self.fly_to_secs.is_finite() && self.fly_to_secs >= 0.0) {
+            return Err(format!("fly_to_secs must be finite and not negative (got {})", self.fly_to_secs));
+        }
+        if !(self.fly_to_height.is_finite() && self.fly_to_height > 0.0) {
+            return Err(format!("fly_to_height must be positive (got {})", self.fly_to_height));
+        }
+        if !(self.target_fps == 0.0 || (self.target_fps.is_finite() && self.target_fps >= 1.0)) {
+            return Err(format!("target_fps must be 0 (uncapped) or at least 1 (got {})", self.target_fps));
+        }
+        if !(self.lod_margin.is_finite() && self.lod_margin >= 0.0) {
+            return Err(format!("lod_margin must be finite and not negative (got {})", self.lod_margin));
+        }
+        if self.impostor_tile == 0 { return Err("impostor_tile must be ≥ 1".into()); }
+        Ok(())
+    }
+}
+
 pub async fn run(is_web: bool) {
+    run_with(is_web, EngineConfig::default()).await
+}
+
+pub async fn run_with(is_web: bool, config: EngineConfig) {
     init_logging(is_web);
+    if let Err(e) = config.validate() { error!("invalid engine config: {e}"); return; }
     let el = EventLoop::new().expect("EL");
     el.set_control_flow(ControlFlow::Poll);
-    let mut app = App::new(is_web);
+    let mut app = App::new(is_web, config);
     if let Err(e) = el.run_app(&mut app) {
         error!("event-loop error: {e:?}");
     }
 }
 
+/// Physical size that keeps a window of `size` at the same logical size
+/// when its scale factor goes from `old` to `new` (moved to another monitor).
+pub fn rescale_size(size: PhysicalSize<u32>, old: f64, new: f64) -> PhysicalSize<u32> {
+    size.to_logical::<f64>(old).to_physical(new)
+}
+
+/// When the frame after one started at `last` is due at `target_fps`;
+/// None when uncapped (`target_fps` 0), or when the wait is too long to
+/// represent (`validate` keeps configs at ≥ 1 fps; this mustn't panic).
+pub fn frame_deadline(last: Instant, target_fps: f32) -> Option<Instant> {
+    if target_fps.is_nan() || target_fps <= 0.0 { return None; }
+    last.checked_add(std::time::Duration::try_from_secs_f32(1.0 / target_fps).ok()?)
+}
+
+// ───────────────────────── instance buckets ─────────────────
+/// Bucket every loaded chunk for the frame: chunks past `bake_distance`
+/// are kept whole (returned keys, drawn from their baked mesh); the rest
+/// go through `lod.bucket` with `rings` = (`lod0`, `lod1`).  `cull` is
+/// (`mesh_cull`, `billboard_cull`): both rings are capped at `mesh_cull`,
+/// and a baked chunk entirely past it is bucketed instead, so only
+/// billboards remain out there.  Chunks are classified against `fr`
+/// through `cache`: those wholly inside skip the per-building frustum test
+/// (every building centre is in the chunk box), those outside are skipped.
+/// Only chunks `cm.shows` are drawn.
+#[allow(clippy::too_many_arguments)]
+pub fn build_instance_buckets(
+    cm: &ChunkManager, assets: &AssetLibrary, fr: &culling::Frustum,
+    cam: Vector3<f32>, rings: (f32, f32), (mesh_cull, cull): (f32, f32), lod: &mut culling::LodHysteresis,
+    cache: &mut culling::ChunkCullCache,
+) -> (culling::Buckets, Vec<ChunkKey>) {
+    let near=|key: ChunkKey| {
+        let (c,h)=cm.chunk_aabb(key).unwrap();
+        Vector3::new(((cam.x-c.x).abs()-h.x).max(0.0),0.0,((cam.z-c.z).abs()-h.z).max(0.0)).magnitude()
+    };
+    let meshed=|key: ChunkKey| cm.is_baked(key,cam) && near(key)<=mesh_cull;
+    let mut baked_keys=Vec::new();
+    let mut live=Vec::new();
+    let axes=cache.axes;
+    for (key,list) in cm.loaded.iter().filter(|(k,_)| cm.shows(**k)) {
+        let aabb=cm.chunk_aabb(*key).unwrap();
+        let containment=cache.classify(fr,*key,cm.revision(*key),aabb,cam,|| culling::horizontal_reach(list,assets,&axes));
+        if containment==culling::Containment::Outside { continue; }
+        if meshed(*key) { baked_keys.push(*key); }
+        else { live.push((*key, containment==culling::Containment::Inside, list.as_slice())); }
+    }
+    let rings=(rings.0.min(mesh_cull), rings.1.min(mesh_cull));
+    (lod.bucket(live,cam,fr,rings,cull,assets), baked_keys)
+}
+
+/// Culled buildings outlined while the frustum is frozen (the rest are skipped).
+pub const MAX_CULLED_BOXES: usize = 2048;
+
+/// Share of the buildings around the viewer the app re-rolls per second
+/// (`ChunkManager::step`), while local mutations are on.
+pub const LIVE_MUTATION_RATE: f32 = 0.02;
+
+/// Debug overlay for a frozen culling frustum `vp`: its wireframe (yellow)
+/// plus a red box around every building within `cull` m of `cam` that it
+/// culls (at most `MAX_CULLED_BOXES`).
+pub fn frustum_debug_lines(cm: &ChunkManager, assets: &AssetLibrary, vp: &Matrix4<f32>,
+                           cam: Vector3<f32>, cull: f32) -> Vec<LineVertex> {
+    let mut out=Vec::new();
+    if let Some(corners)=culling::frustum_corners(vp) {
+        debug_lines::push_frustum(&mut out, &corners, [1.0,0.9,0.2]);
+    }
+    let fr=culling::frustum_from_vp(vp);
+    let shown=cm.loaded.iter().filter(|(k,_)| cm.shows(**k)).flat_map(|(_,list)| list);
+    for (c,h) in culling::frustum_culled(shown, cam, &fr, cull, MAX_CULLED_BOXES, assets) {
+        debug_lines::push_box(&mut out, c, h, [1.0,0.2,0.2]);
+    }
+    out
+}
+
+/// Chunk-border colours by where the chunk came from; white if unknown.
+pub fn chunk_source_color(src: Option<ChunkSource>) -> [f32; 3] {
+    match src {
+        Some(ChunkSource::Designed) => [0.2,1.0,0.3],
+        Some(ChunkSource::Store)    => [0.3,0.6,1.0],
+        Some(ChunkSource::Override) => [1.0,0.3,1.0],
+        None => [1.0,1.0,1.0],
+    }
+}
+
+/// Debug overlay: the footprint rectangle of every shown chunk, just above
+/// the ground in local space, coloured by `chunk_source_color`.
+pub fn chunk_border_lines(cm: &ChunkManager) -> Vec<LineVertex> {
+    let mut out=Vec::with_capacity(cm.loaded.len()*8);
+    let ground=0.05-cm.origin_shift().y;
+    for key in cm.loaded.keys().filter(|k| cm.shows(**k)) {
+        let (c,h)=cm.chunk_aabb(*key).unwrap();
+        debug_lines::push_rect(&mut out, Vector3::new(c.x,ground,c.z), h.x, h.z, chunk_source_color(cm.chunk_source(*key)));
+    }
+    out
+}
+
+/// What the density heatmap (U) shows per chunk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HeatMetric {
+    /// Building boxes, relative to the busiest chunk in view.
+    Count,
+    /// Share of the boxes that are highrises or landmarks.
+    Tall,
+}
+
+/// Debug heatmap (`Engine::set_heatmap`): `metric` for the `HEAT_SIDE`²
+/// chunks around the one under `cam` (local space); hidden or unloaded
+/// chunks have no value.
+pub fn density_heat(cm: &ChunkManager, assets: &AssetLibrary, metric: HeatMetric, cam: Vector3<f32>) -> HeatGrid {
+    let (cw,cd)=chunking::chunk_world_span(&cm.params);
+    let shift=cm.origin_shift();
+    let half=(HEAT_SIDE/2) as i32;
+    let x0=((cam.x+shift.x)/cw+0.5).floor() as i32-half;
+    let z0=((cam.z+shift.z)/cd+0.5).floor() as i32-half;
+    let mut values:Vec<f32>=(0..HEAT_SIDE*HEAT_SIDE).map(|i| {
+        let key=ChunkKey(x0+(i%HEAT_SIDE) as i32, z0+(i/HEAT_SIDE) as i32);
+        let Some(list)=cm.loaded.get(&key).filter(|_| cm.shows(key)) else { return -1.0 };
+        let (mut n,mut tall)=(0usize,0usize);
+        for (_,_,id) in list.iter().flat_map(|p| p.boxes()) {
+            n+=1;
+            if assets.category_of(id as usize)!=BuildingCategory::Lowrise { tall+=1; }
+        }
+        match metric {
+            HeatMetric::Count => n as f32,
+            HeatMetric::Tall  => if n>0 { tall as f32/n as f32 } else { 0.0 },
+        }
+    }).collect();
+    if metric==HeatMetric::Count {
+        let max=values.iter().copied().fold(0.0f32,f32::max);
+        if max>0.0 { for v in values.iter_mut().filter(|v| **v>=0.0) { *v/=max; } }
+    }
+    HeatGrid { origin: [(x0 as f32-0.5)*cw, (z0 as f32-0.5)*cd], cell: [cw,cd], values }
+}
+
+/// Ground checker (`Engine::set_ground_checker`) that reads as a city plan:
+/// one square per block pitch (lots + gaps + minor road), restarting at
+/// every chunk seam so each chunk's squares start where its blocks do.
+/// Major roads push later blocks off the squares a little.
+pub fn ground_checker_for(p: &CityGenParams) -> ([f32;2],[f32;2],[f32;2]) {
+    let (bw,bd)=chunking::block_world_span(p);
+    let (cw,cd)=chunking::chunk_world_span(p);
+    ([bw,bd],[cw,cd],[-0.5*cw,-0.5*cd])
+}
+
 // ───────────────────────── App struct ───────────────────────
-struct App {
+pub(crate) struct App {
     // gfx
     is_web: bool,
+    config: EngineConfig,
     window: Option<Window>,
     surface: Option<wgpu::Surface<'static>>,
     adapter: Option<wgpu::Adapter>,
@@ -72,6 +389,10 @@ struct App {
     keyboard: camera::KeyboardInput,
     camera:   camera::Camera,
     last_cursor: Option<PhysicalPosition<f64>>,
+    mouse_grabbed: bool, // cursor captured: only then does the mouse turn the camera (click / F, Esc releases)
+    // last window scale factor: `ScaleFactorChanged` rescales from it to
+    // keep the window's logical size on a DPI change
+    scale_factor: f64,
 
     // timing
     last_frame: Instant,
@@ -84,66 +405,144 @@ struct App {
 
     // world
     chunk_mgr: ChunkManager,
-    designer:  RuleDesigner,
+    designer: usize,               // index into BUILTIN_DESIGNERS (cycled with J)
     viewer_id: ViewerId,
     world_origin: cgmath::Vector3<f64>,
+    edit_category: BuildingCategory, // what `B` places (keys 1–3)
+    snap_edits: bool,                // placed buildings go to the nearest lot centre (N)
 
     // ground inst
     ground_inst: InstanceRaw,
 
     // LOD / cull
-    lod0:f32, lod1:f32, cull:f32,
+    lod0:f32, lod1:f32, cull:f32,   // cull (billboard_cull) ≤ chunk_mgr.loaded_distance()
+    mesh_cull:f32,                  // meshes stop here, billboards go on to `cull`
+    base_lod:(f32,f32,f32,f32),     // lod0/lod1/mesh_cull/cull at full quality
+    ground_extent:f32,              // ground plane half-width around the camera
+    lod_override: Option<u8>,       // debug: force one LOD (see `culling::lod_rings`)
+    lod_hyst: culling::LodHysteresis, // per-building LOD kept across frames
+    cull_cache: culling::ChunkCullCache, // per-chunk frustum classification, per camera cell
+    frozen_vp: Option<Matrix4<f32>>, // debug: cull with this VP, not the camera's (F4)
+    show_chunk_borders: bool,        // debug: outline loaded chunks (F2)
+    heatmap: Option<HeatMetric>,     // debug: ground tinted by chunk density (U)
+    quality: QualityScaler,
+    smoke: SmokeSystem,
+
+    // flythrough
+    recorder: Option<CameraRecorder>,
+    player:   Option<CameraPlayer>,
+    last_path: Option<CameraPath>,
+    click_to_move: bool,  // left click flies to the ground point (C)
+    fly: Option<FlyTo>,   // click-to-move flight in progress
 
     // misc
+    net: bool,      // poll network mutations (off for deterministic runs)
+    // debug switches (L / P): local `mutate_near` and received packets, each
+    // on its own; the mutation clock keeps running while local is off
+    local_mutations_enabled: bool,
+    network_apply_enabled: bool,
+    oom_strike: bool, // last frame hit OOM and buffers were released
+    inst_capped: bool, // a bucket hit `max_instances_per_bucket` (warned once per episode)
     debug: bool,
+    dump_buckets: bool, // write the next frame's buckets to a CSV (F10, debug only)
     dbg_last: Instant,
 }
 
 impl App {
-    fn new(is_web: bool) -> Self {
+    fn new(is_web: bool, config: EngineConfig) -> Self {
         // generation parameters
-        let params = crate::chunking::CityGenParams {
-        lots_x:3, lots_z:3,
-        lot_w:3.0, lot_d:3.0, lot_gap:0.4,
-        road_w_minor:3.0, road_w_major:8.0, major_every:6,
-        blocks_per_chunk_x:8, blocks_per_chunk_z:8,
-        seed:0xA11CE_u64,
-    };
-    let bounds = (-4,4,-4,4);
-
-    let chunk_mgr = ChunkManager::new(params.clone(), 3, bounds, true, "./city_chunks");
+        let params = config.city.clone();
 
-    let designer  = RuleDesigner { params };
+        // the chunk radius is raised to cover the render range
+        let (lod0, lod1, mesh_cull, cull) = (config.lod0, config.lod1, config.mesh_cull, config.billboard_cull);
+        let ground_extent = config.ground_extent;
+        let lod_hyst = culling::LodHysteresis::new(config.lod_margin);
+        let mut chunk_mgr = ChunkManager::with_store(params.clone(), config.chunk_radius, config.bounds, config.store.clone());
+        chunk_mgr.wrap = config.wrap;
+        chunk_mgr.cover_cull(cull);
+        // spread generation over frames and look ~2 s ahead of the camera
+        chunk_mgr.gen_budget = 6;
+        chunk_mgr.prefetch_secs = 2.0;
+        // teleports mustn't stall on dozens of store reads
+        chunk_mgr.async_store = true;
+        // chunks entirely past the LOD1 ring are static → merged into one mesh
+        chunk_mgr.bake_distance = lod1;
+        #[cfg(target_arch = "wasm32")]
+        { chunk_mgr.on_mutated = Some(Box::new(crate::web::queue_mutation)); }
+        // "skyline": buildings jittered inside their lots, heights rolling
+        chunk_mgr.set_designer(BUILTIN_DESIGNERS[0].1(params));
+        let smoke = SmokeSystem::new(config.smoke.clone());
         Self {
-            is_web,
+            is_web, config,
             window: None, surface: None, adapter: None, engine: None,
             keyboard: camera::KeyboardInput::new(),
             camera:   camera::Camera::new(),
             last_cursor: None,
+            mouse_grabbed: false,
+            scale_factor: 1.0,
             last_frame: Instant::now(),
             ready: Arc::new(AtomicBool::new(false)),
             gpu_slot: Arc::new(Mutex::new(None)),
             ad_slot:  Arc::new(Mutex::new(None)),
             instance: None,
-            chunk_mgr: chunk_mgr,
-            designer:  designer,
+            chunk_mgr,
+            designer: 0,
             viewer_id: 0,
             world_origin: cgmath::vec3(0.0,0.0,0.0),
+            edit_category: BuildingCategory::Lowrise,
+            snap_edits: true,
             ground_inst: InstanceRaw {
                 pos:[0.0,-0.05,0.0,0.0],
                 scale:[1.0,1.0,1.0,0.0],
-                misc:[2.0,0.0,0.0,0.0], // category=2 (landmark colour)
+                misc:[TINT_GROUND,0.0,0.0,0.0],
             },
-            lod0:90.0, lod1:190.0, cull:380.0,
-            debug:false, dbg_last:Instant::now(),
+            lod0, lod1, cull, mesh_cull,
+            base_lod:(lod0,lod1,mesh_cull,cull),
+            ground_extent,
+            lod_override: None,
+            lod_hyst,
+            cull_cache: culling::ChunkCullCache::default(),
+            frozen_vp: None,
+            show_chunk_borders: false,
+            heatmap: None,
+            quality: QualityScaler::new(20.0),
+            smoke,
+            recorder: None, player: None, last_path: None,
+            click_to_move: false, fly: None,
+            net:true, local_mutations_enabled:true, network_apply_enabled:true, oom_strike:false, inst_capped:false, debug:false, dump_buckets:false, dbg_last:Instant::now(),
         }
     }
 
+    /// Windowless app for deterministic runs (`bench`): city seeded with
+    /// `seed`, camera driven by `path`, no network input, and no chunk store,
+    /// so a run neither reads an earlier run's chunks nor leaves any behind.
+    #[cfg(not(target_arch="wasm32"))]
+    pub(crate) fn new_headless(mut engine: Engine, seed: u64, path: CameraPath) -> Self {
+        let city = CityGenParams { seed, ..CityGenParams::default() };
+        let mut app = Self::new(false, EngineConfig { city, store: StoreBackend::None, ..EngineConfig::default() });
+        let (tile,span,offset) = ground_checker_for(&app.chunk_mgr.params);
+        engine.set_ground_checker(tile, span, offset, engine.ground_colors());
+        app.engine = Some(engine);
+        app.net = false;
+        app.quality.enabled = false;
+        app.chunk_mgr.async_store = false;
+        app.player = Some(CameraPlayer::new(path));
+        app
+    }
+
+    #[cfg(not(target_arch="wasm32"))]
+    pub(crate) fn engine(&self) -> Option<&Engine> { self.engine.as_ref() }
+
     // ------------ async device helper ------------
     async fn spawn_device(adapter: wgpu::Adapter,
                           slot: Arc<Mutex<Option<(wgpu::Device,wgpu::Queue)>>> ,
                           flag: Arc<AtomicBool>) {
-        let (device,queue) = adapter.request_device(&wgpu::DeviceDescriptor::default()).await.unwrap();
+        // opt into GPU timestamps when available (FrameStats::gpu_ms)
+        let desc = wgpu::DeviceDescriptor {
+            required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+            ..Default::default()
+        };
+        let (device,queue) = adapter.request_device(&desc).await.unwrap();
         device.on_uncaptured_error(Box::new(|e| error!("WGPU uncaptured {e:?}")));
         { *slot.lock().unwrap() = Some((device,queue)); }
         flag.store(true,Ordering::SeqCst);
@@ -157,21 +556,475 @@ impl App {
         let adapter = if let Some(a)=&self.adapter { a.clone() }
                       else { self.ad_slot.lock().unwrap().take().unwrap() };
         let size = self.window.as_ref().unwrap().inner_size();
-        self.engine = Some(Engine::new(device,queue,surface,&adapter,size));
+        let mut e = Engine::new(device,queue,surface,&adapter,size,&self.config);
+        let (tile,span,offset) = ground_checker_for(&self.chunk_mgr.params);
+        e.set_ground_checker(tile, span, offset, e.ground_colors());
+        self.engine = Some(e);
+    }
+
+    // ------------ world regeneration ------------
+    /// Pick a fresh seed (clock-mixed) and rebuild the city around the camera.
+    fn regenerate_world(&mut self) {
+        let old = self.chunk_mgr.params.seed;
+        let t = instant::now() as u64;
+        let seed = rng::hash2(t as i32, (t >> 32) as i32) ^ old.rotate_left(17);
+        self.chunk_mgr.reseed(seed);
+        info!("world regenerated: seed {seed}");
+        if let Some(e)=&self.engine {
+            self.chunk_mgr.set_viewer(self.viewer_id, self.camera.position.x, self.camera.position.z);
+            self.chunk_mgr.ensure_for_viewers(e.assets_ref());
+        }
+    }
+
+    /// Debug: switch to the next built-in designer and rebuild the city
+    /// with the same seed, to compare their output.
+    fn cycle_designer(&mut self) {
+        self.designer = (self.designer + 1) % BUILTIN_DESIGNERS.len();
+        let (name, make) = BUILTIN_DESIGNERS[self.designer];
+        self.chunk_mgr.set_designer(make(self.chunk_mgr.params.clone()));
+        info!("designer: {name}");
+        if let Some(e)=&self.engine {
+            self.chunk_mgr.set_viewer(self.viewer_id, self.camera.position.x, self.camera.position.z);
+            self.chunk_mgr.ensure_for_viewers(e.assets_ref());
+        }
+    }
+
+    // ------------ asset hot reload ------------
+    /// Rebuild meshes + palette in place.  If the archetype table changed
+    /// size, loaded placements may name ids that no longer exist, so edits
+    /// are flushed and the window is designed again with the same seed.
+    fn reload_assets(&mut self) {
+        let Some(e)=self.engine.as_mut() else { return };
+        let before = e.assets.archetypes.len();
+        e.reload_assets();
+        if e.assets.archetypes.len() != before {
+            if let Err(err) = self.chunk_mgr.flush() { error!("saving chunks failed: {err}"); }
+            self.chunk_mgr.reseed(self.chunk_mgr.params.seed);
+            self.chunk_mgr.set_viewer(self.viewer_id, self.camera.position.x, self.camera.position.z);
+            self.chunk_mgr.ensure_for_viewers(e.assets_ref());
+        }
+    }
+
+    // ------------ editing ------------
+    /// Place an `edit_category` building where the view ray meets the
+    /// ground (within `cull`), snapped to the nearest lot if `snap_edits`;
+    /// its chunk is loaded first if needed.
+    fn place_at_crosshair(&mut self) {
+        let Some(e)=self.engine.as_ref() else { return };
+        let shift=self.chunk_mgr.origin_shift();
+        // screen centre: the aspect doesn't matter
+        let Some(hit)=self.camera.ground_hit(0.0, 0.0, 1.0, -shift.y) else { return };
+        if (hit-self.camera.position.to_vec()).magnitude() > self.cull { return; }
+        let assets=e.assets_ref();
+        let Some(&aid)=assets.indices_by_category(self.edit_category).first() else { return };
+        let scale=Vector3::new(1.0,1.0,1.0);
+        let mut center=Vector3::new(hit.x, assets.ground_y_for(aid, scale)-shift.y, hit.z);
+        if self.snap_edits {
+            center=chunking::snap_to_grid(&self.chunk_mgr.params, center+shift)-shift;
+        }
+        let p=RuntimePlacement::single(center, scale, aid as u16);
+        let Some((key,idx))=self.chunk_mgr.place_building(p, assets) else { return };
+        info!("placed {:?} in chunk ({},{})", self.edit_category, key.0, key.1);
+        if self.net && let Some(pkt)=net_mutations::encode_add(&self.chunk_mgr, key, idx) {
+            net_mutations::broadcast(&pkt);
+        }
+    }
+
+    /// Remove the building under the crosshair.
+    fn delete_at_crosshair(&mut self) {
+        let Some(e)=self.engine.as_mut() else { return };
+        let (o,d)=(self.camera.position.to_vec(), self.camera.forward);
+        let Some((key,idx,_))=self.chunk_mgr.pick_ray(o, d, self.cull, e.assets_ref()) else { return };
+        self.chunk_mgr.remove_building(key, idx);
+        // later indices in the chunk shifted down
+        match e.selection() {
+            Some((k,i)) if k==key && i==idx => e.clear_selection(),
+            Some((k,i)) if k==key && i>idx => e.set_selection(k, i-1),
+            _ => {}
+        }
+        info!("removed building {idx} of chunk ({},{})", key.0, key.1);
+        if self.net { net_mutations::broadcast(&net_mutations::encode_remove(key, idx)); }
+    }
+
+    // ------------ mouse capture ------------
+    /// Lock (or, where that's unsupported, confine) and hide the cursor so
+    /// mouse motion turns the camera; `false` hands it back.  On the web this
+    /// asks the browser for pointer lock on the canvas, which the page may
+    /// refuse or drop on its own (see `web::take_lock_lost`).
+    fn set_mouse_grab(&mut self, on: bool) {
+        let Some(w)=self.window.as_ref() else { return };
+        #[cfg(not(target_arch="wasm32"))] {
+            use winit::window::CursorGrabMode;
+            let res = if on {
+                w.set_cursor_grab(CursorGrabMode::Locked).or_else(|_| w.set_cursor_grab(CursorGrabMode::Confined))
+            } else { w.set_cursor_grab(CursorGrabMode::None) };
+            if let Err(e)=res {
+                warn!("cursor grab failed: {e}");
+                if on { return; }
+            }
+        }
+        #[cfg(target_arch="wasm32")]
+        if let Some(cv)=w.canvas() { crate::web::set_pointer_lock(&cv, on); }
+        w.set_cursor_visible(!on);
+        self.mouse_grabbed=on;
+        // no delta may span the grab: the cursor was warped or never moved
+        self.last_cursor=None;
+        info!("mouse {}", if on {"captured (Esc releases)"} else {"released"});
+    }
+
+    // ------------ click-to-move ------------
+    /// Fly to `fly_to_height` above the ground under the cursor (under the
+    /// crosshair while the mouse is captured); clicks on the sky or past
+    /// `cull` do nothing.
+    fn click_to_move(&mut self) {
+        let Some(w)=self.window.as_ref() else { return };
+        let size=w.inner_size();
+        let (wd,ht)=(size.width.max(1) as f32, size.height.max(1) as f32);
+        let cursor=if self.mouse_grabbed { None } else { self.last_cursor };
+        let (nx,ny)=cursor.map_or((0.0,0.0), |c| (2.0*c.x as f32/wd-1.0, 1.0-2.0*c.y as f32/ht));
+        let ground=-self.chunk_mgr.origin_shift().y;
+        let Some(hit)=self.camera.ground_hit(nx,ny,wd/ht,ground) else { return };
+        let from=self.camera.position.to_vec();
+        if (hit-from).magnitude() > self.cull { return; }
+        // walking arrives on foot, not at flight height
+        let h=if self.camera.walk {self.camera.walk_height} else {self.config.fly_to_height};
+        let to=hit+Vector3::new(0.0,h,0.0);
+        self.fly=Some(FlyTo::new(from, to, self.config.fly_to_secs));
+    }
+
+    // ------------ flythrough ------------
+    #[cfg(not(target_arch="wasm32"))]
+    const FLYTHROUGH_FILE: &'static str = "./flythrough.bin";
+    const FLYTHROUGH_INTERVAL: f32 = 0.1;
+
+    fn to_world(&self, mut s: camera::CameraState) -> camera::CameraState {
+        s.position[0] += self.world_origin.x as f32;
+        s.position[2] += self.world_origin.z as f32;
+        s
+    }
+    fn to_local(&self, mut s: camera::CameraState) -> camera::CameraState {
+        s.position[0] -= self.world_origin.x as f32;
+        s.position[2] -= self.world_origin.z as f32;
+        s
+    }
+
+    fn toggle_recording(&mut self) {
+        if let Some(rec) = self.recorder.take() {
+            let path = rec.finish();
+            info!("flythrough recorded: {} samples, {:.1}s", path.samples.len(), path.duration());
+            #[cfg(not(target_arch="wasm32"))]
+            if let Err(e) = std::fs::write(Self::FLYTHROUGH_FILE, path.to_bytes()) {
+                warn!("flythrough save failed: {e}");
+            }
+            self.last_path = Some(path);
+        } else {
+            self.player = None;
+            self.recorder = Some(CameraRecorder::new(Self::FLYTHROUGH_INTERVAL));
+            info!("flythrough recording…");
+        }
+    }
+
+    fn start_playback(&mut self) {
+        #[cfg(not(target_arch="wasm32"))]
+        if self.last_path.is_none() {
+            self.last_path = std::fs::read(Self::FLYTHROUGH_FILE).ok()
+                .and_then(|b| CameraPath::from_bytes(&b));
+        }
+        match self.last_path.clone() {
+            Some(path) => {
+                self.recorder = None;
+                self.fly = None;
+                info!("flythrough playback: {:.1}s", path.duration());
+                self.player = Some(CameraPlayer::new(path));
+            }
+            None => warn!("no flythrough recorded"),
+        }
+    }
+
+    fn stop_flythrough(&mut self) {
+        if self.recorder.is_some() { self.toggle_recording(); }
+        self.player = None;
+    }
+
+    // ------------ LOD override ------------
+    /// Debug: cycle off → LOD0 → LOD1 → billboards → off.  While forced,
+    /// nothing is baked so every visible building goes through the buckets.
+    fn cycle_lod_override(&mut self) {
+        self.lod_override = match self.lod_override { None => Some(0), Some(l) if l < 2 => Some(l+1), Some(_) => None };
+        self.chunk_mgr.bake_distance = if self.lod_override.is_some() { f32::INFINITY } else { self.lod1 };
+        match self.lod_override {
+            Some(l) => info!("LOD override: {}", ["LOD0","LOD1","billboards"][l as usize]),
+            None => info!("LOD override off"),
+        }
+    }
+
+    /// Debug (U): density heatmap off → building count → tall share → off.
+    fn cycle_heatmap(&mut self) {
+        self.heatmap = match self.heatmap { None => Some(HeatMetric::Count), Some(HeatMetric::Count) => Some(HeatMetric::Tall), Some(HeatMetric::Tall) => None };
+        match self.heatmap {
+            Some(m) => info!("density heatmap: {m:?}"),
+            None => info!("density heatmap off"),
+        }
+    }
+
+    // ------------ chunk isolation ------------
+    /// Debug (K): draw only the chunk under the crosshair's building, else
+    /// the camera's; with Shift also load nothing else.  Again to leave.
+    fn toggle_isolation(&mut self, loads: bool) {
+        let cm=&mut self.chunk_mgr;
+        if cm.isolated.take().is_some() {
+            cm.isolate_loads=false;
+            info!("chunk isolation off");
+            return;
+        }
+        let (o,d)=(self.camera.position.to_vec(), self.camera.forward);
+        let picked=self.engine.as_ref().and_then(|e| cm.pick_ray(o, d, self.cull, e.assets_ref())).map(|h| h.0);
+        let Some(key)=picked.or_else(|| cm.chunk_at(o.x, o.z)) else { return };
+        (cm.isolated, cm.isolate_loads)=(Some(key), loads);
+        info!("isolating chunk ({},{}){}", key.0, key.1, if loads {", loading nothing else"} else {""});
+    }
+
+    // ------------ frustum freeze ------------
+    /// Debug: keep culling with the current view while the camera moves on,
+    /// drawing that frustum and outlining what it culls.
+    fn toggle_frustum_freeze(&mut self) {
+        self.frozen_vp = match self.frozen_vp {
+            Some(_) => None,
+            None => {
+                let aspect = self.window.as_ref().map_or(1.0, |w| {
+                    let s = w.inner_size();
+                    s.width.max(1) as f32 / s.height.max(1) as f32
+                });
+                Some(self.camera.view_projection(aspect))
+            }
+        };
+        info!("culling frustum {}", if self.frozen_vp.is_some() {"frozen"} else {"follows the camera"});
+    }
+
+    // ------------ per-frame update ------------
+    /// Feed the frame's work time (not `dt`, which includes the frame cap's
+    /// sleep) to the quality scaler and apply a new distance scale: LOD
+    /// rings, cull, bake distance and the chunk window together.
+    fn adapt_quality(&mut self, work_ms: f32, dt: f32) {
+        let Some(k) = self.quality.update(work_ms, dt) else { return };
+        let (l0,l1,m,c) = self.base_lod;
+        (self.lod0, self.lod1, self.mesh_cull, self.cull) = (l0*k, l1*k, m*k, c*k);
+        if self.lod_override.is_none() { self.chunk_mgr.bake_distance = self.lod1; }
+        self.chunk_mgr.fit_cull(self.cull);
+        info!("quality: frames ~{:.1} ms (target {:.0} ms) → view distance ×{k:.2}: lod {:.0}/{:.0} m, cull {:.0} m, radius {}x{}",
+              self.quality.avg_ms().unwrap_or(0.0), self.quality.target_ms, self.lod0, self.lod1, self.cull,
+              self.chunk_mgr.chunk_radius_x, self.chunk_mgr.chunk_radius_z);
+    }
+
+    /// Move the camera for this frame (playback or input) and keep the
+    /// world origin / torus wrap in step with it.
+    pub(crate) fn advance_camera(&mut self, dt: f32) {
+        #[cfg(target_arch = "wasm32")]
+        if crate::web::take_blur() { self.keyboard.release_all(); }
+        // the browser ends pointer lock itself (Esc, tab switch) or refuses it
+        #[cfg(target_arch = "wasm32")]
+        if crate::web::take_lock_lost() && self.mouse_grabbed { self.set_mouse_grab(false); }
+        // playback drives the camera and ignores input
+        let p0=self.camera.position;
+        let played = self.player.as_mut().map(|p| p.tick(dt));
+        match played {
+            Some(Some(w)) => { let l=self.to_local(w); self.camera.apply_state(&l); }
+            Some(None) => { self.player=None; info!("flythrough playback finished"); }
+            None => {
+                // steering keys take over from a click-to-move flight
+                if self.fly.is_some() && self.keyboard.steering() { self.fly=None; }
+                match self.fly.as_mut().map(|f| f.tick(dt)) {
+                    Some(Some(p)) => { self.camera.position=Point3::from_vec(p); self.camera.velocity=Vector3::zero(); }
+                    Some(None) => self.fly=None,
+                    None => {
+                        self.camera.update(dt,&self.keyboard);
+                        if self.camera.walk { self.collide_walk(p0); }
+                    }
+                }
+            }
+        }
+        // no time passed (back-to-back redraws) or the camera jumped more than
+        // a chunk (playback start, …): no motion to prefetch along
+        let step=self.camera.position-p0;
+        let (cw,cd)=chunking::chunk_world_span(&self.chunk_mgr.params);
+        let vel=if dt>0.0 && step.x.abs()<cw && step.z.abs()<cd { step/dt } else { Vector3::zero() };
+        self.chunk_mgr.set_viewer_velocity(self.viewer_id, vel.x, vel.z);
+        if self.recorder.is_some() {
+            let w=self.to_world(self.camera.state());
+            if let Some(r)=self.recorder.as_mut() { r.tick(dt,w); }
+        }
+        self.maybe_wrap_torus();
+        self.maybe_float_origin();
+    }
+
+    /// Walk mode: keep the step from `from` out of buildings (slides along walls).
+    fn collide_walk(&mut self, from: Point3<f32>) {
+        const BODY_RADIUS: f32 = 0.3;
+        let Some(e)=self.engine.as_ref() else { return };
+        let p=self.chunk_mgr.resolve_collision(from.to_vec(), self.camera.position.to_vec(), BODY_RADIUS, e.assets_ref());
+        self.camera.position=Point3::from_vec(p);
+    }
+
+    /// Stream chunks around the camera, mutate, cull/bucket the visible
+    /// instances and render one frame.  `mutate_seed` is mixed into every
+    /// mutation tick; with the same seed and `dt`s the frames are reproducible.
+    pub(crate) fn step_frame(&mut self, dt: f32, size: winit::dpi::PhysicalSize<u32>, mutate_seed: u64)
+        -> Result<(),wgpu::SurfaceError> {
+        if let Some(e)=self.engine.as_mut() {
+            let mut lines=Vec::new();
+            let heat;
+            let (mut b,baked_keys)={
+                let assets:&AssetLibrary = e.assets_ref();
+
+                // network mutate packets
+                if self.net {
+                    if self.network_apply_enabled { net_mutations::poll_incoming(&mut self.chunk_mgr, assets); }
+                    else { net_mutations::discard_incoming(); }
+                }
+
+                // chunk ensure + local mutations
+                self.chunk_mgr.set_viewer(self.viewer_id, self.camera.position.x, self.camera.position.z);
+                self.chunk_mgr.ensure_for_viewers(assets);
+
+                if self.debug && self.dbg_last.elapsed().as_secs_f32() >= 1.0 {
+                    self.dbg_last = Instant::now();
+                    let w = self.chunk_mgr.stats();
+                    info!("world: {} chunks, {} placements, ~{:.1} MiB",
+                          w.loaded_chunks, w.placements, w.estimated_bytes as f32 / (1024.0 * 1024.0));
+                }
+
+                // paused, the clock still ticks so resuming stays in step with peers
+                let rate = if self.local_mutations_enabled { LIVE_MUTATION_RATE } else { 0.0 };
+                self.chunk_mgr.step(dt, assets, rate, mutate_seed);
+
+                let aspect=size.width.max(1) as f32 / size.height.max(1) as f32;
+                let vp=self.camera.view_projection(aspect);
+                e.update_camera(&vp);
+                e.update_gizmo(&self.camera.view_matrix());
+
+                let cull_vp=self.frozen_vp.unwrap_or(vp);
+                let fr=culling::frustum_from_vp(&cull_vp);
+                let rings=culling::lod_rings(self.lod_override, self.lod0, self.lod1, self.cull);
+                if self.frozen_vp.is_some() {
+                    lines=frustum_debug_lines(&self.chunk_mgr, assets, &cull_vp, self.camera.position.to_vec(), self.cull);
+                }
+                if self.show_chunk_borders { lines.extend(chunk_border_lines(&self.chunk_mgr)); }
+                heat=self.heatmap.map(|m| density_heat(&self.chunk_mgr, assets, m, self.camera.position.to_vec()));
+                // a frozen frustum doesn't move with the camera cell
+                let cell=self.frozen_vp.is_none().then(|| {
+                    let c=&self.camera;
+                    self.cull_cache.cell_for(c.position.to_vec(),c.yaw,c.pitch,aspect,c.fov())
+                });
+                self.cull_cache.begin(cell);
+                // off, `update` just empties the pool
+                let at=if self.smoke.params.enabled {
+                    smoke_emitters(&self.chunk_mgr, assets, self.camera.position.to_vec(), &self.smoke.params)
+                } else { Vec::new() };
+                self.smoke.update(dt, &at);
+                build_instance_buckets(&self.chunk_mgr, assets, &fr, self.camera.position.to_vec(),
+                                       rings, (self.mesh_cull, self.cull), &mut self.lod_hyst, &mut self.cull_cache)
+            };
+            e.set_debug_lines(&lines);
+            let max=self.config.max_instances_per_bucket;
+            let dropped=b.cap_nearest(self.camera.position.to_vec(), max);
+            if dropped>0 && !self.inst_capped {
+                warn!("instance cap: {dropped} far buildings dropped ({max} per bucket); lower cull or chunk_radius");
+            }
+            self.inst_capped=dropped>0;
+            if std::mem::take(&mut self.dump_buckets) {
+                let path=format!("buckets_{}.csv", instant::now() as u64);
+                match std::fs::write(&path, b.to_csv()) {
+                    Ok(()) => info!("{} instances written to {path}", b.total()),
+                    Err(err) => error!("writing {path} failed: {err}"),
+                }
+            }
+            if e.billboards_blended() { b.sort_billboards_back_to_front(self.camera.position.to_vec()); }
+            #[cfg(target_arch = "wasm32")]
+            crate::web::flush_mutation_events();
+            e.update_baked(&self.chunk_mgr,&baked_keys);
+            e.update_selection(&self.chunk_mgr);
+            e.set_shadow_extent(self.cull);
+            e.set_ground_origin(self.chunk_mgr.origin_shift());
+            e.set_heatmap(heat.as_ref());
+            e.update_shadow(self.camera.position.to_vec());
+
+            // the ground follows the camera so its extent is measured from the eye
+            let k=self.ground_extent/(GROUND_PLANE_SIZE*0.5);
+            self.ground_inst.pos=[self.camera.position.x,-0.05,self.camera.position.z,0.0];
+            self.ground_inst.scale=[k,1.0,k,0.0];
+            e.update_instances(
+                &b.v0_low_common,&b.v0_low_alt,&b.v0_high,&b.v0_land,
+                &b.v1_low_common,&b.v1_low_alt,&b.v1_high,&b.v1_land,
+                &b.v2_bill,&self.ground_inst,
+            );
+            e.update_smoke(&self.smoke.instances(self.camera.position.to_vec()));
+            return e.render();
+        }
+        Ok(())
+    }
+
+    /// Render-error policy: a timeout just drops the frame; a lost/outdated
+    /// surface is reconfigured and the frame retried once; out-of-memory
+    /// first releases the instance buffers and only exits if it happens
+    /// again on the next frame.
+    fn recover_surface(&mut self, el: &ActiveEventLoop, err: wgpu::SurfaceError, size: winit::dpi::PhysicalSize<u32>) {
+        let Some(e)=self.engine.as_mut() else { return };
+        match err {
+            wgpu::SurfaceError::Timeout => {
+                if self.debug { info!("surface timeout, frame skipped"); }
+            }
+            wgpu::SurfaceError::Lost|wgpu::SurfaceError::Outdated => {
+                e.resize(size);
+                if let Err(again)=e.render() { warn!("frame dropped: {err:?}, then {again:?} after reconfigure"); }
+            }
+            wgpu::SurfaceError::OutOfMemory if !self.oom_strike => {
+                warn!("surface OOM: releasing instance buffers");
+                self.oom_strike=true;
+                e.release_instance_memory();
+            }
+            wgpu::SurfaceError::OutOfMemory => { error!("OOM"); el.exit(); }
+            wgpu::SurfaceError::Other => warn!("surface err {err:?}"),
+        }
     }
 
     // ------------ floating origin & torus wrap ------------
     const SHIFT_DIST: f32 = 500.0;
+    /// Horizontal only: the ground (and `ground_y_for`) stays at local y = 0.
     fn maybe_float_origin(&mut self){
-        let p=self.camera.position.to_vec();
-        if p.magnitude() > Self::SHIFT_DIST { self.shift_world(p); }
+        let p=self.camera.position;
+        let off=Vector3::new(p.x,0.0,p.z);
+        if off.magnitude() > Self::SHIFT_DIST { self.shift_world(off); }
     }
     fn shift_world(&mut self, off: Vector3<f32>){
         self.chunk_mgr.apply_shift(off);
+        self.follow_shift(off);
+    }
+    /// Everything outside the chunk manager that lives in local space.
+    fn follow_shift(&mut self, off: Vector3<f32>){
         self.camera.position -= off;
+        if let Some(f)=self.fly.as_mut() { f.shift(off); }
+        self.smoke.shift(off);
+        // local p now was p + off when the VP was frozen
+        if let Some(vp)=self.frozen_vp.as_mut() { *vp = *vp * Matrix4::from_translation(off); }
         self.world_origin += cgmath::vec3(off.x as f64,0.0,off.z as f64);
     }
+    /// Home: camera back to the spawn pose at the true world origin —
+    /// the floating-origin shift is undone and the central chunks loaded.
+    fn reset_to_spawn(&mut self){
+        self.player = None;
+        self.fly = None;
+        let off = self.chunk_mgr.reset_origin();
+        self.follow_shift(off);
+        self.world_origin = cgmath::vec3(0.0,0.0,0.0);
+        self.camera.ground_y = 0.0;
+        self.camera.reset_to_spawn();
+        self.chunk_mgr.set_viewer(self.viewer_id, self.camera.position.x, self.camera.position.z);
+        self.chunk_mgr.set_viewer_velocity(self.viewer_id, 0.0, 0.0);
+        if let Some(e)=&self.engine { self.chunk_mgr.ensure_for_viewers(e.assets_ref()); }
+        info!("camera reset to spawn");
+    }
     fn maybe_wrap_torus(&mut self){
+        if !self.chunk_mgr.wrap { self.clamp_to_world(); return; }
         let (sx,sz) = self.chunk_mgr.world_span();
         let hx=sx*0.5; let hz=sz*0.5;
         let mut off=Vector3::new(0.0,0.0,0.0);
@@ -181,6 +1034,23 @@ impl App {
         if self.camera.position.z < -hz { off.z = -sz; }
         if off.x!=0.0||off.z!=0.0 { self.shift_world(off); }
     }
+    /// Finite world: keep the camera inside `bounds` (local = design - shift).
+    fn clamp_to_world(&mut self){
+        let (x0,x1,z0,z1) = self.chunk_mgr.world_rect();
+        let o = self.chunk_mgr.origin_shift();
+        let p = &mut self.camera.position;
+        p.x = p.x.clamp(x0-o.x, x1-o.x);
+        p.z = p.z.clamp(z0-o.z, z1-o.z);
+    }
+}
+
+// the surfaces borrow the window (see `resumed`), so they must go first
+impl Drop for App {
+    fn drop(&mut self) {
+        if let Some(e)=self.engine.take() { e.destroy(); }
+        self.surface=None;
+        self.window=None;
+    }
 }
 
 // ─────────────────── winit plumbing ────────────────────────
@@ -193,22 +1063,26 @@ impl ApplicationHandler for App {
                 let doc=web_sys::window().unwrap().document().unwrap();
                 let cv = doc.get_element_by_id("wasm-canvas")
                             .expect("canvas").dyn_into::<HtmlCanvasElement>().unwrap();
-                if cv.width()==0 || cv.height()==0 {
-                    let w=cv.client_width() as u32; let h=cv.client_height() as u32;
-                    cv.set_width(w); cv.set_height(h);
-                }
-                WindowAttributes::default().with_title("Techno-Medieval").with_canvas(Some(cv))
+                // backing store follows the CSS size × devicePixelRatio;
+                // config is the fallback
+                let (w,h)=crate::web::backing_size(&cv,(self.config.width,self.config.height));
+                cv.set_width(w); cv.set_height(h);
+                crate::web::watch_blur();
+                WindowAttributes::default().with_title(self.config.title.clone()).with_canvas(Some(cv))
             }
             #[cfg(not(target_arch="wasm32"))] { unreachable!() }
         } else {
-            WindowAttributes::default().with_title("Techno-Medieval")
+            WindowAttributes::default().with_title(self.config.title.clone())
+                .with_inner_size(winit::dpi::LogicalSize::new(self.config.width, self.config.height))
+                .with_fullscreen(self.config.fullscreen.then_some(winit::window::Fullscreen::Borderless(None)))
         };
         let win = el.create_window(attrs).unwrap();
+        self.scale_factor = win.scale_factor();
         self.window = Some(win);
 
         // ---------- Instance & Surface ----------
         let backends = if self.is_web { wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL }
-                       else { wgpu::Backends::all() };
+                       else { self.config.backends };
         let inst = wgpu::Instance::new(&wgpu::InstanceDescriptor{backends,..Default::default()});
         self.instance = Some(inst);
         let surf = unsafe{
@@ -248,15 +1122,35 @@ impl ApplicationHandler for App {
         }
     }
 
-    fn about_to_wait(&mut self, _:&ActiveEventLoop) {
-        if let Some(w)=&self.window { w.request_redraw(); }
+    /// Mouse-look: raw motion keeps coming while the cursor is locked in
+    /// place, where `CursorMoved` stops.
+    fn device_event(&mut self, _el:&ActiveEventLoop, _id:DeviceId, ev:DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta:(dx,dy) }=ev && self.mouse_grabbed && self.player.is_none() {
+            self.camera.process_mouse_delta(dx as f32, dy as f32);
+        }
+    }
+
+    /// Redraw now, or (native, capped) sleep until the next frame is due.
+    fn about_to_wait(&mut self, el:&ActiveEventLoop) {
+        let Some(w)=&self.window else { return };
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(due)=frame_deadline(self.last_frame, self.config.target_fps) && Instant::now()<due {
+            el.set_control_flow(ControlFlow::WaitUntil(due));
+            return;
+        }
+        el.set_control_flow(ControlFlow::Poll);
+        w.request_redraw();
     }
 
     fn window_event(&mut self, el:&ActiveEventLoop, id:WindowId, ev:WindowEvent) {
         if Some(id)!=self.window.as_ref().map(|w|w.id()) { return; }
 
         match ev {
-            WindowEvent::CloseRequested => el.exit(),
+            WindowEvent::CloseRequested => {
+                // keep edits (live + network mutations) across restarts
+                if let Err(e) = self.chunk_mgr.flush() { error!("saving chunks failed: {e}"); }
+                el.exit();
+            }
 
             WindowEvent::KeyboardInput{event,..} =>{
                 if let PhysicalKey::Code(code)=event.physical_key {
@@ -264,143 +1158,172 @@ impl ApplicationHandler for App {
                         ElementState::Pressed   => self.keyboard.key_press(code),
                         ElementState::Released  => self.keyboard.key_release(code),
                     }
+                    if event.state==ElementState::Pressed && !event.repeat {
+                        match code {
+                            KeyCode::Escape if self.mouse_grabbed => self.set_mouse_grab(false),
+                            KeyCode::KeyF => self.set_mouse_grab(!self.mouse_grabbed),
+                            KeyCode::KeyR => self.regenerate_world(),
+                            KeyCode::KeyJ => self.cycle_designer(),
+                            KeyCode::Home => self.reset_to_spawn(),
+                            KeyCode::F2 => {
+                                self.show_chunk_borders = !self.show_chunk_borders;
+                                info!("chunk borders {}", if self.show_chunk_borders {"shown"} else {"hidden"});
+                            }
+                            KeyCode::F4 => self.toggle_frustum_freeze(),
+                            KeyCode::KeyK => self.toggle_isolation(self.keyboard.modifiers().shift_key()),
+                            KeyCode::F5 => self.toggle_recording(),
+                            KeyCode::F6 => self.start_playback(),
+                            KeyCode::F7 => self.stop_flythrough(),
+                            KeyCode::F8 => self.cycle_lod_override(),
+                            KeyCode::KeyU => self.cycle_heatmap(),
+                            KeyCode::F9 => self.reload_assets(),
+                            KeyCode::KeyB => self.place_at_crosshair(),
+                            KeyCode::Delete => self.delete_at_crosshair(),
+                            KeyCode::KeyC => {
+                                self.click_to_move = !self.click_to_move;
+                                if !self.click_to_move { self.fly = None; }
+                                info!("click-to-move {}", if self.click_to_move {"on"} else {"off"});
+                            }
+                            KeyCode::KeyV => {
+                                let on = !self.camera.walk;
+                                self.camera.ground_y = -self.chunk_mgr.origin_shift().y;
+                                self.camera.set_walk(on);
+                                info!("{}", if on {"walk mode"} else {"free fly"});
+                            }
+                            KeyCode::KeyL => {
+                                self.local_mutations_enabled = !self.local_mutations_enabled;
+                                info!("local mutations {}", if self.local_mutations_enabled {"on"} else {"off"});
+                            }
+                            KeyCode::KeyP => {
+                                self.network_apply_enabled = !self.network_apply_enabled;
+                                info!("applying network packets {}", if self.network_apply_enabled {"on"} else {"off (dropped)"});
+                            }
+                            KeyCode::KeyN => {
+                                self.snap_edits = !self.snap_edits;
+                                info!("snap to lots {}", if self.snap_edits {"on"} else {"off"});
+                            }
+                            KeyCode::Digit1 | KeyCode::Digit2 | KeyCode::Digit3 => {
+                                self.edit_category = match code {
+                                    KeyCode::Digit1 => BuildingCategory::Lowrise,
+                                    KeyCode::Digit2 => BuildingCategory::Highrise,
+                                    _ => BuildingCategory::Landmark,
+                                };
+                                info!("placing {:?}", self.edit_category);
+                            }
+                            KeyCode::KeyG => if let Some(e)=self.engine.as_mut() {
+                                let on = !e.axis_gizmo();
+                                e.set_axis_gizmo(on);
+                            },
+                            KeyCode::KeyH => if let Some(e)=self.engine.as_mut() {
+                                let on = !e.billboard_aa();
+                                e.set_billboard_aa(on);
+                                info!("billboard edge AA {}", if on {"on"} else {"off"});
+                            },
+                            KeyCode::KeyO => if let Some(e)=self.engine.as_mut() {
+                                let on = !e.impostors();
+                                e.set_impostors(on);
+                                info!("impostor billboards {}", if on {"on"} else {"off"});
+                            },
+                            KeyCode::KeyI => {
+                                let inv = !self.camera.invert_y;
+                                self.camera.set_invert_y(inv);
+                                info!("invert Y {}", if inv {"on"} else {"off"});
+                            }
+                            KeyCode::KeyQ => {
+                                self.quality.enabled = !self.quality.enabled;
+                                info!("adaptive quality {}", if self.quality.enabled {"on"} else {"off"});
+                            }
+                            KeyCode::KeyT => {
+                                let on = !self.camera.altitude_speed;
+                                self.camera.set_altitude_speed(on);
+                                info!("altitude-scaled speed {}", if on {"on"} else {"off"});
+                            }
+                            KeyCode::KeyM => {
+                                let on = !self.camera.smooth;
+                                self.camera.set_smooth(on);
+                                info!("smooth movement {}", if on {"on"} else {"off"});
+                            }
+                            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                                let k = if code==KeyCode::BracketRight { 1.25 } else { 0.8 };
+                                let s = (self.camera.sensitivity_x*k).clamp(1e-4, 0.05);
+                                self.camera.set_sensitivity(s);
+                                info!("mouse sensitivity {s:.4} rad/px");
+                            }
+                            KeyCode::F3 => {
+                                self.debug = !self.debug;
+                                if let Some(e)=self.engine.as_mut() { e.debug = self.debug; }
+                                info!("debug logging {}", if self.debug {"on"} else {"off"});
+                            }
+                            KeyCode::F10 if self.debug => self.dump_buckets = true,
+                            _ => {}
+                        }
+                    }
                 }
             }
-            WindowEvent::CursorMoved { position, .. } =>{
-                if let Some(prev)=self.last_cursor.replace(position) {
-                    let dx=(position.x-prev.x) as f32;
-                    let dy=(position.y-prev.y) as f32;
-                    self.camera.process_mouse_delta(dx,dy,0.002);
+            // releases are lost while unfocused (alt-tab): don't keep drifting
+            // ... and a captured cursor would stay trapped
+            WindowEvent::Focused(false) =>{
+                self.keyboard.release_all();
+                if self.mouse_grabbed { self.set_mouse_grab(false); }
+                self.last_cursor = None;
+            }
+            WindowEvent::ModifiersChanged(m) => self.keyboard.set_modifiers(m.state()),
+            // a click captures the mouse, unless click-to-move wants it
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } =>{
+                if self.click_to_move && self.player.is_none() { self.click_to_move(); }
+                else if !self.mouse_grabbed { self.set_mouse_grab(true); }
+            }
+            // only tracked for click-to-move; looking uses raw motion
+            WindowEvent::CursorMoved { position, .. } => self.last_cursor = Some(position),
+            // wheel: base speed ×1.1 per notch (altitude scaling applies on
+            // top); with Ctrl, zoom: field of view ×0.9 per notch up.  Logged
+            // only when the printed value changes (touchpads scroll per pixel)
+            WindowEvent::MouseWheel { delta, .. } =>{
+                let notches = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 50.0,
+                };
+                if self.keyboard.modifiers().control_key() {
+                    let before = self.camera.fov_deg.round();
+                    self.camera.set_fov(self.camera.fov()*0.9f32.powf(notches));
+                    if self.camera.fov_deg.round() != before { info!("field of view {:.0}°", self.camera.fov_deg); }
+                } else {
+                    let before = (self.camera.speed * 10.0).round();
+                    self.camera.scale_speed(1.1f32.powf(notches));
+                    if (self.camera.speed * 10.0).round() != before { info!("camera speed {:.1} m/s (base)", self.camera.speed); }
                 }
             }
             WindowEvent::Resized(sz) =>{
                 if let Some(e)=self.engine.as_mut(){ e.resize(sz); }
             }
+            // another monitor's DPI (on the web, zoom or `devicePixelRatio`,
+            // which winit's ResizeObserver reports): keep the logical size and
+            // resize the frame (depth, MSAA and tone-map targets with it)
+            // right away rather than presenting at the old size until `Resized`
+            WindowEvent::ScaleFactorChanged { scale_factor, mut inner_size_writer } =>{
+                let old=std::mem::replace(&mut self.scale_factor, scale_factor);
+                if let Some(w)=self.window.as_ref() {
+                    let sz=rescale_size(w.inner_size(), old, scale_factor);
+                    let _=inner_size_writer.request_inner_size(sz);
+                    if let Some(e)=self.engine.as_mut(){ e.resize(sz); }
+                }
+                self.last_cursor=None;
+            }
             WindowEvent::RedrawRequested =>{
                 let now=Instant::now();
                 let dt=now.duration_since(self.last_frame).as_secs_f32();
-                if dt<0.016 { return; }
                 self.last_frame=now;
 
-                self.camera.update(dt,&self.keyboard);
-                self.maybe_wrap_torus();
-                self.maybe_float_origin();
+                self.advance_camera(dt);
                 self.finalize();
-                
-                let mut v0_low_common = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v0_low_alt    = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v0_high       = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v0_land       = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v1_low_common = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v1_low_alt    = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v1_high       = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v1_land       = Vec::<InstanceRaw>::with_capacity(4096);
-                let mut v2_bill       = Vec::<InstanceRaw>::with_capacity(4096);
-
-
-                if let Some(e)=self.engine.as_mut() {
-                    // -------- (immutable borrow of assets scoped) --------
-                    {
-                        let assets:&AssetLibrary = e.assets_ref();
-
-                        // network mutate packets
-                        net_mutations::poll_incoming(&mut self.chunk_mgr, assets);
-
-                        // chunk ensure + local mutations
-                        self.chunk_mgr.set_viewer(self.viewer_id, self.camera.position.x, self.camera.position.z);
-                        self.chunk_mgr.ensure_for_viewers(&mut self.designer, assets);
-
-                        //info!("loaded chunks = {}", self.chunk_mgr.loaded.len());
-
-                        self.chunk_mgr.mutate_near(assets, 0.02, dt, 1, now.elapsed().as_nanos() as u64);
-                        
-                        // build instance buckets
-                        let size=self.window.as_ref().unwrap().inner_size();
-                        let aspect=size.width.max(1) as f32 / size.height.max(1) as f32;
-                        let vp=self.camera.view_projection(aspect);
-                        e.update_camera(&vp);
-
-                        let fr=culling::frustum_from_vp(&vp);
-                        let cam=self.camera.position.to_vec();
-
-                        // buckets
-                       
-
-                        // alt low-rise archetype id (timber_house_b = id 1)
-                        let alt_id:usize = 1;
-
-                        for list in self.chunk_mgr.loaded.values() {
-                            for b in list {
-                                let dist=(b.center-cam).magnitude();
-                                if dist>self.cull { continue; }
-
-                                let base=assets.base_half(b.archetype_id as usize);
-                                let half=Vector3::new(
-                                    base.x*b.scale.x, base.y*b.scale.y, base.z*b.scale.z);
-                                if !culling::aabb_intersects_frustum(b.center,half,&fr){continue;}
-
-                                let cat=assets.category_of(b.archetype_id as usize);
-                                let inst=InstanceRaw{
-                                    pos:[b.center.x,b.center.y,b.center.z,0.0],
-                                    scale:[b.scale.x,b.scale.y,b.scale.z,0.0],
-                                    misc:[match cat{
-                                        BuildingCategory::Lowrise =>0.0,
-                                        BuildingCategory::Highrise=>1.0,
-                                        BuildingCategory::Landmark=>2.0,
-                                    }, b.archetype_id as f32,0.0,0.0],
-                                };
-
-                                if dist<=self.lod0 {
-                                    match cat {
-                                        BuildingCategory::Lowrise=>{
-                                            if b.archetype_id as usize==alt_id {
-                                                v0_low_alt.push(inst)
-                                            } else { v0_low_common.push(inst) }
-                                        }
-                                        BuildingCategory::Highrise => v0_high.push(inst),
-                                        BuildingCategory::Landmark => v0_land.push(inst),
-                                    }
-                                } else if dist<=self.lod1 {
-                                    match cat {
-                                        BuildingCategory::Lowrise=>{
-                                            if b.archetype_id as usize==alt_id {
-                                                v1_low_alt.push(inst)
-                                            } else { v1_low_common.push(inst) }
-                                        }
-                                        BuildingCategory::Highrise => v1_high.push(inst),
-                                        BuildingCategory::Landmark => v1_land.push(inst),
-                                    }
-                                } else {
-                                    v2_bill.push(InstanceRaw{
-                                        pos:[b.center.x,b.center.y,b.center.z,0.0],
-                                        scale:[half.x.max(0.5), (half.y*2.0).max(0.5),1.0,0.0],
-                                        misc:[1.0,0.0,0.0,0.0], // tint = high-rise colour for far billboard
-                                    });
-                                }
-                            }
-                        }
 
-                        let assets: &AssetLibrary = e.assets_ref();
-                    }
-
-                    e.update_instances(
-                        &v0_low_common,&v0_low_alt,&v0_high,&v0_land,
-                        &v1_low_common,&v1_low_alt,&v1_high,&v1_land,
-                        &v2_bill,&self.ground_inst,
-                    );
-                    if let Err(err)=e.render(){
-                        match err {
-                            wgpu::SurfaceError::Lost|wgpu::SurfaceError::Outdated=>{
-                                e.resize(self.window.as_ref().unwrap().inner_size());
-                            }
-                            wgpu::SurfaceError::OutOfMemory=>{
-                                error!("OOM"); el.exit();
-                            }
-                            _=>warn!("surface err {err:?}"),
-                        }
-                    }
+                let size=self.window.as_ref().unwrap().inner_size();
+                match self.step_frame(dt,size,0) {
+                    Ok(()) => self.oom_strike=false,
+                    Err(err) => self.recover_surface(el,err,size),
                 }
-                if let Some(w)=&self.window { w.request_redraw(); }
+                self.adapt_quality(now.elapsed().as_secs_f32()*1000.0, dt);
+                // the next redraw is requested by `about_to_wait`
             }
             _ => {}
         }
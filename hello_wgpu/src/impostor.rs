@@ -0,0 +1,270 @@
+//! Impostor billboards: at startup each building category's mesh is rendered
+//! orthographically from `IMPOSTOR_ANGLES` directions around it into one
+//! atlas (a column per angle, a row per category).  With impostors on
+//! (`Engine::set_impostors`), `vs_main` turns the LOD2 billboard quads to
+//! face the camera about the vertical axis and `shade` samples the tile of
+//! the nearest angle, so far buildings keep their silhouette at billboard
+//! cost.  Each row's mesh bounds (`ImpostorFrame`) go to the shader in a
+//! uniform, so the quad is sized like the tile it shows.  The shader
+//! hard-codes the constants below; keep them in step.
+
+use wgpu::util::DeviceExt;
+use bytemuck::{Pod, Zeroable};
+use cgmath::{EuclideanSpace, Matrix4, Point3, Vector3};
+
+use crate::mesh::{self, MeshData};
+use crate::shadow::OPENGL_TO_WGPU;
+use crate::types::InstanceRaw;
+
+/// Views per category, evenly spaced about +Y starting from +Z.
+pub const IMPOSTOR_ANGLES: u32 = 8;
+/// Atlas rows: lowrise, highrise, landmark (the instance tint index).
+pub const IMPOSTOR_ROWS: u32 = 3;
+/// Default tile edge (texels).
+pub const IMPOSTOR_TILE: u32 = 128;
+pub const IMPOSTOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+/// A tile spans the mesh's half height × this, and its larger horizontal
+/// half extent × this × √2 (any footprint fits whichever way it turns).
+pub const IMPOSTOR_PAD: f32 = 1.05;
+
+const BAKE_DEPTH: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+// one view-projection per tile, at the uniform offset alignment
+const BAKE_STRIDE: u64 = 256;
+
+/// Tile edge for a requested `tile` on a device whose 2D textures go up to
+/// `max_dim`: the whole atlas must fit in one texture.
+pub fn impostor_tile(tile: u32, max_dim: u32) -> u32 {
+    tile.clamp(1, (max_dim / IMPOSTOR_ANGLES.max(IMPOSTOR_ROWS)).max(1))
+}
+
+/// Horizontal (x, z) direction column `k` of the atlas is seen from.
+pub fn view_dir(k: u32) -> (f32, f32) {
+    let a = k as f32 * std::f32::consts::TAU / IMPOSTOR_ANGLES as f32;
+    (a.sin(), a.cos())
+}
+
+/// Orthographic view-projection of `bounds` (min, max) from column `k`:
+/// the tile's left edge is the left of someone standing on that side.
+pub fn tile_view_proj((min, max): (Vector3<f32>, Vector3<f32>), k: u32) -> Matrix4<f32> {
+    let c = (min + max) * 0.5;
+    let h = (max - min) * 0.5;
+    let r = h.x.max(h.z) * std::f32::consts::SQRT_2 * IMPOSTOR_PAD;
+    let hh = h.y * IMPOSTOR_PAD;
+    let (dx, dz) = view_dir(k);
+    let dist = r + 1.0;
+    let eye = c + Vector3::new(dx, 0.0, dz) * dist;
+    let view = Matrix4::look_at_rh(Point3::from_vec(eye), Point3::from_vec(c), Vector3::unit_y());
+    OPENGL_TO_WGPU * cgmath::ortho(-r, r, -hh, hh, 0.0, dist + r + 1.0) * view
+}
+
+/// Mesh-space bounds an atlas row was framed on (`tile_view_proj`): half
+/// extents and centre, `w` unused.  `IMPOSTOR_FRAMES` in shader.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ImpostorFrame {
+    pub half:   [f32; 4],
+    pub center: [f32; 4],
+}
+
+impl ImpostorFrame {
+    /// Frame of `mesh`; all zero for an empty one.
+    pub fn of(mesh: &MeshData) -> Self {
+        let Some((min, max)) = mesh.bounds() else { return Self::default() };
+        let (h, c) = ((max - min) * 0.5, (min + max) * 0.5);
+        Self { half: [h.x, h.y, h.z, 0.0], center: [c.x, c.y, c.z, 0.0] }
+    }
+}
+
+/// The impostor quad of LOD2 instance `inst` drawn from a row framed on
+/// `frame`, as `vs_impostor` builds it: world centre, half width, half
+/// height.  The tile's padding is included, so the quad is the building's
+/// world bounds × (`IMPOSTOR_PAD` · √2, `IMPOSTOR_PAD`).
+pub fn impostor_quad(frame: &ImpostorFrame, inst: &InstanceRaw) -> (Vector3<f32>, f32, f32) {
+    // LOD2 instances keep the building's scale in pos.w / scale.w / scale.z
+    let s = Vector3::new(inst.pos[3], inst.scale[3], inst.scale[2]);
+    let (h, c) = (frame.half, frame.center);
+    let center = Vector3::new(inst.pos[0] + c[0] * s.x, inst.pos[1] + c[1] * s.y, inst.pos[2] + c[2] * s.z);
+    let half_w = (h[0] * s.x).max(h[2] * s.z) * std::f32::consts::SQRT_2 * IMPOSTOR_PAD;
+    (center, half_w, h[1] * s.y * IMPOSTOR_PAD)
+}
+
+/// The city's atlas rows: the lowrise, highrise and landmark LOD0 meshes.
+pub fn city_meshes() -> [MeshData; IMPOSTOR_ROWS as usize] {
+    [mesh::block_lowrise_data(), mesh::tower_highrise_data(), mesh::pyramid_tower_data()]
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct BakeUniform {
+    view_proj: [[f32; 4]; 4],
+    _pad: [f32; 48],
+}
+
+pub struct ImpostorAtlas {
+    pub tile: u32,
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    /// Per row, the bounds its tiles frame; uploaded to `frame_buf`.
+    pub frames: [ImpostorFrame; IMPOSTOR_ROWS as usize],
+    pub frame_buf: wgpu::Buffer,
+}
+
+impl ImpostorAtlas {
+    /// Render `meshes` (one per row) into a new atlas of `tile`-texel tiles,
+    /// clamped with `impostor_tile`.  Texels hold a fixed per-face shade in
+    /// linear light (the palette tint and scene light apply when drawn);
+    /// alpha is coverage.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, meshes: [&MeshData; IMPOSTOR_ROWS as usize], tile: u32) -> Self {
+        let tile = impostor_tile(tile, device.limits().max_texture_dimension_2d);
+        let size = wgpu::Extent3d { width: tile * IMPOSTOR_ANGLES, height: tile * IMPOSTOR_ROWS, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("impostor atlas"),
+            size, mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2,
+            format: IMPOSTOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("impostor bake depth"),
+            size, mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2,
+            format: BAKE_DEPTH,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("impostor bake shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/impostor.wgsl").into()),
+        });
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("impostor bake bgl"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+        let tiles = (IMPOSTOR_ANGLES * IMPOSTOR_ROWS) as u64;
+        let ubuf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("impostor bake uniform"),
+            size: tiles * BAKE_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("impostor bake bg"),
+            layout: &bgl,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding { buffer: &ubuf, offset: 0, size: wgpu::BufferSize::new(64) }),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("impostor bake layout"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("impostor bake pipe"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader, entry_point: Some("vs_bake"), compilation_options: Default::default(),
+                buffers: &[mesh::Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader, entry_point: Some("fs_bake"), compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: IMPOSTOR_FORMAT, blend: None, write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: BAKE_DEPTH,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let gpu_meshes: Vec<mesh::Mesh> = meshes.iter().map(|m| m.upload(device, "impostor bake mesh")).collect();
+        for (row, m) in meshes.iter().enumerate() {
+            let Some(bounds) = m.bounds() else { continue };
+            for k in 0..IMPOSTOR_ANGLES {
+                let u = BakeUniform { view_proj: tile_view_proj(bounds, k).into(), _pad: [0.0; 48] };
+                let slot = row as u64 * IMPOSTOR_ANGLES as u64 + k as u64;
+                queue.write_buffer(&ubuf, slot * BAKE_STRIDE, bytemuck::bytes_of(&u));
+            }
+        }
+        let mut enc = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("impostor bake") });
+        {
+            let mut pass = enc.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("impostor bake pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view, resolve_target: None, depth_slice: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Discard }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&pipeline);
+            for (row, (m, g)) in meshes.iter().zip(&gpu_meshes).enumerate() {
+                if m.indices.is_empty() { continue; }
+                pass.set_vertex_buffer(0, g.vertex_buffer.slice(..));
+                pass.set_index_buffer(g.index_buffer.slice(..), g.index_format);
+                for k in 0..IMPOSTOR_ANGLES {
+                    let slot = row as u32 * IMPOSTOR_ANGLES + k;
+                    pass.set_viewport((k * tile) as f32, (row as u32 * tile) as f32, tile as f32, tile as f32, 0.0, 1.0);
+                    pass.set_bind_group(0, &bg, &[slot * BAKE_STRIDE as u32]);
+                    pass.draw_indexed(0..g.index_count, 0, 0..1);
+                }
+            }
+        }
+        queue.submit(Some(enc.finish()));
+        // the bake's own resources are done with once it has run
+        for g in &gpu_meshes { g.vertex_buffer.destroy(); g.index_buffer.destroy(); }
+        ubuf.destroy();
+        depth.destroy();
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("impostor sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let frames = meshes.map(ImpostorFrame::of);
+        let frame_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("impostor frames"),
+            contents: bytemuck::cast_slice(&frames),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        Self { tile, texture, view, sampler, frames, frame_buf }
+    }
+
+    /// The city's categories (`city_meshes`).
+    pub fn for_city(device: &wgpu::Device, queue: &wgpu::Queue, tile: u32) -> Self {
+        let [low, high, land] = city_meshes();
+        Self::new(device, queue, [&low, &high, &land], tile)
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture { &self.texture }
+
+    /// Atlas size in texels.
+    pub fn size(&self) -> (u32, u32) { (self.tile * IMPOSTOR_ANGLES, self.tile * IMPOSTOR_ROWS) }
+}
@@ -1,6 +1,7 @@
 use wasm_bindgen::prelude::*;
 pub mod hello_wgpu;
 pub mod mesh;
+pub mod axes;
 pub mod camera;
 pub mod culling;
 pub mod types;
@@ -10,12 +11,30 @@ pub mod city_store;
 pub mod assets;
 pub mod designer_ml;
 pub mod net_mutations;
-pub use hello_wgpu::run; 
+pub mod flythrough;
+pub mod shadow;
+pub mod smoke;
+pub mod facade;
+pub mod impostor;
+pub mod gizmo;
+pub mod tonemap;
+pub mod debug_lines;
+pub mod spatial;
+pub mod quality;
+pub mod rng;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+pub use hello_wgpu::{run, run_with, EngineConfig};
 cfg_if::cfg_if! {
   if #[cfg(target_arch = "wasm32")] {
       #[wasm_bindgen(start)]
       pub async fn start() { 
         //console_error_panic_hook::set_once();
+        if web::manual_start() { return; }
         hello_wgpu::run(true).await; }
   } else {
       pub fn main() { pollster::block_on(hello_wgpu::run(false)); }
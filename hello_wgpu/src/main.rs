@@ -1,17 +1,13 @@
-use hello_wgpu::run;  
-fn main() {
-    // let event_loop = EventLoop::new().unwrap();
-
-    // // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
-    // // dispatched any events. This is ideal for games and similar applications.
-    // event_loop.set_control_flow(ControlFlow::Poll);
+use hello_wgpu::run_with;
+use hello_wgpu::bench::run_bench;
+use hello_wgpu::cli::{parse_args, Command, USAGE};
 
-    // // ControlFlow::Wait pauses the event loop if no events are available to process.
-    // // This is ideal for non-game applications that only update in response to user
-    // // input, and uses significantly less power/CPU time than ControlFlow::Poll.
-    // event_loop.set_control_flow(ControlFlow::Wait);
-
-    // let mut app = App::default();
-    // event_loop.run_app(&mut app);
-    pollster::block_on(run(false));
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match parse_args(&args) {
+        Ok(Command::Run(config)) => pollster::block_on(run_with(false, config)),
+        Ok(Command::Bench(opts)) => println!("{}", run_bench(&opts)),
+        Ok(Command::Help) => println!("{USAGE}"),
+        Err(e) => { eprintln!("{e}\n{USAGE}"); std::process::exit(2); }
+    }
 }
@@ -1,7 +1,14 @@
+use std::collections::HashMap;
+
 use wgpu::util::DeviceExt;
 use bytemuck::{Pod, Zeroable};
-use cgmath::{Matrix4, Vector3};
-use crate::chunking::CityGenParams;
+use cgmath::{InnerSpace, Vector3};
+
+use crate::assets::{AssetLibrary, BuildingCategory};
+use crate::chunking::RuntimePlacement;
+use crate::culling::window_seed;
+use crate::facade::LAYER_COUNT;
+use crate::types::{TINT_HIGHRISE, TINT_LANDMARK, TINT_LOWRISE};
 // ---------- Vertex & Mesh ----------
 
 #[repr(C)]
@@ -9,6 +16,8 @@ use crate::chunking::CityGenParams;
 pub struct Vertex {
     pub position: [f32; 3],
     pub color:    [f32; 4],
+    pub normal:   [f32; 3],
+    pub uv:       [f32; 2],   // facade texture coords (repeat)
 }
 
 impl Vertex {
@@ -19,6 +28,8 @@ impl Vertex {
             attributes: &[
                 wgpu::VertexAttribute { shader_location: 0, offset: 0,  format: wgpu::VertexFormat::Float32x3 },
                 wgpu::VertexAttribute { shader_location: 1, offset: 12, format: wgpu::VertexFormat::Float32x4 },
+                wgpu::VertexAttribute { shader_location: 5, offset: 28, format: wgpu::VertexFormat::Float32x3 },
+                wgpu::VertexAttribute { shader_location: 6, offset: 40, format: wgpu::VertexFormat::Float32x2 },
             ],
         }
     }
@@ -29,25 +40,148 @@ pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer:  wgpu::Buffer,
     pub index_count:   u32,
+    pub index_format:  wgpu::IndexFormat,
+}
+
+/// CPU-side geometry. Builders return this so it can be inspected,
+/// transformed or merged before `upload` turns it into a GPU `Mesh`.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices:  Vec<u32>,
+}
+
+/// Where an imported mesh's normals come from (`MeshData::with_normals`).
+/// Computed normals assume counter-clockwise front faces.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum NormalMode {
+    /// Keep the normals the mesh came with.
+    #[default]
+    FromFile,
+    /// One normal per triangle; vertices are duplicated so none is shared.
+    FlatComputed,
+    /// Per position, the face normals around it averaged (weighted by the
+    /// corner angle, so how a face is triangulated doesn't matter).
+    SmoothComputed,
 }
 
-fn upload(device: &wgpu::Device, vertices: &[Vertex], indices: &[u16], label: &str) -> Mesh {
-    let vb = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some(&format!("{label} VB")),
-        contents: bytemuck::cast_slice(vertices),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let ib = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some(&format!("{label} IB")),
-        contents: bytemuck::cast_slice(indices),
-        usage: wgpu::BufferUsages::INDEX,
-    });
-    Mesh { vertex_buffer: vb, index_buffer: ib, index_count: indices.len() as u32 }
+impl MeshData {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self { Self { vertices, indices } }
+
+    /// Upload as u16 indices when the vertex count allows it, u32 otherwise.
+    pub fn upload(&self, device: &wgpu::Device, label: &str) -> Mesh {
+        let vb = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{label} VB")),
+            contents: bytemuck::cast_slice(&self.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let (contents, index_format) = if self.vertices.len() <= u16::MAX as usize + 1 {
+            let narrow: Vec<u16> = self.indices.iter().map(|&i| i as u16).collect();
+            (bytemuck::cast_slice(&narrow).to_vec(), wgpu::IndexFormat::Uint16)
+        } else {
+            (bytemuck::cast_slice(&self.indices).to_vec(), wgpu::IndexFormat::Uint32)
+        };
+        let ib = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{label} IB")),
+            contents: &contents,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Mesh { vertex_buffer: vb, index_buffer: ib, index_count: self.indices.len() as u32, index_format }
+    }
+
+    /// Axis-aligned bounds `(min, max)`; `None` for an empty mesh.
+    pub fn bounds(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let first = self.vertices.first()?.position;
+        let mut min = Vector3::from(first);
+        let mut max = min;
+        for v in &self.vertices[1..] {
+            let p = v.position;
+            min.x = min.x.min(p[0]); min.y = min.y.min(p[1]); min.z = min.z.min(p[2]);
+            max.x = max.x.max(p[0]); max.y = max.y.max(p[1]); max.z = max.z.max(p[2]);
+        }
+        Some((min, max))
+    }
+
+    /// Multiply every vertex colour by `tint` (component-wise).
+    pub fn tint(&mut self, tint: [f32; 4]) -> &mut Self {
+        for v in &mut self.vertices {
+            for (c, t) in v.color.iter_mut().zip(tint) { *c *= t; }
+        }
+        self
+    }
+
+    /// Apply `p' = p * scale + offset` to every vertex (normals by the inverse scale).
+    pub fn scale_translate(&mut self, scale: Vector3<f32>, offset: Vector3<f32>) -> &mut Self {
+        for v in &mut self.vertices {
+            let p = &mut v.position;
+            p[0] = p[0] * scale.x + offset.x;
+            p[1] = p[1] * scale.y + offset.y;
+            p[2] = p[2] * scale.z + offset.z;
+            let n = Vector3::new(v.normal[0] / scale.x, v.normal[1] / scale.y, v.normal[2] / scale.z);
+            v.normal = n.normalize().into();
+        }
+        self
+    }
+
+    /// Replace the normals as `mode` says; `FlatComputed` also unshares
+    /// the vertices (3 per triangle, indices `0..`).  Degenerate triangles
+    /// add nothing, and a vertex left without a normal points up.
+    pub fn with_normals(mut self, mode: NormalMode) -> Self {
+        let pos = |i: u32| Vector3::from(self.vertices[i as usize].position);
+        let fallback = |n: Vector3<f32>| if n.magnitude2() > 1e-12 { n.normalize() } else { Vector3::unit_y() };
+        match mode {
+            NormalMode::FromFile => {}
+            NormalMode::FlatComputed => {
+                let mut vertices = Vec::with_capacity(self.indices.len());
+                for t in self.indices.chunks_exact(3) {
+                    let n = fallback((pos(t[1]) - pos(t[0])).cross(pos(t[2]) - pos(t[0])));
+                    vertices.extend(t.iter().map(|&i| Vertex { normal: n.into(), ..self.vertices[i as usize] }));
+                }
+                self.indices = (0..vertices.len() as u32).collect();
+                self.vertices = vertices;
+            }
+            NormalMode::SmoothComputed => {
+                let key = |i: u32| self.vertices[i as usize].position.map(f32::to_bits);
+                let mut sums: HashMap<[u32; 3], Vector3<f32>> = HashMap::new();
+                for t in self.indices.chunks_exact(3) {
+                    let n = (pos(t[1]) - pos(t[0])).cross(pos(t[2]) - pos(t[0]));
+                    if n.magnitude2() <= 1e-12 { continue; }
+                    let n = n.normalize();
+                    for k in 0..3 {
+                        let (p, a, b) = (pos(t[k]), pos(t[(k + 1) % 3]), pos(t[(k + 2) % 3]));
+                        let angle = (a - p).normalize().dot((b - p).normalize()).clamp(-1.0, 1.0).acos();
+                        *sums.entry(key(t[k])).or_insert(Vector3::new(0.0, 0.0, 0.0)) += n * angle;
+                    }
+                }
+                let normals: Vec<[f32; 3]> = (0..self.vertices.len() as u32)
+                    .map(|i| fallback(sums.get(&key(i)).copied().unwrap_or(Vector3::new(0.0, 0.0, 0.0))).into())
+                    .collect();
+                for (v, n) in self.vertices.iter_mut().zip(normals) { v.normal = n; }
+            }
+        }
+        self
+    }
+
+    /// Append `other`, rebasing its indices.
+    pub fn append(&mut self, other: &MeshData) -> &mut Self {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+        self
+    }
 }
 
 // ---------- Mesh builders ----------
 
-fn build_box_vertices(hx: f32, hy: f32, hz: f32, face_colors: [[f32; 4]; 6]) -> (Vec<Vertex>, Vec<u16>) {
+/// Unit normal of triangle `a, b, c`, oriented away from the origin side.
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let (a, b, c) = (Vector3::from(a), Vector3::from(b), Vector3::from(c));
+    let mut n = (b - a).cross(c - a).normalize();
+    if n.dot(a + b + c) < 0.0 { n = -n; }
+    [n.x, n.y, n.z]
+}
+
+fn build_box_vertices(hx: f32, hy: f32, hz: f32, face_colors: [[f32; 4]; 6]) -> MeshData {
     let positions = [
         // +X
         [ hx,-hy,-hz], [ hx,-hy, hz], [ hx, hy,-hz], [ hx, hy, hz],
@@ -63,58 +197,100 @@ fn build_box_vertices(hx: f32, hy: f32, hz: f32, face_colors: [[f32; 4]; 6]) ->
         [ hx,-hy,-hz], [-hx,-hy,-hz], [ hx, hy,-hz], [-hx, hy,-hz],
     ];
 
+    const NORMALS: [[f32; 3]; 6] = [
+        [1.0,0.0,0.0], [-1.0,0.0,0.0], [0.0,1.0,0.0], [0.0,-1.0,0.0], [0.0,0.0,1.0], [0.0,0.0,-1.0],
+    ];
+
+    // box unwrap: each face gets its own 0..size rect (one texture tile per
+    // local unit), v running down so the texture's top is the face's top
+    let (w, h, d) = (2.0*hx, 2.0*hy, 2.0*hz);
+    const FACE_DIMS: [(usize, usize); 6] = [(2,1), (2,1), (0,2), (0,2), (0,1), (0,1)];
+    let dims = [w, h, d];
+
     let mut vertices = Vec::with_capacity(24);
-    for face in 0..6 {
-        let color = face_colors[face];
-        for i in 0..4 {
-            vertices.push(Vertex { position: positions[face*4 + i], color });
+    for (face, color) in face_colors.into_iter().enumerate() {
+        let (fu, fv) = (dims[FACE_DIMS[face].0], dims[FACE_DIMS[face].1]);
+        let uvs = [[0.0, fv], [fu, fv], [0.0, 0.0], [fu, 0.0]];
+        for (i, uv) in uvs.into_iter().enumerate() {
+            vertices.push(Vertex { position: positions[face*4 + i], color, normal: NORMALS[face], uv });
         }
     }
 
-    let mut indices = Vec::<u16>::with_capacity(6 * 6);
+    let mut indices = Vec::<u32>::with_capacity(6 * 6);
     for f in 0..6 {
-        let b = (f * 4) as u16;
+        let b = (f * 4) as u32;
         indices.extend_from_slice(&[b, b+1, b+2, b+2, b+1, b+3]);
     }
-    (vertices, indices)
+    MeshData::new(vertices, indices)
+}
+
+/// `build_box_vertices` without the underside (−Y), which nothing standing
+/// on the ground ever shows: 10 triangles instead of 12.
+fn open_box_vertices(hx: f32, hy: f32, hz: f32, color: [f32; 4]) -> MeshData {
+    let full = build_box_vertices(hx, hy, hz, [color; 6]);
+    let vertices = [0, 1, 2, 4, 5].into_iter().flat_map(|f| full.vertices[f*4..f*4 + 4].to_vec()).collect();
+    let indices = (0..5u32).flat_map(|f| { let b = f * 4; [b, b+1, b+2, b+2, b+1, b+3] }).collect();
+    MeshData::new(vertices, indices)
 }
 
+pub fn cuboid_data(w: f32, h: f32, d: f32, color: [f32; 4]) -> MeshData {
+    build_box_vertices(w*0.5, h*0.5, d*0.5, [color; 6])
+}
 pub fn create_cuboid(device: &wgpu::Device, w: f32, h: f32, d: f32, color: [f32; 4]) -> Mesh {
-    let (v, i) = build_box_vertices(w*0.5, h*0.5, d*0.5, [color; 6]);
-    upload(device, &v, &i, "Cuboid")
+    cuboid_data(w, h, d, color).upload(device, "Cuboid")
 }
 
 /// 1×1×1 cube centered at origin.
-pub fn create_cube(device: &wgpu::Device) -> Mesh {
-    let (v, i) = build_box_vertices(
+pub fn cube_data() -> MeshData {
+    build_box_vertices(
         0.5, 0.5, 0.5,
         [
             [0.9,0.2,0.2,1.0], [0.2,0.9,0.2,1.0], [0.2,0.2,0.9,1.0],
             [0.9,0.9,0.2,1.0], [0.9,0.2,0.9,1.0], [0.2,0.9,0.9,1.0],
         ]
-    );
-    upload(device, &v, &i, "Cube")
+    )
+}
+pub fn create_cube(device: &wgpu::Device) -> Mesh {
+    cube_data().upload(device, "Cube")
 }
 
 /// Wide, low-rise block (warehouse-like).
+pub fn block_lowrise_data() -> MeshData {
+    cuboid_data(3.0, 0.8, 2.0, [0.65,0.65,0.70,1.0])
+}
 pub fn create_block_lowrise(device: &wgpu::Device) -> Mesh {
-    create_cuboid(device, 3.0, 0.8, 2.0, [0.65,0.65,0.70,1.0])
+    block_lowrise_data().upload(device, "Lowrise")
+}
+/// LOD1 of `block_lowrise_data`: same box, no underside.
+pub fn block_lowrise_lod1_data() -> MeshData {
+    open_box_vertices(1.5, 0.4, 1.0, [0.65,0.65,0.70,1.0])
+}
+pub fn create_block_lowrise_lod1(device: &wgpu::Device) -> Mesh {
+    block_lowrise_lod1_data().upload(device, "Lowrise LOD1")
 }
 
 /// Tall, slender tower.
+pub fn tower_highrise_data() -> MeshData {
+    cuboid_data(0.9, 6.0, 0.9, [0.55,0.60,0.70,1.0])
+}
 pub fn create_tower_highrise(device: &wgpu::Device) -> Mesh {
-    create_cuboid(device, 0.9, 6.0, 0.9, [0.55,0.60,0.70,1.0])
+    tower_highrise_data().upload(device, "Highrise")
+}
+/// LOD1 of `tower_highrise_data`: same box, no underside.
+pub fn tower_highrise_lod1_data() -> MeshData {
+    open_box_vertices(0.45, 3.0, 0.45, [0.55,0.60,0.70,1.0])
+}
+pub fn create_tower_highrise_lod1(device: &wgpu::Device) -> Mesh {
+    tower_highrise_lod1_data().upload(device, "Highrise LOD1")
 }
 
 /// Cuboid base + pyramid roof.
-pub fn create_pyramid_tower(device: &wgpu::Device) -> Mesh {
+pub fn pyramid_tower_data() -> MeshData {
     // Base 2.0×1.2×2.0
     let base_w = 2.0; let base_h = 1.2; let base_d = 2.0;
     let base_color = [0.6,0.6,0.65,1.0];
 
-    let (mut vertices, mut indices) = {
-        build_box_vertices(base_w*0.5, base_h*0.5, base_d*0.5, [base_color; 6])
-    };
+    let mut m = build_box_vertices(base_w*0.5, base_h*0.5, base_d*0.5, [base_color; 6]);
 
     // Roof pyramid
     let roof_h = 0.9;
@@ -122,37 +298,60 @@ pub fn create_pyramid_tower(device: &wgpu::Device) -> Mesh {
     let hx = base_w * 0.5;
     let hz = base_d * 0.5;
 
-    let c0 = Vertex { position: [-hx, y_base, -hz], color: [0.75,0.25,0.25,1.0] };
-    let c1 = Vertex { position: [ hx, y_base, -hz], color: [0.75,0.25,0.25,1.0] };
-    let c2 = Vertex { position: [-hx, y_base,  hz], color: [0.80,0.30,0.30,1.0] };
-    let c3 = Vertex { position: [ hx, y_base,  hz], color: [0.80,0.30,0.30,1.0] };
-    let apex = Vertex { position: [0.0, y_base + roof_h, 0.0], color: [0.85,0.35,0.35,1.0] };
-
-    let base_idx = vertices.len() as u16;
-    vertices.extend_from_slice(&[c0,c1,c2,c3,apex]);
-
-    let (i0,i1,i2,i3,ia) = (base_idx, base_idx+1, base_idx+2, base_idx+3, base_idx+4);
-    indices.extend_from_slice(&[ i0,i1,ia,  i1,i3,ia,  i3,i2,ia,  i2,i0,ia ]);
-
-    upload(device, &vertices, &indices, "Pyramid Tower")
+    let c0 = [-hx, y_base, -hz];
+    let c1 = [ hx, y_base, -hz];
+    let c2 = [-hx, y_base,  hz];
+    let c3 = [ hx, y_base,  hz];
+    let apex = [0.0, y_base + roof_h, 0.0];
+
+    // one triangle per side, own vertices so each face gets a flat normal
+    for (a, b, color) in [(c0,c1,[0.75,0.25,0.25,1.0]), (c1,c3,[0.80,0.30,0.30,1.0]),
+                          (c3,c2,[0.80,0.30,0.30,1.0]), (c2,c0,[0.75,0.25,0.25,1.0])] {
+        let normal = face_normal(a, b, apex);
+        let base_idx = m.vertices.len() as u32;
+        for (position, uv) in [(a, [0.0, 1.0]), (b, [1.0, 1.0]), (apex, [0.5, 0.0])] {
+            m.vertices.push(Vertex { position, color, normal, uv });
+        }
+        m.indices.extend_from_slice(&[base_idx, base_idx+1, base_idx+2]);
+    }
+    m
+}
+pub fn create_pyramid_tower(device: &wgpu::Device) -> Mesh {
+    pyramid_tower_data().upload(device, "Pyramid Tower")
 }
+/// LOD1 of `pyramid_tower_data`: the base alone, no roof and no underside.
+pub fn pyramid_tower_lod1_data() -> MeshData {
+    open_box_vertices(1.0, 0.6, 1.0, [0.6,0.6,0.65,1.0])
+}
+pub fn create_pyramid_tower_lod1(device: &wgpu::Device) -> Mesh {
+    pyramid_tower_lod1_data().upload(device, "Pyramid Tower LOD1")
+}
+
+pub const BILLBOARD_W: f32 = 1.5;
+pub const BILLBOARD_H: f32 = 2.5;
 
-/// Vertical quad (1.5×2.5) centered at origin in XY plane, facing +Z.
+/// Vertical quad (BILLBOARD_W×BILLBOARD_H) centered at origin in XY plane, facing +Z.
 /// Centered so instance 'pos' places its center correctly for all meshes.
-pub fn create_billboard_quad(device: &wgpu::Device) -> Mesh {
-    let w = 1.5; let h = 2.5; let hw = w*0.5; let hh = h*0.5;
+/// Colour alpha −1 marks it for the shader's impostor path (see impostor.rs).
+pub fn billboard_quad_data() -> MeshData {
+    let hw = BILLBOARD_W*0.5; let hh = BILLBOARD_H*0.5;
     let v = vec![
-        Vertex { position: [-hw, -hh, 0.0], color: [0.80,0.80,0.85,1.0] },
-        Vertex { position: [ hw, -hh, 0.0], color: [0.80,0.80,0.85,1.0] },
-        Vertex { position: [-hw,  hh, 0.0], color: [0.85,0.85,0.90,1.0] },
-        Vertex { position: [ hw,  hh, 0.0], color: [0.85,0.85,0.90,1.0] },
+        Vertex { position: [-hw, -hh, 0.0], color: [0.80,0.80,0.85,-1.0], normal: [0.0,0.0,1.0], uv: [0.0,1.0] },
+        Vertex { position: [ hw, -hh, 0.0], color: [0.80,0.80,0.85,-1.0], normal: [0.0,0.0,1.0], uv: [1.0,1.0] },
+        Vertex { position: [-hw,  hh, 0.0], color: [0.85,0.85,0.90,-1.0], normal: [0.0,0.0,1.0], uv: [0.0,0.0] },
+        Vertex { position: [ hw,  hh, 0.0], color: [0.85,0.85,0.90,-1.0], normal: [0.0,0.0,1.0], uv: [1.0,0.0] },
     ];
-    let i: [u16; 6] = [0,1,2, 2,1,3];
-    upload(device, &v, &i, "Billboard Quad")
+    MeshData::new(v, vec![0,1,2, 2,1,3])
+}
+pub fn create_billboard_quad(device: &wgpu::Device) -> Mesh {
+    billboard_quad_data().upload(device, "Billboard Quad")
 }
 
+pub fn ground_data() -> MeshData {
+    cuboid_data(2000.0, 0.1, 2000.0, [0.12,0.12,0.14,1.0])
+}
 pub fn create_ground(device: &wgpu::Device) -> Mesh {
-    create_cuboid(device, 2000.0, 0.1, 2000.0, [0.12,0.12,0.14,1.0])
+    ground_data().upload(device, "Ground")
 }
 
 pub struct CityMeshes {
@@ -244,22 +443,6 @@ pub fn base_half_for(kind: BuildingKind) -> HalfExtents {
     }
 }
 
-
-
-
-struct XorShift64(u64);
-impl XorShift64 {
-    fn new(seed: u64) -> Self { Self(seed | 1) }
-    fn next(&mut self) -> u64 { let mut x=self.0; x^=x<<13; x^=x>>7; x^=x<<17; self.0=x; x }
-    fn unit_f32(&mut self) -> f32 { (self.next() as f64 / u64::MAX as f64) as f32 }
-}
-fn hash2(a: i32, b: i32) -> u64 {
-    let mut x = (a as i64 as i128) as u128 ^ (((b as i64 as i128) << 1) as u128) ^ 0x9E37_79B9_7F4A_7C15u128;
-    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9u128);
-    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EBu128);
-    (x ^ (x >> 31)) as u64
-}
-
 fn zone_weights(x: f32, z: f32) -> (f32,f32,f32) {
     let dist = x.hypot(z).max(1.0);
     let t = (1.0 - (dist / 1200.0)).clamp(0.0, 1.0);
@@ -272,16 +455,27 @@ fn zone_weights(x: f32, z: f32) -> (f32,f32,f32) {
 pub struct CityChunk { pub buildings: Vec<BuildingRecord> }
 
 // ───────────────────────── Helper wrappers used by assets/render ──────────
-pub fn make_timber_gable(device:&wgpu::Device) -> Mesh {
+pub fn timber_gable_data() -> MeshData {
     // simple low-rise block with coloured roof -- replace with fancy model later
-    create_block_lowrise(device)
+    block_lowrise_data()
 }
-pub fn make_timber_gable_alt(device:&wgpu::Device) -> Mesh {
+pub fn timber_gable_alt_data() -> MeshData {
     // slight colour tweak for visual variety
-    let mut m = create_block_lowrise(device);
-    // (could tint vertices here if desired)
+    let mut m = block_lowrise_data();
+    m.tint([0.95,0.90,0.85,1.0]);
     m
 }
+pub fn make_timber_gable(device:&wgpu::Device) -> Mesh {
+    timber_gable_data().upload(device, "Lowrise")
+}
+pub fn make_timber_gable_alt(device:&wgpu::Device) -> Mesh {
+    timber_gable_alt_data().upload(device, "Lowrise Alt")
+}
+pub fn make_timber_gable_alt_lod1(device:&wgpu::Device) -> Mesh {
+    let mut m = block_lowrise_lod1_data();
+    m.tint([0.95,0.90,0.85,1.0]);
+    m.upload(device, "Lowrise Alt LOD1")
+}
 pub fn make_block_tower(device:&wgpu::Device) -> Mesh {
     create_tower_highrise(device)
 }
@@ -294,5 +488,41 @@ pub fn make_billboard(device:&wgpu::Device) -> Mesh {
 
 /// Parametric ground plane – square of size `s`.
 pub fn make_ground_plane(device:&wgpu::Device, s:f32) -> Mesh {
-    create_cuboid(device, s, 0.05, s, [0.12,0.12,0.14,1.0])
+    cuboid_data(s, 0.05, s, [0.12,0.12,0.14,1.0]).upload(device, "Ground Plane")
+}
+
+// ───────────────────────── Chunk baking ─────────────────────────
+/// Merge every placement of a chunk into one mesh, pre-transformed by each
+/// building's translate/scale.  Vertex `color.w` carries
+/// `tint code (TINT_*) + 4 * facade layer + 4 * LAYER_COUNT * archetype` so
+/// `vs_baked` can pick the palette tint and texture without an instance.
+/// (Exact in f32 for any realistic archetype count.)  `color.xyz` is what
+/// the procedural windows need, as the instanced path has it: the box-local
+/// position across the face and up it, and the box's `window_seed`.
+pub fn bake_chunk_data(placements: &[RuntimePlacement], assets: &AssetLibrary) -> MeshData {
+    let mut out = MeshData::default();
+    for (center, scale, archetype_id) in placements.iter().flat_map(|p| p.boxes()) {
+        let id = archetype_id as usize;
+        let cat = match assets.category_of(id) {
+            BuildingCategory::Lowrise  => TINT_LOWRISE,
+            BuildingCategory::Highrise => TINT_HIGHRISE,
+            BuildingCategory::Landmark => TINT_LANDMARK,
+        };
+        let tag = cat + 4.0 * (assets.texture_of(id) + LAYER_COUNT * id as u32) as f32;
+        let seed = window_seed(scale);
+        let mut m = assets.data_of(id).clone();
+        for v in &mut m.vertices {
+            let (p, n) = (v.position, v.normal);
+            // the axis `window` reads for this face (its test, on the scaled normal)
+            let across = if (n[0] / scale.x).abs() > (n[2] / scale.z).abs() { p[2] * scale.z } else { p[0] * scale.x };
+            v.color = [across, p[1] * scale.y, seed, tag];
+        }
+        m.scale_translate(scale, center);
+        out.append(&m);
+    }
+    out
+}
+
+pub fn bake_chunk(device: &wgpu::Device, placements: &[RuntimePlacement], assets: &AssetLibrary) -> Mesh {
+    bake_chunk_data(placements, assets).upload(device, "Baked Chunk")
 }
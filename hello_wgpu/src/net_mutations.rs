@@ -1,47 +1,127 @@
 // ── net_mutations.rs ───────────────────────────────────────
-use std::net::{UdpSocket, SocketAddr};
-use std::sync::OnceLock;                // ← add
-use crate::chunking::{ChunkManager, RuntimePlacement};
+// Multicast edits between clients on the LAN.  Datagrams:
+//   mutate (12 B): [key i32 = cx<<16 | cz&0xFFFF][idx u32][archetype u16][scale jitter u16]
+//   add    (31 B): ['+'][cx i32][cz i32][archetype u16][x f32][z f32][sx f32][sy f32][sz f32]
+//   remove (13 B): ['-'][cx i32][cz i32][idx u32]
+// Add positions are in design space, so clients with different floating
+// origins agree.  Edits to chunks the receiver hasn't loaded are dropped.
+use std::net::UdpSocket;
+use std::sync::OnceLock;
+use cgmath::Vector3;
+use log::warn;
+use crate::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
 use crate::assets::AssetLibrary;
-use crate::designer_ml::hash2;
 
 static BROADCAST_ADDR: &str = "239.20.20.20:17017";
-static SOCK: OnceLock<UdpSocket> = OnceLock::new();   // ← replace static mut
+static SOCK: OnceLock<Option<UdpSocket>> = OnceLock::new();
 
-pub fn poll_incoming(cm: &mut ChunkManager, assets: &AssetLibrary) {
-    // Initialise on first call, then reuse
-    let sock: &UdpSocket = SOCK.get_or_init(|| {
-        let sock = UdpSocket::bind("0.0.0.0:0").expect("udp bind");
+pub const ADD_LEN: usize = 31;
+pub const REMOVE_LEN: usize = 13;
+const TAG_ADD: u8 = b'+';
+const TAG_REMOVE: u8 = b'-';
+
+/// Bound on first use; `None` (warned once) where UDP isn't available.
+fn socket() -> Option<&'static UdpSocket> {
+    SOCK.get_or_init(|| {
+        let sock = UdpSocket::bind("0.0.0.0:0").map_err(|e| warn!("net mutations off: {e}")).ok()?;
         sock.set_nonblocking(true).ok();
         sock.join_multicast_v4(
             &"239.20.20.20".parse().unwrap(),
             &"0.0.0.0".parse().unwrap(),
         )
         .ok();
-        sock
-    });
+        Some(sock)
+    }).as_ref()
+}
 
-    let mut buf = [0u8; 12];
+pub fn poll_incoming(cm: &mut ChunkManager, assets: &AssetLibrary) {
+    let Some(sock) = socket() else { return };
+    let mut buf = [0u8; 32];
     while let Ok((n, _src)) = sock.recv_from(&mut buf) {
-        if n == 12 {
-            let key = i32::from_le_bytes(buf[0..4].try_into().unwrap());
-            let idx = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
-            let aid = u16::from_le_bytes(buf[8..10].try_into().unwrap());
-            let sc  = u16::from_le_bytes(buf[10..12].try_into().unwrap());
-
-            let cz = key & 0xFFFF;
-            let cx = key >> 16;
-            if let Some(list) = cm.loaded.get_mut(&crate::chunking::ChunkKey(cx, cz)) {
-                if idx < list.len() {
-                    list[idx].archetype_id = aid;
-                    let j = (sc as f32) / 65535.0 * 0.2 + 0.9;
-                    list[idx].scale.x *= j;
-                    list[idx].scale.y *= j;
-                    list[idx].scale.z *= j;
-                    let base = assets.base_half(aid as usize);
-                    list[idx].center.y = base.y * list[idx].scale.y;
-                }
-            }
+        apply_packet(cm, assets, &buf[..n]);
+    }
+}
+
+/// Read and drop every waiting datagram; returns how many.  Used while
+/// applying is switched off, so turning it back on doesn't replay a backlog.
+pub fn discard_incoming() -> usize {
+    let Some(sock) = socket() else { return 0 };
+    let mut buf = [0u8; 32];
+    std::iter::from_fn(|| sock.recv_from(&mut buf).ok()).count()
+}
+
+/// Apply one received datagram; unknown or malformed packets are ignored.
+pub fn apply_packet(cm: &mut ChunkManager, assets: &AssetLibrary, buf: &[u8]) {
+    let i32_at = |o: usize| i32::from_le_bytes(buf[o..o + 4].try_into().unwrap());
+    let f32_at = |o: usize| f32::from_le_bytes(buf[o..o + 4].try_into().unwrap());
+    match (buf.len(), buf.first()) {
+        (12, _) => apply_mutate(cm, assets, buf),
+        (ADD_LEN, Some(&TAG_ADD)) => {
+            let key = ChunkKey(i32_at(1), i32_at(5));
+            let aid = u16::from_le_bytes(buf[9..11].try_into().unwrap());
+            if aid as usize >= assets.archetypes.len() { return; }
+            let scale = Vector3::new(f32_at(19), f32_at(23), f32_at(27));
+            let shift = cm.origin_shift();
+            let center = Vector3::new(f32_at(11), assets.ground_y_for(aid as usize, scale), f32_at(15)) - shift;
+            cm.insert_building(key, RuntimePlacement::single(center, scale, aid));
         }
+        (REMOVE_LEN, Some(&TAG_REMOVE)) => {
+            let key = ChunkKey(i32_at(1), i32_at(5));
+            cm.remove_building(key, i32_at(9) as u32 as usize);
+        }
+        _ => {}
     }
 }
+
+fn apply_mutate(cm: &mut ChunkManager, assets: &AssetLibrary, buf: &[u8]) {
+    let key = i32::from_le_bytes(buf[0..4].try_into().unwrap());
+    let idx = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
+    let aid = u16::from_le_bytes(buf[8..10].try_into().unwrap());
+    let sc  = u16::from_le_bytes(buf[10..12].try_into().unwrap());
+    if aid as usize >= assets.archetypes.len() { return; }
+
+    let cz = key & 0xFFFF;
+    let cx = key >> 16;
+    let ck = ChunkKey(cx, cz);
+    let shift_y = cm.origin_shift().y;
+    if let Some(list) = cm.loaded.get_mut(&ck) && idx < list.len() {
+        list[idx].archetype_id = aid;
+        let j = (sc as f32) / 65535.0 * 0.2 + 0.9;
+        list[idx].scale.x *= j;
+        list[idx].scale.y *= j;
+        list[idx].scale.z *= j;
+        list[idx].center.y = assets.ground_y_for(aid as usize, list[idx].scale) - shift_y;
+        cm.mark_mutated(ck);
+        cm.notify_mutated(ck, idx);
+    }
+}
+
+/// Add packet for building `idx` of `key` (see module header).
+pub fn encode_add(cm: &ChunkManager, key: ChunkKey, idx: usize) -> Option<[u8; ADD_LEN]> {
+    let p = cm.loaded.get(&key)?.get(idx)?;
+    let shift = cm.origin_shift();
+    let mut out = [0u8; ADD_LEN];
+    out[0] = TAG_ADD;
+    out[1..5].copy_from_slice(&key.0.to_le_bytes());
+    out[5..9].copy_from_slice(&key.1.to_le_bytes());
+    out[9..11].copy_from_slice(&p.archetype_id.to_le_bytes());
+    for (o, v) in [(11, p.center.x + shift.x), (15, p.center.z + shift.z), (19, p.scale.x), (23, p.scale.y), (27, p.scale.z)] {
+        out[o..o + 4].copy_from_slice(&v.to_le_bytes());
+    }
+    Some(out)
+}
+
+pub fn encode_remove(key: ChunkKey, idx: usize) -> [u8; REMOVE_LEN] {
+    let mut out = [0u8; REMOVE_LEN];
+    out[0] = TAG_REMOVE;
+    out[1..5].copy_from_slice(&key.0.to_le_bytes());
+    out[5..9].copy_from_slice(&key.1.to_le_bytes());
+    out[9..13].copy_from_slice(&(idx as u32).to_le_bytes());
+    out
+}
+
+/// Multicast a packet to the other clients (best effort).
+pub fn broadcast(packet: &[u8]) {
+    let Some(sock) = socket() else { return };
+    if let Err(e) = sock.send_to(packet, BROADCAST_ADDR) { warn!("net send failed: {e}"); }
+}
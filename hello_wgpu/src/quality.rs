@@ -0,0 +1,61 @@
+//! Adaptive quality: watches the smoothed frame time and scales the view
+//! distances (LOD rings, cull, and with it the chunk radius) down when a
+//! frame takes longer than `target_ms`, back up when there is headroom.
+//! Between the two thresholds nothing changes, and changes are at least
+//! `cooldown` seconds apart so the two don't fight.
+
+pub struct QualityScaler {
+    pub enabled: bool,
+    pub target_ms: f32,
+    /// Bounds of the distance scale; 1.0 = the configured distances.
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Relax only once frames are this fraction under the target.
+    pub headroom: f32,
+    /// Relative change per adjustment.
+    pub step: f32,
+    /// Seconds between adjustments.
+    pub cooldown: f32,
+    scale: f32,
+    avg_ms: Option<f32>,
+    since_change: f32,
+}
+
+impl QualityScaler {
+    pub fn new(target_ms: f32) -> Self {
+        Self {
+            enabled: true, target_ms,
+            min_scale: 0.4, max_scale: 1.0,
+            headroom: 0.25, step: 0.1, cooldown: 1.0,
+            scale: 1.0, avg_ms: None, since_change: 0.0,
+        }
+    }
+
+    #[inline]
+    pub fn scale(&self) -> f32 { self.scale }
+
+    /// Smoothed frame time (ms), once a frame has been seen.
+    #[inline]
+    pub fn avg_ms(&self) -> Option<f32> { self.avg_ms }
+
+    /// Feed one frame of `frame_ms` after `dt` seconds; returns the new
+    /// scale when it changed.
+    pub fn update(&mut self, frame_ms: f32, dt: f32) -> Option<f32> {
+        let avg = match self.avg_ms { Some(a) => a + (frame_ms - a) * 0.1, None => frame_ms };
+        self.avg_ms = Some(avg);
+        self.since_change += dt;
+        if !self.enabled || self.since_change < self.cooldown { return None; }
+
+        let next = if avg > self.target_ms {
+            self.scale * (1.0 - self.step)
+        } else if avg < self.target_ms * (1.0 - self.headroom) {
+            self.scale * (1.0 + self.step)
+        } else {
+            return None;
+        }.clamp(self.min_scale, self.max_scale);
+        if (next - self.scale).abs() < 1e-4 { return None; }
+        self.scale = next;
+        self.since_change = 0.0;
+        Some(next)
+    }
+}
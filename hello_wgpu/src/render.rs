@@ -1,11 +1,21 @@
 use std::collections::HashMap;
+use std::sync::{Arc, atomic::{AtomicU8, Ordering}};
 
 use bytemuck::{Pod, Zeroable};
-use log::info;
+use cgmath::SquareMatrix;
+use log::{info, warn};
 use wgpu::util::DeviceExt;
 
-use crate::assets::{AssetLibrary, CategoryMesh, BuildingCategory};
+use crate::assets::{AssetLibrary, CategoryMesh};
+use crate::chunking::{ChunkKey, ChunkManager, MAX_STACK};
 use crate::mesh;
+use crate::shadow::ShadowMap;
+use crate::facade::{FacadeTextures, LAYER_COUNT};
+use crate::impostor::ImpostorAtlas;
+use crate::debug_lines::{LineOverlay, LineVertex};
+use crate::gizmo::AxisGizmo;
+use crate::tonemap::{HDR_FORMAT, ToneMap};
+use crate::hello_wgpu::EngineConfig;
 use crate::types::{CameraUniform, InstanceRaw, instance_buffer_layout};
 
 // ───────────────────────────────── Palette ────────────────────────────────
@@ -13,46 +23,479 @@ const PALETTE_BYTES: u64 = 256;
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 struct GpuPalette {
-    low:  [f32; 3],
-    high: [f32; 3],
-    land: [f32; 3],
+    low:    [f32; 4],   // w unused (vec3 → 16-byte stride in WGSL)
+    high:   [f32; 4],
+    land:   [f32; 4],
+    ground: [f32; 4],
+    ground_alt:  [f32; 4],   // the checker's other square
+    ground_grid: [f32; 4],   // xy = tile (m, 0 = plain ground)  zw = local→grid phase (see `write_ground_grid`)
+    ground_span: [f32; 4],   // xy = chunk span the squares restart at (m, 0 = never)  zw unused
 }
 impl Default for GpuPalette {
     fn default() -> Self { Self {
-        low:  [0.55, 0.40, 0.30],
-        high: [0.25, 0.28, 0.30],
-        land: [0.60, 0.48, 0.10],
+        low:    [0.55, 0.40, 0.30, 0.0],
+        high:   [0.25, 0.28, 0.30, 0.0],
+        land:   [0.60, 0.48, 0.10, 0.0],
+        ground: [0.32, 0.30, 0.26, 0.0],
+        ground_alt:  [0.37, 0.35, 0.31, 0.0],
+        ground_grid: [0.0; 4],
+        ground_span: [0.0; 4],
     }}
 }
 
-// helper
-fn ensure_buf(device: &wgpu::Device, buf: &mut wgpu::Buffer, needed: usize, label: &str) {
+/// Per-archetype tints (`Archetype::tint`) the shader can index; archetypes
+/// past the end draw untinted.  Matches `TINTS` in shader.wgsl.
+const MAX_TINTS: usize = 64;
+
+// ───────────────────────────────── Light ────────────────────────────────
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GpuLight {
+    dir:    [f32; 4],   // towards the light, w unused
+    sky:    [f32; 4],   // hemispheric ambient from above
+    ground: [f32; 4],   // … and from below
+    windows: [f32; 4],  // procedural windows: x = per metre (0 = off), y = lit probability
+}
+impl Default for GpuLight {
+    fn default() -> Self { Self {
+        dir:    [0.4, 0.9, 0.1, 0.0],
+        sky:    [0.22, 0.24, 0.28, 0.0],
+        ground: [0.10, 0.09, 0.08, 0.0],
+        windows: [1.0 / 3.0, 0.3, 0.0, 0.0],
+    }}
+}
+
+// ──────────────────────────── Density heatmap ───────────────────────────
+/// Chunks per side of the ground heatmap (`Engine::set_heatmap`).  Matches
+/// `HEAT_SIDE` in shader.wgsl.
+pub const HEAT_SIDE: usize = 16;
+
+/// Default heatmap colour stops (sRGB) at 0, ⅓, ⅔ and 1: blue, green, yellow, red.
+pub const HEAT_RAMP: [[f32; 3]; 4] = [[0.10, 0.20, 0.60], [0.15, 0.65, 0.25], [0.95, 0.85, 0.20], [0.85, 0.15, 0.10]];
+
+/// Per-chunk values the ground heatmap shows: `HEAT_SIDE`² cells of
+/// `cell` (x, z) metres, row-major from the design-space corner `origin`
+/// (+x within a row, rows along +z).  Values run 0…1 along the ramp; a
+/// negative one (no chunk loaded) leaves that cell plain ground.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeatGrid {
+    pub origin: [f32; 2],
+    pub cell:   [f32; 2],
+    pub values: Vec<f32>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GpuHeat {
+    ramp:  [[f32; 4]; 4],
+    grid:  [f32; 4],     // xy = local xz of the grid corner  zw = cell size (m, 0 = off)
+    cells: [[f32; 4]; HEAT_SIDE * HEAT_SIDE / 4],
+}
+impl Default for GpuHeat {
+    fn default() -> Self { Self {
+        ramp: HEAT_RAMP.map(|c| [c[0], c[1], c[2], 0.0]),
+        grid: [0.0; 4],
+        cells: [[-1.0; 4]; HEAT_SIDE * HEAT_SIDE / 4],
+    }}
+}
+
+// ───────────────────────────── Instance buffers ───────────────────────────
+/// When instance buffers give memory back (`Engine::set_shrink_policy`): a
+/// buffer is reallocated to 1.5× its high-water mark once the count it
+/// needs has stayed below `below` × its capacity for `window` frames in a
+/// row.  The gap between `below` and the 1.5× headroom is the hysteresis: a
+/// shrunk buffer only regrows once the count passes 1.5× that old peak.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShrinkPolicy {
+    pub enabled: bool,
+    pub window:  u32,
+    pub below:   f32,
+}
+impl Default for ShrinkPolicy {
+    fn default() -> Self { Self { enabled: true, window: 300, below: 0.25 } }
+}
+
+/// `ShrinkPolicy` state of one buffer: the peak count of the current quiet run.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShrinkTracker { high_water: usize, frames: u32 }
+
+impl ShrinkTracker {
+    /// A frame needing `needed` elements of a `capacity`-element buffer;
+    /// `Some(new capacity)` when the buffer should shrink to it.
+    pub fn observe(&mut self, policy: &ShrinkPolicy, needed: usize, capacity: usize) -> Option<usize> {
+        if !policy.enabled || needed as f32 >= capacity as f32 * policy.below {
+            *self = Self::default();
+            return None;
+        }
+        self.high_water = self.high_water.max(needed);
+        self.frames += 1;
+        if self.frames < policy.window { return None; }
+        let shrunk = with_headroom(self.high_water);
+        *self = Self::default();
+        (shrunk < capacity).then_some(shrunk)
+    }
+}
+
+/// Elements to allocate for `n`: 1.5× so a slowly growing count doesn't
+/// reallocate every frame.
+fn with_headroom(n: usize) -> usize { (n.max(1) as f32 * 1.5).ceil() as usize }
+
+fn ensure_buf(device: &wgpu::Device, buf: &mut wgpu::Buffer, needed: usize, label: &str,
+              policy: &ShrinkPolicy, track: &mut ShrinkTracker) {
     let elem = std::mem::size_of::<InstanceRaw>() as u64;
-    let req_bytes = (needed.max(1) as u64) * elem;
-    if req_bytes <= buf.size() { return; }
-    let new_sz = (req_bytes as f32 * 1.5).ceil() as u64;
+    let capacity = (buf.size() / elem) as usize;
+    let len = if needed > capacity { Some(with_headroom(needed)) } else { track.observe(policy, needed, capacity) };
+    let Some(len) = len else { return };
     *buf = device.create_buffer(&wgpu::BufferDescriptor {
-        label: Some(label), size: new_sz,
+        label: Some(label), size: len as u64 * elem,
         usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         mapped_at_creation: false,
     });
 }
 
+/// Per-frame draw counters; `avg_*` are refreshed once per second.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameStats {
+    pub instances:  u32,
+    pub draw_calls: u32,
+    pub triangles:  u64,
+    pub avg_instances:  f32,
+    pub avg_draw_calls: f32,
+    pub avg_triangles:  f32,
+    /// Main-pass GPU time in ms, depth prepass included when enabled (lags
+    /// 1–2 frames); `None` without `TIMESTAMP_QUERY`.
+    pub gpu_ms: Option<f32>,
+    /// CPU time (ms) spent recording and submitting this frame's commands.
+    pub encode_ms: f32,
+}
+
+struct StatsAccum { frames: u32, instances: u64, draw_calls: u64, triangles: u64, since: instant::Instant }
+impl Default for StatsAccum {
+    fn default() -> Self { Self { frames:0, instances:0, draw_calls:0, triangles:0, since:instant::Instant::now() } }
+}
+
+// ───────────────────────────────── GPU timer ──────────────────────────────
+// Timestamps around the main pass, resolved into a small ring of readback
+// buffers so mapping never stalls the frame.
+const TS_SLOTS: usize = 3;
+const TS_FREE: u8 = 0;
+const TS_MAPPING: u8 = 1;
+const TS_READY: u8 = 2;
+
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve:   wgpu::Buffer,
+    readback:  Vec<wgpu::Buffer>,
+    state:     Vec<Arc<AtomicU8>>,
+    pending:   Option<usize>,
+    period_ns: f32,
+    last_ms:   Option<f32>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) { return None; }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor{
+            label:Some("frame timestamps"), ty:wgpu::QueryType::Timestamp, count:2,
+        });
+        let resolve = device.create_buffer(&wgpu::BufferDescriptor{
+            label:Some("ts resolve"), size:16,
+            usage:wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation:false,
+        });
+        let readback = (0..TS_SLOTS).map(|_| device.create_buffer(&wgpu::BufferDescriptor{
+            label:Some("ts readback"), size:16,
+            usage:wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation:false,
+        })).collect();
+        let state = (0..TS_SLOTS).map(|_| Arc::new(AtomicU8::new(TS_FREE))).collect();
+        Some(Self { query_set, resolve, readback, state, pending:None,
+                    period_ns:queue.get_timestamp_period(), last_ms:None })
+    }
+
+    /// Stamp the start and/or end of a pass; with a prepass the span
+    /// starts at the prepass and ends with the main pass.
+    fn pass_writes(&self, begin: bool, end: bool) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites{
+            query_set:&self.query_set,
+            beginning_of_pass_write_index:begin.then_some(0),
+            end_of_pass_write_index:end.then_some(1),
+        }
+    }
+
+    /// After the pass: copy into a free readback slot (skipped if all are busy).
+    fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(slot) = self.state.iter().position(|s| s.load(Ordering::Acquire)==TS_FREE) else { return };
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.readback[slot], 0, 16);
+        self.state[slot].store(TS_MAPPING, Ordering::Release);
+        self.pending = Some(slot);
+    }
+
+    /// After submit: start mapping this frame's slot, harvest any finished ones.
+    fn after_submit(&mut self, device: &wgpu::Device) {
+        if let Some(slot) = self.pending.take() {
+            let flag = self.state[slot].clone();
+            self.readback[slot].map_async(wgpu::MapMode::Read, .., move |r| {
+                flag.store(if r.is_ok() { TS_READY } else { TS_FREE }, Ordering::Release);
+            });
+        }
+        let _ = device.poll(wgpu::PollType::Poll);
+        for (buf, st) in self.readback.iter().zip(&self.state) {
+            if st.load(Ordering::Acquire) != TS_READY { continue; }
+            {
+                let data = buf.slice(..).get_mapped_range();
+                let t: &[u64] = bytemuck::cast_slice(&data);
+                if t[1] > t[0] {
+                    self.last_ms = Some((t[1]-t[0]) as f32 * self.period_ns / 1.0e6);
+                }
+            }
+            buf.unmap();
+            st.store(TS_FREE, Ordering::Release);
+        }
+    }
+}
+
+/// Draw `count` instances of `mesh` from `inst` (no-op when empty).
+fn draw_batch<'a>(rpass:&mut wgpu::RenderPass<'a>, mesh:&'a mesh::Mesh, inst:&'a wgpu::Buffer, count:u32, stats:&mut FrameStats) {
+    if count==0 { return; }
+    rpass.set_vertex_buffer(0,mesh.vertex_buffer.slice(..));
+    rpass.set_index_buffer(mesh.index_buffer.slice(..),mesh.index_format);
+    rpass.set_vertex_buffer(1,inst.slice(..));
+    rpass.draw_indexed(0..mesh.index_count,0,0..count);
+    stats.draw_calls+=1;
+    stats.instances+=count;
+    stats.triangles+=(mesh.index_count/3) as u64 * count as u64;
+}
+
+/// `requested` texels per side limited to `max` (the adapter's
+/// `max_texture_dimension_2d`, only 2048 on some WebGL2 devices); logs
+/// when it had to shrink.
+pub fn clamp_texture_dim(what: &str, requested: u32, max: u32) -> u32 {
+    if requested > max { warn!("{what}: {requested} px exceeds the adapter limit, clamped to {max}"); }
+    requested.clamp(1, max.max(1))
+}
+
+fn depth_target(device: &wgpu::Device, format: wgpu::TextureFormat, w: u32, h: u32, samples: u32) -> wgpu::TextureView {
+    device.create_texture(&wgpu::TextureDescriptor{
+        label:Some("depth"), size:wgpu::Extent3d{width:w,height:h,depth_or_array_layers:1},
+        mip_level_count:1, sample_count:samples, dimension:wgpu::TextureDimension::D2,
+        format, usage:wgpu::TextureUsages::RENDER_ATTACHMENT, view_formats:&[],
+    }).create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Surface format to configure: the first sRGB one offered, else the
+/// first format (its sRGB view is used if it has one, see `frame_format`).
+pub fn pick_surface_format(formats: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+    formats.iter().copied().find(|f| f.is_srgb()).unwrap_or(formats[0])
+}
+
+/// Format the scene (or tone-map) pass writes: the configured format's sRGB
+/// view when it has one, so shaders always output linear colour.
+pub fn frame_format(config: &wgpu::SurfaceConfiguration) -> wgpu::TextureFormat {
+    config.view_formats.first().copied().unwrap_or(config.format)
+}
+
+/// sRGB-encoded component → linear (the standard piecewise curve).
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Colour target used instead of a swapchain when running headless.
+fn offscreen_target(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor{
+        label:Some("offscreen"), size:wgpu::Extent3d{width:config.width,height:config.height,depth_or_array_layers:1},
+        mip_level_count:1, sample_count:1, dimension:wgpu::TextureDimension::D2,
+        format:config.format, usage:config.usage, view_formats:&[],
+    })
+}
+
+/// Multisampled colour target resolved into the frame (or the HDR scene
+/// target); `None` without MSAA.
+fn msaa_target(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, format: wgpu::TextureFormat,
+               samples: u32) -> Option<wgpu::TextureView> {
+    (samples > 1).then(|| device.create_texture(&wgpu::TextureDescriptor{
+        label:Some("msaa colour"), size:wgpu::Extent3d{width:config.width,height:config.height,depth_or_array_layers:1},
+        mip_level_count:1, sample_count:samples, dimension:wgpu::TextureDimension::D2,
+        format, usage:wgpu::TextureUsages::RENDER_ATTACHMENT, view_formats:&[],
+    }).create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Opaque scene pipeline over the shared vertex + instance layouts.
+#[allow(clippy::too_many_arguments)]
+fn scene_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule,
+                  label: &str, vs: &str, fs: &str, targets: &[Option<wgpu::ColorTargetState>],
+                  depth_format: wgpu::TextureFormat, compare: wgpu::CompareFunction, depth_write: bool,
+                  samples: u32, alpha_to_coverage: bool) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor{
+        label:Some(label),
+        layout:Some(layout),
+        vertex: wgpu::VertexState{
+            module:shader,
+            entry_point:Some(vs),
+            compilation_options:Default::default(),
+            buffers:&[mesh::Vertex::layout(), instance_buffer_layout()],
+        },
+        fragment:Some(wgpu::FragmentState{
+            module:shader,
+            entry_point:Some(fs),
+            compilation_options:Default::default(),
+            targets,
+        }),
+        primitive:wgpu::PrimitiveState::default(),
+        depth_stencil:Some(wgpu::DepthStencilState{
+            format:depth_format,
+            depth_write_enabled:depth_write,
+            depth_compare:compare,
+            stencil:wgpu::StencilState::default(),
+            bias:wgpu::DepthBiasState::default(),
+        }),
+        multisample:wgpu::MultisampleState{ count:samples, alpha_to_coverage_enabled:alpha_to_coverage, ..Default::default() },
+        multiview:None,
+        cache:None,
+    })
+}
+
+/// Every pipeline that renders into the scene depth buffer; rebuilt when
+/// the depth format changes.
+struct ScenePipelines {
+    main:  wgpu::RenderPipeline,
+    baked: wgpu::RenderPipeline,
+    // optional depth prepass (+ Equal-compare variants of the colour pipelines)
+    prepass:       wgpu::RenderPipeline,
+    prepass_baked: wgpu::RenderPipeline,
+    main_eq:  wgpu::RenderPipeline,
+    baked_eq: wgpu::RenderPipeline,
+    // selection shell: blended over the scene, depth-tested but not written
+    highlight: wgpu::RenderPipeline,
+    // billboards when blended (`Engine::billboards_blended`: drawn after opaques, sorted)
+    bill_blend: wgpu::RenderPipeline,
+    // opaque billboards with edge alpha-to-coverage (`Engine::set_billboard_aa`, MSAA only)
+    bill_a2c: Option<wgpu::RenderPipeline>,
+    // smoke puffs (`Engine::update_smoke`): blended last, depth-tested only
+    smoke: wgpu::RenderPipeline,
+}
+
+impl ScenePipelines {
+    fn new(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule,
+           color_format: wgpu::TextureFormat, depth_format: wgpu::TextureFormat, samples: u32) -> Self {
+        let pipe = |label, vs, fs, targets:&[Option<wgpu::ColorTargetState>], compare, write|
+            scene_pipeline(device, layout, shader, label, vs, fs, targets, depth_format, compare, write, samples, false);
+        let target = |blend| [Some(wgpu::ColorTargetState{
+            format:color_format, blend:Some(blend), write_mask:wgpu::ColorWrites::ALL,
+        })];
+        let color = target(wgpu::BlendState::REPLACE);
+        use wgpu::CompareFunction::{Less, Equal};
+        Self {
+            main:  pipe("pipe", "vs_main", "fs_main", &color, Less, true),
+            baked: pipe("baked pipe", "vs_baked", "fs_main", &color, Less, true),
+            // depth prepass: alpha-tested depth only, then shade with depth == prepass
+            prepass:       pipe("prepass pipe", "vs_main", "fs_prepass", &[], Less, true),
+            prepass_baked: pipe("prepass baked pipe", "vs_baked", "fs_prepass", &[], Less, true),
+            main_eq:  pipe("pipe (after prepass)", "vs_main", "fs_main", &color, Equal, false),
+            baked_eq: pipe("baked pipe (after prepass)", "vs_baked", "fs_main", &color, Equal, false),
+            highlight: pipe("highlight pipe", "vs_main", "fs_highlight", &target(wgpu::BlendState::ALPHA_BLENDING), Less, false),
+            bill_blend: pipe("blended billboard pipe", "vs_main", "fs_billboard", &target(wgpu::BlendState::ALPHA_BLENDING), Less, false),
+            bill_a2c: (samples > 1).then(|| scene_pipeline(device, layout, shader, "billboard a2c pipe", "vs_main", "fs_billboard",
+                                                           &color, depth_format, Less, true, samples, true)),
+            smoke: pipe("smoke pipe", "vs_smoke", "fs_smoke", &target(wgpu::BlendState::ALPHA_BLENDING), Less, false),
+        }
+    }
+}
+
+/// Depth/stencil attachment ops; the stencil is cleared to 0 (or kept when
+/// `load`) if the format has one.
+fn depth_ops(format: wgpu::TextureFormat, load: bool)
+    -> (Option<wgpu::Operations<f32>>, Option<wgpu::Operations<u32>>) {
+    let depth = wgpu::Operations{
+        load: if load {wgpu::LoadOp::Load} else {wgpu::LoadOp::Clear(1.0)},
+        store: wgpu::StoreOp::Store,
+    };
+    let stencil = format.has_stencil_aspect().then_some(wgpu::Operations{
+        load: if load {wgpu::LoadOp::Load} else {wgpu::LoadOp::Clear(0)},
+        store: wgpu::StoreOp::Store,
+    });
+    (Some(depth), stencil)
+}
+
+/// Default scene depth format; the stencil is free for outlines / masks.
+pub const DEFAULT_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// Draw every baked chunk (instance i = anchor i); counts into `stats`.
+/// Chunks `skip` picks (bundled ones, in the main pass) are left out.
+fn draw_baked<'a>(pass:&mut wgpu::RenderPass<'a>, baked:&'a HashMap<ChunkKey,BakedChunk>, keys:&[ChunkKey],
+                  anchors:&'a wgpu::Buffer, skip:impl Fn(&BakedChunk)->bool, stats:&mut FrameStats) {
+    pass.set_vertex_buffer(1,anchors.slice(..));
+    for (i,key) in keys.iter().enumerate() {
+        let b=&baked[key];
+        if skip(b) { continue; }
+        let m=&b.mesh;
+        pass.set_vertex_buffer(0,m.vertex_buffer.slice(..));
+        pass.set_index_buffer(m.index_buffer.slice(..),m.index_format);
+        pass.draw_indexed(0..m.index_count,0,i as u32..i as u32+1);
+        stats.draw_calls+=1;
+        stats.triangles+=(m.index_count/3) as u64;
+    }
+}
+
+/// One far chunk merged into a single mesh (see `mesh::bake_chunk`).
+struct BakedChunk {
+    mesh: mesh::Mesh,
+    revision: u32,
+    shift_at_bake: cgmath::Vector3<f32>,
+    // frames drawn since the bake; bundled once it reaches `BUNDLE_AFTER_FRAMES`
+    stable_frames: u32,
+    bundle: Option<BakedBundle>,
+}
+
+/// Main-pass draw of a stable baked chunk, recorded once and replayed with
+/// `execute_bundles`.  It reads its own one-instance anchor buffer, since a
+/// chunk's slot in the shared anchor list moves from frame to frame.
+struct BakedBundle {
+    bundle: wgpu::RenderBundle,
+    anchor: wgpu::Buffer,
+    offset: cgmath::Vector3<f32>,
+    prepass: bool, // recorded with `baked_eq`
+    generation: u32, // `Engine::bundle_gen` it was recorded against
+}
+
+/// Frames a baked chunk must go unchanged before its draw is bundled, so
+/// chunks that keep mutating stay on the immediate path.
+pub const BUNDLE_AFTER_FRAMES: u32 = 30;
+
 // ───────────────────────────────── Engine ────────────────────────────────
 pub struct Engine {
     pub device: wgpu::Device,
     pub queue:  wgpu::Queue,
-    pub surface: wgpu::Surface<'static>,
+    /// `None` when headless: frames go to `offscreen` instead.
+    pub surface: Option<wgpu::Surface<'static>>,
     pub config:  wgpu::SurfaceConfiguration,
+    offscreen:   Option<wgpu::Texture>,
 
     shader: wgpu::ShaderModule,
     pipeline_layout: wgpu::PipelineLayout,
-    render_pipeline: wgpu::RenderPipeline,
+    pipes: ScenePipelines,
+    depth_prepass: bool,
+    billboard_blend: bool,
+    billboard_aa: bool,
 
-    // depth
+    // depth (+ stencil, see `set_depth_format`)
     depth_format: wgpu::TextureFormat,
     depth_view:   wgpu::TextureView,
 
+    // MSAA: scene passes render into `msaa_view` and resolve into the frame
+    sample_count: u32,
+    msaa_view: Option<wgpu::TextureView>,
+
+    // HDR (`set_hdr`): scene passes render into `tonemap`'s float target,
+    // tone-mapped into the frame; `None` = straight into the frame
+    hdr_capable: bool,
+    tonemap: Option<ToneMap>,
+    exposure: f32,
+
+    // background
+    clear_color: wgpu::Color,
+
     // camera
     camera_bgl: wgpu::BindGroupLayout,
     camera_bg:  wgpu::BindGroup,
@@ -62,6 +505,28 @@ pub struct Engine {
     palette_bgl: wgpu::BindGroupLayout,
     palette_bg:  wgpu::BindGroup,
     palette_buf: wgpu::Buffer,
+    palette: GpuPalette,
+    ground_checker: ([f32; 2], [f32; 2], [f32; 2]), // tile, span, design-space offset (`set_ground_checker`)
+    ground_shift: [f32; 2],               // floating-origin xz (`set_ground_origin`)
+    tint_buf: wgpu::Buffer,
+    tint_rev: Option<u32>, // `AssetLibrary::tint_revision` last uploaded
+    light: GpuLight,
+    light_buf: wgpu::Buffer,
+    heat: GpuHeat,
+    heat_grid: Option<HeatGrid>, // as last set (`set_heatmap`)
+    heat_buf: wgpu::Buffer,
+
+    // shadows (group 2)
+    shadow: ShadowMap,
+    // facade texture array (group 3)
+    facade: FacadeTextures,
+    // impostor atlas (group 1) and whether billboards use it (`set_impostors`)
+    impostor: ImpostorAtlas,
+    impostors: bool,
+    // corner axis tripod (own pipeline, drawn last)
+    gizmo: AxisGizmo,
+    // world-space debug lines (`set_debug_lines`), drawn before the gizmo
+    lines: LineOverlay,
 
     // asset library (meshes + archetypes)
     pub assets: AssetLibrary,
@@ -89,6 +554,39 @@ pub struct Engine {
     cnt_l1_high: u32,
     cnt_l1_land: u32,
     cnt_l2_bill: u32,
+
+    // instance buffer shrinking: one tracker per buffer above (bar the
+    // ground) in `update_instances` order, then the baked anchors
+    shrink: ShrinkPolicy,
+    shrink_track: [ShrinkTracker; 10],
+
+    // baked far chunks: cache + this frame's draw list (anchor i ↔ draw i)
+    baked: HashMap<ChunkKey, BakedChunk>,
+    baked_draws: Vec<ChunkKey>,
+    buf_baked_anchor: wgpu::Buffer,
+    // replay stable baked chunks from render bundles (`set_render_bundles`);
+    // `bundle_gen` is bumped whenever a recorded pipeline or bind group is replaced
+    bundles: bool,
+    bundle_gen: u32,
+
+    // selected building (chunk, index into `loaded[chunk]`), re-resolved every
+    // frame by `update_selection`; every box of it is drawn again as a
+    // translucent scaled shell (archetype per instance in `buf_selection`)
+    selected: Option<(ChunkKey, usize)>,
+    sel_boxes: Vec<usize>,
+    buf_selection: wgpu::Buffer,
+
+    // smoke puffs (`update_smoke`), drawn on the billboard quad after the
+    // blended billboards; not in the shadow or depth prepass
+    buf_smoke: wgpu::Buffer,
+    cnt_smoke: u32,
+    smoke_track: ShrinkTracker,
+
+    // stats (verbose per-frame logging only when `debug`)
+    pub debug: bool,
+    stats: FrameStats,
+    stats_acc: StatsAccum,
+    gpu_timer: Option<GpuTimer>,
 }
 
 impl Engine {
@@ -97,41 +595,97 @@ impl Engine {
     pub fn new(
         device: wgpu::Device,
         queue:  wgpu::Queue,
-        mut surface: wgpu::Surface<'static>,
+        surface: wgpu::Surface<'static>,
         adapter: &wgpu::Adapter,
         size: winit::dpi::PhysicalSize<u32>,
+        cfg: &EngineConfig,
     ) -> Self {
         // Surface config
         let caps = surface.get_capabilities(adapter);
-        let format = caps.formats[0];
+        let format = pick_surface_format(&caps.formats);
+        // e.g. only `Bgra8Unorm` on the web: draw through an sRGB view of it
+        let view_formats = if format.add_srgb_suffix() != format { vec![format.add_srgb_suffix()] } else { vec![] };
+        // HDR renders to an offscreen float target tone-mapped onto the
+        // (LDR) surface, so only the adapter has to support it
+        let hdr_capable = crate::tonemap::hdr_supported(adapter, cfg.sample_count);
         let alpha = if caps.alpha_modes.contains(&wgpu::CompositeAlphaMode::Opaque) {
             wgpu::CompositeAlphaMode::Opaque
         } else { caps.alpha_modes[0] };
+        // Fifo is the only mode every surface supports
+        let present_mode = if caps.present_modes.contains(&cfg.present_mode) { cfg.present_mode } else {
+            warn!("present mode {:?} unsupported, using Fifo", cfg.present_mode);
+            wgpu::PresentMode::Fifo
+        };
+        let max = device.limits().max_texture_dimension_2d;
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format, width: size.width, height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            format,
+            width:  clamp_texture_dim("surface width", size.width, max),
+            height: clamp_texture_dim("surface height", size.height, max),
+            present_mode,
             alpha_mode: alpha,
-            view_formats: vec![],
+            view_formats,
             desired_maximum_frame_latency: 0,
         };
         surface.configure(&device, &config);
+        info!("surface {format:?}, hdr {}", if hdr_capable {"available"} else {"unavailable"});
+        Self::build(device, queue, Some(surface), config, hdr_capable, cfg)
+    }
 
-        // Depth
-        let depth_format = wgpu::TextureFormat::Depth24Plus;
-        let depth_view = {
-            let tex = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("depth"), size: wgpu::Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 },
-                mip_level_count:1, sample_count:1, dimension: wgpu::TextureDimension::D2,
-                format: depth_format, usage: wgpu::TextureUsages::RENDER_ATTACHMENT, view_formats:&[],
-            });
-            tex.create_view(&wgpu::TextureViewDescriptor::default())
+    /// No window: render into an sRGB offscreen texture of `width`×`height`
+    /// with the default `EngineConfig`.
+    pub fn new_headless(device: wgpu::Device, queue: wgpu::Queue, width: u32, height: u32) -> Self {
+        Self::new_headless_with(device, queue, width, height, &EngineConfig::default())
+    }
+
+    /// `new_headless` with explicit settings (`present_mode` is unused);
+    /// `Rgba16Float` targets are core WebGPU, so `hdr` is always honoured.
+    pub fn new_headless_with(device: wgpu::Device, queue: wgpu::Queue, width: u32, height: u32,
+                             cfg: &EngineConfig) -> Self {
+        let max = device.limits().max_texture_dimension_2d;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width:  clamp_texture_dim("offscreen width", width, max),
+            height: clamp_texture_dim("offscreen height", height, max),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 0,
         };
+        Self::build(device, queue, None, config, true, cfg)
+    }
+
+    fn build(
+        device: wgpu::Device,
+        queue:  wgpu::Queue,
+        surface: Option<wgpu::Surface<'static>>,
+        config: wgpu::SurfaceConfiguration,
+        hdr_capable: bool,
+        cfg: &EngineConfig,
+    ) -> Self {
+        let offscreen = surface.is_none().then(|| offscreen_target(&device, &config));
+        let frame_format = frame_format(&config);
+        let tonemap = (cfg.hdr && hdr_capable).then(|| ToneMap::new(&device, frame_format, config.width, config.height));
+        if let Some(t) = &tonemap { t.update(&queue, 1.0, frame_format); }
+        let scene_format = if tonemap.is_some() { HDR_FORMAT } else { frame_format };
+
+        // MSAA: WebGPU guarantees 1 and 4 samples for every renderable format
+        let sample_count = match cfg.sample_count {
+            1 | 4 => cfg.sample_count,
+            n => { warn!("{n}x MSAA unsupported, using 1"); 1 }
+        };
+        let msaa_view = msaa_target(&device, &config, scene_format, sample_count);
+
+        // Depth
+        let depth_format = DEFAULT_DEPTH_FORMAT;
+        let depth_view = depth_target(&device, depth_format, config.width, config.height, sample_count);
 
-        // Shader
+        // Shader, with the facade layer count it can't see otherwise
+        let source = format!("const LAYER_COUNT : f32 = {LAYER_COUNT}.0;\n{}", include_str!("assets/shader.wgsl"));
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("main shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
 
         // Camera group
@@ -143,7 +697,7 @@ impl Engine {
                 ty:wgpu::BindingType::Buffer{
                     ty:wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset:false,
-                    min_binding_size:wgpu::BufferSize::new(64),
+                    min_binding_size:wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
                 },
                 count:None,
             }],
@@ -172,6 +726,56 @@ impl Engine {
                     min_binding_size: wgpu::BufferSize::new(PALETTE_BYTES),  // 256
                 },
                 count: None,
+            }, wgpu::BindGroupLayoutEntry{
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<GpuLight>() as u64),
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry{
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new((MAX_TINTS * 16) as u64),
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry{
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<GpuHeat>() as u64),
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry{
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry{
+                binding: 5,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            }, wgpu::BindGroupLayoutEntry{
+                binding: 6,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
             }],
         });
 
@@ -182,7 +786,32 @@ impl Engine {
             mapped_at_creation: false,
         });
 
-        queue.write_buffer(&palette_buf, 0, bytemuck::bytes_of(&GpuPalette::default()));
+        let palette = GpuPalette::default();
+        queue.write_buffer(&palette_buf, 0, bytemuck::bytes_of(&palette));
+
+        let tint_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tint buf"),
+            size: (MAX_TINTS * 16) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let light = GpuLight::default();
+        let light_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light buf"),
+            contents: bytemuck::bytes_of(&light),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let heat = GpuHeat::default();
+        let heat_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("heat buf"),
+            contents: bytemuck::bytes_of(&heat),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Impostor atlas, baked once from the category meshes
+        let impostor = ImpostorAtlas::for_city(&device, &queue, cfg.impostor_tile);
 
         let palette_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("palette bg"),
@@ -190,50 +819,49 @@ impl Engine {
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
                 resource: palette_buf.as_entire_binding(),
+            }, wgpu::BindGroupEntry {
+                binding: 1,
+                resource: light_buf.as_entire_binding(),
+            }, wgpu::BindGroupEntry {
+                binding: 2,
+                resource: tint_buf.as_entire_binding(),
+            }, wgpu::BindGroupEntry {
+                binding: 3,
+                resource: heat_buf.as_entire_binding(),
+            }, wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&impostor.view),
+            }, wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&impostor.sampler),
+            }, wgpu::BindGroupEntry {
+                binding: 6,
+                resource: impostor.frame_buf.as_entire_binding(),
             }],
         });
 
+        // Shadow map
+        let shadow_size = clamp_texture_dim("shadow map", 2048, device.limits().max_texture_dimension_2d);
+        let shadow = ShadowMap::new(&device, &shader, &camera_bgl, &palette_bgl, shadow_size);
+        let facade = FacadeTextures::new(&device, &queue);
+        let gizmo = AxisGizmo::new(&device, scene_format, depth_format, sample_count);
+        let lines = LineOverlay::new(&device, &camera_bgl, scene_format, depth_format, sample_count);
+
         // Pipeline
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor{
             label:Some("pipe layout"),
-            bind_group_layouts:&[&camera_bgl,&palette_bgl],
+            bind_group_layouts:&[&camera_bgl,&palette_bgl,&shadow.bgl,&facade.bgl],
             push_constant_ranges:&[],
         });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor{
-            label:Some("pipe"),
-            layout:Some(&pipeline_layout),
-            vertex: wgpu::VertexState{
-                module:&shader,
-                entry_point:Some("vs_main"),
-                compilation_options:Default::default(),
-                buffers:&[mesh::Vertex::layout(), instance_buffer_layout()],
-            },
-            fragment:Some(wgpu::FragmentState{
-                module:&shader,
-                entry_point:Some("fs_main"),
-                compilation_options:Default::default(),
-                targets:&[Some(wgpu::ColorTargetState{
-                    format:config.format,
-                    blend:Some(wgpu::BlendState::REPLACE),
-                    write_mask:wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive:wgpu::PrimitiveState::default(),
-            depth_stencil:Some(wgpu::DepthStencilState{
-                format:depth_format,
-                depth_write_enabled:true,
-                depth_compare:wgpu::CompareFunction::Less,
-                stencil:wgpu::StencilState::default(),
-                bias:wgpu::DepthBiasState::default(),
-            }),
-            multisample:wgpu::MultisampleState::default(),
-            multiview:None,
-            cache:None,
-        });
+        let pipes = ScenePipelines::new(&device, &pipeline_layout, &shader, scene_format, depth_format, sample_count);
 
         // Assets
         let assets = AssetLibrary::new(&device);
 
+        // GPU timing (None when the adapter lacks TIMESTAMP_QUERY)
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        info!("gpu timestamps: {}", if gpu_timer.is_some() {"enabled"} else {"unsupported"});
+
         // Tiny helpers
         let mk = |lbl:&str| device.create_buffer(&wgpu::BufferDescriptor{
             label:Some(lbl),
@@ -252,13 +880,25 @@ impl Engine {
         let buf_l1_high   = mk("l1 high");
         let buf_l1_land   = mk("l1 land");
         let buf_l2_bill   = mk("l2 bill");
+        let buf_baked_anchor = mk("baked anchors");
+        let buf_selection = device.create_buffer(&wgpu::BufferDescriptor{
+            label:Some("selection"),
+            size: ((1 + MAX_STACK) * std::mem::size_of::<InstanceRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation:false,
+        });
+        let buf_smoke = mk("smoke");
 
         Self {
-            device, queue, surface, config,
-            shader, pipeline_layout, render_pipeline,
-            depth_format, depth_view,
+            device, queue, surface, config, offscreen,
+            shader, pipeline_layout, pipes, depth_prepass: false, billboard_blend: false, billboard_aa: false,
+            depth_format, depth_view, sample_count, msaa_view,
+            hdr_capable, tonemap, exposure: 1.0,
+            clear_color: cfg.clear_color,
             camera_bgl, camera_bg, camera_buf,
-            palette_bgl, palette_bg, palette_buf,
+            palette_bgl, palette_bg, palette_buf, palette, ground_checker: ([0.0; 2], [0.0; 2], [0.0; 2]), ground_shift: [0.0; 2], tint_buf, tint_rev: None, light, light_buf,
+            heat, heat_grid: None, heat_buf,
+            shadow, facade, impostor, impostors: cfg.impostors, gizmo, lines,
             assets,
             buf_ground,
             buf_l0_low_common, buf_l0_low_alt, buf_l0_high, buf_l0_land,
@@ -268,34 +908,330 @@ impl Engine {
             cnt_l0_low_common:0, cnt_l0_low_alt:0, cnt_l0_high:0, cnt_l0_land:0,
             cnt_l1_low_common:0, cnt_l1_low_alt:0, cnt_l1_high:0, cnt_l1_land:0,
             cnt_l2_bill:0,
+            shrink: ShrinkPolicy::default(), shrink_track: [ShrinkTracker::default(); 10],
+            baked: HashMap::new(), baked_draws: Vec::new(), buf_baked_anchor, bundles: true, bundle_gen: 0,
+            selected: None, sel_boxes: Vec::new(), buf_selection,
+            buf_smoke, cnt_smoke: 0, smoke_track: ShrinkTracker::default(),
+            debug: false, stats: FrameStats::default(), stats_acc: StatsAccum::default(),
+            gpu_timer,
         }
     }
 
+    /// Largest width/height (texels) of any 2D texture on this device; every
+    /// frame-sized or offscreen target is clamped to it.
+    pub fn max_texture_dim(&self) -> u32 { self.device.limits().max_texture_dimension_2d }
+
     // ---------- window resize ----------
+    /// Sizes beyond `max_texture_dim` are clamped (the image is stretched).
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width==0 || new_size.height==0 { return; }
-        self.config.width=new_size.width; self.config.height=new_size.height;
-        self.surface.configure(&self.device,&self.config);
-        let tex=self.device.create_texture(&wgpu::TextureDescriptor{
-            label:Some("depth"),
-            size: wgpu::Extent3d{width:new_size.width,height:new_size.height,depth_or_array_layers:1},
-            mip_level_count:1,sample_count:1,dimension:wgpu::TextureDimension::D2,
-            format:self.depth_format,usage:wgpu::TextureUsages::RENDER_ATTACHMENT,view_formats:&[],
-        });
-        self.depth_view=tex.create_view(&wgpu::TextureViewDescriptor::default());
+        let max=self.max_texture_dim();
+        self.config.width=clamp_texture_dim("frame width",new_size.width,max);
+        self.config.height=clamp_texture_dim("frame height",new_size.height,max);
+        match &self.surface {
+            Some(s) => s.configure(&self.device,&self.config),
+            None => self.offscreen=Some(offscreen_target(&self.device,&self.config)),
+        }
+        self.depth_view=depth_target(&self.device,self.depth_format,self.config.width,self.config.height,self.sample_count);
+        self.msaa_view=msaa_target(&self.device,&self.config,self.scene_format(),self.sample_count);
+        if let Some(t)=self.tonemap.as_mut() { t.resize(&self.device,self.config.width,self.config.height); }
+    }
+
+    // ---------- depth format ----------
+    /// Switch the scene depth/stencil format (default `DEFAULT_DEPTH_FORMAT`);
+    /// the depth texture and every pipeline drawing into it are rebuilt.
+    pub fn set_depth_format(&mut self, format: wgpu::TextureFormat) {
+        assert!(format.is_depth_stencil_format(), "{format:?} is not a depth format");
+        if format == self.depth_format { return; }
+        self.depth_format = format;
+        self.depth_view = depth_target(&self.device, format, self.config.width, self.config.height, self.sample_count);
+        self.rebuild_pipelines();
+    }
+    pub fn depth_format(&self) -> wgpu::TextureFormat { self.depth_format }
+
+    /// Every pipeline bound to the scene colour/depth formats (the debug
+    /// lines come back empty until the next `set_debug_lines`).
+    fn rebuild_pipelines(&mut self) {
+        let (color, depth) = (self.scene_format(), self.depth_format);
+        self.pipes = ScenePipelines::new(&self.device, &self.pipeline_layout, &self.shader, color, depth, self.sample_count);
+        let enabled = self.gizmo.enabled;
+        self.gizmo = AxisGizmo::new(&self.device, color, depth, self.sample_count);
+        self.gizmo.enabled = enabled;
+        self.lines = LineOverlay::new(&self.device, &self.camera_bgl, color, depth, self.sample_count);
+        self.bundle_gen += 1;
+    }
+    /// Scene MSAA samples (1 = off), fixed at construction.
+    pub fn sample_count(&self) -> u32 { self.sample_count }
+
+    // ---------- HDR ----------
+    /// Swap between linear HDR + tone mapping and rendering straight into
+    /// the frame; ignored when `hdr_supported` is false.
+    pub fn set_hdr(&mut self, on: bool) {
+        let on = on && self.hdr_capable;
+        if on == self.tonemap.is_some() { return; }
+        self.tonemap = on.then(|| ToneMap::new(&self.device, self.frame_format(), self.config.width, self.config.height));
+        if let Some(t) = &self.tonemap { t.update(&self.queue, self.exposure, self.frame_format()); }
+        self.msaa_view = msaa_target(&self.device, &self.config, self.scene_format(), self.sample_count);
+        self.rebuild_pipelines();
+    }
+    pub fn hdr(&self) -> bool { self.tonemap.is_some() }
+    /// The adapter can render and sample `HDR_FORMAT` at this sample count.
+    pub fn hdr_supported(&self) -> bool { self.hdr_capable }
+    /// Format of the presented frame: always an sRGB view when the surface
+    /// format has one (`frame_format`).
+    pub fn frame_format(&self) -> wgpu::TextureFormat { frame_format(&self.config) }
+    /// Colour format of the scene passes: `HDR_FORMAT` while tone mapping.
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        if self.tonemap.is_some() { HDR_FORMAT } else { self.frame_format() }
+    }
+    /// Linear scale applied before the tone curve (1 = as lit; no effect
+    /// without HDR); negative values clamp to 0.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.max(0.0);
+        if let Some(t) = &self.tonemap { t.update(&self.queue, self.exposure, self.frame_format()); }
+    }
+    pub fn exposure(&self) -> f32 { self.exposure }
+
+    // ---------- render bundles ----------
+    /// Replay baked chunks unchanged for `BUNDLE_AFTER_FRAMES` from
+    /// pre-recorded render bundles in the main pass (on by default); off
+    /// encodes every draw each frame.  Compare with `FrameStats::encode_ms`.
+    pub fn set_render_bundles(&mut self, on: bool) {
+        self.bundles = on;
+        if !on { for b in self.baked.values_mut() { b.bundle = None; } }
+    }
+    pub fn render_bundles(&self) -> bool { self.bundles }
+    /// Baked chunks drawn from a bundle last frame.
+    pub fn bundled_chunks(&self) -> usize {
+        self.baked_draws.iter().filter(|k| self.baked[*k].bundle.is_some()).count()
+    }
+
+    // ---------- depth prepass ----------
+    /// Lay down depth first so the colour pass shades each pixel once.
+    pub fn set_depth_prepass(&mut self, on: bool) { self.depth_prepass = on; }
+    pub fn depth_prepass(&self) -> bool { self.depth_prepass }
+
+    // ---------- billboard blending ----------
+    /// Alpha-blend the billboard batch after every opaque draw (no depth
+    /// write, skipped by the prepass).  Blending needs back-to-front order:
+    /// sort with `Buckets::sort_billboards_back_to_front` before upload.
+    pub fn set_billboard_blend(&mut self, on: bool) { self.billboard_blend = on; }
+    pub fn billboard_blend(&self) -> bool { self.billboard_blend }
+
+    /// Anti-alias billboard silhouettes from an edge alpha: with MSAA it
+    /// drives alpha-to-coverage on the opaque billboards (not under the
+    /// depth prepass); without MSAA the billboards are blended instead.
+    pub fn set_billboard_aa(&mut self, on: bool) { self.billboard_aa = on; }
+    pub fn billboard_aa(&self) -> bool { self.billboard_aa }
+
+    /// Whether billboards go in the blended pass this frame (blending on,
+    /// or edge AA without MSAA), i.e. need the back-to-front sort.
+    pub fn billboards_blended(&self) -> bool {
+        self.billboard_blend || (self.billboard_aa && self.sample_count == 1)
+    }
+
+    // ---------- background ----------
+    /// sRGB, like every colour the engine takes (see "Colour" in shader.wgsl).
+    pub fn set_clear_color(&mut self, c: wgpu::Color) { self.clear_color = c; }
+    pub fn clear_color(&self) -> wgpu::Color { self.clear_color }
+    fn clear_color_linear(&self) -> wgpu::Color {
+        let c = self.clear_color;
+        wgpu::Color { r: srgb_to_linear(c.r), g: srgb_to_linear(c.g), b: srgb_to_linear(c.b), a: c.a }
+    }
+
+    // ---------- palette ----------
+    /// Tint of the ground plane (`TINT_GROUND`), independent of the building categories.
+    pub fn set_ground_color(&mut self, rgb: [f32; 3]) {
+        self.palette.ground = [rgb[0], rgb[1], rgb[2], 0.0];
+        self.queue.write_buffer(&self.palette_buf, 0, bytemuck::bytes_of(&self.palette));
+    }
+
+    /// Checkerboard the ground: squares of `tile` (x, z) metres, one corner
+    /// at the design-space `offset` and the squares starting over every
+    /// `span` metres from there (chunk seams; 0 runs them on), alternately
+    /// `colors[0]` and `colors[1]` (`colors[0]` becomes the ground colour).
+    /// A zero tile leaves it plain.
+    pub fn set_ground_checker(&mut self, tile: [f32; 2], span: [f32; 2], offset: [f32; 2], colors: [[f32; 3]; 2]) {
+        let [a, b] = colors;
+        self.palette.ground     = [a[0], a[1], a[2], 0.0];
+        self.palette.ground_alt = [b[0], b[1], b[2], 0.0];
+        self.ground_checker = (tile, span, offset);
+        self.write_ground_grid();
+    }
+    /// The checker's two colours (`set_ground_color` sets the first).
+    pub fn ground_colors(&self) -> [[f32; 3]; 2] {
+        let (a, b) = (self.palette.ground, self.palette.ground_alt);
+        [[a[0], a[1], a[2]], [b[0], b[1], b[2]]]
+    }
+    /// (tile, span, offset) as last set.
+    pub fn ground_checker(&self) -> ([f32; 2], [f32; 2], [f32; 2]) { self.ground_checker }
+
+    /// Square the design-space point `(x, z)` falls in, counted like
+    /// `checker_tint` in shader.wgsl (which may be off by an even number);
+    /// squares with an odd sum get `colors[1]`.
+    pub fn ground_square(&self, x: f32, z: f32) -> [i32; 2] {
+        let (tile, span, offset) = self.ground_checker;
+        let restart = span[0] > 0.0 && span[1] > 0.0;
+        let cell = |i: usize, p: f32| {
+            let q = p - offset[i];
+            if !restart { return (q / tile[i]).floor() as i32; }
+            let k = (q / span[i]).floor();
+            ((q - k * span[i]) / tile[i]).floor() as i32 + k as i32 * (span[i] / tile[i]).ceil() as i32
+        };
+        [cell(0, x), cell(1, z)]
+    }
+
+    /// The chunk manager's `origin_shift`, so the squares stay put in design
+    /// space; call every frame (uploads only on change).
+    pub fn set_ground_origin(&mut self, shift: cgmath::Vector3<f32>) {
+        if self.ground_shift == [shift.x, shift.z] { return; }
+        self.ground_shift = [shift.x, shift.z];
+        self.write_ground_grid();
+        if self.heat_grid.is_some() { self.write_heat(); }
+    }
+
+    // grid cell = floor((local + phase) / tile), phase = shift - offset kept
+    // within one tile (two spans when the squares restart per chunk, so the
+    // chunk parity survives) so the shader never adds large numbers
+    fn write_ground_grid(&mut self) {
+        let (tile, span, offset) = self.ground_checker;
+        let restart = span[0] > 0.0 && span[1] > 0.0;
+        let period = |i: usize| if restart { 2.0 * span[i] } else { tile[i] };
+        let phase = |i: usize| if tile[i] > 0.0 { (self.ground_shift[i] - offset[i]).rem_euclid(period(i)) } else { 0.0 };
+        self.palette.ground_grid = [tile[0].max(0.0), tile[1].max(0.0), phase(0), phase(1)];
+        self.palette.ground_span = if restart { [span[0], span[1], 0.0, 0.0] } else { [0.0; 4] };
+        self.queue.write_buffer(&self.palette_buf, 0, bytemuck::bytes_of(&self.palette));
+    }
+
+    // ---------- density heatmap ----------
+    /// Tint the ground per chunk by `grid` (e.g. `hello_wgpu::density_heat`),
+    /// over the checker; `None` turns it off.  Uploads only on change, so it
+    /// can be called every frame.
+    pub fn set_heatmap(&mut self, grid: Option<&HeatGrid>) {
+        if self.heat_grid.as_ref() == grid { return; }
+        self.heat_grid = grid.cloned();
+        let values = grid.map_or(&[][..], |g| &g.values);
+        for (i, c) in self.heat.cells.as_flattened_mut().iter_mut().enumerate() {
+            *c = values.get(i).copied().unwrap_or(-1.0);
+        }
+        self.write_heat();
+    }
+    pub fn heatmap(&self) -> Option<&HeatGrid> { self.heat_grid.as_ref() }
+
+    /// Heatmap colour stops (sRGB) at 0, ⅓, ⅔ and 1 (default `HEAT_RAMP`).
+    pub fn set_heat_ramp(&mut self, ramp: [[f32; 3]; 4]) {
+        self.heat.ramp = ramp.map(|c| [c[0], c[1], c[2], 0.0]);
+        self.write_heat();
+    }
+    pub fn heat_ramp(&self) -> [[f32; 3]; 4] { self.heat.ramp.map(|c| [c[0], c[1], c[2]]) }
+
+    // the corner goes up in local space, like the checker phase
+    fn write_heat(&mut self) {
+        self.heat.grid = match &self.heat_grid {
+            Some(g) => [g.origin[0] - self.ground_shift[0], g.origin[1] - self.ground_shift[1], g.cell[0].max(0.0), g.cell[1].max(0.0)],
+            None => [0.0; 4],
+        };
+        self.queue.write_buffer(&self.heat_buf, 0, bytemuck::bytes_of(&self.heat));
+    }
+
+    /// Upload `Archetype::tint`s if `AssetLibrary::set_tint` ran since last time.
+    fn sync_tints(&mut self) {
+        let rev = self.assets.tint_revision();
+        if self.tint_rev == Some(rev) { return; }
+        if self.assets.archetypes.len() > MAX_TINTS {
+            warn!("{} archetypes, only the first {MAX_TINTS} can be tinted", self.assets.archetypes.len());
+        }
+        let mut table = [[1.0f32; 4]; MAX_TINTS];
+        for (t, a) in table.iter_mut().zip(&self.assets.archetypes) { *t = a.tint; }
+        self.queue.write_buffer(&self.tint_buf, 0, bytemuck::cast_slice(&table));
+        self.tint_rev = Some(rev);
+    }
+
+    // ---------- lighting ----------
+    /// Hemispheric ambient: `sky` lights up-facing normals, `ground` down-facing.
+    pub fn set_ambient(&mut self, sky: [f32; 3], ground: [f32; 3]) {
+        self.light.sky    = [sky[0], sky[1], sky[2], 0.0];
+        self.light.ground = [ground[0], ground[1], ground[2], 0.0];
+        self.queue.write_buffer(&self.light_buf, 0, bytemuck::bytes_of(&self.light));
+    }
+    /// Direction *towards* the key light (normalised in the shader).
+    pub fn set_light_direction(&mut self, dir: [f32; 3]) {
+        self.light.dir = [dir[0], dir[1], dir[2], 0.0];
+        self.queue.write_buffer(&self.light_buf, 0, bytemuck::bytes_of(&self.light));
+    }
+
+    // ---------- procedural windows ----------
+    /// Window grid on plain (`LAYER_PLAIN`) walls: `per_metre` windows
+    /// along each axis of a face (0 turns them off), each lit with
+    /// probability `lit` per building (`culling::window_seed`).
+    pub fn set_windows(&mut self, per_metre: f32, lit: f32) {
+        self.light.windows = [per_metre.max(0.0), lit.clamp(0.0, 1.0), 0.0, 0.0];
+        self.queue.write_buffer(&self.light_buf, 0, bytemuck::bytes_of(&self.light));
+    }
+    /// (`per_metre`, `lit`) as last set.
+    pub fn windows(&self) -> (f32, f32) { (self.light.windows[0], self.light.windows[1]) }
+
+    // ---------- shadows ----------
+    pub fn set_shadows_enabled(&mut self, on: bool) { self.shadow.enabled = on; }
+    pub fn set_shadow_map_size(&mut self, size: u32) {
+        let size = clamp_texture_dim("shadow map", size, self.max_texture_dim());
+        self.shadow.set_size(&self.device, size);
+        self.bundle_gen += 1; // new shadow bind group
+    }
+    pub fn shadow_map_size(&self) -> u32 { self.shadow.size }
+    /// Half-width (m) of the light frustum; keep it near the cull radius.
+    pub fn set_shadow_extent(&mut self, half_width: f32) { self.shadow.extent = half_width; }
+
+    /// Re-fit the light frustum around the camera (call once per frame).
+    pub fn update_shadow(&self, cam: cgmath::Vector3<f32>) {
+        let d = self.light.dir;
+        self.shadow.fit(&self.queue, cam, cgmath::Vector3::new(d[0], d[1], d[2]));
     }
 
     // ---------- camera ----------
     pub fn update_camera(&self, vp:&cgmath::Matrix4<f32>) {
+        // a perspective view-projection maps the eye to (0, 0, z, 0)
+        let eye = vp.invert().map(|inv| inv * cgmath::Vector4::new(0.0, 0.0, 1.0, 0.0))
+            .filter(|e| e.w.abs() > 1e-12)
+            .map_or([0.0; 3], |e| [e.x / e.w, e.y / e.w, e.z / e.w]);
         let data = CameraUniform{ view_proj:[
             [vp.x.x,vp.x.y,vp.x.z,vp.x.w],
             [vp.y.x,vp.y.y,vp.y.z,vp.y.w],
             [vp.z.x,vp.z.y,vp.z.z,vp.z.w],
             [vp.w.x,vp.w.y,vp.w.z,vp.w.w],
-        ]};
+        ], eye: [eye[0], eye[1], eye[2], self.impostors as u32 as f32]};
         self.queue.write_buffer(&self.camera_buf,0,bytemuck::bytes_of(&data));
     }
 
+    // ---------- impostors ----------
+    /// Draw LOD2 billboards as impostors: camera-facing quads textured with
+    /// the atlas view nearest the view direction (see impostor.rs).  Off,
+    /// they are the flat fixed-facing quads.
+    pub fn set_impostors(&mut self, on: bool) {
+        self.impostors = on;
+        let flag = (on as u32 as f32).to_ne_bytes();
+        self.queue.write_buffer(&self.camera_buf, std::mem::offset_of!(CameraUniform, eye) as u64 + 12, &flag);
+    }
+    pub fn impostors(&self) -> bool { self.impostors }
+    pub fn impostor_atlas(&self) -> &ImpostorAtlas { &self.impostor }
+
+    // ---------- axis gizmo ----------
+    pub fn set_axis_gizmo(&mut self, on: bool) { self.gizmo.enabled = on; }
+    pub fn axis_gizmo(&self) -> bool { self.gizmo.enabled }
+    /// Camera view matrix for the gizmo (only its rotation is used).
+    pub fn update_gizmo(&self, view: &cgmath::Matrix4<f32>) {
+        if self.gizmo.enabled { self.gizmo.update(&self.queue, view); }
+    }
+
+    // ---------- debug lines ----------
+    /// World-space (local) line list drawn over the scene until replaced;
+    /// empty clears it.  A depth-format switch clears it too.
+    pub fn set_debug_lines(&mut self, verts: &[LineVertex]) {
+        self.lines.set(&self.device, &self.queue, verts);
+    }
+    /// Vertices currently in the debug line list.
+    pub fn debug_line_count(&self) -> usize { self.lines.len() }
+
     // ---------- instances ----------
     /// Call once per frame after culling.
     pub fn update_instances(
@@ -307,16 +1243,16 @@ impl Engine {
         v2_bill:&[InstanceRaw],
         ground:&InstanceRaw,
     ){
-        ensure_buf(&self.device,&mut self.buf_ground,1,"ground buf");
-        ensure_buf(&self.device,&mut self.buf_l0_low_common,v0_low_common.len(),"l0 low com");
-        ensure_buf(&self.device,&mut self.buf_l0_low_alt,v0_low_alt.len(),"l0 low alt");
-        ensure_buf(&self.device,&mut self.buf_l0_high,v0_high.len(),"l0 high");
-        ensure_buf(&self.device,&mut self.buf_l0_land,v0_land.len(),"l0 land");
-        ensure_buf(&self.device,&mut self.buf_l1_low_common,v1_low_common.len(),"l1 low com");
-        ensure_buf(&self.device,&mut self.buf_l1_low_alt,v1_low_alt.len(),"l1 low alt");
-        ensure_buf(&self.device,&mut self.buf_l1_high,v1_high.len(),"l1 high");
-        ensure_buf(&self.device,&mut self.buf_l1_land,v1_land.len(),"l1 land");
-        ensure_buf(&self.device,&mut self.buf_l2_bill,v2_bill.len(),"l2 bill");
+        let (d,p,t)=(&self.device,&self.shrink,&mut self.shrink_track);
+        ensure_buf(d,&mut self.buf_l0_low_common,v0_low_common.len(),"l0 low com",p,&mut t[0]);
+        ensure_buf(d,&mut self.buf_l0_low_alt,v0_low_alt.len(),"l0 low alt",p,&mut t[1]);
+        ensure_buf(d,&mut self.buf_l0_high,v0_high.len(),"l0 high",p,&mut t[2]);
+        ensure_buf(d,&mut self.buf_l0_land,v0_land.len(),"l0 land",p,&mut t[3]);
+        ensure_buf(d,&mut self.buf_l1_low_common,v1_low_common.len(),"l1 low com",p,&mut t[4]);
+        ensure_buf(d,&mut self.buf_l1_low_alt,v1_low_alt.len(),"l1 low alt",p,&mut t[5]);
+        ensure_buf(d,&mut self.buf_l1_high,v1_high.len(),"l1 high",p,&mut t[6]);
+        ensure_buf(d,&mut self.buf_l1_land,v1_land.len(),"l1 land",p,&mut t[7]);
+        ensure_buf(d,&mut self.buf_l2_bill,v2_bill.len(),"l2 bill",p,&mut t[8]);
 
         self.queue.write_buffer(&self.buf_ground,0,bytemuck::bytes_of(ground));
         if !v0_low_common.is_empty(){ self.queue.write_buffer(&self.buf_l0_low_common,0,bytemuck::cast_slice(v0_low_common)); }
@@ -340,106 +1276,442 @@ impl Engine {
         self.cnt_l1_land       = v1_land.len()     as u32;
         self.cnt_l2_bill       = v2_bill.len()     as u32;
 
-        info!("cnt0={} / cnt1={} / cnt2={}", self.cnt_l0_low_common, self.cnt_l1_low_common, self.cnt_l2_bill);
+    }
+
+    /// Shrink instance buffers that stay far bigger than needed (on by default).
+    pub fn set_shrink_policy(&mut self, policy: ShrinkPolicy) {
+        self.shrink = policy;
+        self.shrink_track = [ShrinkTracker::default(); 10];
+    }
+    pub fn shrink_policy(&self) -> ShrinkPolicy { self.shrink }
+    pub fn set_buffer_shrink(&mut self, on: bool) { self.set_shrink_policy(ShrinkPolicy { enabled: on, ..self.shrink }); }
+
+    /// Bytes allocated for instance data (every batch buffer + the baked anchors).
+    pub fn instance_buffer_bytes(&self) -> u64 {
+        self.instance_batches().iter().map(|(_,b,_)| b.size()).sum::<u64>() + self.buf_baked_anchor.size()
+    }
+
+    /// Drop every instance buffer back to a single element and forget the
+    /// baked meshes; they regrow from the next `update_instances`/`update_baked`.
+    pub fn release_instance_memory(&mut self) {
+        let one = |d:&wgpu::Device, lbl:&str| d.create_buffer(&wgpu::BufferDescriptor{
+            label:Some(lbl), size:std::mem::size_of::<InstanceRaw>() as u64,
+            usage:wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST, mapped_at_creation:false,
+        });
+        let d=&self.device;
+        for (buf,cnt,lbl) in [
+            (&mut self.buf_l0_low_common,&mut self.cnt_l0_low_common,"l0 low common"),
+            (&mut self.buf_l0_low_alt,&mut self.cnt_l0_low_alt,"l0 low alt"),
+            (&mut self.buf_l0_high,&mut self.cnt_l0_high,"l0 high"),
+            (&mut self.buf_l0_land,&mut self.cnt_l0_land,"l0 land"),
+            (&mut self.buf_l1_low_common,&mut self.cnt_l1_low_common,"l1 low common"),
+            (&mut self.buf_l1_low_alt,&mut self.cnt_l1_low_alt,"l1 low alt"),
+            (&mut self.buf_l1_high,&mut self.cnt_l1_high,"l1 high"),
+            (&mut self.buf_l1_land,&mut self.cnt_l1_land,"l1 land"),
+            (&mut self.buf_l2_bill,&mut self.cnt_l2_bill,"l2 bill"),
+        ] { *buf=one(d,lbl); *cnt=0; }
+        self.buf_baked_anchor=one(d,"baked anchors");
+        self.buf_smoke=one(d,"smoke");
+        self.cnt_smoke=0;
+        self.shrink_track=[ShrinkTracker::default(); 10];
+        self.smoke_track=ShrinkTracker::default();
+        self.baked.clear();
+        self.baked_draws.clear();
+    }
+
+    // ---------- teardown ----------
+    /// Release the GPU memory explicitly, in dependency order: wait for the
+    /// GPU, destroy the baked meshes and their bundles, the instance and
+    /// uniform buffers, every texture (depth, MSAA, HDR, shadow map, facade
+    /// array, impostor atlas, offscreen target), then drop the surface
+    /// (before the window it was created from, which the caller still owns).
+    /// Pipelines, bind groups, the shared asset meshes and the overlays'
+    /// small buffers go when the engine drops.  Dropping frees everything
+    /// too, but a browser may hold on to it long after; on wasm this also
+    /// destroys the device, so there the engine must be its only user.
+    pub fn destroy(mut self) {
+        let _ = self.device.poll(wgpu::PollType::Wait);
+        self.baked_draws.clear();
+        for (_, b) in self.baked.drain() {
+            if let Some(bb) = b.bundle { bb.anchor.destroy(); }
+            b.mesh.vertex_buffer.destroy();
+            b.mesh.index_buffer.destroy();
+        }
+        for buf in [
+            &self.buf_ground,
+            &self.buf_l0_low_common, &self.buf_l0_low_alt, &self.buf_l0_high, &self.buf_l0_land,
+            &self.buf_l1_low_common, &self.buf_l1_low_alt, &self.buf_l1_high, &self.buf_l1_land,
+            &self.buf_l2_bill, &self.buf_baked_anchor, &self.buf_selection, &self.buf_smoke,
+            &self.camera_buf, &self.palette_buf, &self.tint_buf, &self.light_buf, &self.heat_buf,
+        ] { buf.destroy(); }
+        self.depth_view.texture().destroy();
+        if let Some(v) = &self.msaa_view { v.texture().destroy(); }
+        if let Some(t) = &self.tonemap { t.destroy(); }
+        self.shadow.destroy();
+        self.facade.texture().destroy();
+        self.impostor.texture().destroy();
+        if let Some(t) = self.offscreen.take() { t.destroy(); }
+        drop(self.surface.take());
+        #[cfg(target_arch = "wasm32")]
+        self.device.destroy();
+    }
+
+    // ---------- asset hot reload ----------
+    /// Re-run the `mesh::*` builders and reset the palette on the current
+    /// device; surface, pipelines and bind groups are kept.  Archetype ids and
+    /// categories may differ afterwards, so instance buffers, baked chunk
+    /// meshes and the selection highlight are dropped too (the ground
+    /// colours and checker survive).
+    pub fn reload_assets(&mut self) {
+        self.assets = AssetLibrary::new(&self.device);
+        let p = self.palette;
+        self.palette = GpuPalette { ground: p.ground, ground_alt: p.ground_alt, ground_grid: p.ground_grid, ground_span: p.ground_span, ..GpuPalette::default() };
+        self.queue.write_buffer(&self.palette_buf, 0, bytemuck::bytes_of(&self.palette));
+        self.release_instance_memory();
+        self.sel_boxes.clear();
+        self.tint_rev = None;
+        info!("assets reloaded: {} archetypes", self.assets.archetypes.len());
+    }
+
+    // ---------- baked chunks ----------
+    /// Draw `keys` as baked meshes this frame, (re)baking any whose placements
+    /// changed since the last bake.  Bakes not listed are kept (with their
+    /// bundles) for when the chunk comes back into view; they are dropped
+    /// once the chunk is unloaded or mutated.
+    pub fn update_baked(&mut self, cm: &ChunkManager, keys: &[ChunkKey]) {
+        self.baked.retain(|k, b| cm.loaded.contains_key(k) && cm.revision(*k) == b.revision);
+        let shift = cm.origin_shift();
+        let mut anchors = Vec::with_capacity(keys.len());
+        self.baked_draws.clear();
+        for &key in keys {
+            let Some(list) = cm.loaded.get(&key) else { continue };
+            let rev = cm.revision(key);
+            let stale = self.baked.get(&key).is_none_or(|b| b.revision != rev);
+            if stale {
+                let mesh = mesh::bake_chunk(&self.device, list, &self.assets);
+                self.baked.insert(key, BakedChunk { mesh, revision: rev, shift_at_bake: shift, stable_frames: 0, bundle: None });
+            }
+            let off = self.baked[&key].shift_at_bake - shift;
+            self.update_bundle(key, off);
+            anchors.push(InstanceRaw {
+                pos:[off.x,off.y,off.z,0.0], scale:[1.0,1.0,1.0,0.0], misc:[0.0;4],
+            });
+            self.baked_draws.push(key);
+        }
+        ensure_buf(&self.device,&mut self.buf_baked_anchor,anchors.len(),"baked anchors",&self.shrink,&mut self.shrink_track[9]);
+        if !anchors.is_empty() {
+            self.queue.write_buffer(&self.buf_baked_anchor,0,bytemuck::cast_slice(&anchors));
+        }
+    }
+
+    /// Count a frame for a baked chunk and (re)record or re-anchor its
+    /// bundle once it is stable.
+    fn update_bundle(&mut self, key: ChunkKey, off: cgmath::Vector3<f32>) {
+        let (prepass, generation, bundles, color) = (self.depth_prepass, self.bundle_gen, self.bundles, self.scene_format());
+        let b = self.baked.get_mut(&key).unwrap();
+        b.stable_frames = b.stable_frames.saturating_add(1);
+        if !bundles || b.stable_frames < BUNDLE_AFTER_FRAMES { return; }
+        let anchor = |off: cgmath::Vector3<f32>| InstanceRaw { pos:[off.x,off.y,off.z,0.0], scale:[1.0,1.0,1.0,0.0], misc:[0.0;4] };
+        match &mut b.bundle {
+            Some(bb) if bb.prepass == prepass && bb.generation == generation => {
+                if bb.offset != off {
+                    self.queue.write_buffer(&bb.anchor, 0, bytemuck::bytes_of(&anchor(off)));
+                    bb.offset = off;
+                }
+            }
+            _ => {
+                let buf = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("bundle anchor"),
+                    contents: bytemuck::bytes_of(&anchor(off)),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+                let mut enc = self.device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: Some("baked chunk bundle"),
+                    color_formats: &[Some(color)],
+                    depth_stencil: Some(wgpu::RenderBundleDepthStencil { format: self.depth_format, depth_read_only: false, stencil_read_only: false }),
+                    sample_count: self.sample_count,
+                    multiview: None,
+                });
+                enc.set_pipeline(if prepass {&self.pipes.baked_eq} else {&self.pipes.baked});
+                enc.set_bind_group(0, &self.camera_bg, &[]);
+                enc.set_bind_group(1, &self.palette_bg, &[]);
+                enc.set_bind_group(2, &self.shadow.bg, &[]);
+                enc.set_bind_group(3, &self.facade.bg, &[]);
+                let m = &b.mesh;
+                enc.set_vertex_buffer(0, m.vertex_buffer.slice(..));
+                enc.set_vertex_buffer(1, buf.slice(..));
+                enc.set_index_buffer(m.index_buffer.slice(..), m.index_format);
+                enc.draw_indexed(0..m.index_count, 0, 0..1);
+                let bundle = enc.finish(&wgpu::RenderBundleDescriptor { label: Some("baked chunk bundle") });
+                b.bundle = Some(BakedBundle { bundle, anchor: buf, offset: off, prepass, generation });
+            }
+        }
+    }
+
+    // ---------- selection ----------
+    /// Highlight building `index` of chunk `key` (an index into `ChunkManager::loaded`).
+    pub fn set_selection(&mut self, key: ChunkKey, index: usize) { self.selected = Some((key, index)); }
+    pub fn clear_selection(&mut self) { self.selected = None; self.sel_boxes.clear(); }
+    pub fn selection(&self) -> Option<(ChunkKey, usize)> { self.selected }
+
+    /// Look the selected building up again (call once per frame), so the
+    /// highlight follows origin shifts and mutations.  Nothing is drawn while
+    /// its chunk is unloaded.
+    pub fn update_selection(&mut self, cm: &ChunkManager) {
+        self.sel_boxes.clear();
+        let Some((key, index)) = self.selected else { return };
+        let Some(p) = cm.loaded.get(&key).and_then(|l| l.get(index)) else { return };
+        // grow by ~0.15 m on every side so the shell sits just outside the faces
+        let grow = |s: f32, h: f32| s + 0.15 / h.max(0.05);
+        // placements from the designer or the store carry at most MAX_STACK extra boxes
+        let boxes: Vec<_> = p.boxes().take(1 + MAX_STACK).collect();
+        let insts: Vec<InstanceRaw> = boxes.iter().map(|&(c, s, id)| {
+            let half = self.assets.base_half(id as usize);
+            InstanceRaw {
+                pos:  [c.x, c.y, c.z, 0.0],
+                scale:[grow(s.x, half.x), grow(s.y, half.y), grow(s.z, half.z), 0.0],
+                misc: [0.0, id as f32, 0.0, 0.0],
+            }
+        }).collect();
+        self.queue.write_buffer(&self.buf_selection, 0, bytemuck::cast_slice(&insts));
+        self.sel_boxes = boxes.iter().map(|&(_, _, id)| id as usize).collect();
+    }
+
+    /// Archetype of each box the selection shell is drawn for this frame
+    /// (main box first); empty when nothing is highlighted.
+    pub fn selection_boxes(&self) -> &[usize] { &self.sel_boxes }
+
+    // ---------- smoke ----------
+    /// This frame's smoke puffs (`SmokeSystem::instances`, back to front);
+    /// empty draws nothing.
+    pub fn update_smoke(&mut self, puffs: &[InstanceRaw]) {
+        ensure_buf(&self.device, &mut self.buf_smoke, puffs.len(), "smoke", &self.shrink, &mut self.smoke_track);
+        if !puffs.is_empty() { self.queue.write_buffer(&self.buf_smoke, 0, bytemuck::cast_slice(puffs)); }
+        self.cnt_smoke = puffs.len() as u32;
+    }
+    pub fn smoke_count(&self) -> u32 { self.cnt_smoke }
+
+    // ---------- stats ----------
+    /// Last frame's counts plus the rolling once-per-second averages.
+    pub fn frame_stats(&self) -> FrameStats { self.stats }
+
+    fn record_frame(&mut self, frame: FrameStats) {
+        let acc = &mut self.stats_acc;
+        acc.frames += 1;
+        acc.instances += frame.instances as u64;
+        acc.draw_calls += frame.draw_calls as u64;
+        acc.triangles += frame.triangles;
+        if self.debug {
+            info!("frame: inst={} draws={} tris={}", frame.instances, frame.draw_calls, frame.triangles);
+        }
+        let avg = self.stats;
+        self.stats = FrameStats { avg_instances: avg.avg_instances, avg_draw_calls: avg.avg_draw_calls,
+                                  avg_triangles: avg.avg_triangles, ..frame };
+        if acc.since.elapsed().as_secs_f32() >= 1.0 {
+            let n = acc.frames.max(1) as f32;
+            self.stats.avg_instances  = acc.instances as f32 / n;
+            self.stats.avg_draw_calls = acc.draw_calls as f32 / n;
+            self.stats.avg_triangles  = acc.triangles as f32 / n;
+            info!("stats/1s: {} frames, avg inst={:.0} draws={:.1} tris={:.0} gpu={}",
+                  acc.frames, self.stats.avg_instances, self.stats.avg_draw_calls, self.stats.avg_triangles,
+                  self.stats.gpu_ms.map_or("n/a".to_string(), |ms| format!("{ms:.2}ms")));
+            *acc = StatsAccum::default();
+        }
     }
 
     // ---------- draw ----------
+    /// Every instanced batch in draw order: ground, LOD0 (low common/alt,
+    /// high, land), LOD1 (same, with the LOD1 meshes), LOD2 billboards.
+    fn instance_batches(&self) -> [(&mesh::Mesh,&wgpu::Buffer,u32);10] {
+        let a=&self.assets;
+        let cat=|cm,lod| a.mesh_for_lod(cm,lod);
+        const ALT:usize=1; // timber_house_b, bucketed on its own
+        [
+            (&a.meshes().ground,&self.buf_ground,self.cnt_ground),
+            (cat(CategoryMesh::Lowrise,0),&self.buf_l0_low_common,self.cnt_l0_low_common),
+            (a.mesh_of(ALT,0),&self.buf_l0_low_alt,self.cnt_l0_low_alt),
+            (cat(CategoryMesh::Highrise,0),&self.buf_l0_high,self.cnt_l0_high),
+            (cat(CategoryMesh::Landmark,0),&self.buf_l0_land,self.cnt_l0_land),
+            (cat(CategoryMesh::Lowrise,1),&self.buf_l1_low_common,self.cnt_l1_low_common),
+            (a.mesh_of(ALT,1),&self.buf_l1_low_alt,self.cnt_l1_low_alt),
+            (cat(CategoryMesh::Highrise,1),&self.buf_l1_high,self.cnt_l1_high),
+            (cat(CategoryMesh::Landmark,1),&self.buf_l1_land,self.cnt_l1_land),
+            (&a.meshes().billboard,&self.buf_l2_bill,self.cnt_l2_bill),
+        ]
+    }
+
     pub fn render(&mut self)->Result<(),wgpu::SurfaceError>{
-        let frame=self.surface.get_current_texture()?;
-        let view=frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let t0=instant::Instant::now();
+        self.sync_tints();
+        let frame=match &self.surface { Some(s)=>Some(s.get_current_texture()?), None=>None };
+        let view=match (&frame,&self.offscreen) {
+            (Some(f),_) => f.texture.create_view(&wgpu::TextureViewDescriptor{format:Some(frame_format(&self.config)),..Default::default()}),
+            (None,Some(t)) => t.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None,None) => unreachable!("engine without surface or offscreen target"),
+        };
         let mut encoder=self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label:Some("enc")});
+        let mut stats=FrameStats::default();
+        let clear=self.clear_color_linear();
+        // with HDR the scene goes to the float target, tone-mapped into `view` at the end
+        let scene=self.tonemap.as_ref().map_or(&view,|t| t.view());
+
+        let prepass=self.depth_prepass;
+        let timer=self.gpu_timer.as_ref();
+        let batches=self.instance_batches();
+        // blended billboards (the last batch) go after everything opaque
+        let opaque=if self.billboards_blended() {batches.len()-1} else {batches.len()};
+        // opaque billboards with alpha-to-coverage: their own pipeline
+        let a2c=self.pipes.bill_a2c.as_ref().filter(|_| self.billboard_aa && !prepass && opaque==batches.len());
+        let plain=if a2c.is_some() {opaque-1} else {opaque};
+        let clear_ops=depth_ops(self.depth_format,false);
+        let main_ops=depth_ops(self.depth_format,prepass);
+
+        // Shadow depth pass: every building batch (no ground/billboards) from the light
+        if self.shadow.enabled {
+            let mut spass=encoder.begin_render_pass(&wgpu::RenderPassDescriptor{
+                label:Some("shadow pass"),
+                color_attachments:&[],
+                depth_stencil_attachment:Some(wgpu::RenderPassDepthStencilAttachment{
+                    view:self.shadow.view(),
+                    depth_ops:Some(wgpu::Operations{load:wgpu::LoadOp::Clear(1.0),store:wgpu::StoreOp::Store}),
+                    stencil_ops:None,
+                }),
+                timestamp_writes:None, occlusion_query_set:None,
+            });
+            spass.set_pipeline(&self.shadow.pipeline);
+            spass.set_bind_group(0,&self.shadow.light_cam_bg,&[]);
+            spass.set_bind_group(1,&self.palette_bg,&[]);
+            let mut none=FrameStats::default();
+            for &(m,b,c) in &batches[1..batches.len()-1] { draw_batch(&mut spass,m,b,c,&mut none); }
+            if !self.baked_draws.is_empty() {
+                spass.set_pipeline(&self.shadow.baked_pipeline);
+                draw_baked(&mut spass,&self.baked,&self.baked_draws,&self.buf_baked_anchor,|_| false,&mut none);
+            }
+        }
+
+        // Optional depth prepass: same instances, alpha-tested, no colour
+        if prepass {
+            let mut ppass=encoder.begin_render_pass(&wgpu::RenderPassDescriptor{
+                label:Some("depth prepass"),
+                color_attachments:&[],
+                depth_stencil_attachment:Some(wgpu::RenderPassDepthStencilAttachment{
+                    view:&self.depth_view,
+                    depth_ops:clear_ops.0,
+                    stencil_ops:clear_ops.1,
+                }),
+                timestamp_writes:timer.map(|t| t.pass_writes(true,false)), occlusion_query_set:None,
+            });
+            ppass.set_pipeline(&self.pipes.prepass);
+            ppass.set_bind_group(0,&self.camera_bg,&[]);
+            ppass.set_bind_group(1,&self.palette_bg,&[]);
+            ppass.set_bind_group(2,&self.shadow.bg,&[]);
+            ppass.set_bind_group(3,&self.facade.bg,&[]);
+            let mut none=FrameStats::default();
+            for &(m,b,c) in &batches[..opaque] { draw_batch(&mut ppass,m,b,c,&mut none); }
+            if !self.baked_draws.is_empty() {
+                ppass.set_pipeline(&self.pipes.prepass_baked);
+                draw_baked(&mut ppass,&self.baked,&self.baked_draws,&self.buf_baked_anchor,|_| false,&mut none);
+            }
+        }
 
         {
             let mut rpass=encoder.begin_render_pass(&wgpu::RenderPassDescriptor{
                 label:Some("main pass"),
-                color_attachments:&[Some(wgpu::RenderPassColorAttachment{
-                    view:&view,depth_slice:None,resolve_target:None,
-                    ops:wgpu::Operations{load:wgpu::LoadOp::Clear(wgpu::Color{r:0.06,g:0.06,b:0.08,a:1.0}),store:wgpu::StoreOp::Store},
+                color_attachments:&[Some(match &self.msaa_view {
+                    // samples are only needed until they are resolved
+                    Some(msaa) => wgpu::RenderPassColorAttachment{
+                        view:msaa,depth_slice:None,resolve_target:Some(scene),
+                        ops:wgpu::Operations{load:wgpu::LoadOp::Clear(clear),store:wgpu::StoreOp::Discard},
+                    },
+                    None => wgpu::RenderPassColorAttachment{
+                        view:scene,depth_slice:None,resolve_target:None,
+                        ops:wgpu::Operations{load:wgpu::LoadOp::Clear(clear),store:wgpu::StoreOp::Store},
+                    },
                 })],
                 depth_stencil_attachment:Some(wgpu::RenderPassDepthStencilAttachment{
                     view:&self.depth_view,
-                    depth_ops:Some(wgpu::Operations{load:wgpu::LoadOp::Clear(1.0),store:wgpu::StoreOp::Store}),
-                    stencil_ops:None,
+                    depth_ops:main_ops.0,
+                    stencil_ops:main_ops.1,
                 }),
-                timestamp_writes:None, occlusion_query_set:None,
+                timestamp_writes:timer.map(|t| t.pass_writes(!prepass,true)), occlusion_query_set:None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_pipeline(if prepass {&self.pipes.main_eq} else {&self.pipes.main});
             rpass.set_bind_group(0,&self.camera_bg,&[]);
             rpass.set_bind_group(1,&self.palette_bg,&[]);
+            rpass.set_bind_group(2,&self.shadow.bg,&[]);
+            rpass.set_bind_group(3,&self.facade.bg,&[]);
 
-            // Ground
-            rpass.set_vertex_buffer(0,self.assets.mesh_ground.vertex_buffer.slice(..));
-            rpass.set_index_buffer(self.assets.mesh_ground.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-            rpass.set_vertex_buffer(1,self.buf_ground.slice(..));
-            rpass.draw_indexed(0..self.assets.mesh_ground.index_count,0,0..self.cnt_ground);
-
-            // LOD0 lowrise: common + alt
-            if self.cnt_l0_low_common>0 {
-                rpass.set_vertex_buffer(0,self.assets.mesh_lowrise.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_lowrise.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l0_low_common.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_lowrise.index_count,0,0..self.cnt_l0_low_common);
-            }
-            if self.cnt_l0_low_alt>0 {
-                let alt_mesh = self.assets.mesh_of(1/*timber_house_b*/).unwrap(); // assumes id=1
-                rpass.set_vertex_buffer(0,alt_mesh.vertex_buffer.slice(..));
-                rpass.set_index_buffer(alt_mesh.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l0_low_alt.slice(..));
-                rpass.draw_indexed(0..alt_mesh.index_count,0,0..self.cnt_l0_low_alt);
-            }
-            // LOD0 highrise & landmark
-            if self.cnt_l0_high>0{
-                rpass.set_vertex_buffer(0,self.assets.mesh_highrise.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_highrise.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l0_high.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_highrise.index_count,0,0..self.cnt_l0_high);
-            }
-            if self.cnt_l0_land>0{
-                rpass.set_vertex_buffer(0,self.assets.mesh_landmark.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_landmark.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l0_land.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_landmark.index_count,0,0..self.cnt_l0_land);
-            }
+            // ground, LOD0, LOD1, LOD2 billboards (unless blended or alpha-to-coverage)
+            for &(m,b,c) in &batches[..plain] { draw_batch(&mut rpass,m,b,c,&mut stats); }
 
-            // LOD1 batches
-            if self.cnt_l1_low_common>0{
-                rpass.set_vertex_buffer(0,self.assets.mesh_lowrise.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_lowrise.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l1_low_common.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_lowrise.index_count,0,0..self.cnt_l1_low_common);
+            // Baked far chunks: one draw each, instance i = anchor offset;
+            // stable ones replay their bundle (which resets the pass state)
+            if !self.baked_draws.is_empty() {
+                rpass.set_pipeline(if prepass {&self.pipes.baked_eq} else {&self.pipes.baked});
+                // recorded for another prepass setting or stale resources: immediate
+                let generation=self.bundle_gen;
+                let live=|b:&BakedChunk| b.bundle.as_ref().is_some_and(|bb| bb.prepass==prepass && bb.generation==generation);
+                draw_baked(&mut rpass,&self.baked,&self.baked_draws,&self.buf_baked_anchor,live,&mut stats);
+                let bundled: Vec<&BakedChunk>=self.baked_draws.iter().map(|k| &self.baked[k]).filter(|b| live(b)).collect();
+                if !bundled.is_empty() {
+                    rpass.execute_bundles(bundled.iter().filter_map(|b| b.bundle.as_ref()).map(|bb| &bb.bundle));
+                    for b in &bundled {
+                        stats.draw_calls+=1;
+                        stats.triangles+=(b.mesh.index_count/3) as u64;
+                    }
+                    rpass.set_bind_group(0,&self.camera_bg,&[]);
+                    rpass.set_bind_group(1,&self.palette_bg,&[]);
+                    rpass.set_bind_group(2,&self.shadow.bg,&[]);
+                    rpass.set_bind_group(3,&self.facade.bg,&[]);
+                }
             }
-            if self.cnt_l1_low_alt>0{
-                let alt_mesh=self.assets.mesh_of(1).unwrap();
-                rpass.set_vertex_buffer(0,alt_mesh.vertex_buffer.slice(..));
-                rpass.set_index_buffer(alt_mesh.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l1_low_alt.slice(..));
-                rpass.draw_indexed(0..alt_mesh.index_count,0,0..self.cnt_l1_low_alt);
+
+            if let Some(pipe)=a2c {
+                rpass.set_pipeline(pipe);
+                for &(m,b,c) in &batches[plain..opaque] { draw_batch(&mut rpass,m,b,c,&mut stats); }
             }
-            if self.cnt_l1_high>0{
-                rpass.set_vertex_buffer(0,self.assets.mesh_highrise.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_highrise.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l1_high.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_highrise.index_count,0,0..self.cnt_l1_high);
+
+            for &(m,b,c) in &batches[opaque..] {
+                rpass.set_pipeline(&self.pipes.bill_blend);
+                draw_batch(&mut rpass,m,b,c,&mut stats);
             }
-            if self.cnt_l1_land>0{
-                rpass.set_vertex_buffer(0,self.assets.mesh_landmark.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_landmark.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l1_land.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_landmark.index_count,0,0..self.cnt_l1_land);
+
+            if self.cnt_smoke>0 {
+                rpass.set_pipeline(&self.pipes.smoke);
+                draw_batch(&mut rpass,&self.assets.meshes().billboard,&self.buf_smoke,self.cnt_smoke,&mut stats);
             }
 
-            // LOD2 billboards
-            if self.cnt_l2_bill>0{
-                rpass.set_vertex_buffer(0,self.assets.mesh_billboard.vertex_buffer.slice(..));
-                rpass.set_index_buffer(self.assets.mesh_billboard.index_buffer.slice(..),wgpu::IndexFormat::Uint16);
-                rpass.set_vertex_buffer(1,self.buf_l2_bill.slice(..));
-                rpass.draw_indexed(0..self.assets.mesh_billboard.index_count,0,0..self.cnt_l2_bill);
+            if !self.sel_boxes.is_empty() {
+                rpass.set_pipeline(&self.pipes.highlight);
+                let stride=std::mem::size_of::<InstanceRaw>() as u64;
+                for (i,&id) in self.sel_boxes.iter().enumerate() {
+                    let m=self.assets.mesh_of(id,0);
+                    rpass.set_vertex_buffer(0,m.vertex_buffer.slice(..));
+                    rpass.set_index_buffer(m.index_buffer.slice(..),m.index_format);
+                    rpass.set_vertex_buffer(1,self.buf_selection.slice(i as u64*stride..));
+                    rpass.draw_indexed(0..m.index_count,0,0..1);
+                    stats.draw_calls+=1;
+                    stats.instances+=1;
+                    stats.triangles+=(m.index_count/3) as u64;
+                }
             }
+
+            self.lines.draw(&mut rpass,&self.camera_bg);
+            self.gizmo.draw(&mut rpass,self.config.width,self.config.height);
         }
 
+        if let Some(t)=&self.tonemap { t.draw(&mut encoder,&view); }
+
+        if let Some(t)=self.gpu_timer.as_mut() { t.resolve(&mut encoder); }
         self.queue.submit(Some(encoder.finish()));
-        frame.present();
+        stats.encode_ms=t0.elapsed().as_secs_f32()*1000.0;
+        if let Some(f)=frame { f.present(); }
+        if let Some(t)=self.gpu_timer.as_mut() {
+            t.after_submit(&self.device);
+            stats.gpu_ms = t.last_ms;
+        }
+        self.record_frame(stats);
         Ok(())
     }
 }
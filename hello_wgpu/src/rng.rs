@@ -0,0 +1,26 @@
+//! The one seedable RNG and integer hash behind every generated city.
+//! Worlds are rebuilt from their seed, so both are pinned by `tests/rng.rs`:
+//! a change here moves every building.
+
+/// xorshift64 (13, 7, 17).  Not for anything security-related.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Any seed works; the low bit is forced so the state is never zero.
+    pub fn new(seed: u64) -> Self { Self(seed | 1) }
+
+    pub fn next_u64(&mut self) -> u64 { let mut x=self.0; x^=x<<13; x^=x>>7; x^=x<<17; self.0=x; x }
+
+    /// Uniform in `[0, 1]`.
+    pub fn next_f32(&mut self) -> f32 { (self.next_u64() as f64 / u64::MAX as f64) as f32 }
+}
+
+/// Mix two integers (chunk/lattice coordinates) into a well-spread 64-bit
+/// value (splitmix64 finaliser).
+pub fn hash2(a: i32, b: i32) -> u64 {
+    let mut x = (a as i64 as i128) as u128 ^ (((b as i64 as i128) << 1) as u128) ^ 0x9E37_79B9_7F4A_7C15u128;
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9u128);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EBu128);
+    (x ^ (x >> 31)) as u64
+}
@@ -0,0 +1,214 @@
+//! Directional-light shadow map: an orthographic depth render from the key
+//! light, fitted around the camera each frame, sampled with 3×3 PCF in
+//! `fs_main`.  The depth pass reuses `vs_main`/`vs_baked` with the light's
+//! VP bound as the group-0 camera, so it shares every instance buffer; the
+//! palette group rides along because `vs_main` reads the impostor frames.
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3};
+
+use crate::mesh;
+use crate::types::instance_buffer_layout;
+
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// wgpu clip space has z in 0..1; cgmath's projections produce -1..1.
+pub const OPENGL_TO_WGPU: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GpuShadow {
+    light_vp: [[f32; 4]; 4],
+    params:   [f32; 4],   // x=texel (uv)  y=depth bias  z=enabled  w unused
+}
+
+pub struct ShadowMap {
+    pub size:    u32,
+    pub extent:  f32,   // half-width of the light frustum (m)
+    pub bias:    f32,
+    pub enabled: bool,
+
+    pub bgl: wgpu::BindGroupLayout,   // group 2 of the main pipeline
+    pub bg:  wgpu::BindGroup,
+    uniform_buf: wgpu::Buffer,
+    view:    wgpu::TextureView,
+    sampler: wgpu::Sampler,
+
+    // depth pass: light VP bound with the camera layout
+    light_cam_buf: wgpu::Buffer,
+    pub light_cam_bg: wgpu::BindGroup,
+    pub pipeline: wgpu::RenderPipeline,
+    pub baked_pipeline: wgpu::RenderPipeline,
+}
+
+fn depth_target(device: &wgpu::Device, size: u32) -> wgpu::TextureView {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("shadow map"),
+        size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2,
+        format: SHADOW_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    }).create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn make_bg(device: &wgpu::Device, bgl: &wgpu::BindGroupLayout, ubuf: &wgpu::Buffer,
+           view: &wgpu::TextureView, sampler: &wgpu::Sampler) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("shadow bg"),
+        layout: bgl,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: ubuf.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(view) },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+        ],
+    })
+}
+
+fn depth_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule,
+                  entry: &str, label: &str) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some(entry),
+            compilation_options: Default::default(),
+            buffers: &[mesh::Vertex::layout(), instance_buffer_layout()],
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: SHADOW_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState { constant: 2, slope_scale: 2.0, clamp: 0.0 },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+impl ShadowMap {
+    pub fn new(device: &wgpu::Device, shader: &wgpu::ShaderModule,
+               camera_bgl: &wgpu::BindGroupLayout, palette_bgl: &wgpu::BindGroupLayout, size: u32) -> Self {
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow bgl"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<GpuShadow>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow uniform"),
+            size: std::mem::size_of::<GpuShadow>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let size = size.max(1);
+        let view = depth_target(device, size);
+        let bg = make_bg(device, &bgl, &uniform_buf, &view, &sampler);
+
+        let light_cam_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("light cam buf"), size: 256,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let light_cam_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light cam bg"),
+            layout: camera_bgl,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: light_cam_buf.as_entire_binding() }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("shadow pipe layout"),
+            bind_group_layouts: &[camera_bgl, palette_bgl],
+            push_constant_ranges: &[],
+        });
+        let pipeline       = depth_pipeline(device, &layout, shader, "vs_main",  "shadow pipe");
+        let baked_pipeline = depth_pipeline(device, &layout, shader, "vs_baked", "shadow baked pipe");
+
+        Self {
+            size, extent: 200.0, bias: 0.002, enabled: true,
+            bgl, bg, uniform_buf, view, sampler,
+            light_cam_buf, light_cam_bg, pipeline, baked_pipeline,
+        }
+    }
+
+    #[inline] pub fn view(&self) -> &wgpu::TextureView { &self.view }
+
+    /// Change the shadow-map resolution (texture + bind group are rebuilt).
+    pub fn set_size(&mut self, device: &wgpu::Device, size: u32) {
+        let size = size.max(1);
+        if size == self.size { return; }
+        self.size = size;
+        self.view = depth_target(device, size);
+        self.bg = make_bg(device, &self.bgl, &self.uniform_buf, &self.view, &self.sampler);
+    }
+
+    /// Fit the orthographic light frustum (half-width `extent`) around `center`
+    /// and upload both the depth-pass VP and the sampling uniform.
+    pub fn fit(&self, queue: &wgpu::Queue, center: Vector3<f32>, to_light: Vector3<f32>) {
+        let r = self.extent.max(1.0);
+        // snap to the texel grid so the map doesn't shimmer while moving
+        let texel = 2.0 * r / self.size as f32;
+        let c = Vector3::new((center.x / texel).floor() * texel, 0.0, (center.z / texel).floor() * texel);
+        let dir = to_light.normalize();
+        let up = if dir.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+        let depth = r + 200.0;
+        let view = Matrix4::look_at_rh(Point3::from_vec(c + dir * depth), Point3::from_vec(c), up);
+        let proj = cgmath::ortho(-r, r, -r, r, 0.1, depth * 2.0);
+        let vp = OPENGL_TO_WGPU * proj * view;
+
+        let data = GpuShadow {
+            light_vp: vp.into(),
+            params: [1.0 / self.size as f32, self.bias, if self.enabled { 1.0 } else { 0.0 }, 0.0],
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&data));
+        queue.write_buffer(&self.light_cam_buf, 0, bytemuck::bytes_of(&data.light_vp));
+    }
+
+    /// Free the map and its uniforms now (`Engine::destroy`).
+    pub fn destroy(&self) {
+        self.view.texture().destroy();
+        self.uniform_buf.destroy();
+        self.light_cam_buf.destroy();
+    }
+}
@@ -0,0 +1,187 @@
+//! Chimney smoke: a fixed pool of CPU-simulated particles rising from the
+//! roofs of landmarks and of buildings in industrial quarters, drawn as
+//! soft camera-facing billboard quads after the blended billboards (see
+//! `vs_smoke` / `fs_smoke`).  Emitters are picked each frame from the
+//! loaded chunks near the camera, by a hash of the roof's design-space
+//! position, so the same chimneys smoke across floating-origin shifts; the
+//! particles themselves live in local space and follow `shift`.
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::assets::{AssetLibrary, BuildingCategory};
+use crate::chunking::{chunk_world_span, ChunkKey, ChunkManager};
+use crate::designer_ml::District;
+use crate::mesh::{BILLBOARD_H, BILLBOARD_W};
+use crate::rng::{hash2, Rng};
+use crate::types::InstanceRaw;
+
+#[derive(Clone, Debug)]
+pub struct SmokeParams {
+    /// Off by default; off, `update` drops every particle.
+    pub enabled: bool,
+    /// Share (0–1) of the eligible buildings that smoke.
+    pub density: f32,
+    /// Caps: emitters (the nearest win) and live particles.
+    pub max_emitters: usize,
+    pub max_particles: usize,
+    /// Only buildings within this many metres of the camera smoke.
+    pub radius: f32,
+    /// Particles per second per emitter, and how long each lives (s).
+    pub rate: f32,
+    pub lifetime: f32,
+    /// Rise speed (m/s), sideways drift (m/s, xz) and puff size (m) at
+    /// birth; puffs grow by `growth` × their birth size over their life.
+    pub rise: f32,
+    pub wind: [f32; 2],
+    pub size: f32,
+    pub growth: f32,
+}
+
+impl Default for SmokeParams {
+    fn default() -> Self {
+        Self {
+            enabled: false, density: 0.3, max_emitters: 24, max_particles: 768,
+            radius: 250.0, rate: 4.0, lifetime: 6.0,
+            rise: 2.5, wind: [0.6, 0.2], size: 1.5, growth: 2.0,
+        }
+    }
+}
+
+impl SmokeParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.density) { return Err(format!("smoke density must be in 0..=1 (got {})", self.density)); }
+        for (name, v) in [("radius", self.radius), ("rate", self.rate), ("lifetime", self.lifetime), ("size", self.size)] {
+            if !(v.is_finite() && v > 0.0) { return Err(format!("smoke {name} must be positive (got {v})")); }
+        }
+        if !(self.rise.is_finite() && self.growth.is_finite() && self.growth >= 0.0 && self.wind.iter().all(|w| w.is_finite())) {
+            return Err("smoke rise, wind and growth must be finite (growth not negative)".into());
+        }
+        Ok(())
+    }
+}
+
+/// Whether the roof at design-space `(x, z)` smokes at `density`.  Hashed
+/// per metre cell (floor, as lot centres tend to sit on half metres).
+pub fn smokes(x: f32, z: f32, density: f32) -> bool {
+    let h = hash2(x.floor() as i32, z.floor() as i32 ^ 0x0053_104E);
+    ((h >> 40) as f32 / (1u64 << 24) as f32) < density
+}
+
+/// Roof tops (local space) of the buildings that smoke near `cam`, nearest
+/// first, at most `p.max_emitters`.  Only the chunks overlapping `p.radius`
+/// are visited, however many are loaded.
+pub fn smoke_emitters(cm: &ChunkManager, assets: &AssetLibrary, cam: Vector3<f32>, p: &SmokeParams) -> Vec<Vector3<f32>> {
+    let shift = cm.origin_shift();
+    let (cw, cd) = chunk_world_span(&cm.params);
+    let (minx, maxx, minz, maxz) = cm.bounds;
+    // no wider than the world, so a huge radius can't explode the walk
+    let rx = ((p.radius / cw).ceil() as i32).min(maxx - minx + 1);
+    let rz = ((p.radius / cd).ceil() as i32).min(maxz - minz + 1);
+    let mut keys: Vec<ChunkKey> = (-rz..=rz)
+        .flat_map(|dz| (-rx..=rx).map(move |dx| (dx, dz)))
+        .filter_map(|(dx, dz)| cm.chunk_at(cam.x + dx as f32 * cw, cam.z + dz as f32 * cd))
+        .collect();
+    keys.sort_by_key(|k| (k.0, k.1));
+    keys.dedup(); // a wrapped world smaller than the radius repeats keys
+    let mut out: Vec<(f32, Vector3<f32>)> = Vec::new();
+    for key in keys {
+        let Some(list) = cm.loaded.get(&key) else { continue };
+        if !cm.shows(key) { continue; }
+        for pl in list {
+            let (dx, dz) = (pl.center.x - cam.x, pl.center.z - cam.z);
+            let d2 = dx * dx + dz * dz;
+            if d2 > p.radius * p.radius { continue; }
+            let (wx, wz) = (pl.center.x + shift.x, pl.center.z + shift.z);
+            let eligible = assets.category_of(pl.archetype_id as usize) == BuildingCategory::Landmark
+                || cm.districts.sample(cm.params.seed, wx, wz) == District::Industrial;
+            if !eligible || !smokes(wx, wz, p.density) { continue; }
+            let top = pl.boxes().map(|(c, s, id)| c.y + assets.base_half(id as usize).y * s.y).fold(f32::MIN, f32::max);
+            out.push((d2, Vector3::new(pl.center.x, top, pl.center.z)));
+        }
+    }
+    // ties broken by position, so the cut doesn't follow HashMap order
+    out.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.x.total_cmp(&b.1.x)).then(a.1.z.total_cmp(&b.1.z)));
+    out.truncate(p.max_emitters);
+    out.into_iter().map(|(_, v)| v).collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub pos: Vector3<f32>,
+    pub vel: Vector3<f32>,
+    pub age: f32,
+    pub seed: f32,
+}
+
+pub struct SmokeSystem {
+    pub params: SmokeParams,
+    particles: Vec<Particle>,
+    spawn_acc: f32,
+    rng: Rng,
+}
+
+impl SmokeSystem {
+    pub fn new(params: SmokeParams) -> Self {
+        let cap = params.max_particles;
+        Self { params, particles: Vec::with_capacity(cap), spawn_acc: 0.0, rng: Rng::new(0x0005_30CE) }
+    }
+
+    pub fn particles(&self) -> &[Particle] { &self.particles }
+
+    /// Age, move and retire particles, then spawn `rate` per second per
+    /// emitter (round-robin from a random start) while the pool has room.
+    pub fn update(&mut self, dt: f32, emitters: &[Vector3<f32>]) {
+        let p = &self.params;
+        if !p.enabled {
+            self.particles.clear();
+            self.spawn_acc = 0.0;
+            return;
+        }
+        let life = p.lifetime;
+        self.particles.retain_mut(|q| {
+            q.age += dt;
+            q.pos += q.vel * dt;
+            q.age < life
+        });
+        self.particles.truncate(p.max_particles);
+        if emitters.is_empty() { self.spawn_acc = 0.0; return; }
+        self.spawn_acc += p.rate * emitters.len() as f32 * dt;
+        let room = p.max_particles - self.particles.len();
+        let n = (self.spawn_acc as usize).min(room);
+        self.spawn_acc -= self.spawn_acc.floor();
+        let start = self.rng.next_u64() as usize;
+        for i in 0..n {
+            let e = emitters[(start + i) % emitters.len()];
+            let mut j = || self.rng.next_f32() - 0.5;
+            let pos = e + Vector3::new(j() * 0.6, 0.0, j() * 0.6);
+            let vel = Vector3::new(p.wind[0] + j() * 0.4, p.rise * (0.8 + j() * 0.4), p.wind[1] + j() * 0.4);
+            // a little pre-aged so one frame's batch doesn't rise as a slab
+            let age = (j() + 0.5) * dt;
+            self.particles.push(Particle { pos: pos + vel * age, vel, age, seed: (self.rng.next_u64() >> 40) as f32 });
+        }
+    }
+
+    /// Follow a floating-origin shift (`ChunkManager::apply_shift`).
+    pub fn shift(&mut self, off: Vector3<f32>) {
+        for q in &mut self.particles { q.pos -= off; }
+    }
+
+    /// One billboard instance per particle, farthest from `cam` first (they
+    /// are alpha-blended): `scale` makes the quad `size` m square, `misc.x`
+    /// is the opacity, `misc.w` a per-puff seed.
+    pub fn instances(&self, cam: Vector3<f32>) -> Vec<InstanceRaw> {
+        let p = &self.params;
+        let mut order: Vec<(f32, &Particle)> = self.particles.iter().map(|q| ((q.pos - cam).magnitude2(), q)).collect();
+        order.sort_by(|a, b| b.0.total_cmp(&a.0));
+        order.into_iter().map(|(_, q)| {
+            let t = (q.age / p.lifetime).clamp(0.0, 1.0);
+            let size = p.size * (1.0 + p.growth * t);
+            let alpha = (t / 0.15).min(1.0) * (1.0 - t);
+            InstanceRaw {
+                pos: [q.pos.x, q.pos.y, q.pos.z, 0.0],
+                scale: [size / BILLBOARD_W, size / BILLBOARD_H, 1.0, 0.0],
+                misc: [alpha, t, 0.0, q.seed],
+            }
+        }).collect()
+    }
+}
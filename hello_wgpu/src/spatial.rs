@@ -0,0 +1,72 @@
+//! Per-chunk uniform grid over placement centres, so point / radius / ray
+//! queries only touch the cells they overlap instead of the whole chunk.
+//! Cells are one lot pitch wide (about one building each); the grid lives in
+//! design space, so floating-origin shifts don't invalidate it.  Mutations
+//! only change scale and archetype, never `center.xz`; adding or removing a
+//! building rebuilds the chunk's grid.
+
+/// Compressed cell lists: `items[starts[c]..starts[c + 1]]` are the
+/// placement indices whose centre falls in cell `c` (row-major, x fastest).
+#[derive(Clone, Debug)]
+pub struct ChunkGrid {
+    origin: (f32, f32), // design-space min corner (x, z)
+    cell:   f32,
+    nx: usize,
+    nz: usize,
+    starts: Vec<u32>,
+    items:  Vec<u32>,
+}
+
+impl ChunkGrid {
+    /// Bucket `centers` (design-space `(x, z)`, in placement order) over the
+    /// `size` rectangle starting at `origin`.  Centres outside it go to the
+    /// nearest border cell.
+    pub fn build(origin: (f32, f32), size: (f32, f32), cell: f32, centers: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        let cell = cell.max(1e-3);
+        let nx = ((size.0 / cell).ceil() as usize).max(1);
+        let nz = ((size.1 / cell).ceil() as usize).max(1);
+        let mut grid = Self { origin, cell, nx, nz, starts: vec![0; nx * nz + 1], items: Vec::new() };
+
+        let cells: Vec<usize> = centers.into_iter().map(|(x, z)| grid.cell_of(x, z)).collect();
+        for &c in &cells { grid.starts[c + 1] += 1; }
+        for c in 0..nx * nz { grid.starts[c + 1] += grid.starts[c]; }
+        let mut fill = grid.starts.clone();
+        grid.items = vec![0; cells.len()];
+        for (i, &c) in cells.iter().enumerate() {
+            grid.items[fill[c] as usize] = i as u32;
+            fill[c] += 1;
+        }
+        grid
+    }
+
+    /// Number of placements the grid was built from.
+    #[inline]
+    pub fn len(&self) -> usize { self.items.len() }
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+
+    /// Heap footprint (bytes), for `WorldStats`.
+    pub fn heap_bytes(&self) -> usize {
+        (self.starts.capacity() + self.items.capacity()) * std::mem::size_of::<u32>()
+    }
+
+    fn axis(&self, v: f32, o: f32, n: usize) -> usize {
+        (((v - o) / self.cell).floor().max(0.0) as usize).min(n - 1)
+    }
+
+    fn cell_of(&self, x: f32, z: f32) -> usize {
+        self.axis(z, self.origin.1, self.nz) * self.nx + self.axis(x, self.origin.0, self.nx)
+    }
+
+    /// Indices of every placement whose cell overlaps the design-space
+    /// rectangle `[min, max]` (a superset of those inside it).
+    pub fn query_rect(&self, min: (f32, f32), max: (f32, f32)) -> impl Iterator<Item = usize> + '_ {
+        let (x0, x1) = (self.axis(min.0, self.origin.0, self.nx), self.axis(max.0, self.origin.0, self.nx));
+        let (z0, z1) = (self.axis(min.1, self.origin.1, self.nz), self.axis(max.1, self.origin.1, self.nz));
+        (z0..=z1).flat_map(move |z| {
+            let row = z * self.nx;
+            let span = self.starts[row + x0] as usize..self.starts[row + x1 + 1] as usize;
+            self.items[span].iter().map(|&i| i as usize)
+        })
+    }
+}
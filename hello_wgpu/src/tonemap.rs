@@ -0,0 +1,152 @@
+//! HDR output: the scene passes render linear light into a float target
+//! (`HDR_FORMAT`), which one full-screen pass tone-maps (ACES, scaled by the
+//! exposure) into the frame, gamma-encoding when the frame format isn't sRGB.
+//! Without a usable float format the engine renders straight into the frame.
+
+/// Offscreen scene format when HDR is on.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Frame formats the tone-map pass must gamma-encode itself: linear 8/10-bit
+/// unorm (e.g. `Bgra8Unorm` on the web); sRGB and float frames need nothing.
+pub fn needs_gamma(format: wgpu::TextureFormat) -> bool {
+    !format.is_srgb() && !matches!(format, wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float)
+}
+
+/// Whether `adapter` can render, multisample (when `samples > 1`) and
+/// sample `HDR_FORMAT`.
+pub fn hdr_supported(adapter: &wgpu::Adapter, samples: u32) -> bool {
+    let f = adapter.get_texture_format_features(HDR_FORMAT);
+    let usages = f.allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING);
+    usages && (samples == 1 || f.flags.sample_count_supported(samples))
+}
+
+pub struct ToneMap {
+    pipeline: wgpu::RenderPipeline,
+    bgl:  wgpu::BindGroupLayout,
+    ubuf: wgpu::Buffer,
+    view: wgpu::TextureView,
+    bg:   wgpu::BindGroup,
+}
+
+impl ToneMap {
+    /// Pipeline writing `out_format`, plus a `width`×`height` scene target.
+    pub fn new(device: &wgpu::Device, out_format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("tonemap shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/tonemap.wgsl").into()),
+        });
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tonemap bgl"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(16),
+                },
+                count: None,
+            }],
+        });
+        let ubuf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tonemap uniform"), size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("tonemap pipe layout"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("tonemap pipe"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_tonemap"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_tonemap"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: out_format, blend: None, write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let (view, bg) = Self::target(device, &bgl, &ubuf, width, height);
+        Self { pipeline, bgl, ubuf, view, bg }
+    }
+
+    fn target(device: &wgpu::Device, bgl: &wgpu::BindGroupLayout, ubuf: &wgpu::Buffer, width: u32, height: u32)
+        -> (wgpu::TextureView, wgpu::BindGroup) {
+        let view = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("hdr scene"), size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default());
+        let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap bg"),
+            layout: bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: ubuf.as_entire_binding() },
+            ],
+        });
+        (view, bg)
+    }
+
+    /// Recreate the scene target for a new frame size.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.view, self.bg) = Self::target(device, &self.bgl, &self.ubuf, width, height);
+    }
+
+    /// Upload the exposure (linear scale before the curve) and the frame's gamma need.
+    pub fn update(&self, queue: &wgpu::Queue, exposure: f32, out_format: wgpu::TextureFormat) {
+        let gamma = if needs_gamma(out_format) { 1.0 } else { 0.0 };
+        queue.write_buffer(&self.ubuf, 0, bytemuck::bytes_of(&[exposure, gamma, 0.0, 0.0]));
+    }
+
+    /// The single-sampled scene target (MSAA resolves into it).
+    pub fn view(&self) -> &wgpu::TextureView { &self.view }
+
+    /// Free the scene target and uniform now (`Engine::destroy`).
+    pub fn destroy(&self) {
+        self.view.texture().destroy();
+        self.ubuf.destroy();
+    }
+
+    /// Tone-map the scene target into `out`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, out: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("tonemap pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: out, depth_slice: None, resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None, occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bg, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
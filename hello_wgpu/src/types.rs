@@ -4,18 +4,28 @@ use bytemuck::{Pod, Zeroable};
 #[derive(Copy, Clone, Pod, Zeroable)]
 pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
+    /// .xyz = eye position (impostors face it), .w = impostors on (0/1).
+    pub eye: [f32; 4],
 }
 
 /// Compact instance: world center + non-uniform scale.
 /// Rotation is omitted (axis-aligned buildings); add a yaw later if needed.
+/// LOD2 billboards are stretched to the building, so they keep its own
+/// scale for the impostor quad in pos.w (x), scale.w (y) and scale.z (z).
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
-    pub pos:   [f32; 4], // w unused
-    pub scale: [f32; 4], // w unused
-    pub misc:  [f32; 4], // x=categoryIdx(0/1/2)  y=archetypeId  z unused
+    pub pos:   [f32; 4], // w = building scale.x on LOD2, else unused
+    pub scale: [f32; 4], // w = building scale.y on LOD2, else unused
+    pub misc:  [f32; 4], // x=tint code (TINT_*)  y=archetypeId  z=facade layer  w=window seed
 }
 
+// `misc.x` palette codes, matched by `fs_main`
+pub const TINT_LOWRISE:  f32 = 0.0;
+pub const TINT_HIGHRISE: f32 = 1.0;
+pub const TINT_LANDMARK: f32 = 2.0;
+pub const TINT_GROUND:   f32 = 3.0;
+
 pub const fn instance_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     use wgpu::{VertexAttribute, VertexFormat::*};
     wgpu::VertexBufferLayout {
@@ -24,7 +34,9 @@ pub const fn instance_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
         attributes: &[
             VertexAttribute { shader_location: 2, offset: 0,  format: Float32x3 }, // pos.xyz
             VertexAttribute { shader_location: 3, offset: 16, format: Float32x3 }, // scale.xyz
-            VertexAttribute { shader_location: 4, offset: 32, format: Float32x3 }, // misc.xyz
+            VertexAttribute { shader_location: 4, offset: 32, format: Float32x4 }, // misc
+            VertexAttribute { shader_location: 7, offset: 12, format: Float32 },   // pos.w
+            VertexAttribute { shader_location: 8, offset: 28, format: Float32 },   // scale.w
         ],
     }
 }
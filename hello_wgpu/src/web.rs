@@ -0,0 +1,213 @@
+//! JavaScript-facing API of the wasm build.  By default `start()` runs the
+//! demo city as soon as the module loads; a page that wants its own city
+//! marks the canvas `<canvas id="wasm-canvas" data-manual-start>` and calls
+//! `run_city(new CityConfig())` after adjusting the fields (city layout
+//! and world bounds).
+//!
+//! `set_mutation_callback(f)` makes the engine call `f(cx, cz, index)` for
+//! buildings edited by live or network mutations.
+
+use std::cell::{Cell, RefCell};
+
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use crate::chunking::{ChunkKey, CityGenParams};
+use crate::hello_wgpu::{run_with, EngineConfig};
+
+/// `CityGenParams` as seen from JS, plus the world's chunk bounds and
+/// wrap (`EngineConfig::bounds` / `wrap`); `new CityConfig()` holds the
+/// demo city.
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug)]
+pub struct CityConfig {
+    pub seed: u64,
+    pub lots_x: u32,
+    pub lots_z: u32,
+    pub lot_w: f32,
+    pub lot_d: f32,
+    pub lot_gap: f32,
+    pub road_w_minor: f32,
+    pub road_w_major: f32,
+    pub major_every: u32,
+    pub blocks_per_chunk_x: u32,
+    pub blocks_per_chunk_z: u32,
+    pub min_cx: i32,
+    pub max_cx: i32,
+    pub min_cz: i32,
+    pub max_cz: i32,
+    pub wrap: bool,
+}
+
+#[wasm_bindgen]
+impl CityConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CityConfig { CityGenParams::default().into() }
+
+    /// Same check `run_city` applies; throws with the first problem found.
+    pub fn validate(&self) -> Result<(), JsValue> {
+        self.engine_config().validate().map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+impl CityConfig {
+    fn engine_config(&self) -> EngineConfig {
+        EngineConfig {
+            city: (*self).into(),
+            bounds: (self.min_cx, self.max_cx, self.min_cz, self.max_cz), wrap: self.wrap,
+            ..EngineConfig::default()
+        }
+    }
+}
+
+impl Default for CityConfig {
+    fn default() -> Self { Self::new() }
+}
+
+impl From<CityGenParams> for CityConfig {
+    fn from(p: CityGenParams) -> Self {
+        // in the default world
+        let EngineConfig { bounds: (min_cx, max_cx, min_cz, max_cz), wrap, .. } = EngineConfig::default();
+        Self {
+            seed: p.seed,
+            lots_x: p.lots_x as u32, lots_z: p.lots_z as u32,
+            lot_w: p.lot_w, lot_d: p.lot_d, lot_gap: p.lot_gap,
+            road_w_minor: p.road_w_minor, road_w_major: p.road_w_major, major_every: p.major_every as u32,
+            blocks_per_chunk_x: p.blocks_per_chunk_x as u32, blocks_per_chunk_z: p.blocks_per_chunk_z as u32,
+            min_cx, max_cx, min_cz, max_cz, wrap,
+        }
+    }
+}
+
+impl From<CityConfig> for CityGenParams {
+    fn from(c: CityConfig) -> Self {
+        Self {
+            lots_x: c.lots_x as usize, lots_z: c.lots_z as usize,
+            lot_w: c.lot_w, lot_d: c.lot_d, lot_gap: c.lot_gap,
+            road_w_minor: c.road_w_minor, road_w_major: c.road_w_major, major_every: c.major_every as usize,
+            blocks_per_chunk_x: c.blocks_per_chunk_x as usize, blocks_per_chunk_z: c.blocks_per_chunk_z as usize,
+            seed: c.seed,
+        }
+    }
+}
+
+/// Run the engine on `config`'s city; rejects invalid parameters before
+/// anything starts.
+#[wasm_bindgen]
+pub async fn run_city(config: CityConfig) -> Result<(), JsValue> {
+    config.validate()?;
+    run_with(true, config.engine_config()).await;
+    Ok(())
+}
+
+/// Has the page asked to start the engine itself (see module docs)?
+pub(crate) fn manual_start() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id("wasm-canvas"))
+        .is_some_and(|c| c.has_attribute("data-manual-start"))
+}
+
+// ---------- mutation events ----------
+
+/// At most this many mutation callbacks per frame; the rest of the frame's
+/// events are dropped so a high mutation rate can't flood the JS boundary.
+pub const MAX_MUTATION_EVENTS_PER_FRAME: usize = 32;
+
+thread_local! {
+    static MUTATION_CB: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static PENDING: RefCell<Vec<(ChunkKey, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// JS: `f(cx, cz, index)` per mutated building (throttled per frame);
+/// `undefined`/`null` unsubscribes.
+#[wasm_bindgen]
+pub fn set_mutation_callback(f: Option<js_sys::Function>) {
+    if f.is_none() { PENDING.with_borrow_mut(Vec::clear); }
+    MUTATION_CB.set(f);
+}
+
+/// `ChunkManager::on_mutated` hook: queue until the end of the frame.
+pub(crate) fn queue_mutation(key: ChunkKey, idx: usize) {
+    if !MUTATION_CB.with_borrow(Option::is_some) { return; }
+    PENDING.with_borrow_mut(|p| if p.len() < MAX_MUTATION_EVENTS_PER_FRAME { p.push((key, idx)); });
+}
+
+/// Deliver the frame's queued events (once per frame).
+pub(crate) fn flush_mutation_events() {
+    let events = PENDING.with_borrow_mut(std::mem::take);
+    if events.is_empty() { return; }
+    MUTATION_CB.with_borrow(|cb| {
+        let Some(cb) = cb else { return };
+        for (key, idx) in events {
+            let args = (JsValue::from(key.0), JsValue::from(key.1), JsValue::from(idx as u32));
+            if let Err(e) = cb.call3(&JsValue::NULL, &args.0, &args.1, &args.2) {
+                log::warn!("mutation callback threw: {e:?}");
+                break;
+            }
+        }
+    });
+}
+
+// ---------- focus ----------
+
+thread_local! {
+    static BLURRED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Note `blur` on the page's window: switching tabs or apps leaves keys
+/// held without a keyup, and the canvas doesn't always see `Focused(false)`.
+pub(crate) fn watch_blur() {
+    let Some(win) = web_sys::window() else { return };
+    let on_blur = Closure::<dyn FnMut()>::new(|| BLURRED.set(true));
+    if win.add_event_listener_with_callback("blur", on_blur.as_ref().unchecked_ref()).is_ok() {
+        on_blur.forget(); // lives as long as the page
+    }
+}
+
+/// Has the window been blurred since the last call?
+pub(crate) fn take_blur() -> bool { BLURRED.replace(false) }
+
+// ---------- pointer lock ----------
+
+thread_local! {
+    static LOCK_LOST: Cell<bool> = const { Cell::new(false) };
+    static LOCK_WATCHED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Ask for pointer lock on `cv` (needs the click or key press being
+/// handled), or give it up.  The browser may refuse, and drops the lock
+/// itself on Esc; both are reported by `take_lock_lost`.
+pub(crate) fn set_pointer_lock(cv: &HtmlCanvasElement, on: bool) {
+    let Some(doc) = web_sys::window().and_then(|w| w.document()) else { return };
+    if !on {
+        doc.exit_pointer_lock();
+        return;
+    }
+    if !LOCK_WATCHED.replace(true) {
+        let lost = Closure::<dyn FnMut()>::new(|| {
+            let locked = web_sys::window().and_then(|w| w.document()).and_then(|d| d.pointer_lock_element()).is_some();
+            if !locked { LOCK_LOST.set(true); }
+        });
+        for ev in ["pointerlockchange", "pointerlockerror"] {
+            let _ = doc.add_event_listener_with_callback(ev, lost.as_ref().unchecked_ref());
+        }
+        lost.forget(); // lives as long as the page
+    }
+    LOCK_LOST.set(false);
+    cv.request_pointer_lock();
+}
+
+/// Has pointer lock been refused or ended since the last call?
+pub(crate) fn take_lock_lost() -> bool { LOCK_LOST.replace(false) }
+
+// ---------- device pixel ratio ----------
+
+/// Backing-store size of `cv`: its CSS size at the page's
+/// `devicePixelRatio`, or `fallback` while it has no layout yet.
+pub(crate) fn backing_size(cv: &HtmlCanvasElement, fallback: (u32, u32)) -> (u32, u32) {
+    let (cw, ch) = (cv.client_width(), cv.client_height());
+    if cw <= 0 || ch <= 0 { return fallback; }
+    let dpr = web_sys::window().map_or(1.0, |w| w.device_pixel_ratio());
+    ((cw as f64 * dpr).round() as u32, (ch as f64 * dpr).round() as u32)
+}
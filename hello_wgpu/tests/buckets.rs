@@ -0,0 +1,252 @@
+//! Instance bucketing (`culling::bucket_instances`): LOD bands and overrides,
+//! archetype groups, frustum / distance culling, LOD hysteresis, baked chunks, `mesh_cull`,
+//! and the CSV dump.
+
+mod common;
+
+use cgmath::{Deg, Matrix4, Point3, Vector3, perspective};
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::culling::{bucket_instances, frustum_from_vp, lod_rings, ChunkCullCache, Frustum, LodHysteresis};
+use hello_wgpu::hello_wgpu::build_instance_buckets;
+use hello_wgpu::types::InstanceRaw;
+
+const LOD0: f32 = 90.0;
+const LOD1: f32 = 190.0;
+const CULL: f32 = 380.0;
+const CAM: Vector3<f32> = Vector3::new(0.0, 5.0, 0.0);
+
+/// Camera at (0, 5, 0) looking down +Z.
+fn frustum() -> Frustum {
+    let eye = Point3::new(0.0, 5.0, 0.0);
+    let view = Matrix4::look_at_rh(eye, Point3::new(0.0, 5.0, 1.0), Vector3::unit_y());
+    frustum_from_vp(&(perspective(Deg(60.0), 1.0, 0.1, 1000.0) * view))
+}
+
+fn at(archetype_id: u16, z: f32) -> RuntimePlacement {
+    RuntimePlacement::single(Vector3::new(0.0, 1.0, z), Vector3::new(1.0, 1.0, 1.0), archetype_id)
+}
+
+fn z(v: &[InstanceRaw]) -> Vec<f32> { v.iter().map(|i| i.pos[2]).collect() }
+
+fn manager(placements: Vec<(ChunkKey, Vec<RuntimePlacement>)>) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(1), 1, (-4, 4, -4, 4), false, "unused");
+    cm.loaded.extend(placements);
+    cm
+}
+
+#[test]
+fn placements_land_in_their_lod_and_group() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let list = [
+        at(0, 20.0),    // low-rise, LOD0
+        at(1, 30.0),    // alt low-rise, LOD0
+        at(3, 120.0),   // high-rise, LOD1
+        at(6, 250.0),   // landmark, billboard
+        at(0, 500.0),   // past the cull distance
+    ];
+
+    let b = bucket_instances(&list, CAM, &frustum(), LOD0, LOD1, CULL, &assets);
+    assert_eq!(z(&b.v0_low_common), [20.0]);
+    assert_eq!(z(&b.v0_low_alt), [30.0]);
+    assert_eq!(z(&b.v1_high), [120.0]);
+    assert_eq!(z(&b.v2_bill), [250.0]);
+    for empty in [&b.v0_high, &b.v0_land, &b.v1_low_common, &b.v1_low_alt, &b.v1_land] {
+        assert!(empty.is_empty());
+    }
+}
+
+#[test]
+fn csv_dump_lists_every_instance_with_its_bucket() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let b = bucket_instances(&[at(1, 30.0), at(3, 120.0), at(6, 250.0)], CAM, &frustum(), LOD0, LOD1, CULL, &assets);
+    let csv = b.to_csv();
+    let rows: Vec<Vec<&str>> = csv.lines().skip(1).map(|l| l.split(',').collect()).collect();
+    assert_eq!(csv.lines().next(), Some("bucket,x,y,z,sx,sy,sz,category,archetype,layer"));
+    assert_eq!(rows.len(), b.total());
+    let picked: Vec<(&str, &str, &str, &str)> = rows.iter().map(|r| (r[0], r[3], r[7], r[8])).collect();
+    assert_eq!(picked, [("v0_low_alt", "30", "lowrise", "1"), ("v1_high", "120", "highrise", "3"),
+                        ("v2_bill", "250", "landmark", "6")]);
+}
+
+#[test]
+fn just_inside_lod0_and_just_past_lod1() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    // distances are measured from the camera to the building centre
+    let inside_lod0 = RuntimePlacement { center: CAM + Vector3::new(0.0, 0.0, LOD0 - 0.01), ..at(0, 0.0) };
+    let past_lod1   = RuntimePlacement { center: CAM + Vector3::new(0.0, 0.0, LOD1 + 0.01), ..at(0, 0.0) };
+
+    let b = bucket_instances([&inside_lod0, &past_lod1], CAM, &frustum(), LOD0, LOD1, CULL, &assets);
+    assert_eq!(b.v0_low_common.len(), 1);
+    assert!(b.v1_low_common.is_empty());
+    assert_eq!(z(&b.v2_bill), [CAM.z + LOD1 + 0.01]);
+    // billboards are stretched to the building and drop the facade layer
+    assert_eq!(b.v2_bill[0].misc[2], 0.0);
+}
+
+#[test]
+fn off_frustum_buildings_are_excluded() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let behind = at(0, -30.0);
+    let beside = RuntimePlacement { center: Vector3::new(60.0, 1.0, 5.0), ..at(3, 0.0) };
+
+    let b = bucket_instances([&behind, &beside], CAM, &frustum(), LOD0, LOD1, CULL, &assets);
+    assert!(b.v0_low_common.is_empty() && b.v0_high.is_empty() && b.v2_bill.is_empty());
+}
+
+#[test]
+fn far_chunks_are_drawn_baked() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let (_, span_z) = manager(Vec::new()).world_span();
+    let cd = span_z / 9.0;
+    let mut cm = manager(vec![
+        (ChunkKey(0, 0), vec![at(0, 20.0)]),
+        (ChunkKey(0, 1), vec![at(0, cd)]),
+    ]);
+    cm.bake_distance = 1.0;
+
+    let (b, baked_keys) = build_instance_buckets(&cm, &assets, &frustum(), CAM, (LOD0, LOD1), (CULL, CULL),
+                                                 &mut LodHysteresis::default(), &mut ChunkCullCache::default());
+    assert_eq!(baked_keys, [ChunkKey(0, 1)]);
+    assert_eq!(b.v0_low_common.len() + b.v1_low_common.len(), 1, "baked chunk adds no instances");
+}
+
+#[test]
+fn past_mesh_cull_only_billboards_remain() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let (_, span_z) = manager(Vec::new()).world_span();
+    let cd = span_z / 9.0;
+    let mut cm = manager(vec![
+        (ChunkKey(0, 0), vec![at(0, 20.0), at(3, 120.0)]),
+        (ChunkKey(0, 1), vec![at(0, cd)]),
+    ]);
+    cm.bake_distance = 1.0;
+    // closer than the baked chunk's near edge, and inside LOD1 for the z = 120 tower
+    let mesh_cull = (cd * 0.5 - 1.0).min(100.0);
+    assert!(mesh_cull > 20.0);
+
+    let (b, baked_keys) = build_instance_buckets(&cm, &assets, &frustum(), CAM, (LOD0, LOD1), (mesh_cull, CULL),
+                                                 &mut LodHysteresis::default(), &mut ChunkCullCache::default());
+    assert!(baked_keys.is_empty(), "no baked mesh past mesh_cull");
+    assert_eq!(z(&b.v0_low_common), [20.0]);
+    assert!(b.v1_high.is_empty());
+    let mut far = z(&b.v2_bill);
+    far.sort_by(f32::total_cmp); // chunks come in hash order
+    assert_eq!(far, [120.0f32.min(cd), 120.0f32.max(cd)], "the far buildings are billboards, not meshes");
+}
+
+#[test]
+fn lod_override_forces_one_bucket_but_still_culls() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let list = [at(0, 20.0), at(0, 120.0), at(0, 250.0), at(0, 500.0), at(0, -30.0)];
+    let visible = [20.0, 120.0, 250.0];
+
+    let bucket = |level| {
+        let (l0, l1) = lod_rings(Some(level), LOD0, LOD1, CULL);
+        bucket_instances(&list, CAM, &frustum(), l0, l1, CULL, &assets)
+    };
+    assert_eq!(z(&bucket(0).v0_low_common), visible);
+    assert_eq!(z(&bucket(1).v1_low_common), visible);
+    let b = bucket(2);
+    assert_eq!(z(&b.v2_bill), visible);
+    assert!(b.v0_low_common.is_empty() && b.v1_low_common.is_empty());
+
+    assert_eq!(lod_rings(None, LOD0, LOD1, CULL), (LOD0, LOD1));
+}
+
+#[test]
+fn hysteresis_holds_the_lod_of_a_bobbing_camera() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let key = ChunkKey(0, 0);
+    let list = [at(0, LOD0 + 1.0)];
+    let lod_at = |lod: &mut LodHysteresis, cam_z: f32| {
+        let cam = Vector3::new(0.0, 5.0, cam_z);
+        let b = lod.bucket([(key, false, &list[..])], cam, &frustum(), (LOD0, LOD1), CULL, &assets);
+        if b.v0_low_common.len() == 1 { 0 } else { assert_eq!(b.v1_low_common.len(), 1); 1 }
+    };
+
+    // ±2 m around the ring: without a margin it flips every frame
+    let bob = |i: usize| if i.is_multiple_of(2) { 3.0 } else { -1.0 };
+    let mut plain = LodHysteresis::new(0.0);
+    let flips: Vec<u8> = (0..8).map(|i| lod_at(&mut plain, bob(i))).collect();
+    assert_eq!(flips, [0, 1, 0, 1, 0, 1, 0, 1]);
+
+    let mut lod = LodHysteresis::new(8.0);
+    let held: Vec<u8> = (0..8).map(|i| lod_at(&mut lod, bob(i))).collect();
+    assert_eq!(held, [0; 8], "promoted once, never demoted inside the margin");
+    assert_eq!(lod.len(), 1);
+    // well past the margin it does drop
+    assert_eq!(lod_at(&mut lod, -10.0), 1);
+    assert_eq!(lod_at(&mut lod, -4.0), 1, "and stays down until back inside the ring");
+    assert_eq!(lod_at(&mut lod, 2.0), 0);
+
+    // a frame without the building forgets it
+    lod.bucket([(key, false, &[][..])], CAM, &frustum(), (LOD0, LOD1), CULL, &assets);
+    assert!(lod.is_empty());
+
+    // a negative margin is no margin, not a panic
+    assert_eq!(LodHysteresis::new(-3.0).margin, 0.0);
+    assert_eq!(LodHysteresis::new(f32::NAN).margin, 0.0);
+    lod.margin = -8.0;
+    let flips: Vec<u8> = (0..8).map(|i| lod_at(&mut lod, bob(i))).collect();
+    assert!(flips.iter().all(|&l| l <= 1), "{flips:?}");
+}
+
+#[test]
+fn instance_cap_keeps_the_nearest() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let list: Vec<RuntimePlacement> = [60.0, 10.0, 80.0, 30.0, 50.0, 20.0, 300.0, 200.0]
+        .into_iter().map(|d| at(0, d)).collect();
+
+    let mut b = bucket_instances(&list, CAM, &frustum(), LOD0, LOD1, CULL, &assets);
+    assert_eq!(b.total(), 8);
+    assert_eq!(b.cap_nearest(CAM, 3), 3, "three LOD0 dropped, billboards under the cap");
+    let mut near = z(&b.v0_low_common);
+    near.sort_by(f32::total_cmp);
+    assert_eq!(near, [10.0, 20.0, 30.0]);
+    assert_eq!(b.v2_bill.len(), 2);
+
+    assert_eq!(b.cap_nearest(CAM, 0), 5);
+    assert_eq!(b.total(), 0);
+}
+
+#[test]
+fn billboards_sort_far_to_near() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let list: Vec<RuntimePlacement> = [250.0, 340.0, 200.0, 300.0, 220.0].into_iter().map(|d| at(0, d)).collect();
+
+    let mut b = bucket_instances(&list, CAM, &frustum(), LOD0, LOD1, CULL, &assets);
+    b.sort_billboards_back_to_front(CAM);
+    assert_eq!(z(&b.v2_bill), [340.0, 300.0, 250.0, 220.0, 200.0]);
+
+    // seen from the far end the order flips
+    b.sort_billboards_back_to_front(Vector3::new(0.0, 5.0, 400.0));
+    assert_eq!(z(&b.v2_bill), [200.0, 220.0, 250.0, 300.0, 340.0]);
+}
+
+#[test]
+fn blended_billboards_draw_last() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = hello_wgpu::render::Engine::new_headless(device, queue, 64, 64);
+    let inst = InstanceRaw { pos: [0.0; 4], scale: [1.0, 1.0, 1.0, 0.0], misc: [0.0; 4] };
+    let two = [inst; 2];
+    engine.update_instances(&two, &[], &[], &[], &[], &[], &[], &[], &two, &inst);
+    engine.set_billboard_blend(true);
+    for prepass in [false, true] {
+        engine.set_depth_prepass(prepass);
+        engine.render().expect("frame");
+        let s = engine.frame_stats();
+        assert_eq!((s.draw_calls, s.instances), (3, 5), "prepass {prepass}");
+    }
+}
@@ -0,0 +1,67 @@
+//! Instance buffers give memory back after a dense area: the
+//! `ShrinkTracker` state machine and its effect on a headless engine.
+
+mod common;
+
+use hello_wgpu::render::{Engine, ShrinkPolicy, ShrinkTracker};
+use hello_wgpu::types::InstanceRaw;
+
+const POLICY: ShrinkPolicy = ShrinkPolicy { enabled: true, window: 4, below: 0.25 };
+
+/// Feed `counts` against a buffer of `capacity`, applying each shrink;
+/// returns the capacity after every frame.
+fn run(counts: &[usize], mut capacity: usize, policy: &ShrinkPolicy) -> Vec<usize> {
+    let mut t = ShrinkTracker::default();
+    counts.iter().map(|&n| {
+        if let Some(c) = t.observe(policy, n, capacity) { capacity = c; }
+        capacity
+    }).collect()
+}
+
+#[test]
+fn shrinks_to_the_window_peak_after_a_quiet_window() {
+    // 1000 → quiet at ≤ 100 for 4 frames → 1.5 × the peak (100)
+    assert_eq!(run(&[80, 100, 60, 90, 90], 1000, &POLICY), [1000, 1000, 1000, 150, 150]);
+}
+
+#[test]
+fn a_busy_frame_restarts_the_window() {
+    // 300 ≥ 0.25 × 1000 resets the run, so four more quiet frames are needed
+    assert_eq!(run(&[10, 10, 10, 300, 10, 10, 10, 10], 1000, &POLICY),
+               [1000, 1000, 1000, 1000, 1000, 1000, 1000, 15]);
+}
+
+#[test]
+fn hysteresis_keeps_a_shrunk_buffer_steady() {
+    // after shrinking to 150 a count of 100 is 2/3 full: no second shrink
+    let caps = run(&[100, 100, 100, 100, 100, 100, 100, 100, 100, 100], 1000, &POLICY);
+    assert_eq!(caps[3..], [150; 7]);
+    // nothing to gain when the headroom wouldn't be smaller than the buffer
+    assert_eq!(run(&[1, 1, 1, 1], 2, &ShrinkPolicy { below: 1.0, ..POLICY }), [2; 4]);
+}
+
+#[test]
+fn disabled_never_shrinks() {
+    assert_eq!(run(&[0; 8], 1000, &ShrinkPolicy { enabled: false, ..POLICY }), [1000; 8]);
+}
+
+#[test]
+fn engine_buffers_shrink_only_when_enabled() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 16, 16);
+    let inst = InstanceRaw { pos: [0.0; 4], scale: [1.0, 1.0, 1.0, 0.0], misc: [0.0; 4] };
+    let many = vec![inst; 4000];
+    let frame = |e: &mut Engine, list: &[InstanceRaw]| e.update_instances(list, &[], &[], &[], &[], &[], &[], &[], &[], &inst);
+
+    engine.set_buffer_shrink(false);
+    frame(&mut engine, &many);
+    let dense = engine.instance_buffer_bytes();
+    for _ in 0..(2 * POLICY.window) { frame(&mut engine, &many[..10]); }
+    assert_eq!(engine.instance_buffer_bytes(), dense, "off: buffers only grow");
+
+    engine.set_shrink_policy(POLICY);
+    for _ in 0..POLICY.window { frame(&mut engine, &many[..10]); }
+    assert!(engine.instance_buffer_bytes() < dense / 10, "{} of {dense} bytes", engine.instance_buffer_bytes());
+    engine.render().expect("frame from the shrunk buffer");
+    assert_eq!(engine.frame_stats().instances, 11);
+}
@@ -0,0 +1,357 @@
+//! Mouse look (sensitivity, invert-Y, pitch clamp), keyboard movement, walk
+//! mode, held-key bookkeeping, field of view and non-default world axes.
+
+use cgmath::{EuclideanSpace, InnerSpace, Vector3, Vector4};
+use hello_wgpu::axes::{Handedness, UpAxis, WorldAxes};
+use hello_wgpu::camera::{Camera, CameraState, DEFAULT_SENSITIVITY, FOV_RANGE, FOV_Y_DEG, KeyboardInput};
+use winit::keyboard::{KeyCode, ModifiersState};
+
+#[test]
+fn mouse_up_looks_up_unless_inverted() {
+    let mut cam = Camera::new();
+    cam.process_mouse_delta(0.0, -10.0);
+    assert!((cam.pitch - 10.0 * DEFAULT_SENSITIVITY).abs() < 1e-6);
+
+    let mut inv = Camera::new();
+    inv.set_invert_y(true);
+    inv.process_mouse_delta(0.0, -10.0);
+    assert!((inv.pitch + 10.0 * DEFAULT_SENSITIVITY).abs() < 1e-6);
+}
+
+#[test]
+fn sensitivity_is_per_axis() {
+    let mut cam = Camera::new();
+    cam.sensitivity_x = 0.01;
+    cam.sensitivity_y = 0.001;
+    cam.process_mouse_delta(10.0, -10.0);
+    assert!((cam.yaw - 0.1).abs() < 1e-6);
+    assert!((cam.pitch - 0.01).abs() < 1e-6);
+
+    cam.set_sensitivity(-0.003);
+    assert_eq!((cam.sensitivity_x, cam.sensitivity_y), (0.003, 0.003));
+}
+
+#[test]
+fn pitch_stays_clamped() {
+    let mut cam = Camera::new();
+    cam.set_invert_y(true);
+    cam.process_mouse_delta(0.0, 1.0e6);
+    assert!(cam.pitch <= 89f32.to_radians() + 1e-6);
+}
+
+fn holding_w() -> KeyboardInput {
+    let mut keys = KeyboardInput::new();
+    keys.key_press(KeyCode::KeyW);
+    keys
+}
+
+#[test]
+fn instant_movement_without_smoothing() {
+    let mut cam = Camera::new();
+    let p0 = cam.position;
+    cam.update(0.5, &holding_w());
+    assert!(((cam.position - p0).magnitude() - 0.5 * cam.speed).abs() < 1e-4);
+}
+
+#[test]
+fn smooth_movement_eases_in_and_out() {
+    let mut cam = Camera::new();
+    cam.set_smooth(true);
+    let p0 = cam.position;
+    cam.update(0.05, &holding_w());
+    assert!((cam.position - p0).magnitude() < 0.05 * cam.speed * 0.5, "starts slower than full speed");
+
+    for _ in 0..120 { cam.update(1.0 / 60.0, &holding_w()); }
+    assert!(cam.velocity.magnitude() <= cam.speed + 1e-4, "speed caps the velocity");
+
+    let idle = KeyboardInput::new();
+    for _ in 0..300 { cam.update(1.0 / 60.0, &idle); }
+    assert_eq!(cam.velocity, Vector3::new(0.0, 0.0, 0.0), "coasts to a stop");
+}
+
+#[test]
+fn smooth_movement_is_frame_rate_independent() {
+    let run = |dt: f32| {
+        let mut cam = Camera::new();
+        cam.set_smooth(true);
+        for _ in 0..(1.0 / dt).round() as u32 { cam.update(dt, &holding_w()); }
+        cam.position
+    };
+    assert!((run(1.0 / 30.0) - run(1.0 / 240.0)).magnitude() < 1e-2);
+}
+
+#[test]
+fn smooth_movement_tops_out_at_speed() {
+    let mut cam = Camera::new();
+    cam.set_smooth(true);
+    cam.speed = 50.0;
+    for _ in 0..300 { cam.update(1.0 / 60.0, &holding_w()); }
+    assert!((cam.velocity.magnitude() - 50.0).abs() < 1e-2, "{}", cam.velocity.magnitude());
+
+    // below the old acceleration-bound top speed as well
+    cam.speed = 2.0;
+    for _ in 0..300 { cam.update(1.0 / 60.0, &holding_w()); }
+    assert!((cam.velocity.magnitude() - 2.0).abs() < 1e-2, "{}", cam.velocity.magnitude());
+}
+
+#[test]
+fn smooth_distance_does_not_depend_on_the_step() {
+    let run = |steps: u32, dt: f32| {
+        let mut cam = Camera::new();
+        cam.set_smooth(true);
+        cam.speed = 2.0;
+        let p0 = cam.position;
+        for _ in 0..steps { cam.update(dt, &holding_w()); }
+        (cam.position - p0).magnitude()
+    };
+    let (coarse, fine) = (run(10, 0.1), run(100, 0.01));
+    assert!((coarse - fine).abs() < 1e-3, "{coarse} vs {fine}");
+}
+
+#[test]
+fn walk_mode_stays_on_the_ground() {
+    let mut cam = Camera::new();
+    cam.position.y = 40.0;
+    cam.set_walk(true);
+    assert_eq!(cam.position.y, cam.walk_height, "stood on the ground");
+
+    // looking down does not dive, Space does not climb
+    cam.process_mouse_delta(0.0, 300.0);
+    assert!(cam.forward.y < -0.5);
+    let mut input = holding_w();
+    input.key_press(KeyCode::Space);
+    let p0 = cam.position;
+    cam.update(1.0, &input);
+    assert_eq!(cam.position.y, cam.walk_height);
+    assert!(((cam.position - p0).magnitude() - cam.speed).abs() < 1e-4, "full speed along the ground");
+
+    // never below eye height, even when placed there
+    cam.position.y = -3.0;
+    cam.update(0.1, &KeyboardInput::new());
+    assert_eq!(cam.position.y, cam.ground_y + cam.walk_height);
+
+    // free fly (the default) flies through the ground
+    cam.set_walk(false);
+    let mut down = KeyboardInput::new();
+    down.key_press(KeyCode::ShiftLeft);
+    cam.update(2.0, &down);
+    assert!(cam.position.y < 0.0);
+}
+
+#[test]
+fn opposite_keys_cancel() {
+    let mut keys = holding_w();
+    keys.key_press(KeyCode::KeyS);
+    keys.key_press(KeyCode::KeyA);
+    keys.key_press(KeyCode::KeyD);
+    let mut cam = Camera::new();
+    let p0 = cam.position;
+    cam.update(0.5, &keys);
+    assert!((cam.position - p0).magnitude() < 1e-6);
+
+    keys.key_release(KeyCode::KeyS);
+    cam.update(0.5, &keys);
+    assert!((cam.position - p0).magnitude() > 0.0, "W alone moves again");
+}
+
+#[test]
+fn focus_loss_releases_held_keys() {
+    let mut keys = holding_w();
+    keys.key_press(KeyCode::ShiftLeft);
+    keys.release_all();
+    let mut cam = Camera::new();
+    let p0 = cam.position;
+    cam.update(0.5, &keys);
+    assert_eq!(cam.position, p0, "no drift after alt-tab");
+}
+
+#[test]
+fn modifiers_up_drop_stale_modifier_keys() {
+    let mut keys = holding_w();
+    keys.key_press(KeyCode::ShiftLeft);
+    keys.key_press(KeyCode::ControlRight);
+    keys.set_modifiers(ModifiersState::SHIFT);
+    assert!(keys.is_pressed(KeyCode::ShiftLeft));
+    assert!(!keys.is_pressed(KeyCode::ControlRight));
+
+    keys.set_modifiers(ModifiersState::empty());
+    assert!(!keys.is_pressed(KeyCode::ShiftLeft));
+    assert!(keys.is_pressed(KeyCode::KeyW), "ordinary keys are untouched");
+    assert_eq!(keys.modifiers(), ModifiersState::empty());
+}
+
+#[test]
+fn ground_hit_projects_back_to_the_same_pixel() {
+    let mut cam = Camera::new();
+    cam.apply_state(&hello_wgpu::camera::CameraState { position: [3.0, 20.0, -4.0], yaw: 0.7, pitch: -0.6 });
+    let aspect = 16.0 / 9.0;
+    for (nx, ny) in [(0.0, 0.0), (0.5, -0.3), (-0.9, 0.2)] {
+        let hit = cam.ground_hit(nx, ny, aspect, -1.5).expect("looking down");
+        assert!((hit.y + 1.5).abs() < 1e-4);
+        let clip = cam.view_projection(aspect) * hit.extend(1.0);
+        assert!((clip.x / clip.w - nx).abs() < 1e-3 && (clip.y / clip.w - ny).abs() < 1e-3,
+                "({nx}, {ny}) came back as ({}, {})", clip.x / clip.w, clip.y / clip.w);
+    }
+}
+
+#[test]
+fn ground_hit_misses_the_sky() {
+    let mut cam = Camera::new();
+    cam.apply_state(&hello_wgpu::camera::CameraState { position: [0.0, 10.0, 0.0], yaw: 0.0, pitch: 0.3 });
+    assert!(cam.ground_hit(0.0, 0.0, 1.0, 0.0).is_none(), "pointing up");
+    // level view: the centre ray is parallel, the lower half still hits
+    cam.apply_state(&hello_wgpu::camera::CameraState { position: [0.0, 10.0, 0.0], yaw: 0.0, pitch: 0.0 });
+    assert!(cam.ground_hit(0.0, 0.0, 1.0, 0.0).is_none());
+    assert!(cam.ground_hit(0.0, -0.5, 1.0, 0.0).is_some());
+    // below the plane and looking down
+    assert!(cam.ground_hit(0.0, -0.5, 1.0, 20.0).is_none());
+}
+
+#[test]
+fn steering_keys_are_reported() {
+    let mut keys = KeyboardInput::new();
+    keys.key_press(KeyCode::KeyG);
+    assert!(!keys.steering(), "not a movement key");
+    keys.key_press(KeyCode::ArrowUp);
+    assert!(keys.steering());
+    keys.release_all();
+    assert!(!keys.steering());
+}
+
+#[test]
+fn altitude_scales_speed_up_to_the_cap() {
+    let mut cam = Camera::new();
+    cam.position.y = 100.0;
+    assert_eq!(cam.effective_speed(), cam.speed, "off by default");
+    cam.set_altitude_speed(true);
+    let at = |cam: &mut Camera, y: f32| { cam.position.y = y; cam.effective_speed() };
+    assert!((at(&mut cam, 0.0) - cam.speed).abs() < 1e-6, "ground level: the base speed");
+    assert!((at(&mut cam, 100.0) - cam.speed * 6.0).abs() < 1e-4, "1 + 100 * 0.05");
+    assert!((at(&mut cam, 1.0e5) - cam.speed * cam.max_speed_scale).abs() < 1e-3, "capped");
+    assert!((at(&mut cam, -50.0) - cam.speed).abs() < 1e-6, "below the ground");
+
+    cam.position.y = 100.0;
+    let p0 = cam.position;
+    cam.update(0.5, &holding_w());
+    assert!(((cam.position - p0).magnitude() - 0.5 * cam.speed * 6.0).abs() < 1e-3);
+}
+
+#[test]
+fn wheel_scales_the_base_speed_not_the_effective() {
+    let mut cam = Camera::new();
+    cam.set_altitude_speed(true);
+    cam.position.y = 100.0;
+    cam.scale_speed(2.0);
+    assert_eq!(cam.speed, 10.0);
+    assert!((cam.effective_speed() - 60.0).abs() < 1e-3, "altitude still multiplies on top");
+    cam.scale_speed(1.0e6);
+    assert_eq!(cam.speed, hello_wgpu::camera::SPEED_RANGE.1);
+    cam.scale_speed(0.0);
+    assert_eq!(cam.speed, hello_wgpu::camera::SPEED_RANGE.0);
+}
+
+#[test]
+fn field_of_view_sets_the_projection_and_stays_in_range() {
+    let mut cam = Camera::new();
+    assert_eq!(cam.fov_deg, FOV_Y_DEG);
+    assert_eq!(cam.projection_matrix(1.0), cgmath::perspective(cgmath::Deg(60.0), 1.0, 0.1, 1_000.0));
+    // y-scale of a perspective matrix is cot(fov / 2)
+    cam.set_fov(90.0);
+    assert!((cam.projection_matrix(1.0).y.y - 1.0).abs() < 1e-5);
+    let edge = cam.view_ray(0.0, 1.0, 1.0);
+    assert!((edge.y - edge.z).abs() < 1e-5, "top edge 45° up: {edge:?}");
+
+    cam.set_fov(1.0);
+    assert_eq!(cam.fov_deg, FOV_RANGE.0);
+    cam.set_fov(500.0);
+    assert_eq!(cam.fov_deg, FOV_RANGE.1);
+    cam.set_fov(f32::NAN);
+    assert_eq!(cam.fov_deg, FOV_RANGE.1, "NaN ignored");
+    // the field is public: the projection clamps too
+    for bad in [0.0, 180.0, f32::INFINITY] {
+        cam.fov_deg = bad;
+        let p = cam.projection_matrix(1.0);
+        assert!(p.y.y.is_finite() && p.y.y > 0.0, "{bad}: {p:?}");
+    }
+}
+
+#[test]
+fn smooth_movement_tops_out_at_the_scaled_speed() {
+    let mut cam = Camera::new();
+    cam.set_smooth(true);
+    cam.set_altitude_speed(true);
+    cam.position.y = 100.0;
+    for _ in 0..200 { cam.update(0.05, &holding_w()); }
+    // flying forward at a level pitch keeps the altitude
+    assert!((cam.velocity.magnitude() - cam.effective_speed()).abs() < 1e-2);
+}
+
+const Z_UP_LH: WorldAxes = WorldAxes { up: UpAxis::Z, handedness: Handedness::Left };
+
+fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool { (a - b).magnitude() < 1e-5 }
+
+#[test]
+fn basis_is_orthonormal_for_every_axes() {
+    for axes in [WorldAxes::Y_UP_RH, WorldAxes::Z_UP_RH, Z_UP_LH] {
+        let mut cam = Camera::new();
+        cam.set_axes(axes);
+        for (yaw, pitch) in [(0.0, 0.0), (0.7, 0.3), (-2.0, -1.2)] {
+            cam.apply_state(&CameraState { position: [0.0; 3], yaw, pitch });
+            let (f, r, u) = (cam.forward, cam.right, cam.up);
+            for v in [f, r, u] { assert!((v.magnitude() - 1.0).abs() < 1e-5, "{axes:?}"); }
+            assert!(f.dot(r).abs() < 1e-5 && f.dot(u).abs() < 1e-5 && r.dot(u).abs() < 1e-5, "{axes:?}");
+            // right-handed camera: right × up = back; left-handed: = forward
+            let back = if axes.handedness == Handedness::Right { -f } else { f };
+            assert!(close(r.cross(u), back), "{axes:?} yaw {yaw} pitch {pitch}");
+            assert!((axes.height(f) - pitch.sin()).abs() < 1e-5, "pitch tilts towards up");
+            assert!(axes.height(r).abs() < 1e-5, "right stays level");
+        }
+    }
+}
+
+#[test]
+fn z_up_yaw_turns_in_the_xy_plane() {
+    let mut cam = Camera::new();
+    cam.set_axes(WorldAxes::Z_UP_RH);
+    assert!(close(cam.forward, Vector3::unit_x()) && close(cam.up, Vector3::unit_z()));
+    assert!(close(cam.right, -Vector3::unit_y()));
+    let right = cam.right;
+    cam.process_mouse_delta(100.0, 0.0);
+    assert!(cam.forward.z.abs() < 1e-6 && cam.forward.dot(right) > 0.0, "mouse right turns right");
+}
+
+#[test]
+fn view_matrix_keeps_screen_directions_for_every_axes() {
+    for axes in [WorldAxes::Y_UP_RH, WorldAxes::Z_UP_RH, Z_UP_LH] {
+        let mut cam = Camera::new();
+        cam.set_axes(axes);
+        cam.apply_state(&CameraState { position: [3.0, -2.0, 7.0], yaw: 0.9, pitch: 0.2 });
+        let vp = cam.view_projection(1.0);
+        let ndc = |v: Vector3<f32>| {
+            let c = vp * (cam.position.to_vec() + v).extend(1.0);
+            Vector4::new(c.x / c.w, c.y / c.w, c.z / c.w, c.w)
+        };
+        let centre = ndc(cam.forward * 10.0);
+        assert!(centre.x.abs() < 1e-5 && centre.y.abs() < 1e-5 && centre.w > 0.0, "{axes:?}");
+        assert!(ndc(cam.forward * 10.0 + cam.right).x > 0.1, "{axes:?} right is screen right");
+        assert!(ndc(cam.forward * 10.0 + cam.up).y > 0.1, "{axes:?} up is screen up");
+        assert!(ndc(-cam.forward * 10.0).w < 0.0, "{axes:?} behind");
+    }
+}
+
+#[test]
+fn z_up_walk_spawn_and_ground_hit_measure_along_z() {
+    let mut cam = Camera::new();
+    cam.set_axes(WorldAxes::Z_UP_RH);
+    cam.reset_to_spawn();
+    assert_eq!(cam.height(), CameraState::SPAWN.position[1]);
+    cam.ground_y = 2.0;
+    cam.set_walk(true);
+    assert!((cam.position.z - 3.7).abs() < 1e-5);
+    for _ in 0..10 { cam.update(0.1, &holding_w()); }
+    assert!((cam.position.z - 3.7).abs() < 1e-5, "walking stays level");
+
+    cam.apply_state(&CameraState { position: [0.0, 0.0, 10.0], yaw: 0.0, pitch: -45f32.to_radians() });
+    let hit = cam.ground_hit(0.0, 0.0, 1.0, 0.0).expect("looking down hits the ground");
+    assert!(close(hit, Vector3::new(10.0, 0.0, 0.0)), "{hit:?}");
+}
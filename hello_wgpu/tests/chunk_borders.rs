@@ -0,0 +1,52 @@
+//! Chunk-border overlay: one ground rectangle per loaded chunk, coloured by
+//! where the chunk came from.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager, ChunkSource, RuntimePlacement};
+use hello_wgpu::hello_wgpu::{chunk_border_lines, chunk_source_color};
+
+#[test]
+fn borders_follow_the_source_and_the_origin() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_borders_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+    let (cw, cd) = chunk_world_span(&params(0xB0D3));
+
+    let mut cm = ChunkManager::new(params(0xB0D3), 1, (-1, 1, -1, 1), true, &dir);
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.set_chunk_override(1, 0, vec![RuntimePlacement::single(Vector3::new(cw, 1.0, 0.0), Vector3::new(1.0, 1.0, 1.0), 0)]);
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.chunk_source(ChunkKey(0, 0)), Some(ChunkSource::Designed));
+    assert_eq!(cm.chunk_source(ChunkKey(1, 0)), Some(ChunkSource::Override));
+
+    // baked on miss, so a second manager reads the designed chunks back from the store
+    let mut again = ChunkManager::new(params(0xB0D3), 1, (-1, 1, -1, 1), true, &dir);
+    again.set_viewer(0, 0.0, 0.0);
+    again.ensure_for_viewers(&assets);
+    assert_eq!(again.chunk_source(ChunkKey(0, 0)), Some(ChunkSource::Store));
+    assert!(again.evict(ChunkKey(0, 0)));
+    assert_eq!(again.chunk_source(ChunkKey(0, 0)), None);
+
+    let lines = chunk_border_lines(&cm);
+    assert_eq!(lines.len(), cm.loaded.len() * 8);
+    let of = |color| lines.iter().filter(|v| v.color == color).count();
+    assert_eq!(of(chunk_source_color(Some(ChunkSource::Override))), 8);
+    assert_eq!(of(chunk_source_color(Some(ChunkSource::Designed))), (cm.loaded.len() - 1) * 8);
+
+    // the override's rectangle spans its chunk and moves with the origin
+    cm.apply_shift(Vector3::new(5.0, 0.0, -3.0));
+    let pink = chunk_source_color(Some(ChunkSource::Override));
+    let xs: Vec<f32> = chunk_border_lines(&cm).iter().filter(|v| v.color == pink).map(|v| v.pos[0]).collect();
+    let zs: Vec<f32> = chunk_border_lines(&cm).iter().filter(|v| v.color == pink).map(|v| v.pos[2]).collect();
+    let span = |v: &[f32]| (v.iter().cloned().fold(f32::MAX, f32::min), v.iter().cloned().fold(f32::MIN, f32::max));
+    let ((x0, x1), (z0, z1)) = (span(&xs), span(&zs));
+    assert!((x0 - (0.5 * cw - 5.0)).abs() < 1e-3 && (x1 - (1.5 * cw - 5.0)).abs() < 1e-3, "{x0}..{x1}");
+    assert!((z0 - (-0.5 * cd + 3.0)).abs() < 1e-3 && (z1 - (0.5 * cd + 3.0)).abs() < 1e-3, "{z0}..{z1}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
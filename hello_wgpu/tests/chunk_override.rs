@@ -0,0 +1,75 @@
+//! Hand-authored chunks: `set_chunk_override` wins over the store and the
+//! designer, survives eviction, and can be cleared again.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager, ChunkSource, RuntimePlacement};
+
+type LoadLog = std::rc::Rc<std::cell::RefCell<Vec<(ChunkKey, ChunkSource)>>>;
+
+fn manager(dir: &str, log: &LoadLog) -> ChunkManager {
+    // bake_on_miss: every designed chunk is also in the store
+    let mut cm = ChunkManager::new(params(0x0BE7), 1, (-2, 2, -2, 2), true, dir);
+    cm.set_viewer(0, 0.0, 0.0);
+    let log = log.clone();
+    cm.on_chunk_loaded = Some(Box::new(move |k, src, _| log.borrow_mut().push((k, src))));
+    cm
+}
+
+fn hero(cw: f32) -> Vec<RuntimePlacement> {
+    vec![
+        RuntimePlacement::single(Vector3::new(cw + 3.0, 10.0, -2.0), Vector3::new(2.0, 10.0, 2.0), 0),
+        RuntimePlacement::single(Vector3::new(cw - 6.0, 1.0, 4.0), Vector3::new(1.0, 1.0, 1.0), 1),
+    ]
+}
+
+#[test]
+fn overridden_chunk_ignores_the_designer_and_store() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_override_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+    let (cw, _) = chunk_world_span(&params(0x0BE7));
+    let key = ChunkKey(1, 0);
+    let ids = |cm: &ChunkManager| cm.loaded[&key].iter().map(|p| (p.archetype_id, p.center.x)).collect::<Vec<_>>();
+    let log = LoadLog::default();
+
+    let mut cm = manager(&dir, &log);
+    cm.ensure_for_viewers(&assets);
+    let designed = ids(&cm);
+    assert!(designed.len() > 2);
+
+    // a loaded chunk is replaced at once; wrapped coordinates name the same chunk
+    cm.set_chunk_override(1 + 5, 0, hero(cw));
+    assert!(cm.has_chunk_override(key));
+    let authored = vec![(0, cw + 3.0), (1, cw - 6.0)];
+    assert_eq!(ids(&cm), authored);
+    assert_eq!(log.borrow().last(), Some(&(key, ChunkSource::Override)));
+
+    // edits last until eviction, then the override comes back (shifted)
+    cm.remove_building(key, 0);
+    cm.apply_shift(Vector3::new(2.0, 0.0, 0.0));
+    assert!(cm.evict(key));
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(ids(&cm), vec![(0, cw + 1.0), (1, cw - 8.0)]);
+
+    // a fresh manager with the baked store still loads the override
+    let mut again = manager(&dir, &log);
+    again.set_chunk_override(1, 0, hero(cw));
+    let mark = log.borrow().len();
+    again.ensure_for_viewers(&assets);
+    assert_eq!(ids(&again), authored);
+    let loads: Vec<ChunkSource> = log.borrow()[mark..].iter().filter(|l| l.0 == key).map(|l| l.1).collect();
+    assert_eq!(loads, [ChunkSource::Override]);
+
+    assert!(again.clear_chunk_override(1, 0));
+    assert!(!again.clear_chunk_override(1, 0));
+    assert!(!again.loaded.contains_key(&key));
+    again.ensure_for_viewers(&assets);
+    assert_eq!(ids(&again), designed);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,250 @@
+//! Chunk persistence: edits survive `flush` + a fresh `ChunkManager`.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, ChunkSource};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::designer_ml::BUILTIN_DESIGNERS;
+
+fn manager(dir: &str) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-2, 2, -2, 2), false, dir);
+    cm.set_viewer(0, 0.0, 0.0);
+    cm
+}
+
+#[test]
+fn mutate_flush_reload_restores_edits() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_flush_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut cm = manager(&dir);
+    cm.ensure_for_viewers(&assets);
+    let key = ChunkKey(0, 0);
+    assert!(!cm.is_dirty(key));
+
+    // re-roll every building in the viewer's chunk
+    cm.mutate_near(&assets, 1.0, 1.0, 0, 7);
+    assert!(cm.is_dirty(key));
+    let edited: Vec<(u16, [f32; 3])> = cm.loaded[&key].iter()
+        .map(|p| (p.archetype_id, [p.scale.x, p.scale.y, p.scale.z])).collect();
+    cm.flush().expect("flush");
+    assert!(!cm.is_dirty(key));
+
+    let mut again = manager(&dir);
+    let sources = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let log = sources.clone();
+    again.on_chunk_loaded = Some(Box::new(move |k, src, _| log.borrow_mut().push((k, src))));
+    again.ensure_for_viewers(&assets);
+
+    assert!(sources.borrow().contains(&(key, ChunkSource::Store)));
+    assert!(sources.borrow().iter().filter(|(k, _)| *k != key).all(|(_, s)| *s == ChunkSource::Designed),
+            "only dirty chunks are written");
+    let reloaded: Vec<(u16, [f32; 3])> = again.loaded[&key].iter()
+        .map(|p| (p.archetype_id, [p.scale.x, p.scale.y, p.scale.z])).collect();
+    assert_eq!(reloaded, edited);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Whether ensuring the viewer's window leaves a file for chunk (0, 0).
+fn ensure_writes_file(bake_on_miss: bool) -> bool {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return bake_on_miss };
+    let assets = AssetLibrary::new(&device);
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_bake_{}_{bake_on_miss}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-2, 2, -2, 2), bake_on_miss, dir.to_str().unwrap());
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    assert!(cm.loaded.contains_key(&ChunkKey(0, 0)));
+    let file = dir.join(format!("{}_0_0.bin", cm.store_namespace()));
+    let exists = file.exists();
+    let _ = std::fs::remove_dir_all(&dir);
+    exists
+}
+
+#[test]
+fn bake_on_miss_saves_designed_chunks() {
+    assert!(ensure_writes_file(true));
+}
+
+#[test]
+fn designed_chunks_are_ephemeral_without_bake_on_miss() {
+    assert!(!ensure_writes_file(false));
+}
+
+#[test]
+fn another_seed_never_reads_this_seeds_chunks() {
+    let assets = AssetLibrary::data_only();
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_seedns_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut a = ChunkManager::new(params(0x5EED), 1, (-2, 2, -2, 2), true, &dir);
+    a.set_viewer(0, 0.0, 0.0);
+    a.ensure_for_viewers(&assets);
+    a.mutate_near(&assets, 1.0, 1.0, 0, 7);
+    a.flush().expect("flush");
+
+    let mut b = ChunkManager::new(params(0x5EED + 1), 1, (-2, 2, -2, 2), false, &dir);
+    assert_ne!(b.store_namespace(), a.store_namespace());
+    b.set_viewer(0, 0.0, 0.0);
+    let log: LoadLog = Default::default();
+    let sink = log.clone();
+    b.on_chunk_loaded = Some(Box::new(move |k, src, _| sink.borrow_mut().push((k, src))));
+    b.ensure_for_viewers(&assets);
+    assert_eq!(log.borrow().len(), 9);
+    assert!(log.borrow().iter().all(|(_, s)| *s == ChunkSource::Designed), "{:?}", log.borrow());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn with_store_bakes_only_into_a_real_store() {
+    let none = ChunkManager::with_store(params(0x5EED), 1, (-2, 2, -2, 2), StoreBackend::None);
+    assert!(none.store == StoreBackend::None && !none.bake_on_miss);
+    let disk = ChunkManager::with_store(params(0x5EED), 1, (-2, 2, -2, 2), StoreBackend::Disk("unused".into()));
+    assert!(disk.bake_on_miss);
+}
+
+#[test]
+fn no_store_backend_never_touches_the_filesystem() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_nostore_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // every write path enabled: bake on miss, edits, flush, eviction
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-2, 2, -2, 2), true, dir.to_str().unwrap());
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    assert!(!cm.loaded.is_empty());
+    cm.mutate_near(&assets, 1.0, 1.0, 0, 7);
+    cm.flush().expect("flush is a no-op");
+    let keys: Vec<ChunkKey> = cm.loaded.keys().copied().collect();
+    for k in keys { cm.evict(k); }
+
+    assert!(!dir.exists(), "{} was created", dir.display());
+}
+
+/// Store sources of every chunk loaded per `ensure_for_viewers` call.
+type LoadLog = std::rc::Rc<std::cell::RefCell<Vec<(ChunkKey, ChunkSource)>>>;
+
+fn async_manager(dir: &str, budget: usize) -> (ChunkManager, LoadLog) {
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-2, 2, -2, 2), false, dir);
+    cm.async_store = true;
+    cm.store_budget = budget;
+    cm.set_viewer(0, 0.0, 0.0);
+    let log: LoadLog = Default::default();
+    let sink = log.clone();
+    cm.on_chunk_loaded = Some(Box::new(move |k, src, _| sink.borrow_mut().push((k, src))));
+    (cm, log)
+}
+
+#[test]
+fn async_store_drains_within_budget() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+
+    // inline loader (no store): misses are designed, `store_budget` per call
+    let (mut cm, log) = async_manager("unused", 2);
+    cm.store = StoreBackend::None;
+    let mut per_call = Vec::new();
+    for _ in 0..8 {
+        let before = cm.loaded.len();
+        cm.ensure_for_viewers(&assets);
+        per_call.push(cm.loaded.len() - before);
+    }
+    assert_eq!(per_call, [2, 2, 2, 2, 1, 0, 0, 0]);
+    assert_eq!(cm.pending_loads(), 0);
+    let mut keys: Vec<ChunkKey> = log.borrow().iter().map(|(k, _)| *k).collect();
+    keys.sort_by_key(|k| (k.0, k.1));
+    keys.dedup();
+    assert_eq!(keys.len(), 9, "each chunk loaded exactly once");
+
+    // threaded disk reads: bake a world, then stream it back
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_async_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut baker = ChunkManager::new(params(0x5EED), 1, (-2, 2, -2, 2), true, &dir);
+    baker.set_viewer(0, 0.0, 0.0);
+    baker.ensure_for_viewers(&assets);
+
+    let (mut cm, log) = async_manager(&dir, 3);
+    for _ in 0..500 {
+        let before = cm.loaded.len();
+        cm.ensure_for_viewers(&assets);
+        assert!(cm.loaded.len() - before <= 3);
+        if cm.loaded.len() == 9 { break; }
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    assert_eq!(cm.loaded.len(), 9);
+    assert!(log.borrow().iter().all(|(_, s)| *s == ChunkSource::Store), "nothing designed while pending");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn chunk_files_are_versioned_and_v1_migrates() {
+    use hello_wgpu::city_store::{decode_chunk, encode_chunk, ChunkFile, PlacementDisk, SubBoxDisk, CHUNK_FORMAT, CHUNK_MAGIC};
+    let stack = vec![SubBoxDisk { offset: [0.0, 2.0, 0.0], scale: [0.5; 3], archetype_id: 3 }];
+    let file = ChunkFile { namespace: "ns".into(), cx: 2, cz: -1,
+                           buildings: vec![PlacementDisk { pos: [1.0, 2.0, 3.0], scale: [1.0; 3], archetype_id: 4, stack }] };
+    let bytes = encode_chunk(&file);
+    assert_eq!(bytes[..4], CHUNK_MAGIC);
+    let back = decode_chunk(&bytes).unwrap();
+    assert_eq!((back.cx, back.cz, back.buildings[0].stack.len()), (2, -1, 1));
+
+    // written before the header: same fields, no stacks
+    #[derive(serde::Serialize)]
+    struct V1 { namespace: String, cx: i32, cz: i32, buildings: Vec<([f32; 3], [f32; 3], u16)> }
+    let old = bincode::serialize(&V1 { namespace: "ns".into(), cx: 2, cz: -1, buildings: vec![([1.0, 2.0, 3.0], [1.0; 3], 4)] }).unwrap();
+    let v1 = decode_chunk(&old).expect("v1 migrates");
+    assert_eq!((v1.namespace.as_str(), v1.buildings[0].pos, v1.buildings[0].archetype_id), ("ns", [1.0, 2.0, 3.0], 4));
+    assert!(v1.buildings[0].stack.is_empty());
+
+    let mut newer = bytes.clone();
+    newer[4..8].copy_from_slice(&(CHUNK_FORMAT + 1).to_le_bytes());
+    assert!(decode_chunk(&newer).unwrap_err().contains(&format!("v{}", CHUNK_FORMAT + 1)));
+    assert!(decode_chunk(&bytes[..6]).is_err(), "truncated");
+    assert!(decode_chunk(b"junk").is_err());
+}
+
+#[test]
+fn switching_worlds_saves_edits_first() {
+    let assets = AssetLibrary::data_only();
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_switch_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+    let snapshot = |cm: &ChunkManager| -> Vec<(u16, [f32; 3])> {
+        cm.loaded[&ChunkKey(0, 0)].iter().map(|p| (p.archetype_id, [p.scale.x, p.scale.y, p.scale.z])).collect()
+    };
+
+    let mut cm = manager(&dir);
+    cm.ensure_for_viewers(&assets);
+    cm.mutate_near(&assets, 1.0, 1.0, 0, 7);
+    let edited = snapshot(&cm);
+    let seed = cm.params.seed;
+    cm.reseed(seed ^ 1);
+    cm.ensure_for_viewers(&assets);
+    assert_ne!(snapshot(&cm), edited);
+    cm.reseed(seed);
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(snapshot(&cm), edited, "R and back");
+
+    // J: saved under the designer that made them
+    cm.mutate_near(&assets, 1.0, 1.0, 0, 8);
+    let edited = snapshot(&cm);
+    let (_, make) = BUILTIN_DESIGNERS[1];
+    cm.set_designer(make(cm.params.clone()));
+    cm.ensure_for_viewers(&assets);
+    let (_, make) = BUILTIN_DESIGNERS[0];
+    cm.set_designer(make(cm.params.clone()));
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(snapshot(&cm), edited, "J and back");
+    let _ = std::fs::remove_dir_all(&dir);
+}
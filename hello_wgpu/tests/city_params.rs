@@ -0,0 +1,59 @@
+//! `CityGenParams::validate`, applied to embedder-supplied cities, and the
+//! TOML form used to share them.
+
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::CityGenParams;
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, RuleDesigner};
+
+#[test]
+fn default_city_is_valid() {
+    assert_eq!(CityGenParams::default().validate(), Ok(()));
+}
+
+#[test]
+fn degenerate_layouts_are_rejected() {
+    let bad = [
+        CityGenParams { lots_x: 0, ..Default::default() },
+        CityGenParams { blocks_per_chunk_z: 0, ..Default::default() },
+        CityGenParams { lot_w: 0.0, ..Default::default() },
+        CityGenParams { lot_d: f32::NAN, ..Default::default() },
+        CityGenParams { lot_gap: -0.1, ..Default::default() },
+        CityGenParams { road_w_major: f32::INFINITY, ..Default::default() },
+    ];
+    for p in bad {
+        assert!(p.validate().is_err(), "{p:?} should be rejected");
+    }
+}
+
+#[test]
+fn roads_may_be_absent() {
+    let p = CityGenParams { road_w_minor: 0.0, major_every: 0, ..Default::default() };
+    assert_eq!(p.validate(), Ok(()));
+    // and generates: every block is built on, no major roads to skip
+    let out = RuleDesigner::new(p.clone()).design_chunk(&DesignContext::new(1, -1, p.seed), &AssetLibrary::data_only());
+    assert!(!out.is_empty());
+}
+
+#[test]
+fn toml_round_trips() {
+    let p = CityGenParams { lot_w: 2.75, road_w_minor: 0.0, major_every: 5, seed: u64::MAX - 7, ..Default::default() };
+    let text = p.to_toml_str();
+    assert_eq!(CityGenParams::from_toml_str(&text), Ok(p.clone()));
+    assert_eq!(CityGenParams::from_toml_str(&text).unwrap().fingerprint(), p.fingerprint());
+}
+
+#[test]
+fn toml_keeps_defaults_and_checks_values() {
+    let p = CityGenParams::from_toml_str("# bug repro\nseed = 0xBEEF  # hex\n\nlots_x = 4\nlot_gap = 1_000.5\n").unwrap();
+    assert_eq!(p, CityGenParams { seed: 0xBEEF, lots_x: 4, lot_gap: 1000.5, ..Default::default() });
+
+    // real TOML: integers for floats, seeds past i64 as strings
+    let p = CityGenParams::from_toml_str("lot_w = 4\nseed = \"0xFFFF_FFFF_FFFF_FFF8\"\n").unwrap();
+    assert_eq!((p.lot_w, p.seed), (4.0, u64::MAX - 7));
+    assert_eq!(CityGenParams::from_toml_str("seed = \"42\"").unwrap().seed, 42);
+
+    for bad in ["lots_x = 0", "lot_w = wide", "colour = 3", "seed 12", "seed = -1", "lots_x = 'x'", "[city]\nlots_x = 2"] {
+        assert!(CityGenParams::from_toml_str(bad).is_err(), "{bad}");
+    }
+    assert!(CityGenParams::load("/nonexistent/city.toml").unwrap_err().contains("city.toml"));
+}
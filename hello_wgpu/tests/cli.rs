@@ -0,0 +1,65 @@
+//! Native command line: flags land in `EngineConfig` / `BenchOptions`, and
+//! no arguments is the default app.
+
+use hello_wgpu::cli::{parse_args, parse_backends, Command};
+use hello_wgpu::chunking::CityGenParams;
+use hello_wgpu::EngineConfig;
+
+fn args(line: &str) -> Vec<String> { line.split_whitespace().map(String::from).collect() }
+
+fn run(line: &str) -> EngineConfig {
+    match parse_args(&args(line)) {
+        Ok(Command::Run(c)) => c,
+        other => panic!("{line:?}: {other:?}"),
+    }
+}
+
+#[test]
+fn no_arguments_is_the_default_app() {
+    let (c, d) = (run(""), EngineConfig::default());
+    assert_eq!((c.width, c.height, c.fullscreen, c.backends), (d.width, d.height, d.fullscreen, d.backends));
+    assert_eq!(c.city.fingerprint(), d.city.fingerprint());
+}
+
+#[test]
+fn window_and_backend_flags() {
+    let c = run("--width 800 --height 600 --fullscreen --backend vulkan,gl --seed 9");
+    assert_eq!((c.width, c.height, c.fullscreen), (800, 600, true));
+    assert_eq!(c.backends, wgpu::Backends::VULKAN | wgpu::Backends::GL);
+    assert_eq!(c.city.seed, 9);
+    assert_eq!(parse_backends("all"), Ok(wgpu::Backends::all()));
+    assert!(parse_backends("vulkan,glide").is_err());
+    assert!(parse_backends("").is_err());
+}
+
+#[test]
+fn seed_overrides_the_params_file_in_any_order() {
+    let path = std::env::temp_dir().join(format!("cli_params_{}.toml", std::process::id()));
+    let city = CityGenParams { lots_x: 2, seed: 5, ..Default::default() };
+    std::fs::write(&path, city.to_toml_str()).unwrap();
+    let p = path.to_str().unwrap();
+    for line in [format!("--seed 77 --params {p}"), format!("--params {p} --seed 77")] {
+        let c = run(&line);
+        assert_eq!((c.city.lots_x, c.city.seed), (2, 77), "{line}");
+    }
+    assert_eq!(run(&format!("--city {p}")).city.seed, 5);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bench_flag_keeps_the_earlier_settings() {
+    let Ok(Command::Bench(o)) = parse_args(&args("--seed 3 --width 320 --height 180 --backend gl --bench --frames 5 --no-hdr")) else { panic!() };
+    assert_eq!((o.seed, o.width, o.height, o.frames, o.hdr), (3, 320, 180, 5, false));
+    assert_eq!(o.backends, wgpu::Backends::GL);
+    let Ok(Command::Bench(o)) = parse_args(&args("bench --size 64x32 --prepass")) else { panic!() };
+    assert_eq!((o.width, o.height, o.depth_prepass, o.seed), (64, 32, true, 42));
+}
+
+#[test]
+fn mistakes_are_reported() {
+    for line in ["--width", "--width wide", "--frobnicate", "--fullscreen --bench", "--params /nonexistent.toml",
+                 "--bench --fullscreen", "bench --size 640"] {
+        assert!(parse_args(&args(line)).is_err(), "{line}");
+    }
+    assert!(matches!(parse_args(&args("--help")), Ok(Command::Help)));
+}
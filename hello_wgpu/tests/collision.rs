@@ -0,0 +1,55 @@
+//! Walk-mode collision: `ChunkManager::resolve_collision` against a known
+//! building.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement, COLLISION_SKIN};
+
+const R: f32 = 0.3;
+
+/// One building at (0, _, 10) in an otherwise empty world; returns its half extent.
+fn world(assets: &AssetLibrary) -> (ChunkManager, Vector3<f32>) {
+    let mut cm = ChunkManager::new(params(1), 1, (-1, 1, -1, 1), false, "unused");
+    cm.store = hello_wgpu::city_store::StoreBackend::None;
+    let scale = Vector3::new(2.0, 3.0, 2.0);
+    let b = assets.base_half(0);
+    cm.loaded.insert(ChunkKey(0, 0), Vec::new());
+    cm.insert_building(ChunkKey(0, 0), RuntimePlacement::single(Vector3::new(0.0, assets.ground_y_for(0, scale), 10.0), scale, 0));
+    (cm, Vector3::new(b.x * scale.x, b.y * scale.y, b.z * scale.z))
+}
+
+#[test]
+fn walls_block_and_slide() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let (cm, half) = world(&assets);
+    let wall = 10.0 - half.z - R - COLLISION_SKIN;
+
+    // straight at it: stops at the wall
+    let p = cm.resolve_collision(Vector3::new(0.0, 1.7, 0.0), Vector3::new(0.0, 1.7, 20.0), R, &assets);
+    assert!((p.z - wall).abs() < 1e-4 && p.x == 0.0, "{p:?}");
+
+    // diagonally (still in front of it): keeps the sideways part
+    let side = half.x * 0.5;
+    let p = cm.resolve_collision(Vector3::new(0.0, 1.7, 0.0), Vector3::new(side, 1.7, 20.0), R, &assets);
+    assert!((p.z - wall).abs() < 1e-4 && p.x == side, "{p:?}");
+
+    // beside it: untouched
+    let (a, b) = (Vector3::new(half.x + 2.0, 1.7, 0.0), Vector3::new(half.x + 2.0, 1.7, 20.0));
+    assert_eq!(cm.resolve_collision(a, b, R, &assets), b);
+}
+
+#[test]
+fn spawning_inside_pushes_out() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let (cm, half) = world(&assets);
+    // just inside the -x face: out that way, not through the roof
+    let inside = Vector3::new(-half.x + 0.2, 1.7, 10.0);
+    let p = cm.resolve_collision(inside, inside, R, &assets);
+    assert!((p.x - (-half.x - R - COLLISION_SKIN)).abs() < 1e-4, "{p:?}");
+    assert_eq!((p.y, p.z), (1.7, 10.0));
+}
@@ -0,0 +1,43 @@
+//! Gamma-correct colour: sRGB frames are preferred, authored colours are
+//! decoded to linear.
+
+mod common;
+
+use hello_wgpu::render::{Engine, frame_format, pick_surface_format, srgb_to_linear};
+use wgpu::TextureFormat::*;
+
+#[test]
+fn prefers_an_srgb_surface_format() {
+    assert_eq!(pick_surface_format(&[Bgra8Unorm, Bgra8UnormSrgb, Rgba16Float]), Bgra8UnormSrgb);
+    assert_eq!(pick_surface_format(&[Rgba16Float, Rgba8UnormSrgb]), Rgba8UnormSrgb);
+    assert_eq!(pick_surface_format(&[Bgra8Unorm, Rgba16Float]), Bgra8Unorm, "none offered: the first");
+}
+
+#[test]
+fn non_srgb_frames_draw_through_an_srgb_view() {
+    let config = |format, view_formats| wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT, format, width: 1, height: 1,
+        present_mode: wgpu::PresentMode::Fifo, alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats, desired_maximum_frame_latency: 0,
+    };
+    assert_eq!(frame_format(&config(Bgra8Unorm, vec![Bgra8UnormSrgb])), Bgra8UnormSrgb);
+    assert_eq!(frame_format(&config(Bgra8UnormSrgb, vec![])), Bgra8UnormSrgb);
+}
+
+#[test]
+fn srgb_decode_matches_the_standard_curve() {
+    assert_eq!(srgb_to_linear(0.0), 0.0);
+    assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-12);
+    assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-5);
+    assert!((srgb_to_linear(0.02) - 0.02 / 12.92).abs() < 1e-12, "linear toe");
+}
+
+#[test]
+fn headless_frames_are_srgb() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 16, 16);
+    assert!(engine.frame_format().is_srgb());
+    engine.set_hdr(false);
+    assert!(engine.scene_format().is_srgb());
+    engine.render().expect("ldr frame");
+}
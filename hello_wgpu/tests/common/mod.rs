@@ -0,0 +1,37 @@
+//! Helpers shared by the integration tests.
+
+use hello_wgpu::chunking::CityGenParams;
+
+/// The app's city layout with a caller-chosen seed.
+#[allow(dead_code)]
+pub fn params(seed: u64) -> CityGenParams {
+    CityGenParams {
+        lots_x: 3, lots_z: 3,
+        lot_w: 3.0, lot_d: 3.0, lot_gap: 0.4,
+        road_w_minor: 3.0, road_w_major: 8.0, major_every: 6,
+        blocks_per_chunk_x: 8, blocks_per_chunk_z: 8,
+        seed,
+    }
+}
+
+/// `AssetLibrary` owns GPU meshes, so it needs a (headless) device; `None`
+/// when the machine has no adapter at all.
+#[allow(dead_code)]
+pub fn device() -> Option<wgpu::Device> {
+    gpu().map(|(device, _queue)| device)
+}
+
+/// Device and queue, for tests that drive a headless `Engine`.
+#[allow(dead_code)]
+pub fn gpu() -> Option<(wgpu::Device, wgpu::Queue)> {
+    gpu_with_instance().map(|(_instance, device, queue)| (device, queue))
+}
+
+/// `gpu` plus the instance, whose `generate_report` counts live resources.
+#[allow(dead_code)]
+pub fn gpu_with_instance() -> Option<(wgpu::Instance, wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends: wgpu::Backends::all(), ..Default::default() });
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).ok()?;
+    let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+    Some((instance, device, queue))
+}
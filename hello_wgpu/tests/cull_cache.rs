@@ -0,0 +1,86 @@
+//! `ChunkCullCache`: chunk classifications are reused while the camera
+//! stays in one cell and redone on a cell change or a mutation.
+
+mod common;
+
+use cgmath::{EuclideanSpace, Vector3};
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::camera::{Camera, CameraState};
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::culling::{frustum_from_vp, ChunkCullCache, Containment, LodHysteresis};
+use hello_wgpu::hello_wgpu::build_instance_buckets;
+
+fn manager(assets: &AssetLibrary) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0xCE11), 3, (-3, 3, -3, 3), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(assets);
+    cm
+}
+
+/// Buckets for a camera at `pos` looking roughly down +Z; `cache: None`
+/// classifies exactly.  Returns (instances, baked chunks, cache misses).
+fn frame(cm: &ChunkManager, assets: &AssetLibrary, pos: [f32; 3], cache: Option<&mut ChunkCullCache>) -> (usize, usize, usize) {
+    let mut cam = Camera::new();
+    cam.apply_state(&CameraState { position: pos, yaw: 1.55, pitch: -0.1 });
+    let fr = frustum_from_vp(&cam.view_projection(1.0));
+    let mut exact = ChunkCullCache::default();
+    let cache = match cache {
+        Some(c) => { let cell = c.cell_for(cam.position.to_vec(), cam.yaw, cam.pitch, 1.0, cam.fov()); c.begin(Some(cell)); c }
+        None => { exact.begin(None); &mut exact }
+    };
+    let (b, baked) = build_instance_buckets(cm, assets, &fr, cam.position.to_vec(), (90.0, 190.0), (380.0, 380.0),
+                                            &mut LodHysteresis::default(), cache);
+    (b.total(), baked.len(), cache.misses())
+}
+
+#[test]
+fn staying_in_a_cell_reuses_the_classification() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager(&assets);
+    let chunks = cm.loaded.len();
+    let mut cache = ChunkCullCache::default();
+
+    let (total, baked, misses) = frame(&cm, &assets, [1.0, 21.0, 1.0], Some(&mut cache));
+    assert_eq!(misses, chunks, "first frame classifies every chunk");
+    let (exact_total, exact_baked, _) = frame(&cm, &assets, [1.0, 21.0, 1.0], None);
+    assert_eq!((total, baked), (exact_total, exact_baked));
+
+    // the view has chunks of every kind, so reuse covers Inside and Outside
+    let mut cam = Camera::new();
+    cam.apply_state(&CameraState { position: [1.0, 21.0, 1.0], yaw: 1.55, pitch: -0.1 });
+    let fr = frustum_from_vp(&cam.view_projection(1.0));
+    let kinds: Vec<Containment> = cm.loaded.keys().map(|k| {
+        let (c, h) = cm.chunk_aabb(*k).unwrap();
+        fr.classify_aabb(c, h)
+    }).collect();
+    assert!(kinds.contains(&Containment::Inside) && kinds.contains(&Containment::Outside), "{kinds:?}");
+
+    // drift within the 4 m cell: nothing re-classified, same buckets as an exact cull
+    let nudged = [2.5, 22.5, 3.0];
+    let (total, baked, misses) = frame(&cm, &assets, nudged, Some(&mut cache));
+    assert_eq!(misses, 0);
+    let (exact_total, exact_baked, _) = frame(&cm, &assets, nudged, None);
+    assert_eq!((total, baked), (exact_total, exact_baked));
+
+    // a mutation redoes only its chunk
+    cm.mark_mutated(ChunkKey(0, 1));
+    assert_eq!(frame(&cm, &assets, nudged, Some(&mut cache)).2, 1);
+
+    // a new cell starts over
+    assert_eq!(frame(&cm, &assets, [9.0, 21.0, 1.0], Some(&mut cache)).2, chunks);
+}
+
+#[test]
+fn cells_quantise_position_and_heading() {
+    let cache = ChunkCullCache::new(4.0, 0.1);
+    let at = |x: f32, yaw: f32| cache.cell_for(Vector3::new(x, 0.0, 0.0), yaw, 0.0, 1.5, 60.0);
+    assert_eq!(at(0.5, 0.01), at(3.9, 0.09));
+    assert_ne!(at(3.9, 0.0), at(4.1, 0.0));
+    assert_ne!(at(1.0, 0.05), at(1.0, 0.15));
+    assert_ne!(at(1.0, 0.0), cache.cell_for(Vector3::new(1.0, 0.0, 0.0), 0.0, 0.0, 1.0, 60.0), "aspect");
+    assert_ne!(at(1.0, 0.0), cache.cell_for(Vector3::new(1.0, 0.0, 0.0), 0.0, 0.0, 1.5, 40.0), "field of view");
+}
@@ -0,0 +1,162 @@
+//! Frustum extraction / AABB culling against a known camera.
+
+use cgmath::{Deg, InnerSpace, Matrix4, Point3, Vector3, perspective};
+use hello_wgpu::axes::WorldAxes;
+use hello_wgpu::camera::{Camera, CameraState};
+use hello_wgpu::chunking::ChunkKey;
+use hello_wgpu::culling::{aabb_intersects_frustum, frustum_corners, frustum_from_vp, ChunkCullCache, Containment, Frustum};
+
+const NEAR: f32 = 0.1;
+const FAR:  f32 = 100.0;
+
+/// Camera at the origin looking down -Z (right-handed), 90° vertical FOV, square.
+fn vp() -> Matrix4<f32> {
+    let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0), Vector3::unit_y());
+    perspective(Deg(90.0), 1.0, NEAR, FAR) * view
+}
+
+fn frustum() -> Frustum { frustum_from_vp(&vp()) }
+
+fn point_inside(p: Vector3<f32>, fr: &Frustum) -> bool {
+    aabb_intersects_frustum(p, Vector3::new(0.0, 0.0, 0.0), fr)
+}
+
+#[test]
+fn planes_are_normalized() {
+    for p in frustum().planes {
+        assert!((p.n.magnitude() - 1.0).abs() < 1e-5, "{p:?}");
+    }
+}
+
+#[test]
+fn plane_distances_match_the_camera() {
+    // left, right, bottom, top, near, far
+    let fr = frustum();
+    let near = fr.planes[4];
+    let far  = fr.planes[5];
+    assert!((near.n - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-4, "{near:?}");
+    assert!((near.d + NEAR).abs() < 1e-3, "{near:?}");
+    assert!((far.n - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-4, "{far:?}");
+    assert!((far.d - FAR).abs() < 1e-2, "{far:?}");
+    // 90° FOV: side planes are at 45°, all pass through the eye
+    let s = std::f32::consts::FRAC_1_SQRT_2;
+    let expect = [
+        Vector3::new( s, 0.0, -s), Vector3::new(-s, 0.0, -s),
+        Vector3::new(0.0,  s, -s), Vector3::new(0.0, -s, -s),
+    ];
+    for (p, n) in fr.planes[..4].iter().zip(expect) {
+        assert!((p.n - n).magnitude() < 1e-4, "{p:?} vs {n:?}");
+        assert!(p.d.abs() < 1e-4, "{p:?}");
+    }
+}
+
+#[test]
+fn point_in_front_is_inside() {
+    let fr = frustum();
+    assert!(point_inside(Vector3::new(0.0, 0.0, -10.0), &fr));
+    assert!(point_inside(Vector3::new(4.0, -4.0, -5.0), &fr));
+}
+
+#[test]
+fn points_outside_each_plane_are_rejected() {
+    let fr = frustum();
+    assert!(!point_inside(Vector3::new(0.0, 0.0, 10.0), &fr), "behind the camera");
+    assert!(!point_inside(Vector3::new(0.0, 0.0, -0.05), &fr), "before the near plane");
+    assert!(!point_inside(Vector3::new(0.0, 0.0, -150.0), &fr), "past the far plane");
+    assert!(!point_inside(Vector3::new(-11.0, 0.0, -10.0), &fr), "left");
+    assert!(!point_inside(Vector3::new( 11.0, 0.0, -10.0), &fr), "right");
+    assert!(!point_inside(Vector3::new(0.0, -11.0, -10.0), &fr), "below");
+    assert!(!point_inside(Vector3::new(0.0,  11.0, -10.0), &fr), "above");
+}
+
+#[test]
+fn aabbs_straddling_each_plane_intersect() {
+    let fr = frustum();
+    let h = Vector3::new(1.0, 1.0, 1.0);
+    // centers just outside each plane; the box reaches back inside
+    for c in [
+        Vector3::new(-10.5, 0.0, -10.0), Vector3::new(10.5, 0.0, -10.0),
+        Vector3::new(0.0, -10.5, -10.0), Vector3::new(0.0, 10.5, -10.0),
+        Vector3::new(0.0, 0.0, 0.5),     Vector3::new(0.0, 0.0, -100.5),
+    ] {
+        assert!(aabb_intersects_frustum(c, h, &fr), "{c:?}");
+        assert!(!point_inside(c, &fr), "{c:?} center");
+    }
+}
+
+#[test]
+fn aabbs_are_classified_inside_intersecting_or_outside() {
+    let fr = frustum();
+    let h = Vector3::new(1.0, 1.0, 1.0);
+    assert_eq!(fr.classify_aabb(Vector3::new(0.0, 0.0, -10.0), h), Containment::Inside);
+    assert_eq!(fr.classify_aabb(Vector3::new(3.0, -3.0, -50.0), Vector3::new(20.0, 20.0, 20.0)), Containment::Inside);
+    // the straddling boxes above, and one swallowing the whole frustum
+    for c in [Vector3::new(-10.5, 0.0, -10.0), Vector3::new(0.0, 10.5, -10.0), Vector3::new(0.0, 0.0, -100.5)] {
+        assert_eq!(fr.classify_aabb(c, h), Containment::Intersect, "{c:?}");
+    }
+    assert_eq!(fr.classify_aabb(Vector3::new(0.0, 0.0, -50.0), Vector3::new(200.0, 200.0, 200.0)), Containment::Intersect);
+    for c in [Vector3::new(0.0, 0.0, 10.0), Vector3::new(-13.0, 0.0, -10.0), Vector3::new(0.0, 0.0, -150.0)] {
+        assert_eq!(fr.classify_aabb(c, h), Containment::Outside, "{c:?}");
+        assert!(!aabb_intersects_frustum(c, h, &fr));
+    }
+}
+
+#[test]
+fn culling_follows_camera_yaw() {
+    // turning right (towards +X) must not keep culling against the old heading
+    let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Vector3::unit_y());
+    let fr = frustum_from_vp(&(perspective(Deg(60.0), 16.0 / 9.0, NEAR, FAR) * view));
+    assert!(point_inside(Vector3::new(20.0, 0.0, 0.0), &fr));
+    assert!(!point_inside(Vector3::new(-20.0, 0.0, 0.0), &fr));
+    assert!(!point_inside(Vector3::new(0.0, 0.0, -20.0), &fr));
+}
+
+#[test]
+fn z_up_camera_culls_like_a_y_up_one() {
+    let mut cam = Camera::new();
+    cam.set_axes(WorldAxes::Z_UP_RH);
+    cam.apply_state(&CameraState { position: [0.0, 0.0, 5.0], yaw: 0.0, pitch: 0.0 });
+    let fr = frustum_from_vp(&cam.view_projection(1.0));
+    assert!(point_inside(Vector3::new(20.0, 0.0, 5.0), &fr));
+    assert!(point_inside(Vector3::new(20.0, 0.0, 12.0), &fr), "above the horizon, within the FOV");
+    assert!(!point_inside(Vector3::new(20.0, 0.0, 60.0), &fr), "straight up");
+    assert!(!point_inside(Vector3::new(-20.0, 0.0, 5.0), &fr), "behind");
+    assert!(!point_inside(Vector3::new(0.0, 20.0, 5.0), &fr), "beside");
+}
+
+#[test]
+fn cull_cache_reach_grows_the_horizontal_axes() {
+    assert_eq!(WorldAxes::Y_UP_RH.grow(Vector3::new(1.0, 1.0, 1.0), 2.0, 0.5), Vector3::new(3.0, 1.5, 3.0));
+    assert_eq!(WorldAxes::Z_UP_RH.grow(Vector3::new(1.0, 1.0, 1.0), 2.0, 0.5), Vector3::new(3.0, 3.0, 1.5));
+
+    // a tall box beside a Z-up view whose buildings reach into it
+    let mut cam = Camera::new();
+    cam.set_axes(WorldAxes::Z_UP_RH);
+    cam.apply_state(&CameraState { position: [0.0, 0.0, 5.0], yaw: 0.0, pitch: 0.0 });
+    let fr = frustum_from_vp(&cam.view_projection(1.0));
+    let aabb = (Vector3::new(20.0, 17.0, 5.0), Vector3::new(1.0, 1.0, 5.0));
+    let classify = |axes: WorldAxes| {
+        let mut cache = ChunkCullCache::default();
+        cache.axes = axes;
+        cache.begin(None);
+        cache.classify(&fr, ChunkKey(0, 0), 0, aabb, Vector3::new(0.0, 0.0, 5.0), || 5.0)
+    };
+    assert_eq!(classify(WorldAxes::Z_UP_RH), Containment::Intersect);
+    assert_eq!(classify(WorldAxes::Y_UP_RH), Containment::Outside, "reach applied to the wrong axes");
+}
+
+#[test]
+fn corners_span_the_near_and_far_quads() {
+    let c = frustum_corners(&vp()).expect("invertible");
+    // 90° FOV, square: half-extent == depth
+    for (i, want) in [(0, Vector3::new(-NEAR, -NEAR, -NEAR)), (2, Vector3::new(NEAR, NEAR, -NEAR)),
+                      (4, Vector3::new(-FAR, -FAR, -FAR)), (6, Vector3::new(FAR, FAR, -FAR))] {
+        assert!((c[i] - want).magnitude() < 1e-2 * want.magnitude(), "corner {i}: {:?}", c[i]);
+    }
+    // every corner sits on (or just inside) all six planes
+    let fr = frustum();
+    for p in c {
+        for pl in fr.planes { assert!(pl.n.dot(p) + pl.d > -1e-2 * p.magnitude(), "{p:?} outside {pl:?}"); }
+    }
+    assert!(frustum_corners(&Matrix4::from_scale(0.0)).is_none());
+}
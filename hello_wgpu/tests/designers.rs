@@ -0,0 +1,48 @@
+//! Switching the `ChunkManager`'s designer at runtime: the city is rebuilt
+//! with the same seed, and each designer keeps its own store namespace.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::designer_ml::BUILTIN_DESIGNERS;
+
+fn centres(cm: &ChunkManager) -> Vec<[f32; 3]> {
+    cm.loaded[&ChunkKey(0, 0)].iter().map(|p| p.center.into()).collect()
+}
+
+#[test]
+fn switching_redesigns_the_loaded_city() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = ChunkManager::new(params(0xD351), 1, (-2, 2, -2, 2), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    let plain = centres(&cm);
+    let ns = cm.store_namespace();
+
+    let (_, jittered) = BUILTIN_DESIGNERS.iter().find(|(n, _)| *n == "jittered").unwrap();
+    cm.set_designer(jittered(params(0xD351)));
+    assert!(cm.loaded.is_empty(), "dropped on switch");
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.len(), 9);
+    assert_ne!(centres(&cm), plain);
+    assert_ne!(cm.store_namespace(), ns);
+
+    // back to the grid: same seed, same city
+    let (_, grid) = BUILTIN_DESIGNERS.iter().find(|(n, _)| *n == "grid").unwrap();
+    cm.set_designer(grid(params(0xD351)));
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(centres(&cm), plain);
+}
+
+#[test]
+fn builtin_names_are_unique() {
+    let mut names: Vec<&str> = BUILTIN_DESIGNERS.iter().map(|(n, _)| *n).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), BUILTIN_DESIGNERS.len());
+}
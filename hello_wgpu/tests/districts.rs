@@ -0,0 +1,100 @@
+//! District layer: a seamless world-space map the designer sees through
+//! `DesignContext`, skewing category odds per quarter.
+
+mod common;
+
+use hello_wgpu::assets::{AssetLibrary, BuildingCategory};
+use hello_wgpu::chunking::chunk_world_span;
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, District, DistrictMap, Placement, RuleDesigner};
+
+/// Category as a tell of the district: at this strength each district's
+/// favourite category outweighs the rest past f32 precision.
+const TELLING: f32 = 30.0;
+
+fn told(cat: BuildingCategory) -> District {
+    match cat {
+        BuildingCategory::Lowrise => District::Market,
+        BuildingCategory::Highrise => District::Industrial,
+        BuildingCategory::Landmark => District::Citadel,
+    }
+}
+
+#[test]
+fn lots_across_a_seam_get_the_district_of_their_world_position() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let p = common::params(0xD15C);
+    let (sx, _) = chunk_world_span(&p);
+    // small quarters, so several meet along the seam
+    let map = DistrictMap { cell: 60.0 };
+    let mut d = RuleDesigner { district_strength: TELLING, ..RuleDesigner::new(p.clone()) };
+    let mut near_seam = [0, 0];
+    let mut seen = Vec::new();
+    for (side, cx) in [(0, 2), (1, 3)] {
+        let ctx = DesignContext { districts: map, ..DesignContext::new(cx, -1, p.seed) };
+        for b in d.design_chunk(&ctx, &assets) {
+            // grid lots: the centre is the lot position the designer sampled
+            let (x, z) = (b.center.x, b.center.z);
+            let want = map.sample(p.seed, x, z);
+            assert_eq!(told(assets.archetypes[b.archetype_id as usize].category), want, "chunk {cx}, ({x}, {z})");
+            // the nearest lots sit a major road back from the seam
+            if (x - 2.5 * sx).abs() < 30.0 { near_seam[side] += 1; }
+            if !seen.contains(&want) { seen.push(want); }
+        }
+    }
+    assert!(near_seam.iter().all(|&n| n > 0), "lots on both sides of the seam: {near_seam:?}");
+    assert!(seen.len() > 1, "the seam crosses quarters");
+}
+
+#[test]
+fn map_covers_every_district_and_follows_the_seed() {
+    let map = DistrictMap::default();
+    let grid: Vec<(f32, f32)> = (0..40).flat_map(|i| (0..40).map(move |j| (i as f32 * 50.0, j as f32 * 50.0))).collect();
+    let a: Vec<District> = grid.iter().map(|&(x, z)| map.sample(1, x, z)).collect();
+    for d in District::ALL { assert!(a.contains(&d), "{d:?} missing over 2 km"); }
+    assert_eq!(a, grid.iter().map(|&(x, z)| map.sample(1, x, z)).collect::<Vec<_>>(), "deterministic");
+    assert_ne!(a, grid.iter().map(|&(x, z)| map.sample(2, x, z)).collect::<Vec<_>>(), "seeded");
+    // coarse: neighbouring lots mostly share a district
+    let same = grid.windows(2).filter(|w| map.sample(1, w[0].0, w[0].1) == map.sample(1, w[1].0, w[1].1)).count();
+    assert!(same * 10 > grid.len() * 7, "{same} of {}", grid.len());
+}
+
+/// (category, district) of every building in a 9×9 block of chunks.
+fn design(assets: &AssetLibrary, strength: f32) -> Vec<(BuildingCategory, District)> {
+    let mut d = RuleDesigner { district_strength: strength, ..RuleDesigner::new(common::params(0xC0FFEE)) };
+    let mut out = Vec::new();
+    for cz in -4..=4 {
+        for cx in -4..=4 {
+            let ctx = DesignContext::new(cx, cz, d.params.seed);
+            out.extend(d.design_chunk(&ctx, assets).iter().map(|p| {
+                (assets.archetypes[p.archetype_id as usize].category, ctx.district_at(p.center.x, p.center.z))
+            }));
+        }
+    }
+    out
+}
+
+#[test]
+fn districts_bias_the_category_mix() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let share = |v: &[(BuildingCategory, District)], cat, d| {
+        let n = v.iter().filter(|p| p.1 == d).count().max(1);
+        v.iter().filter(|p| p.1 == d && p.0 == cat).count() as f32 / n as f32
+    };
+    let (off, on) = (design(&assets, 0.0), design(&assets, 1.0));
+    assert!(share(&on, BuildingCategory::Lowrise, District::Market) > share(&off, BuildingCategory::Lowrise, District::Market));
+    assert!(share(&on, BuildingCategory::Highrise, District::Industrial) > share(&off, BuildingCategory::Highrise, District::Industrial));
+    assert!(share(&on, BuildingCategory::Landmark, District::Citadel) > share(&off, BuildingCategory::Landmark, District::Citadel));
+}
+
+#[test]
+fn zero_strength_designs_the_plain_city() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let p = common::params(7);
+    let (mut plain, mut off) = (RuleDesigner::new(p.clone()), RuleDesigner::new(p.clone()));
+    let ctx = DesignContext { districts: DistrictMap { cell: 40.0 }, ..DesignContext::new(2, -1, p.seed) };
+    let ids = |v: Vec<Placement>| v.iter().map(|p| (p.archetype_id, p.scale)).collect::<Vec<_>>();
+    assert_eq!(ids(off.design_chunk(&ctx, &assets)), ids(plain.design_chunk(&DesignContext::new(2, -1, p.seed), &assets)));
+}
@@ -0,0 +1,91 @@
+//! Placing / deleting buildings and the matching network packets.
+
+mod common;
+
+use cgmath::{InnerSpace, Vector3};
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::net_mutations::{apply_packet, encode_add, encode_remove};
+
+fn manager(viewer_x: f32) -> ChunkManager {
+    let dir = std::env::temp_dir().join("hello_wgpu_editing_unused");
+    let mut cm = ChunkManager::new(params(0xED17), 1, (-4, 4, -4, 4), false, dir.to_str().unwrap());
+    cm.store = hello_wgpu::city_store::StoreBackend::None;
+    cm.set_viewer(0, viewer_x, 0.0);
+    cm
+}
+
+/// A tower far taller than anything designed, so a ray from above hits it first.
+fn tower(assets: &AssetLibrary, x: f32, z: f32) -> RuntimePlacement {
+    let scale = Vector3::new(1.0, 60.0, 1.0);
+    RuntimePlacement::single(Vector3::new(x, assets.base_half(0).y * scale.y, z), scale, 0)
+}
+
+#[test]
+fn place_loads_the_chunk_and_pick_removes_it() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager(0.0);
+    cm.ensure_for_viewers(&assets);
+
+    let (cw, _) = chunk_world_span(&cm.params);
+    let key = ChunkKey(3, 0);
+    assert!(!cm.loaded.contains_key(&key), "outside the viewer's window");
+    let (x, z) = (3.0 * cw + 1.3, 0.7);
+    let (k, idx) = cm.place_building(tower(&assets, x, z), &assets).expect("inside the world");
+    assert_eq!(k, key);
+    assert!(cm.is_dirty(key));
+    assert!(cm.revision(key) > 0);
+    assert_eq!(idx, cm.loaded[&key].len() - 1);
+    assert!(cm.buildings_in_radius(Vector3::new(x, 0.0, z), 0.5).contains(&(key, idx)));
+
+    let down = Vector3::new(0.0, -1.0, 0.0);
+    let (pk, pi, t) = cm.pick_ray(Vector3::new(x, 500.0, z), down, 1000.0, &assets).expect("hit");
+    assert_eq!((pk, pi), (key, idx));
+    assert!(t > 0.0 && t < 500.0);
+    let before = cm.loaded[&key].len();
+    assert!(cm.remove_building(key, idx).is_some());
+    assert_eq!(cm.loaded[&key].len(), before - 1);
+    assert!(cm.remove_building(key, before).is_none());
+
+    // a wrapped image of the chunk lands in the loaded copy
+    let (k, idx) = cm.place_building(tower(&assets, x + 9.0 * cw, z), &assets).unwrap();
+    assert_eq!(k, key);
+    assert!((cm.loaded[&key][idx].center.x - x).abs() < 1e-3);
+
+    // past the edge of a finite world
+    cm.wrap = false;
+    assert!(cm.place_building(tower(&assets, 9.0 * cw, z), &assets).is_none());
+}
+
+#[test]
+fn add_and_remove_packets_round_trip() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let (cw, _) = chunk_world_span(&params(0xED17));
+    let key = ChunkKey(1, 0);
+
+    let mut sender = manager(0.0);
+    sender.ensure_for_viewers(&assets);
+    let mut receiver = manager(cw);
+    receiver.ensure_for_viewers(&assets);
+    assert!(receiver.loaded.contains_key(&key));
+
+    let (_, idx) = sender.place_building(tower(&assets, cw + 2.0, -1.5), &assets).unwrap();
+    apply_packet(&mut receiver, &assets, &encode_add(&sender, key, idx).unwrap());
+    assert_eq!(receiver.loaded[&key].len(), sender.loaded[&key].len());
+    let (a, b) = (&sender.loaded[&key][idx], receiver.loaded[&key].last().unwrap());
+    assert_eq!((a.archetype_id, a.scale), (b.archetype_id, b.scale));
+    assert!((a.center - b.center).magnitude2() < 1e-6, "{:?} vs {:?}", a.center, b.center);
+    assert!(receiver.is_dirty(key));
+
+    apply_packet(&mut receiver, &assets, &encode_remove(key, idx));
+    assert_eq!(receiver.loaded[&key].len(), sender.loaded[&key].len() - 1);
+
+    // truncated / unknown packets are ignored
+    let len = receiver.loaded[&key].len();
+    apply_packet(&mut receiver, &assets, &encode_remove(key, 0)[..12 - 1]);
+    apply_packet(&mut receiver, &assets, b"?junk");
+    assert_eq!(receiver.loaded[&key].len(), len);
+}
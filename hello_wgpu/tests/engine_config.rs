@@ -0,0 +1,82 @@
+//! `EngineConfig`: defaults, validation, the frame cap, and MSAA on a
+//! headless engine.
+
+mod common;
+
+use hello_wgpu::EngineConfig;
+use hello_wgpu::hello_wgpu::frame_deadline;
+use hello_wgpu::render::Engine;
+
+#[test]
+fn default_is_valid() {
+    let cfg = EngineConfig::default();
+    assert_eq!(cfg.validate(), Ok(()));
+    assert_eq!((cfg.sample_count, cfg.present_mode), (1, wgpu::PresentMode::Fifo));
+    assert!(cfg.lod0 <= cfg.lod1 && cfg.lod1 <= cfg.billboard_cull && cfg.mesh_cull <= cfg.billboard_cull);
+}
+
+#[test]
+fn rejects_inconsistent_settings() {
+    let bad = [
+        EngineConfig { lod0: 200.0, ..EngineConfig::default() },
+        EngineConfig { billboard_cull: 100.0, ..EngineConfig::default() },
+        EngineConfig { mesh_cull: 500.0, ..EngineConfig::default() },
+        EngineConfig { ground_extent: 0.0, ..EngineConfig::default() },
+        EngineConfig { chunk_radius: 0, ..EngineConfig::default() },
+        EngineConfig { bounds: (2, 1, -4, 4), ..EngineConfig::default() },
+        EngineConfig { sample_count: 3, ..EngineConfig::default() },
+        EngineConfig { max_instances_per_bucket: 0, ..EngineConfig::default() },
+        EngineConfig { fly_to_secs: -1.0, ..EngineConfig::default() },
+        EngineConfig { fly_to_height: 0.0, ..EngineConfig::default() },
+        EngineConfig { lod_margin: f32::NAN, ..EngineConfig::default() },
+        EngineConfig { impostor_tile: 0, ..EngineConfig::default() },
+        EngineConfig { target_fps: -30.0, ..EngineConfig::default() },
+        EngineConfig { target_fps: 0.5, ..EngineConfig::default() },
+        EngineConfig { target_fps: f32::INFINITY, ..EngineConfig::default() },
+        EngineConfig { backends: wgpu::Backends::empty(), ..EngineConfig::default() },
+    ];
+    for cfg in bad { assert!(cfg.validate().is_err(), "{cfg:?}"); }
+
+    let mut cfg = EngineConfig::default();
+    cfg.city.lots_x = 0;
+    assert!(cfg.validate().is_err(), "city is checked too");
+}
+
+#[test]
+fn frame_cap_schedules_the_next_frame() {
+    let t = instant::Instant::now();
+    assert_eq!(frame_deadline(t, 0.0), None, "uncapped");
+    assert_eq!(frame_deadline(t, 50.0), Some(t + std::time::Duration::from_millis(20)));
+    assert!(EngineConfig { target_fps: 0.0, ..EngineConfig::default() }.validate().is_ok());
+    assert!(EngineConfig { target_fps: 1.0, ..EngineConfig::default() }.validate().is_ok());
+    // unvalidated values don't panic
+    assert_eq!(frame_deadline(t, 1e-30), None, "wait overflows Duration");
+    assert_eq!(frame_deadline(t, f32::NAN), None);
+    assert_eq!(frame_deadline(t, f32::INFINITY), Some(t));
+}
+
+#[test]
+fn headless_engine_renders_with_msaa() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let clear_color = wgpu::Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+    let cfg = EngineConfig { sample_count: 4, clear_color, ..EngineConfig::default() };
+    let mut engine = Engine::new_headless_with(device, queue, 64, 64, &cfg);
+    assert_eq!(engine.sample_count(), 4);
+    assert_eq!(engine.clear_color(), clear_color);
+    engine.render().expect("frame");
+    engine.resize(winit::dpi::PhysicalSize::new(32, 48));
+    engine.render().expect("frame after resize");
+}
+
+#[test]
+fn billboard_edge_aa_blends_only_without_msaa() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    for samples in [1, 4] {
+        let cfg = EngineConfig { sample_count: samples, ..EngineConfig::default() };
+        let mut engine = Engine::new_headless_with(device.clone(), queue.clone(), 64, 64, &cfg);
+        assert!(!engine.billboards_blended());
+        engine.set_billboard_aa(true);
+        assert_eq!(engine.billboards_blended(), samples == 1, "{samples}x");
+        engine.render().expect("frame with edge AA");
+    }
+}
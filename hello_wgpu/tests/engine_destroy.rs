@@ -0,0 +1,49 @@
+//! `Engine::destroy` frees the engine's buffers and textures and lets the
+//! rest drop: building and tearing down a headless engine over and over
+//! must not grow the device's live resource counts.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::render::{BUNDLE_AFTER_FRAMES, Engine};
+use hello_wgpu::types::InstanceRaw;
+
+/// Live (user-held) buffers, textures, views, bind groups and pipelines.
+fn live(instance: &wgpu::Instance) -> Option<[usize; 5]> {
+    let hub = instance.generate_report()?.hub;
+    Some([&hub.buffers, &hub.textures, &hub.texture_views, &hub.bind_groups, &hub.render_pipelines]
+        .map(|r| r.num_kept_from_user))
+}
+
+/// One engine lifetime: instances, baked chunks with bundles, a few frames.
+fn cycle(device: &wgpu::Device, queue: &wgpu::Queue, cm: &ChunkManager, keys: &[ChunkKey]) {
+    let mut engine = Engine::new_headless(device.clone(), queue.clone(), 32, 32);
+    let inst = InstanceRaw { pos: [0.0; 4], scale: [1.0, 1.0, 1.0, 0.0], misc: [0.0; 4] };
+    engine.update_instances(&[inst; 50], &[], &[], &[], &[], &[], &[], &[], &[inst; 3], &inst);
+    for _ in 0..=BUNDLE_AFTER_FRAMES {
+        engine.update_baked(cm, keys);
+        engine.render().expect("frame");
+    }
+    engine.destroy();
+}
+
+#[test]
+fn create_destroy_loop_does_not_leak() {
+    let Some((instance, device, queue)) = common::gpu_with_instance() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut cm = ChunkManager::new(params(1), 1, (-4, 4, -4, 4), false, "unused");
+    let keys = vec![ChunkKey(0, 1), ChunkKey(1, 1)];
+    for &k in &keys {
+        cm.loaded.insert(k, vec![RuntimePlacement::single(Vector3::new(k.0 as f32 * 40.0, 1.0, 60.0), Vector3::new(1.0, 1.0, 1.0), 0)]);
+    }
+
+    // the first engine may leave device-level caches behind
+    cycle(&device, &queue, &cm, &keys);
+    let _ = device.poll(wgpu::PollType::Wait);
+    let Some(before) = live(&instance) else { eprintln!("backend has no resource report; skipping"); return };
+    for _ in 0..8 { cycle(&device, &queue, &cm, &keys); }
+    let _ = device.poll(wgpu::PollType::Wait);
+    let after = live(&instance).expect("report");
+    assert_eq!(after, before, "[buffers, textures, views, bind groups, pipelines] after 8 more engines");
+}
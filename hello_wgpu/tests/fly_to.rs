@@ -0,0 +1,42 @@
+//! Click-to-move flights: eased, ending exactly on the target, and carried
+//! along by origin shifts.
+
+use cgmath::{InnerSpace, Vector3};
+use hello_wgpu::flythrough::FlyTo;
+
+fn flight(fly: &mut FlyTo, dt: f32) -> Vec<Vector3<f32>> {
+    std::iter::from_fn(|| fly.tick(dt)).take(10_000).collect()
+}
+
+#[test]
+fn eases_in_and_out_and_lands_on_the_target() {
+    let (from, to) = (Vector3::new(0.0, 10.0, 0.0), Vector3::new(100.0, 12.0, -50.0));
+    let path = flight(&mut FlyTo::new(from, to, 2.0), 0.125);
+    assert_eq!(path.len(), 17, "t = 0, 0.125 … 2.0");
+    assert_eq!(path[0], from);
+    assert_eq!(*path.last().unwrap(), to);
+
+    // monotone along the line, slow at both ends
+    let along: Vec<f32> = path.iter().map(|p| (p - from).magnitude()).collect();
+    assert!(along.windows(2).all(|w| w[1] >= w[0]));
+    let step = |i: usize| along[i + 1] - along[i];
+    assert!(step(0) < step(7) && step(15) < step(7));
+    // symmetric: halfway in time is halfway in space
+    assert!(((path[8] - from).magnitude() - (to - from).magnitude() * 0.5).abs() < 1e-3);
+}
+
+#[test]
+fn zero_duration_jumps() {
+    let to = Vector3::new(1.0, 2.0, 3.0);
+    assert_eq!(flight(&mut FlyTo::new(Vector3::new(0.0, 0.0, 0.0), to, 0.0), 0.016), [to]);
+}
+
+#[test]
+fn shift_moves_the_whole_flight() {
+    let mut fly = FlyTo::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(10.0, 5.0, 0.0), 1.0);
+    fly.tick(0.5);
+    let off = Vector3::new(4.0, 0.0, -2.0);
+    fly.shift(off);
+    assert_eq!(fly.target(), Vector3::new(6.0, 5.0, 2.0));
+    assert_eq!(fly.tick(0.5), Some(Vector3::new(1.0, 5.0, 2.0)), "halfway, in the shifted frame");
+}
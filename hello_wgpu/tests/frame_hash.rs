@@ -0,0 +1,58 @@
+//! End-to-end regression guard: a fixed seed, viewer and camera must keep
+//! producing the same buckets.  A failure here means generation, culling
+//! or LOD bucketing changed; if that was the point, re-pin the values from
+//! the assertion message.  Runs on `AssetLibrary::data_only`: no adapter,
+//! no frame rendered.
+
+mod common;
+
+use cgmath::{Deg, Matrix4, Point3, Vector3, perspective};
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::culling::{bucket_instances, frustum_from_vp, Buckets};
+use hello_wgpu::EngineConfig;
+
+/// FNV-1a over each bucket's length and every instance field, in order.
+/// Fields are rounded to thousandths first: positions come out of
+/// `sin`/`cos`, whose last bits differ between libms.
+fn fingerprint(b: &Buckets) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut eat = |x: u64| for byte in x.to_le_bytes() { h = (h ^ byte as u64).wrapping_mul(0x0100_0000_01b3); };
+    for list in b.lists() {
+        eat(list.len() as u64);
+        for i in list {
+            for f in i.pos.iter().chain(&i.scale).chain(&i.misc) { eat((*f as f64 * 1000.0).round() as i64 as u64); }
+        }
+    }
+    h
+}
+
+fn frame(assets: &AssetLibrary) -> Buckets {
+    let cfg = EngineConfig::default();
+    let mut cm = ChunkManager::new(common::params(0x00F1_4A5E), 2, (-4, 4, -4, 4), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 12.0, -30.0);
+    cm.ensure_for_viewers(assets);
+
+    let eye = Point3::new(12.0, 18.0, -30.0);
+    let vp = perspective(Deg(60.0), 16.0 / 9.0, 0.1, 2000.0)
+        * Matrix4::look_at_rh(eye, Point3::new(80.0, 0.0, 150.0), Vector3::unit_y());
+    // chunk order fixed, so the lists come out in the same order every run
+    let mut keys: Vec<ChunkKey> = cm.loaded.keys().copied().collect();
+    keys.sort_by_key(|k| (k.0, k.1));
+    let placements = keys.iter().flat_map(|k| &cm.loaded[k]);
+    bucket_instances(placements, Vector3::new(eye.x, eye.y, eye.z), &frustum_from_vp(&vp),
+                     cfg.lod0, cfg.lod1, cfg.billboard_cull, assets)
+}
+
+#[test]
+fn fixed_world_and_camera_give_pinned_buckets() {
+    let assets = AssetLibrary::data_only();
+    let b = frame(&assets);
+    let counts = b.lists().map(|l| l.len());
+    let hash = fingerprint(&b);
+    assert_eq!(fingerprint(&frame(&assets)), hash, "same process, same buckets");
+    assert!(counts.iter().all(|&n| n > 0), "every bucket exercised: {counts:?}");
+    assert_eq!((counts, hash), ([49, 30, 19, 58, 290, 120, 77, 162, 1533], 0x3875_f197_cb95_b4cb), "buckets moved: re-pin if intended");
+}
@@ -0,0 +1,54 @@
+//! Frozen-frustum overlay: the wireframe plus one box per culled building,
+//! and the engine drawing it.
+
+mod common;
+
+use cgmath::{Deg, Matrix4, Point3, Vector3, perspective};
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::hello_wgpu::frustum_debug_lines;
+use hello_wgpu::render::Engine;
+
+/// Looking down -Z from 2 m up.
+fn vp() -> Matrix4<f32> {
+    let view = Matrix4::look_at_rh(Point3::new(0.0, 2.0, 0.0), Point3::new(0.0, 2.0, -1.0), Vector3::unit_y());
+    perspective(Deg(60.0), 1.0, 0.1, 1000.0) * view
+}
+
+fn world(assets: &AssetLibrary) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(1), 1, (0, 0, 0, 0), false, "unused");
+    cm.store = StoreBackend::None;
+    let at = |z: f32| RuntimePlacement::single(Vector3::new(0.0, assets.ground_y_for(0, Vector3::new(1.0, 1.0, 1.0)), z),
+                                               Vector3::new(1.0, 1.0, 1.0), 0);
+    cm.loaded.insert(ChunkKey(0, 0), vec![at(-30.0), at(30.0), at(-500.0)]);
+    cm
+}
+
+#[test]
+fn overlay_outlines_only_culled_buildings_in_range() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let cm = world(&assets);
+    // the one behind the camera; the one far ahead is visible but out of range anyway
+    let lines = frustum_debug_lines(&cm, &assets, &vp(), Vector3::new(0.0, 2.0, 0.0), 100.0);
+    assert_eq!(lines.len(), 2 * 12 + 2 * 12);
+    let culled: Vec<f32> = lines[24..].iter().map(|v| v.pos[2]).collect();
+    assert!(culled.iter().all(|&z| z > 25.0), "{culled:?}");
+}
+
+#[test]
+fn engine_draws_and_clears_debug_lines() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    let cm = world(&engine.assets);
+    let lines = frustum_debug_lines(&cm, &engine.assets, &vp(), Vector3::new(0.0, 2.0, 0.0), 100.0);
+    engine.update_camera(&vp());
+    engine.set_debug_lines(&lines);
+    assert_eq!(engine.debug_line_count(), lines.len());
+    engine.render().expect("frame with lines");
+    engine.set_debug_lines(&[]);
+    assert_eq!(engine.debug_line_count(), 0);
+    engine.render().expect("frame without lines");
+}
@@ -0,0 +1,96 @@
+//! Wall-clock generation budget: `ensure_for_viewers` stops designing once
+//! `generation_budget_ms` is spent and picks the rest up on later calls.
+
+mod common;
+
+use std::time::Duration;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, Placement, RuleDesigner};
+
+const CHUNK_MS: u64 = 4;
+
+/// `RuleDesigner` taking `CHUNK_MS` per chunk.
+struct SlowDesigner(RuleDesigner);
+
+impl CityDesigner for SlowDesigner {
+    fn design_chunk(&mut self, ctx: &DesignContext, assets: &AssetLibrary) -> Vec<Placement> {
+        std::thread::sleep(Duration::from_millis(CHUNK_MS));
+        self.0.design_chunk(ctx, assets)
+    }
+}
+
+#[test]
+fn budget_is_clamped_and_off_by_default() {
+    let mut cm = ChunkManager::new(params(1), 1, (-2, 2, -2, 2), false, "unused");
+    assert_eq!(cm.generation_budget_ms(), None);
+    cm.set_generation_budget_ms(Some(-3.0));
+    assert_eq!(cm.generation_budget_ms(), Some(0.0));
+    cm.set_generation_budget_ms(Some(f32::NAN));
+    assert_eq!(cm.generation_budget_ms(), None);
+}
+
+#[test]
+fn slow_designer_is_spread_over_calls() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = ChunkManager::new(params(0xB4D6), 2, (-4, 4, -4, 4), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_designer(Box::new(SlowDesigner(RuleDesigner::new(params(0xB4D6)))));
+    cm.set_generation_budget_ms(Some(10.0));
+    cm.set_viewer(0, 0.0, 0.0);
+
+    cm.ensure_for_viewers(&assets);
+    // 4 ms a chunk: the third one crosses 10 ms, so no fourth is started
+    assert!((1..=3).contains(&cm.loaded.len()), "{} chunks in one call", cm.loaded.len());
+    assert!(cm.loaded.contains_key(&ChunkKey(0, 0)), "the viewer's chunk goes first");
+
+    let mut calls = 1;
+    while cm.loaded.len() < 25 {
+        let before = cm.loaded.len();
+        cm.ensure_for_viewers(&assets);
+        assert!(cm.loaded.len() > before, "every call makes progress");
+        calls += 1;
+    }
+    assert!(calls >= 25 / 3, "{calls} calls");
+
+    // lifted: the whole window in one call
+    cm.set_generation_budget_ms(None);
+    cm.set_chunk_radius(3);
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.len(), 49);
+}
+
+#[test]
+fn store_misses_are_designed_within_the_budget() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = ChunkManager::new(params(0xB4D6), 1, (-4, 4, -4, 4), false, "unused");
+    // inline loader: every read is a miss, all 9 back in the first call
+    cm.store = StoreBackend::None;
+    cm.async_store = true;
+    cm.store_budget = 16;
+    cm.set_designer(Box::new(SlowDesigner(RuleDesigner::new(params(0xB4D6)))));
+    cm.set_generation_budget_ms(Some(6.0));
+    cm.set_viewer(0, 0.0, 0.0);
+
+    cm.ensure_for_viewers(&assets);
+    assert!((1..=2).contains(&cm.loaded.len()), "{} chunks in one call", cm.loaded.len());
+    assert_eq!(cm.pending_loads(), 9 - cm.loaded.len(), "the rest wait, still requested");
+    while cm.loaded.len() < 9 {
+        let before = cm.loaded.len();
+        cm.ensure_for_viewers(&assets);
+        assert!((1..=2).contains(&(cm.loaded.len() - before)), "{} more", cm.loaded.len() - before);
+    }
+    assert_eq!(cm.pending_loads(), 0);
+
+    // the chunk count caps them too
+    cm.set_generation_budget_ms(None);
+    cm.gen_budget = 3;
+    cm.set_chunk_radius(2);
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.len(), 9 + 3);
+}
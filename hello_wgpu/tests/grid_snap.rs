@@ -0,0 +1,48 @@
+//! `chunking::snap_to_grid`: edits land on the designer's lot centres.
+
+mod common;
+
+use cgmath::Vector3;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, snap_to_grid};
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, LotLayout, RuleDesigner};
+
+fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3 && (a.z - b.z).abs() < 1e-3
+}
+
+#[test]
+fn jittered_point_snaps_to_its_lot() {
+    let p = common::params(1);
+    // chunk (0, 0): block 1 / lot 0 along x, block 2 / lot 1 along z
+    //   x = -53.7 + 12.8 + 1.5 + 1.5,  z = -53.7 + 25.6 + 1.5 + 3.4 + 1.5
+    let lot = Vector3::new(-37.9, 2.0, -21.7);
+    assert!(close(snap_to_grid(&p, lot + Vector3::new(0.6, 0.0, -0.8)), lot));
+    // the same lot one chunk over
+    let (cw, cd) = chunk_world_span(&p);
+    let there = lot + Vector3::new(cw, 0.0, -cd);
+    assert!(close(snap_to_grid(&p, there + Vector3::new(-1.1, 0.0, 0.9)), there));
+    // on the major road (block 0): the last lot of chunk -1 is nearer than block 1's
+    //   x = -53.7 + 7 * 12.8 + 1.5 + 5 + 2 * 3.4 + 1.5 - 107.4
+    let road = snap_to_grid(&p, Vector3::new(-52.0, 0.0, -21.7));
+    assert!(close(road, Vector3::new(-56.7, 0.0, -21.7)), "{road:?}");
+}
+
+#[test]
+fn designed_lots_are_fixed_points() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let p = common::params(0xA11CE);
+    let mut grid = RuleDesigner::new(p.clone());
+    let mut jittered = RuleDesigner { layout: LotLayout::Jittered { min_gap: 0.4 }, ..RuleDesigner::new(p.clone()) };
+    for (cx, cz) in [(0, 0), (-3, 2)] {
+        let ctx = DesignContext::new(cx, cz, p.seed);
+        let on_grid = grid.design_chunk(&ctx, &assets);
+        let moved = jittered.design_chunk(&ctx, &assets);
+        assert!(!on_grid.is_empty());
+        for (g, j) in on_grid.iter().zip(&moved) {
+            assert!(close(snap_to_grid(&p, g.center), g.center), "{:?} moved", g.center);
+            assert!(close(snap_to_grid(&p, j.center), g.center), "{:?} didn't snap to {:?}", j.center, g.center);
+        }
+    }
+}
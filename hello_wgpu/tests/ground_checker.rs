@@ -0,0 +1,60 @@
+//! Ground checkerboard: squares sized to the city blocks and restarting at
+//! every chunk seam, colours and tile kept on the engine, and the floating
+//! origin fed to the shader.
+
+mod common;
+
+use cgmath::Vector3;
+use hello_wgpu::chunking::{CityGenParams, block_world_span, chunk_world_span};
+use hello_wgpu::hello_wgpu::ground_checker_for;
+use hello_wgpu::render::Engine;
+
+#[test]
+fn squares_follow_the_block_pitch() {
+    let p = CityGenParams::default();
+    let (tile, span, offset) = ground_checker_for(&p);
+    let ((bw, bd), (cw, cd)) = (block_world_span(&p), chunk_world_span(&p));
+    assert_eq!(tile, [bw, bd]);
+    assert_eq!(span, [cw, cd]);
+    // a chunk seam is a square corner
+    assert_eq!(offset, [-0.5 * cw, -0.5 * cd]);
+}
+
+#[test]
+fn every_chunk_starts_its_squares_on_its_seam() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    let p = CityGenParams::default();
+    let (tile, span, offset) = ground_checker_for(&p);
+    let ((bw, bd), (cw, cd)) = (block_world_span(&p), chunk_world_span(&p));
+    assert!((cw / bw).fract() > 0.1, "the span is no multiple of the tile, so one pattern would drift");
+    engine.set_ground_checker(tile, span, offset, engine.ground_colors());
+    for k in -3..=3 {
+        let (sx, sz) = ((k as f32 - 0.5) * cw, (k as f32 - 0.5) * cd);
+        // block j of the chunk lies in one square, a new one past its end
+        for j in 0..8 {
+            let (x, z) = (sx + j as f32 * bw, sz + j as f32 * bd);
+            let inside = engine.ground_square(x + 0.05, z + 0.05);
+            assert_eq!(engine.ground_square(x + bw - 0.05, z + bd - 0.05), inside, "chunk {k}, block {j}");
+            assert_eq!(engine.ground_square(x - 0.05, z - 0.05), [inside[0] - 1, inside[1] - 1], "chunk {k}, block {j}");
+        }
+        // across the seam the squares still alternate
+        let (a, b) = (engine.ground_square(sx - 0.05, 0.0), engine.ground_square(sx + 0.05, 0.0));
+        assert_eq!(b[0] - a[0], 1, "chunk {k}");
+    }
+}
+
+#[test]
+fn checker_settings_round_trip_and_render() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    assert_eq!(engine.ground_checker().0, [0.0, 0.0], "plain until set");
+    let colors = [[0.2, 0.2, 0.2], [0.6, 0.6, 0.6]];
+    engine.set_ground_checker([12.0, 9.0], [100.0, 90.0], [-40.0, 3.0], colors);
+    assert_eq!(engine.ground_checker(), ([12.0, 9.0], [100.0, 90.0], [-40.0, 3.0]));
+    assert_eq!(engine.ground_colors(), colors);
+    engine.set_ground_color([0.1, 0.3, 0.1]);
+    assert_eq!(engine.ground_colors()[0], [0.1, 0.3, 0.1]);
+    engine.set_ground_origin(Vector3::new(1.0e4, 0.0, -2.5e4));
+    engine.render().expect("frame with a checkered ground");
+}
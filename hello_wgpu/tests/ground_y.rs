@@ -0,0 +1,61 @@
+//! One rule for standing buildings on the ground: designer, live mutations
+//! and network edits all land on `AssetLibrary::ground_y_for`.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::net_mutations::{apply_packet, encode_add};
+
+fn on_ground(assets: &AssetLibrary, cm: &ChunkManager) {
+    let shift_y = cm.origin_shift().y;
+    for list in cm.loaded.values() {
+        for p in list {
+            let want = assets.ground_y_for(p.archetype_id as usize, p.scale) - shift_y;
+            assert!((p.center.y - want).abs() < 1e-5, "centre y {} for archetype {}, want {want}", p.center.y, p.archetype_id);
+        }
+    }
+}
+
+fn manager(assets: &AssetLibrary) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0x6A0D), 1, (-2, 2, -2, 2), false, "unused");
+    cm.store = hello_wgpu::city_store::StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(assets);
+    cm
+}
+
+#[test]
+fn designer_mutations_and_network_agree() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager(&assets);
+    on_ground(&assets, &cm);
+
+    // vertical origin shift too: local heights follow it
+    cm.apply_shift(Vector3::new(30.0, 4.0, -20.0));
+    for _ in 0..20 { cm.mutate_near(&assets, 0.5, 0.1, 1, 3); }
+    on_ground(&assets, &cm);
+
+    // a mutate packet switching building 0 of (0, 0) to a landmark
+    let landmark = assets.archetypes.len() - 1;
+    let mut pkt = [0u8; 12];
+    pkt[8..10].copy_from_slice(&(landmark as u16).to_le_bytes());
+    pkt[10..12].copy_from_slice(&40_000u16.to_le_bytes());
+    apply_packet(&mut cm, &assets, &pkt);
+    assert_eq!(cm.loaded[&ChunkKey(0, 0)][0].archetype_id as usize, landmark);
+    // … and an add carrying an odd height from another client
+    let mut sender = manager(&assets);
+    let scale = Vector3::new(1.0, 2.5, 1.0);
+    let p = RuntimePlacement::single(Vector3::new(1.0, 99.0, 1.0), scale, 3);
+    let idx = sender.insert_building(ChunkKey(0, 0), p).unwrap();
+    apply_packet(&mut cm, &assets, &encode_add(&sender, ChunkKey(0, 0), idx).unwrap());
+    on_ground(&assets, &cm);
+
+    // unknown archetypes are dropped instead of indexing past the table
+    pkt[8..10].copy_from_slice(&u16::MAX.to_le_bytes());
+    apply_packet(&mut cm, &assets, &pkt);
+    assert_eq!(cm.loaded[&ChunkKey(0, 0)][0].archetype_id as usize, landmark);
+}
@@ -0,0 +1,43 @@
+//! HDR scene target + tone mapping on a headless engine.
+
+mod common;
+
+use hello_wgpu::EngineConfig;
+use hello_wgpu::render::Engine;
+use hello_wgpu::tonemap::{HDR_FORMAT, needs_gamma};
+
+#[test]
+fn headless_renders_hdr_and_falls_back_to_ldr() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    for samples in [1, 4] {
+        let cfg = EngineConfig { sample_count: samples, ..EngineConfig::default() };
+        let mut engine = Engine::new_headless_with(device.clone(), queue.clone(), 64, 64, &cfg);
+        assert!(engine.hdr_supported() && engine.hdr(), "on by default");
+        assert_eq!(engine.scene_format(), HDR_FORMAT);
+        engine.set_exposure(2.5);
+        engine.render().expect("hdr frame");
+        engine.resize(winit::dpi::PhysicalSize::new(40, 24));
+        engine.render().expect("hdr frame after resize");
+
+        engine.set_hdr(false);
+        assert_eq!(engine.scene_format(), engine.config.format);
+        engine.render().expect("ldr frame");
+        engine.set_hdr(true);
+        assert!(engine.hdr());
+        assert_eq!(engine.exposure(), 2.5, "kept across toggles");
+        engine.render().expect("hdr again");
+    }
+    let cfg = EngineConfig { hdr: false, ..EngineConfig::default() };
+    assert!(!Engine::new_headless_with(device, queue, 16, 16, &cfg).hdr());
+}
+
+#[test]
+fn exposure_clamps_and_gamma_follows_the_frame_format() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 16, 16);
+    engine.set_exposure(-1.0);
+    assert_eq!(engine.exposure(), 0.0);
+    assert!(needs_gamma(wgpu::TextureFormat::Bgra8Unorm));
+    assert!(!needs_gamma(wgpu::TextureFormat::Bgra8UnormSrgb));
+    assert!(!needs_gamma(HDR_FORMAT));
+}
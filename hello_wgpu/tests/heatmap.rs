@@ -0,0 +1,72 @@
+//! Density heatmap: per-chunk values around the camera, normalised per
+//! metric, and the grid uploaded to (and cleared from) the engine.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::assets::{AssetLibrary, BuildingCategory};
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::hello_wgpu::{density_heat, HeatMetric};
+use hello_wgpu::render::{Engine, HeatGrid, HEAT_RAMP, HEAT_SIDE};
+
+/// Cell of `key` when the camera stands in chunk (0, 0).
+fn cell(key: ChunkKey) -> usize {
+    let h = (HEAT_SIDE / 2) as i32;
+    ((key.1 + h) * HEAT_SIDE as i32 + key.0 + h) as usize
+}
+
+#[test]
+fn values_follow_building_count_and_tall_share() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let low = assets.archetypes.iter().position(|a| a.category == BuildingCategory::Lowrise).unwrap() as u16;
+    let tall = assets.archetypes.iter().position(|a| a.category != BuildingCategory::Lowrise).unwrap() as u16;
+    let at = |id| RuntimePlacement::single(Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 1.0, 1.0), id);
+
+    let mut cm = ChunkManager::new(params(3), 1, (-4, 4, -4, 4), false, "unused");
+    cm.loaded.insert(ChunkKey(0, 0), vec![at(low); 4]);
+    cm.loaded.insert(ChunkKey(1, 0), vec![at(low), at(tall)]);
+    cm.loaded.insert(ChunkKey(0, -1), vec![]);
+
+    let g = density_heat(&cm, &assets, HeatMetric::Count, Vector3::new(1.0, 5.0, -2.0));
+    assert_eq!(g.values.len(), HEAT_SIDE * HEAT_SIDE);
+    let (cw, cd) = chunk_world_span(&cm.params);
+    let h = (HEAT_SIDE / 2) as f32;
+    assert_eq!(g.origin, [(-h - 0.5) * cw, (-h - 0.5) * cd], "centred on the camera's chunk");
+    assert_eq!(g.cell, [cw, cd]);
+    assert_eq!(g.values[cell(ChunkKey(0, 0))], 1.0, "busiest chunk tops the ramp");
+    assert_eq!(g.values[cell(ChunkKey(1, 0))], 0.5);
+    assert_eq!(g.values[cell(ChunkKey(0, -1))], 0.0, "loaded but empty");
+    assert_eq!(g.values[cell(ChunkKey(2, 2))], -1.0, "not loaded");
+
+    let t = density_heat(&cm, &assets, HeatMetric::Tall, Vector3::new(1.0, 5.0, -2.0));
+    assert_eq!(t.values[cell(ChunkKey(0, 0))], 0.0);
+    assert_eq!(t.values[cell(ChunkKey(1, 0))], 0.5);
+
+    cm.isolated = Some(ChunkKey(1, 0));
+    let iso = density_heat(&cm, &assets, HeatMetric::Count, Vector3::new(1.0, 5.0, -2.0));
+    assert_eq!(iso.values[cell(ChunkKey(0, 0))], -1.0, "hidden by isolation");
+    assert_eq!(iso.values[cell(ChunkKey(1, 0))], 1.0);
+}
+
+#[test]
+fn engine_keeps_the_grid_and_ramp() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 32, 32);
+    assert!(engine.heatmap().is_none(), "off by default");
+    assert_eq!(engine.heat_ramp(), HEAT_RAMP);
+
+    let grid = HeatGrid { origin: [-80.0, -80.0], cell: [10.0, 10.0], values: (0..HEAT_SIDE * HEAT_SIDE).map(|i| (i % 7) as f32 / 6.0).collect() };
+    engine.set_heatmap(Some(&grid));
+    assert_eq!(engine.heatmap(), Some(&grid));
+    engine.set_ground_origin(Vector3::new(500.0, 0.0, -300.0));
+    engine.render().expect("frame with the heatmap");
+
+    let ramp = [[0.0; 3], [0.3; 3], [0.6; 3], [1.0; 3]];
+    engine.set_heat_ramp(ramp);
+    assert_eq!(engine.heat_ramp(), ramp);
+    engine.set_heatmap(None);
+    assert!(engine.heatmap().is_none());
+    engine.render().expect("frame without");
+}
@@ -0,0 +1,38 @@
+//! World-space height multiplier: deterministic, bounded and continuous.
+
+use hello_wgpu::designer_ml::HeightField;
+
+const FIELD: HeightField = HeightField { scale: 400.0, amplitude: 0.45 };
+
+#[test]
+fn flat_field_is_one() {
+    for (x, z) in [(0.0, 0.0), (123.4, -987.6), (-5000.0, 5000.0)] {
+        assert_eq!(HeightField::FLAT.sample(7, x, z), 1.0);
+    }
+}
+
+#[test]
+fn samples_are_deterministic_and_bounded() {
+    let mut lo = f32::MAX;
+    let mut hi = f32::MIN;
+    for i in -200..200 {
+        let (x, z) = (i as f32 * 37.3, i as f32 * -11.9 + 400.0);
+        let v = FIELD.sample(42, x, z);
+        assert_eq!(v, FIELD.sample(42, x, z));
+        assert!((1.0 - FIELD.amplitude..=1.0 + FIELD.amplitude).contains(&v), "{v} at ({x},{z})");
+        lo = lo.min(v);
+        hi = hi.max(v);
+    }
+    assert!(hi - lo > FIELD.amplitude * 0.5, "field barely varies: {lo}..{hi}");
+    assert_ne!(FIELD.sample(1, 100.0, 100.0), FIELD.sample(2, 100.0, 100.0), "seed has no effect");
+}
+
+#[test]
+fn field_is_continuous() {
+    // no jumps across lattice cells, chunk seams or the origin
+    for i in -2000..2000 {
+        let x = i as f32 * 0.73;
+        let (a, b) = (FIELD.sample(9, x, 0.5 * x), FIELD.sample(9, x + 0.05, 0.5 * x + 0.025));
+        assert!((a - b).abs() < 0.01, "step {} at x={x}", (a - b).abs());
+    }
+}
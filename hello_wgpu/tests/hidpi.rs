@@ -0,0 +1,31 @@
+//! Scale-factor changes: the window keeps its logical size, and the engine's
+//! frame-sized targets follow the new physical size.
+
+mod common;
+
+use hello_wgpu::hello_wgpu::rescale_size;
+use hello_wgpu::render::Engine;
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn logical_size_is_kept_across_monitors() {
+    let sz = PhysicalSize::new(1280, 720);
+    assert_eq!(rescale_size(sz, 1.0, 2.0), PhysicalSize::new(2560, 1440));
+    assert_eq!(rescale_size(rescale_size(sz, 1.0, 2.0), 2.0, 1.0), sz, "round trip");
+    assert_eq!(rescale_size(PhysicalSize::new(1001, 601), 1.0, 1.5), PhysicalSize::new(1502, 902), "rounded");
+    assert_eq!(rescale_size(sz, 1.25, 1.25), sz);
+}
+
+#[test]
+fn msaa_engine_renders_after_a_dpi_change() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let cfg = hello_wgpu::EngineConfig { sample_count: 4, ..Default::default() };
+    let mut engine = Engine::new_headless_with(device, queue, 64, 36, &cfg);
+    engine.render().expect("frame at 1×");
+    // depth and MSAA targets must match the frame, or the pass fails validation
+    for (old, new) in [(1.0, 2.0), (2.0, 1.5), (1.5, 1.0)] {
+        let sz = rescale_size(PhysicalSize::new(64, 36), 1.0, old);
+        engine.resize(rescale_size(sz, old, new));
+        engine.render().expect("frame after the scale change");
+    }
+}
@@ -0,0 +1,128 @@
+//! Impostor atlas: tile sizing against the device limit, the bake's
+//! framing, what lands in the atlas, the quad drawn from it, and frames
+//! drawn with impostors on.
+
+mod common;
+
+use cgmath::{Deg, ElementWise, EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3, Vector4, perspective};
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::RuntimePlacement;
+use hello_wgpu::culling::{bucket_instances, frustum_from_vp};
+use hello_wgpu::impostor::{
+    city_meshes, impostor_quad, impostor_tile, tile_view_proj, ImpostorAtlas, ImpostorFrame,
+    IMPOSTOR_ANGLES, IMPOSTOR_PAD, IMPOSTOR_ROWS,
+};
+use hello_wgpu::render::Engine;
+use hello_wgpu::types::InstanceRaw;
+
+#[test]
+fn tile_fits_the_largest_texture() {
+    assert_eq!(impostor_tile(128, 8192), 128);
+    assert_eq!(impostor_tile(4096, 8192), 8192 / IMPOSTOR_ANGLES);
+    assert_eq!(impostor_tile(4096, 2048) * IMPOSTOR_ANGLES, 2048);
+    assert_eq!(impostor_tile(0, 8192), 1);
+    assert_eq!(impostor_tile(128, 4), 1, "never zero");
+}
+
+#[test]
+fn every_view_frames_the_whole_mesh() {
+    let bounds = (Vector3::new(-1.0, 0.0, -3.0), Vector3::new(1.0, 8.0, 3.0));
+    for k in 0..IMPOSTOR_ANGLES {
+        let vp = tile_view_proj(bounds, k);
+        let c = vp * Vector4::new(0.0, 4.0, 0.0, 1.0);
+        assert!(c.x.abs() < 1e-4 && c.y.abs() < 1e-4, "view {k}: centre at {c:?}");
+        for i in 0..8 {
+            let p = Vector3::new(
+                if i & 1 == 0 { bounds.0.x } else { bounds.1.x },
+                if i & 2 == 0 { bounds.0.y } else { bounds.1.y },
+                if i & 4 == 0 { bounds.0.z } else { bounds.1.z });
+            let q = vp * p.extend(1.0);
+            assert!(q.x.abs() < 1.0 && q.y.abs() < 1.0 && (0.0..=1.0).contains(&q.z), "view {k}: corner {p:?} at {q:?}");
+        }
+    }
+}
+
+fn read_atlas(device: &wgpu::Device, queue: &wgpu::Queue, atlas: &ImpostorAtlas) -> Vec<u8> {
+    let (w, h) = atlas.size();
+    let row = (w * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None, size: (row * h) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ, mapped_at_creation: false,
+    });
+    let mut enc = device.create_command_encoder(&Default::default());
+    enc.copy_texture_to_buffer(
+        atlas.texture().as_image_copy(),
+        wgpu::TexelCopyBufferInfo { buffer: &buf, layout: wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(row), rows_per_image: None } },
+        wgpu::Extent3d { width: w, height: h, depth_or_array_layers: 1 });
+    queue.submit(Some(enc.finish()));
+    buf.slice(..).map_async(wgpu::MapMode::Read, |r| r.unwrap());
+    device.poll(wgpu::PollType::Wait).unwrap();
+    let data = buf.slice(..).get_mapped_range();
+    (0..h).flat_map(|y| data[(y * row) as usize..(y * row + w * 4) as usize].to_vec()).collect()
+}
+
+#[test]
+fn atlas_holds_a_silhouette_per_view() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let atlas = ImpostorAtlas::for_city(&device, &queue, 32);
+    assert_eq!(atlas.size(), (32 * IMPOSTOR_ANGLES, 32 * IMPOSTOR_ROWS));
+    let px = read_atlas(&device, &queue, &atlas);
+    let (w, t) = (atlas.size().0, atlas.tile);
+    let alpha = |x: u32, y: u32| px[((y * w + x) * 4 + 3) as usize];
+    for row in 0..IMPOSTOR_ROWS {
+        let mut coverage = Vec::new();
+        for k in 0..IMPOSTOR_ANGLES {
+            let (x0, y0) = (k * t, row * t);
+            assert_eq!(alpha(x0, y0), 0, "row {row} view {k}: corner is clear");
+            assert_eq!(alpha(x0 + t / 2, y0 + t / 2), 255, "row {row} view {k}: centre is covered");
+            coverage.push((0..t * t).filter(|i| alpha(x0 + i % t, y0 + i / t) > 0).count());
+        }
+        assert!(coverage.iter().any(|&c| c != coverage[0]), "row {row}: every view alike {coverage:?}");
+    }
+}
+
+#[test]
+fn impostor_quad_spans_the_lod0_mesh() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let eye = Point3::new(0.0, 5.0, 0.0);
+    let fr = frustum_from_vp(&(perspective(Deg(60.0), 1.0, 0.1, 1000.0)
+        * Matrix4::look_at_rh(eye, Point3::new(0.0, 5.0, 1.0), Vector3::unit_y())));
+    let frames = city_meshes().map(|m| ImpostorFrame::of(&m));
+    let scale = Vector3::new(2.0, 3.0, 1.5);
+    // a lowrise, its alt archetype, a highrise and a landmark, in the billboard band
+    for id in [0u16, 1, 3, 6] {
+        let p = RuntimePlacement::single(Vector3::new(4.0, 1.0, 250.0), scale, id);
+        let b = bucket_instances([&p], eye.to_vec(), &fr, 90.0, 190.0, 380.0, &assets);
+        let [bill] = b.v2_bill[..] else { panic!("archetype {id}: one billboard, got {}", b.v2_bill.len()) };
+        let (lo, hi) = assets.data_of(id as usize).bounds().unwrap();
+        let (lo, hi) = (p.center + lo.mul_element_wise(scale), p.center + hi.mul_element_wise(scale));
+        let (centre, half_w, half_h) = impostor_quad(&frames[bill.misc[0] as usize], &bill);
+        let want = (hi - lo) * 0.5;
+        assert!((centre - (lo + hi) * 0.5).magnitude() < 1e-4, "archetype {id}: centre {centre:?} vs AABB {lo:?}..{hi:?}");
+        assert!((half_w - want.x.max(want.z) * std::f32::consts::SQRT_2 * IMPOSTOR_PAD).abs() < 1e-4, "archetype {id}: half width {half_w}");
+        assert!((half_h - want.y * IMPOSTOR_PAD).abs() < 1e-4, "archetype {id}: half height {half_h}");
+    }
+}
+
+#[test]
+fn engine_draws_impostors_in_every_billboard_mode() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let cfg = hello_wgpu::EngineConfig { sample_count: 4, impostors: true, impostor_tile: 16, ..Default::default() };
+    let mut engine = Engine::new_headless_with(device, queue, 64, 64, &cfg);
+    assert!(engine.impostors());
+    assert_eq!(engine.impostor_atlas().tile, 16);
+    let bill = InstanceRaw { pos: [0.0, 4.0, -30.0, 1.0], scale: [2.0, 4.0, 1.0, 1.0], misc: [1.0, 0.0, 0.0, 77.0] };
+    engine.update_instances(&[], &[], &[], &[], &[], &[], &[], &[], &[bill], &bill);
+    engine.update_camera(&(cgmath::perspective(cgmath::Deg(60.0), 1.0, 0.1, 500.0)
+        * cgmath::Matrix4::look_at_rh(cgmath::Point3::new(5.0, 4.0, 0.0), cgmath::Point3::new(0.0, 4.0, -30.0), Vector3::unit_y())));
+    for (blend, aa, prepass) in [(false, false, false), (true, false, false), (false, true, false), (false, false, true)] {
+        engine.set_billboard_blend(blend);
+        engine.set_billboard_aa(aa);
+        engine.set_depth_prepass(prepass);
+        engine.render().expect("frame with impostors");
+    }
+    engine.set_impostors(false);
+    assert!(!engine.impostors());
+    engine.render().expect("frame with flat billboards");
+}
@@ -0,0 +1,74 @@
+//! Chunk isolation: only `ChunkManager::isolated` is drawn and picked, and
+//! with `isolate_loads` it is the only chunk loaded.
+
+mod common;
+
+use cgmath::{Deg, Matrix4, Point3, Vector3, perspective};
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::culling::{frustum_from_vp, ChunkCullCache, LodHysteresis};
+use hello_wgpu::hello_wgpu::{build_instance_buckets, chunk_border_lines};
+
+fn manager() -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0x150), 1, (-3, 3, -3, 3), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm
+}
+
+#[test]
+fn only_the_isolated_chunk_is_drawn_and_picked() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager();
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.len(), 9);
+
+    // looking straight down from high above the origin: every chunk in view
+    let eye = Point3::new(0.0, 800.0, 0.0);
+    let view = Matrix4::look_at_rh(eye, Point3::new(0.0, 0.0, 0.0), Vector3::unit_z());
+    let fr = frustum_from_vp(&(perspective(Deg(120.0), 1.0, 1.0, 2000.0) * view));
+    let total = |cm: &ChunkManager| {
+        let (b, _) = build_instance_buckets(cm, &assets, &fr, Vector3::new(0.0, 800.0, 0.0), (0.0, 0.0),
+                                            (f32::INFINITY, f32::INFINITY), &mut LodHysteresis::default(),
+                                            &mut ChunkCullCache::default());
+        b.total()
+    };
+    let key = ChunkKey(1, 0);
+    assert_eq!(total(&cm), cm.loaded.values().flatten().map(|p| p.boxes().count()).sum::<usize>());
+
+    cm.isolated = Some(key);
+    assert!(cm.shows(key) && !cm.shows(ChunkKey(0, 0)));
+    assert_eq!(total(&cm), cm.loaded[&key].iter().map(|p| p.boxes().count()).sum::<usize>());
+    assert_eq!(chunk_border_lines(&cm).len(), 8);
+    // a ray down through the origin chunk finds nothing, one through the isolated chunk does
+    let down = Vector3::new(0.0, -1.0, 0.0);
+    let p = &cm.loaded[&key][0];
+    assert_eq!(cm.pick_ray(Vector3::new(p.center.x, 500.0, p.center.z), down, 1000.0, &assets).map(|h| h.0), Some(key));
+    let q = &cm.loaded[&ChunkKey(0, 0)][0];
+    assert!(cm.pick_ray(Vector3::new(q.center.x, 500.0, q.center.z), down, 1000.0, &assets).is_none_or(|h| h.0 == key));
+}
+
+#[test]
+fn isolated_loading_ignores_the_viewer_window() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let (cw, cd) = chunk_world_span(&params(0x150));
+    let mut cm = manager();
+    assert_eq!(cm.chunk_at(2.6 * cw, -0.4 * cd), Some(ChunkKey(3, 0)));
+    assert_eq!(cm.chunk_at(3.6 * cw, 0.0), Some(ChunkKey(-3, 0)), "wrapped");
+
+    let far = ChunkKey(3, 3);
+    cm.isolated = Some(far);
+    cm.isolate_loads = true;
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.keys().copied().collect::<Vec<_>>(), [far]);
+
+    // leaving isolation streams the viewer's window again, without `far`
+    cm.isolated = None;
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.len(), 9);
+    assert!(!cm.loaded.contains_key(&far));
+}
@@ -0,0 +1,68 @@
+//! Per-axis load window: `chunk_radius_x` by `chunk_radius_z` chunks around
+//! each viewer, and `cover_cull` sizing each axis by its own chunk span.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+
+#[test]
+fn asymmetric_window_spans_each_radius() {
+    let assets = AssetLibrary::data_only();
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-6, 6, -6, 6), false, "unused");
+    cm.store = StoreBackend::None;
+    (cm.chunk_radius_x, cm.chunk_radius_z) = (3, 1);
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+
+    let mut keys: Vec<ChunkKey> = cm.loaded.keys().copied().collect();
+    keys.sort_by_key(|k| (k.1, k.0));
+    let expect: Vec<ChunkKey> = (-1..=1).flat_map(|z| (-3..=3).map(move |x| ChunkKey(x, z))).collect();
+    assert_eq!(keys, expect, "7 wide along x, 3 deep along z");
+
+    let (cw, cd) = chunk_world_span(&cm.params);
+    assert_eq!(cm.loaded_distance(), (3.0 * cw).min(cd));
+}
+
+#[test]
+fn chunks_outside_every_window_are_evicted() {
+    let assets = AssetLibrary::data_only();
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_evict_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-20, 20, -20, 20), false, &dir);
+    (cm.chunk_radius_x, cm.chunk_radius_z) = (2, 1);
+    let (cw, _) = chunk_world_span(&cm.params);
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    cm.mutate_near(&assets, 1.0, 1.0, 0, 7);
+    assert!(cm.is_dirty(ChunkKey(0, 0)));
+    let edited: Vec<u16> = cm.loaded[&ChunkKey(0, 0)].iter().map(|p| p.archetype_id).collect();
+
+    for step in 1..=12 {
+        cm.set_viewer(0, step as f32 * cw, 0.0);
+        cm.ensure_for_viewers(&assets);
+        assert_eq!(cm.loaded.len(), 15, "5 by 3 after step {step}");
+        assert!(cm.loaded.keys().all(|k| (k.0 - step).abs() <= 2 && k.1.abs() <= 1));
+    }
+    assert!(!cm.is_dirty(ChunkKey(0, 0)));
+
+    // a second viewer keeps its own window; the edits came back from the store
+    cm.set_viewer(1, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    assert_eq!(cm.loaded.len(), 30);
+    let back: Vec<u16> = cm.loaded[&ChunkKey(0, 0)].iter().map(|p| p.archetype_id).collect();
+    assert_eq!(back, edited, "saved on eviction");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cover_cull_raises_only_the_short_axis() {
+    let mut cm = ChunkManager::new(params(0x5EED), 1, (-6, 6, -6, 6), false, "unused");
+    let (cw, _) = chunk_world_span(&cm.params);
+    (cm.chunk_radius_x, cm.chunk_radius_z) = (1, 4);
+    cm.cover_cull(2.5 * cw);
+    assert_eq!((cm.chunk_radius_x, cm.chunk_radius_z), (3, 4));
+}
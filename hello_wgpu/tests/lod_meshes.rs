@@ -0,0 +1,60 @@
+//! Per-LOD models: LOD1 draws a simplified mesh per category (and per
+//! archetype where it has its own), LOD2 the billboard.
+
+mod common;
+
+use hello_wgpu::assets::{AssetLibrary, CategoryMesh};
+use hello_wgpu::mesh::{self, MeshData};
+use hello_wgpu::render::Engine;
+use hello_wgpu::types::InstanceRaw;
+
+const BUILDINGS: [CategoryMesh; 3] = [CategoryMesh::Lowrise, CategoryMesh::Highrise, CategoryMesh::Landmark];
+
+#[test]
+fn lod1_builders_are_cheaper_and_no_bigger() {
+    for (lod0, lod1) in [(mesh::block_lowrise_data(), mesh::block_lowrise_lod1_data()),
+                         (mesh::tower_highrise_data(), mesh::tower_highrise_lod1_data()),
+                         (mesh::pyramid_tower_data(), mesh::pyramid_tower_lod1_data())] {
+        assert!(lod1.indices.len() < lod0.indices.len());
+        let ((min0, max0), (min1, max1)) = (lod0.bounds().unwrap(), lod1.bounds().unwrap());
+        assert_eq!(min1, min0, "same footprint, standing on the same ground");
+        assert!(max1.y <= max0.y && (max1.x, max1.z) == (max0.x, max0.z));
+    }
+    let no_underside = |m: &MeshData| m.vertices.iter().all(|v| v.normal != [0.0, -1.0, 0.0]);
+    assert!(no_underside(&mesh::block_lowrise_lod1_data()));
+}
+
+#[test]
+fn meshes_are_picked_per_lod() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    for cm in BUILDINGS {
+        let (l0, l1, l2) = (assets.mesh_for_lod(cm, 0), assets.mesh_for_lod(cm, 1), assets.mesh_for_lod(cm, 2));
+        assert!(l1.index_count < l0.index_count, "{cm:?}");
+        assert_eq!(l2.index_count, assets.meshes().billboard.index_count, "{cm:?}");
+    }
+    assert_eq!(assets.mesh_for_lod(CategoryMesh::Ground, 1).index_count, assets.meshes().ground.index_count);
+
+    // timber_house_b has its own models; the rest fall back to the category's
+    let alt = &assets.archetypes[1];
+    assert!(alt.lod_meshes[0].is_some() && alt.lod_meshes[1].is_some());
+    assert!(assets.mesh_of(1, 1).index_buffer == alt.lod_meshes[1].as_ref().unwrap().index_buffer);
+    assert!(assets.mesh_of(0, 1).index_buffer == assets.meshes().lowrise_lod1.index_buffer);
+    assert!(assets.mesh_of(0, 7).index_buffer == assets.meshes().billboard.index_buffer);
+}
+
+#[test]
+fn lod1_buckets_draw_fewer_triangles() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 32, 32);
+    let inst = InstanceRaw { pos: [0.0; 4], scale: [1.0, 1.0, 1.0, 0.0], misc: [0.0; 4] };
+    let mut tris = |lod1: bool| {
+        let (l0, l1): (&[InstanceRaw], &[InstanceRaw]) = if lod1 { (&[], &[inst]) } else { (&[inst], &[]) };
+        engine.update_instances(l0, l0, l0, l0, l1, l1, l1, l1, &[], &inst);
+        engine.render().expect("frame");
+        engine.frame_stats().triangles
+    };
+    let (near, far) = (tris(false), tris(true));
+    // an underside (2 triangles) saved per bucket, plus the landmark's roof
+    assert_eq!(near - far, 4 * 2 + 4);
+}
@@ -0,0 +1,20 @@
+//! `RUST_LOG`-style level specs accepted by the web build.
+
+use hello_wgpu::hello_wgpu::parse_log_level;
+use log::LevelFilter;
+
+#[test]
+fn bare_levels_parse_case_insensitively() {
+    assert_eq!(parse_log_level("debug"), Some(LevelFilter::Debug));
+    assert_eq!(parse_log_level(" WARN "), Some(LevelFilter::Warn));
+    assert_eq!(parse_log_level("off"), Some(LevelFilter::Off));
+    assert_eq!(parse_log_level("loud"), None);
+    assert_eq!(parse_log_level(""), None);
+}
+
+#[test]
+fn crate_directive_wins_and_others_are_ignored() {
+    assert_eq!(parse_log_level("wgpu_core=warn,hello_wgpu=trace,info"), Some(LevelFilter::Trace));
+    assert_eq!(parse_log_level("wgpu_core=warn,error"), Some(LevelFilter::Error));
+    assert_eq!(parse_log_level("wgpu_core=debug"), None);
+}
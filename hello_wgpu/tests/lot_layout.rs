@@ -0,0 +1,61 @@
+//! Jittered lot layout: deterministic, off-grid, and never overlapping.
+
+mod common;
+
+use cgmath::Vector3;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, LotLayout, Placement, RuleDesigner};
+
+const MIN_GAP: f32 = 0.4;
+
+fn jittered() -> RuleDesigner {
+    RuleDesigner { layout: LotLayout::Jittered { min_gap: MIN_GAP }, ..RuleDesigner::new(common::params(0xA11CE)) }
+}
+
+fn design(d: &mut RuleDesigner, assets: &AssetLibrary, cx: i32, cz: i32) -> Vec<Placement> {
+    d.design_chunk(&DesignContext::new(cx, cz, d.params.seed), assets)
+}
+
+fn footprint(p: &Placement, assets: &AssetLibrary) -> Vector3<f32> {
+    let b = assets.base_half(p.archetype_id as usize);
+    Vector3::new(b.x * p.scale.x, b.y * p.scale.y, b.z * p.scale.z)
+}
+
+#[test]
+fn jittered_lots_do_not_overlap() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut d = jittered();
+    // a 2×2 patch of chunks, so buildings on either side of a chunk seam are compared too
+    let all: Vec<Placement> = [(0, 0), (1, 0), (0, 1), (1, 1)].iter()
+        .flat_map(|&(cx, cz)| design(&mut d, &assets, cx, cz)).collect();
+    assert!(!all.is_empty());
+    for (i, a) in all.iter().enumerate() {
+        let ha = footprint(a, &assets);
+        for b in &all[i + 1..] {
+            let hb = footprint(b, &assets);
+            let gap_x = (a.center.x - b.center.x).abs() - ha.x - hb.x;
+            let gap_z = (a.center.z - b.center.z).abs() - ha.z - hb.z;
+            assert!(gap_x >= MIN_GAP - 1e-3 || gap_z >= MIN_GAP - 1e-3,
+                    "buildings at {:?} and {:?} are closer than {MIN_GAP} m", a.center, b.center);
+        }
+    }
+}
+
+#[test]
+fn jitter_is_deterministic_and_moves_buildings() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let key = |v: &[Placement]| v.iter().map(|p| (p.archetype_id, p.center.x.to_bits(), p.center.z.to_bits())).collect::<Vec<_>>();
+
+    let a = design(&mut jittered(), &assets, 2, -1);
+    let b = design(&mut jittered(), &assets, 2, -1);
+    assert_eq!(key(&a), key(&b));
+
+    // same archetypes/scales as the grid layout, only the positions move
+    let grid = design(&mut RuleDesigner::new(common::params(0xA11CE)), &assets, 2, -1);
+    assert_eq!(a.len(), grid.len());
+    assert!(a.iter().zip(&grid).all(|(j, g)| j.archetype_id == g.archetype_id && j.scale == g.scale));
+    let moved = a.iter().zip(&grid).filter(|(j, g)| (j.center - g.center).x.abs() > 1e-3).count();
+    assert!(moved > a.len() / 2, "only {moved} of {} buildings moved", a.len());
+}
@@ -0,0 +1,21 @@
+//! `MeshData`: builder geometry checked on the CPU, no GPU needed.
+
+use cgmath::Vector3;
+use hello_wgpu::mesh::{self, MeshData};
+
+#[test]
+fn cuboid_has_24_vertices_and_its_bounds() {
+    let m = mesh::cuboid_data(3.0, 0.8, 2.0, [1.0; 4]);
+    assert_eq!((m.vertices.len(), m.indices.len()), (24, 36), "4 corners and 2 triangles per face");
+    assert!(m.indices.iter().all(|&i| (i as usize) < m.vertices.len()));
+    assert_eq!(m.bounds(), Some((Vector3::new(-1.5, -0.4, -1.0), Vector3::new(1.5, 0.4, 1.0))));
+}
+
+#[test]
+fn transforms_move_the_bounds() {
+    let mut m = mesh::cuboid_data(2.0, 2.0, 2.0, [1.0, 0.5, 0.5, 1.0]);
+    m.scale_translate(Vector3::new(2.0, 1.0, 1.0), Vector3::new(0.0, 1.0, 0.0)).tint([0.5, 1.0, 1.0, 1.0]);
+    assert_eq!(m.bounds(), Some((Vector3::new(-2.0, 0.0, -1.0), Vector3::new(2.0, 2.0, 1.0))));
+    assert!(m.vertices.iter().all(|v| v.color == [0.5, 0.5, 0.5, 1.0]));
+    assert_eq!(MeshData::default().bounds(), None);
+}
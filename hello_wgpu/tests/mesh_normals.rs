@@ -0,0 +1,60 @@
+//! `NormalMode`: normals kept, computed per face, or smoothed per position.
+
+use std::collections::HashSet;
+
+use hello_wgpu::mesh::{MeshData, NormalMode, Vertex};
+
+/// Unit cube as an OBJ would give it: 8 shared corners, no normals, each
+/// face two counter-clockwise triangles.
+fn cube() -> MeshData {
+    let vertices = (0..8).map(|i| Vertex {
+        position: [(i & 1) as f32 * 2.0 - 1.0, (i >> 1 & 1) as f32 * 2.0 - 1.0, (i >> 2 & 1) as f32 * 2.0 - 1.0],
+        color: [1.0; 4], normal: [0.0; 3], uv: [0.0; 2],
+    }).collect();
+    let quads = [[0, 4, 6, 2], [1, 3, 7, 5], [0, 1, 5, 4], [2, 6, 7, 3], [0, 2, 3, 1], [4, 5, 7, 6]];
+    let indices = quads.iter().flat_map(|q| [q[0], q[1], q[2], q[0], q[2], q[3]]).collect();
+    MeshData::new(vertices, indices)
+}
+
+fn normal_set(m: &MeshData) -> HashSet<[i32; 3]> {
+    m.vertices.iter().map(|v| v.normal.map(|c| (c * 1000.0).round() as i32)).collect()
+}
+
+#[test]
+fn flat_gives_the_six_axes_on_unshared_vertices() {
+    let flat = cube().with_normals(NormalMode::FlatComputed);
+    assert_eq!((flat.vertices.len(), flat.indices.len()), (36, 36));
+    let axes: HashSet<[i32; 3]> = [[1000, 0, 0], [-1000, 0, 0], [0, 1000, 0], [0, -1000, 0], [0, 0, 1000], [0, 0, -1000]].into();
+    assert_eq!(normal_set(&flat), axes);
+    // each points out of the cube, through its own triangle
+    for t in flat.indices.chunks(3) {
+        let c: Vec<f32> = (0..3).map(|k| t.iter().map(|&i| flat.vertices[i as usize].position[k]).sum()).collect();
+        let n = flat.vertices[t[0] as usize].normal;
+        assert!(c[0] * n[0] + c[1] * n[1] + c[2] * n[2] > 0.0);
+    }
+}
+
+#[test]
+fn smooth_gives_the_eight_corner_diagonals() {
+    let smooth = cube().with_normals(NormalMode::SmoothComputed);
+    assert_eq!((smooth.vertices.len(), smooth.indices.len()), (8, 36), "topology kept");
+    let d = (1000.0 / 3f32.sqrt()).round() as i32;
+    let corners: HashSet<[i32; 3]> = (0..8).map(|i| [if i & 1 == 0 { -d } else { d }, if i & 2 == 0 { -d } else { d }, if i & 4 == 0 { -d } else { d }]).collect();
+    assert_eq!(normal_set(&smooth), corners, "angle weighting ignores the face diagonals");
+    for v in &smooth.vertices {
+        assert!(v.position.iter().zip(v.normal).all(|(p, n)| p.signum() == n.signum()));
+    }
+
+    // vertices split for uvs still share the smoothed normal per position
+    let split = cube().with_normals(NormalMode::FlatComputed).with_normals(NormalMode::SmoothComputed);
+    assert_eq!(normal_set(&split), corners);
+}
+
+#[test]
+fn from_file_keeps_the_normals() {
+    let mut m = cube();
+    for v in &mut m.vertices { v.normal = [0.0, 0.0, 1.0]; }
+    let kept = m.clone().with_normals(NormalMode::FromFile);
+    assert_eq!(normal_set(&kept), HashSet::from([[0, 0, 1000]]));
+    assert_eq!(kept.indices, m.indices);
+}
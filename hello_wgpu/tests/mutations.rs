@@ -0,0 +1,94 @@
+//! Live mutations: fixed tick independent of the frame rate, pausing with
+//! `skip_mutations`, and the `on_mutated` notifications.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::ChunkManager;
+
+/// (cx, cz, [(archetype, scale bits)]) per loaded chunk, sorted by key.
+type CitySnapshot = Vec<(i32, i32, Vec<(u16, [u32; 3])>)>;
+
+/// Run mutations for `frames` frames of `dt` and return the resulting city.
+fn simulate(assets: &AssetLibrary, frames: u32, dt: f32) -> CitySnapshot {
+    let mut cm = world(assets);
+    for _ in 0..frames { cm.mutate_near(assets, 0.5, dt, 1, 99); }
+    snapshot(&cm)
+}
+
+fn world(assets: &AssetLibrary) -> ChunkManager {
+    let dir = std::env::temp_dir().join("hello_wgpu_mutations_unused");
+    let mut cm = ChunkManager::new(params(0xA11CE), 1, (-2, 2, -2, 2), false, dir.to_str().unwrap());
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(assets);
+    cm
+}
+
+fn snapshot(cm: &ChunkManager) -> CitySnapshot {
+    let mut out: Vec<_> = cm.loaded.iter().map(|(k, l)| {
+        (k.0, k.1, l.iter().map(|p| (p.archetype_id, [p.scale.x.to_bits(), p.scale.y.to_bits(), p.scale.z.to_bits()])).collect())
+    }).collect();
+    out.sort_by_key(|c| (c.0, c.1));
+    out
+}
+
+#[test]
+fn mutations_do_not_depend_on_frame_rate() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    // 1.05 s either way: 10 ticks at the default 10 Hz
+    let slow = simulate(&assets, 42, 1.0 / 40.0);
+    let fast = simulate(&assets, 126, 1.0 / 120.0);
+    let untouched = simulate(&assets, 0, 0.0);
+    assert_eq!(slow, fast);
+    assert_ne!(slow, untouched, "something should have mutated");
+}
+
+#[test]
+fn paused_mutations_keep_the_clock_running() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let untouched = simulate(&assets, 0, 0.0);
+    let resumed = |frames: u32, dt: f32| {
+        let mut cm = world(&assets);
+        for _ in 0..frames { cm.skip_mutations(dt); }
+        assert_eq!(snapshot(&cm), untouched, "skipping changes nothing");
+        cm.mutate_near(&assets, 0.5, 0.1, 1, 99);
+        snapshot(&cm)
+    };
+    // ~1 s paused at either frame rate lands on the same tick afterwards...
+    let slow = resumed(42, 1.0 / 40.0);
+    assert_eq!(slow, resumed(126, 1.0 / 120.0));
+    // ...which is not the first tick a never-paused client would run
+    assert_ne!(slow, simulate(&assets, 1, 0.1));
+    assert_ne!(slow, untouched);
+}
+
+#[test]
+fn on_mutated_reports_every_edited_building() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = ChunkManager::new(params(0xA11CE), 1, (-2, 2, -2, 2), false, "unused");
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    let before: Vec<_> = cm.loaded.iter().map(|(k, l)| (*k, l.iter().map(|p| p.archetype_id).collect::<Vec<_>>())).collect();
+
+    let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let log = events.clone();
+    cm.on_mutated = Some(Box::new(move |k, i| log.borrow_mut().push((k, i))));
+    cm.mutate_near(&assets, 0.5, 0.1, 1, 99);
+
+    let events = events.borrow();
+    assert!(!events.is_empty());
+    for (k, i) in events.iter() {
+        assert!(i < &cm.loaded[k].len());
+        assert!(cm.revision(*k) > 0 && cm.is_dirty(*k));
+    }
+    // every building whose archetype changed was reported
+    for (k, ids) in before {
+        for (i, id) in ids.into_iter().enumerate() {
+            if cm.loaded[&k][i].archetype_id != id { assert!(events.contains(&(k, i)), "{k:?}/{i} not reported"); }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Resetting to spawn: `ChunkManager::reset_origin` undoes the floating-origin
+//! shifts, so the central chunks come back exactly where they were designed;
+//! chunks designed while shifted land at their design positions too.
+
+mod common;
+
+use cgmath::{InnerSpace, Vector3};
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::camera::{Camera, CameraState};
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+
+fn manager() -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0x0121), 1, (-8, 8, -8, 8), false, "unused");
+    cm.store = StoreBackend::None;
+    cm
+}
+
+#[test]
+fn reset_returns_to_the_true_origin() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let spawn = CameraState::SPAWN.position;
+
+    let mut fresh = manager();
+    fresh.set_viewer(0, spawn[0], spawn[2]);
+    fresh.ensure_for_viewers(&assets);
+
+    // fly out along +x, re-centring every 500 m like the app does
+    let mut cm = manager();
+    let mut cam = Camera::new();
+    for _ in 0..4 {
+        cam.position.x += 520.0;
+        cm.set_viewer(0, cam.position.x, cam.position.z);
+        cm.ensure_for_viewers(&assets);
+        let off = Vector3::new(cam.position.x, 0.0, cam.position.z);
+        cm.apply_shift(off);
+        cam.position -= off;
+    }
+    assert!(cm.origin_shift().x > 2000.0);
+    assert!(!cm.loaded.contains_key(&ChunkKey(0, 0)));
+
+    let off = cm.reset_origin();
+    assert!(off.x < -2000.0);
+    assert_eq!(cm.origin_shift(), Vector3::new(0.0, 0.0, 0.0));
+    cam.position -= off;
+    cam.reset_to_spawn();
+    assert_eq!(cam.state(), CameraState::SPAWN);
+
+    cm.set_viewer(0, cam.position.x, cam.position.z);
+    cm.ensure_for_viewers(&assets);
+    let centre = ChunkKey(0, 0);
+    let got: Vec<_> = cm.loaded[&centre].iter().map(|p| (p.archetype_id, p.center)).collect();
+    let want: Vec<_> = fresh.loaded[&centre].iter().map(|p| (p.archetype_id, p.center)).collect();
+    assert!(!want.is_empty());
+    assert_eq!(got, want, "centre chunk reloaded at design positions");
+}
+
+#[test]
+fn chunks_designed_while_shifted_sit_at_their_design_positions() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let spawn = CameraState::SPAWN.position;
+
+    let mut fresh = manager();
+    fresh.set_viewer(0, spawn[0], spawn[2]);
+    fresh.ensure_for_viewers(&assets);
+
+    // re-centred before anything loaded: every chunk below is designed shifted
+    let off = Vector3::new(520.0, 0.0, -1040.0);
+    let mut cm = manager();
+    cm.apply_shift(off);
+    cm.set_viewer(0, spawn[0] - off.x, spawn[2] - off.z);
+    cm.ensure_for_viewers(&assets);
+
+    let centre = ChunkKey(0, 0);
+    let (got, want) = (&cm.loaded[&centre], &fresh.loaded[&centre]);
+    assert!(!want.is_empty());
+    assert_eq!(got.len(), want.len());
+    for (g, w) in got.iter().zip(want) {
+        assert_eq!(g.archetype_id, w.archetype_id);
+        assert!((g.center + off - w.center).magnitude() < 1e-3, "designed at {:?}, want {:?}", g.center + off, w.center);
+    }
+}
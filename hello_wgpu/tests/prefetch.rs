@@ -0,0 +1,75 @@
+//! Prefetch: `ensure_for_viewers` also loads the window around where each
+//! viewer will be in `prefetch_secs`, from a velocity that must be sane.
+
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager};
+use hello_wgpu::city_store::StoreBackend;
+
+fn manager() -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0x9EF7), 1, (-8, 8, -8, 8), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.prefetch_secs = 2.0;
+    cm
+}
+
+/// Every chunk loaded, in order, by a viewer at the origin heading +x at a
+/// speed that puts its lead point 3 chunks ahead, `gen_budget` per call.
+fn load_order(assets: &AssetLibrary, gen_budget: usize) -> Vec<ChunkKey> {
+    let mut cm = manager();
+    cm.gen_budget = gen_budget;
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let sink = log.clone();
+    cm.on_chunk_loaded = Some(Box::new(move |k, _, _| sink.borrow_mut().push(k)));
+    let (cw, _) = chunk_world_span(&cm.params);
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.set_viewer_velocity(0, 3.0 * cw / cm.prefetch_secs, 0.0);
+    while cm.loaded.len() < 18 {
+        let before = cm.loaded.len();
+        cm.ensure_for_viewers(assets);
+        let n = cm.loaded.len() - before;
+        assert!((1..=gen_budget).contains(&n), "{n} chunks in one call");
+    }
+    cm.ensure_for_viewers(assets);
+    assert_eq!(cm.loaded.len(), 18, "both windows, nothing more");
+    log.take()
+}
+
+#[test]
+fn leading_edge_loads_first_within_the_budget() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let order = load_order(&assets, 2);
+    assert_eq!(order.len(), 18);
+    let (window, ahead) = order.split_at(9);
+    // the viewer's own window first, its leading column before its trailing one
+    assert!(window.iter().all(|k| (-1..=1).contains(&k.0) && (-1..=1).contains(&k.1)), "{window:?}");
+    assert!(window.windows(2).all(|w| w[0].0 >= w[1].0), "leading edge first: {window:?}");
+    assert_eq!(&window[..3], &[ChunkKey(1, 0), ChunkKey(1, -1), ChunkKey(1, 1)]);
+    // then the window around the lead point, its centre first
+    assert_eq!(ahead[0], ChunkKey(3, 0));
+    assert!(ahead.iter().all(|k| (2..=4).contains(&k.0) && (-1..=1).contains(&k.1)), "{ahead:?}");
+
+    assert_eq!(load_order(&assets, 2), order, "same order every run");
+    assert_eq!(load_order(&assets, 5), order, "the budget only splits it over calls");
+}
+
+#[test]
+fn non_finite_velocity_is_dropped() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager();
+    let (cw, cd) = chunk_world_span(&cm.params);
+    cm.set_viewer(0, 4.0 * cw, 4.0 * cd);
+    cm.set_viewer_velocity(0, f32::NAN, 0.0);
+    cm.set_viewer_velocity(1, 0.0, f32::INFINITY);
+    cm.ensure_for_viewers(&assets);
+    // a NaN lead point would land on chunk 0 and load the design origin
+    assert_eq!(cm.loaded.len(), 9);
+    assert!(cm.loaded.keys().all(|k| (3..=5).contains(&k.0) && (3..=5).contains(&k.1)), "{:?}", cm.loaded.keys());
+}
@@ -0,0 +1,42 @@
+//! Adaptive quality: scale down on slow frames, up with headroom, hold in between.
+
+use hello_wgpu::quality::QualityScaler;
+
+/// Feed `secs` of frames lasting `ms`; returns every scale change.
+fn run(q: &mut QualityScaler, ms: f32, secs: f32) -> Vec<f32> {
+    let dt = ms / 1000.0;
+    (0..(secs / dt) as u32).filter_map(|_| q.update(ms, dt)).collect()
+}
+
+#[test]
+fn slow_frames_pull_distances_in_down_to_the_minimum() {
+    let mut q = QualityScaler::new(20.0);
+    let changes = run(&mut q, 40.0, 30.0);
+    assert!(changes.windows(2).all(|w| w[1] < w[0]), "monotonic: {changes:?}");
+    assert_eq!(q.scale(), q.min_scale);
+    // at most one change per cooldown
+    assert!(changes.len() <= 30);
+}
+
+#[test]
+fn headroom_band_holds_and_fast_frames_relax_to_the_maximum() {
+    let mut q = QualityScaler::new(20.0);
+    run(&mut q, 40.0, 5.0);
+    let reduced = q.scale();
+    assert!(reduced < 1.0);
+
+    // 18 ms: under target but inside the 25 % headroom → no oscillation
+    assert!(run(&mut q, 18.0, 10.0).is_empty());
+    assert_eq!(q.scale(), reduced);
+
+    run(&mut q, 8.0, 30.0);
+    assert_eq!(q.scale(), q.max_scale);
+}
+
+#[test]
+fn disabled_scaler_never_changes() {
+    let mut q = QualityScaler::new(20.0);
+    q.enabled = false;
+    assert!(run(&mut q, 100.0, 10.0).is_empty());
+    assert_eq!(q.scale(), 1.0);
+}
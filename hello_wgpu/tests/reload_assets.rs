@@ -0,0 +1,35 @@
+//! `Engine::reload_assets` rebuilds the library in place and drops every
+//! instance / baked draw that referenced the old one.
+
+mod common;
+
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+use hello_wgpu::render::Engine;
+use hello_wgpu::types::InstanceRaw;
+
+#[test]
+fn reload_drops_instances_and_bakes() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    let archetypes = engine.assets.archetypes.len();
+
+    let inst = InstanceRaw { pos: [0.0; 4], scale: [1.0, 1.0, 1.0, 0.0], misc: [0.0; 4] };
+    let many = vec![inst; 5];
+    engine.update_instances(&many, &[], &many, &[], &[], &[], &[], &[], &many, &inst);
+
+    let mut cm = ChunkManager::new(common::params(0x5EED), 1, (-2, 2, -2, 2), false, "unused");
+    cm.store = hello_wgpu::city_store::StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&engine.assets);
+    engine.update_baked(&cm, &[ChunkKey(1, 0)]);
+
+    engine.render().expect("frame");
+    let before = engine.frame_stats();
+    assert_eq!(before.draw_calls, 1 + 3 + 1, "ground, three batches, one bake");
+
+    engine.reload_assets();
+    assert_eq!(engine.assets.archetypes.len(), archetypes);
+    engine.render().expect("frame");
+    let after = engine.frame_stats();
+    assert_eq!((after.draw_calls, after.instances), (1, 1), "only the ground is left");
+}
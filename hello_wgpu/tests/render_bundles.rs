@@ -0,0 +1,60 @@
+//! Baked chunks replayed from render bundles: recorded once a chunk has been
+//! stable for `BUNDLE_AFTER_FRAMES`, kept while out of view, dropped when
+//! it mutates, and drawn the same as the immediate path.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::render::{BUNDLE_AFTER_FRAMES, Engine};
+
+fn city() -> (ChunkManager, Vec<ChunkKey>) {
+    let mut cm = ChunkManager::new(params(1), 1, (-4, 4, -4, 4), false, "unused");
+    let keys = vec![ChunkKey(0, 1), ChunkKey(1, 1), ChunkKey(-1, 1)];
+    for (i, &k) in keys.iter().enumerate() {
+        let p = RuntimePlacement::single(Vector3::new(k.0 as f32 * 40.0, 1.0, 60.0), Vector3::new(1.0, 1.0 + i as f32, 1.0), 0);
+        cm.loaded.insert(k, vec![p]);
+    }
+    (cm, keys)
+}
+
+/// Draw calls and triangles of `frames` frames of `keys` baked.
+fn frames(engine: &mut Engine, cm: &ChunkManager, keys: &[ChunkKey], n: u32) -> (u32, u64) {
+    for _ in 0..n {
+        engine.update_baked(cm, keys);
+        engine.render().expect("frame");
+    }
+    let s = engine.frame_stats();
+    (s.draw_calls, s.triangles)
+}
+
+#[test]
+fn stable_chunks_are_bundled_and_draw_the_same() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    let (mut cm, keys) = city();
+
+    let early = frames(&mut engine, &cm, &keys, BUNDLE_AFTER_FRAMES - 1);
+    assert_eq!(engine.bundled_chunks(), 0, "not stable yet");
+    let bundled = frames(&mut engine, &cm, &keys, 1);
+    assert_eq!(engine.bundled_chunks(), keys.len());
+    assert_eq!(bundled, early);
+
+    // turning away and back doesn't rebake or re-record
+    frames(&mut engine, &cm, &keys[1..], 1);
+    assert_eq!(frames(&mut engine, &cm, &keys, 1), bundled);
+    assert_eq!(engine.bundled_chunks(), keys.len());
+
+    // a mutated chunk goes back to the immediate path until it settles
+    cm.insert_building(keys[0], RuntimePlacement::single(Vector3::new(3.0, 1.0, 62.0), Vector3::new(1.0, 1.0, 1.0), 0)).unwrap();
+    frames(&mut engine, &cm, &keys, 1);
+    assert_eq!(engine.bundled_chunks(), keys.len() - 1);
+
+    // prepass switches the recorded pipeline; off draws everything immediately
+    engine.set_depth_prepass(true);
+    let with_prepass = frames(&mut engine, &cm, &keys, 1);
+    engine.set_render_bundles(false);
+    assert_eq!(frames(&mut engine, &cm, &keys, BUNDLE_AFTER_FRAMES), with_prepass);
+    assert_eq!(engine.bundled_chunks(), 0);
+}
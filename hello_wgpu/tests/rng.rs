@@ -0,0 +1,30 @@
+//! Pinned `rng` output: every generated city depends on these exact values,
+//! so if one of these fails, existing seeds now build different worlds.
+
+use hello_wgpu::rng::{hash2, Rng};
+
+#[test]
+fn next_u64_sequence_is_pinned() {
+    let mut r = Rng::new(0xA11CE);
+    let seq: Vec<u64> = (0..4).map(|_| r.next_u64()).collect();
+    assert_eq!(seq, [0x0002_816e_6cef_962c, 0x745b_e046_fd16_4200, 0x3b74_63e2_2034_ee84, 0xd930_de94_382d_a659]);
+    // a zero seed still moves
+    assert_eq!(Rng::new(0).next_u64(), 0x4082_2041);
+}
+
+#[test]
+fn next_f32_sequence_is_pinned() {
+    let mut r = Rng::new(42);
+    let seq: Vec<u32> = (0..3).map(|_| r.next_f32().to_bits()).collect();
+    assert_eq!(seq, [0x312d_5d37, 0x3f30_0aec, 0x3d80_6dee]);
+    let mut r = Rng::new(7);
+    assert!((0..1000).map(|_| r.next_f32()).all(|f| (0.0..=1.0).contains(&f)));
+}
+
+#[test]
+fn hash2_is_pinned() {
+    assert_eq!(hash2(0, 0), 0x96a1_743c_36ed_852f);
+    assert_eq!(hash2(1, -1), 0x04cf_2d3c_36ed_852f);
+    assert_eq!(hash2(-7, 123_456), 0xa326_6506_b386_d206);
+    assert_eq!(hash2(i32::MIN, i32::MAX), 0x9349_e0ac_2812_27a7);
+}
@@ -0,0 +1,60 @@
+//! Headless simulation: `ChunkManager::step` mutates a designed city the
+//! same way on every run, independent of how `dt` is sliced.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::ChunkManager;
+use hello_wgpu::city_store::StoreBackend;
+
+/// FNV-1a over every loaded placement (sorted by chunk), floats by bit pattern.
+fn city_hash(cm: &ChunkManager) -> u64 {
+    let mut keys: Vec<_> = cm.loaded.keys().copied().collect();
+    keys.sort_by_key(|k| (k.0, k.1));
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut eat = |v: u32| for b in v.to_le_bytes() { h = (h ^ b as u64).wrapping_mul(0x0100_0000_01b3); };
+    for k in keys {
+        for p in &cm.loaded[&k] {
+            eat(p.archetype_id as u32);
+            for f in [p.center.x, p.center.y, p.center.z, p.scale.x, p.scale.y, p.scale.z] { eat(f.to_bits()); }
+        }
+    }
+    h
+}
+
+/// Design the city around the origin, then `steps` steps of `dt`, with
+/// the live app's mutation seed (0).
+fn run(assets: &AssetLibrary, steps: u32, dt: f32, rate: f32) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(0x51A7), 1, (-2, 2, -2, 2), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(assets);
+    for _ in 0..steps { cm.step(dt, assets, rate, 0); }
+    cm
+}
+
+#[test]
+fn stepping_is_reproducible() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let designed = city_hash(&run(&assets, 0, 0.0, 0.5));
+    let a = run(&assets, 20, 0.1, 0.5);
+    assert_ne!(city_hash(&a), designed, "2 s at 0.5/s mutates something");
+    assert_eq!(city_hash(&run(&assets, 20, 0.1, 0.5)), city_hash(&a), "second run");
+    assert_eq!(city_hash(&run(&assets, 40, 0.05, 0.5)), city_hash(&a), "same ticks, finer steps");
+    assert!(a.revision(hello_wgpu::chunking::ChunkKey(0, 0)) > 0);
+
+    // rate 0 only runs the clock
+    assert_eq!(city_hash(&run(&assets, 20, 0.1, 0.0)), designed);
+}
+
+#[test]
+fn stepped_city_matches_the_golden_hash() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    // the live app's path (seed 0); update deliberately when the designer
+    // or the mutation rules change
+    let h = city_hash(&run(&assets, 20, 0.1, 0.5));
+    assert_eq!(h, 0xf9a3_996b_90ed_1474, "got {h:#x}");
+}
@@ -0,0 +1,122 @@
+//! Chimney smoke: the bounded particle pool, emitter picking from the
+//! loaded chunks across origin shifts, and the blended draw.
+
+mod common;
+
+use cgmath::{InnerSpace, Vector3};
+use hello_wgpu::assets::{AssetLibrary, BuildingCategory};
+use hello_wgpu::chunking::ChunkManager;
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::render::Engine;
+use hello_wgpu::smoke::{smoke_emitters, SmokeParams, SmokeSystem};
+
+fn on() -> SmokeParams { SmokeParams { enabled: true, ..Default::default() } }
+
+fn chimneys(n: usize) -> Vec<Vector3<f32>> {
+    (0..n).map(|i| Vector3::new(i as f32 * 20.0, 12.0, -40.0)).collect()
+}
+
+#[test]
+fn pool_stays_bounded_and_puffs_rise_then_die() {
+    let mut s = SmokeSystem::new(SmokeParams { max_particles: 50, ..on() });
+    let at = chimneys(10);
+    for _ in 0..100 { s.update(0.1, &at); }
+    assert_eq!(s.particles().len(), 50, "full, never past the cap");
+    assert!(s.particles().iter().all(|q| q.pos.y > 12.0 && q.age < s.params.lifetime));
+    // no emitters: the pool drains within one lifetime
+    for _ in 0..61 { s.update(0.1, &[]); }
+    assert!(s.particles().is_empty());
+}
+
+#[test]
+fn off_spawns_nothing_and_empties_the_pool() {
+    assert!(!hello_wgpu::EngineConfig::default().smoke.enabled, "off by default");
+    let mut s = SmokeSystem::new(on());
+    s.update(0.5, &chimneys(3));
+    assert!(!s.particles().is_empty());
+    s.params.enabled = false;
+    s.update(0.5, &chimneys(3));
+    assert!(s.particles().is_empty());
+    assert!(s.instances(Vector3::new(0.0, 0.0, 0.0)).is_empty());
+}
+
+#[test]
+fn puffs_sort_back_to_front_and_follow_shifts() {
+    let mut s = SmokeSystem::new(on());
+    for _ in 0..20 { s.update(0.1, &chimneys(4)); }
+    let cam = Vector3::new(0.0, 2.0, 0.0);
+    let d = |i: &hello_wgpu::types::InstanceRaw| Vector3::new(i.pos[0], i.pos[1], i.pos[2]) - cam;
+    let inst = s.instances(cam);
+    assert_eq!(inst.len(), s.particles().len());
+    assert!(inst.windows(2).all(|w| d(&w[0]).magnitude2() >= d(&w[1]).magnitude2()));
+    assert!(inst.iter().all(|i| (0.0..=1.0).contains(&i.misc[0])));
+    let before: Vec<_> = s.particles().iter().map(|q| q.pos).collect();
+    s.shift(Vector3::new(500.0, 0.0, -250.0));
+    for (q, p) in s.particles().iter().zip(before) { assert_eq!(q.pos, p - Vector3::new(500.0, 0.0, -250.0)); }
+}
+
+#[test]
+fn bad_settings_are_rejected() {
+    assert!(SmokeParams::default().validate().is_ok());
+    for p in [SmokeParams { density: 1.5, ..on() }, SmokeParams { lifetime: 0.0, ..on() },
+              SmokeParams { rate: f32::NAN, ..on() }, SmokeParams { growth: -1.0, ..on() }] {
+        assert!(p.validate().is_err(), "{p:?}");
+    }
+    let cfg = hello_wgpu::EngineConfig { smoke: SmokeParams { size: -1.0, ..on() }, ..Default::default() };
+    assert!(cfg.validate().is_err());
+}
+
+#[test]
+fn the_same_chimneys_smoke_after_an_origin_shift() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = ChunkManager::new(common::params(0x0005_40CE), 1, (-4, 4, -4, 4), false, "unused");
+    cm.store = StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+    let p = SmokeParams { density: 1.0, max_emitters: 1000, radius: 1e4, ..on() };
+    let all = smoke_emitters(&cm, &assets, Vector3::new(0.0, 0.0, 0.0), &p);
+    let landmarks = cm.loaded.values().flatten()
+        .filter(|pl| assets.category_of(pl.archetype_id as usize) == BuildingCategory::Landmark).count();
+    assert!(all.len() >= landmarks && landmarks > 0, "{} emitters, {landmarks} landmarks", all.len());
+    assert!(all.iter().all(|e| e.y > 0.0), "on the roofs");
+
+    let half = smoke_emitters(&cm, &assets, Vector3::new(0.0, 0.0, 0.0), &SmokeParams { density: 0.5, ..p.clone() });
+    assert!(half.len() < all.len() && !half.is_empty(), "{} of {}", half.len(), all.len());
+    let near = smoke_emitters(&cm, &assets, Vector3::new(0.0, 0.0, 0.0), &SmokeParams { max_emitters: 5, ..p.clone() });
+    assert_eq!(&near[..], &all[..5], "nearest first");
+    // a radius inside the window walks fewer chunks but misses nothing
+    let r = 120.0;
+    let close = smoke_emitters(&cm, &assets, Vector3::new(0.0, 0.0, 0.0), &SmokeParams { radius: r, ..p.clone() });
+    let expect: Vec<_> = all.iter().filter(|e| e.x * e.x + e.z * e.z <= r * r).copied().collect();
+    assert!(!close.is_empty() && close.len() < all.len());
+    assert_eq!(close, expect);
+
+    let off = Vector3::new(300.0, 0.0, -120.0);
+    cm.apply_shift(off);
+    let key = |v: &Vector3<f32>| ((v.x * 10.0).round() as i32, (v.z * 10.0).round() as i32);
+    let mut moved: Vec<_> = smoke_emitters(&cm, &assets, -off, &SmokeParams { density: 0.5, ..p }).iter().map(|e| key(&(e + off))).collect();
+    let mut half: Vec<_> = half.iter().map(key).collect();
+    moved.sort();
+    half.sort();
+    assert_eq!(moved, half);
+}
+
+#[test]
+fn engine_blends_smoke_over_the_scene() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let cfg = hello_wgpu::EngineConfig { sample_count: 4, ..Default::default() };
+    let mut engine = Engine::new_headless_with(device, queue, 64, 64, &cfg);
+    let mut s = SmokeSystem::new(on());
+    for _ in 0..10 { s.update(0.1, &chimneys(2)); }
+    let cam = Vector3::new(5.0, 4.0, 0.0);
+    engine.update_camera(&(cgmath::perspective(cgmath::Deg(60.0), 1.0, 0.1, 500.0)
+        * cgmath::Matrix4::look_at_rh(cgmath::Point3::new(5.0, 4.0, 0.0), cgmath::Point3::new(10.0, 12.0, -40.0), Vector3::unit_y())));
+    engine.update_smoke(&s.instances(cam));
+    assert_eq!(engine.smoke_count() as usize, s.particles().len());
+    engine.render().expect("frame with smoke");
+    let with = engine.frame_stats().draw_calls;
+    engine.update_smoke(&[]);
+    engine.render().expect("frame without smoke");
+    assert_eq!(engine.frame_stats().draw_calls + 1, with, "one instanced draw for every puff");
+}
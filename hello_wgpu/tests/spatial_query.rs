@@ -0,0 +1,78 @@
+//! Spatial queries: `buildings_in_radius` and the per-chunk `ChunkGrid`.
+
+mod common;
+
+use cgmath::Vector3;
+use common::params;
+use hello_wgpu::chunking::{chunk_world_span, ChunkKey, ChunkManager, RuntimePlacement};
+use hello_wgpu::spatial::ChunkGrid;
+
+fn at(x: f32, z: f32) -> RuntimePlacement {
+    RuntimePlacement::single(Vector3::new(x, 1.0, z), Vector3::new(1.0, 1.0, 1.0), 0)
+}
+
+#[test]
+fn returns_exactly_the_buildings_inside_the_circle() {
+    let p = params(3);
+    let (cw, _) = chunk_world_span(&p);
+    let mut cm = ChunkManager::new(p, 1, (-2, 2, -2, 2), false, "unused");
+    cm.loaded.insert(ChunkKey(0, 0), vec![at(0.0, 0.0), at(9.99, 0.0), at(10.01, 0.0), at(3.0, 4.0)]);
+    // the neighbour's building sits across the chunk border, 8 m from the query point
+    let edge = cw * 0.5;
+    cm.loaded.insert(ChunkKey(1, 0), vec![at(edge + 8.0, 0.0), at(cw, 0.0)]);
+    // a far chunk is never reported
+    cm.loaded.insert(ChunkKey(-2, -2), vec![at(-2.0 * cw, -2.0 * cw)]);
+
+    let near = cm.buildings_in_radius(Vector3::new(0.0, 0.0, 0.0), 10.0);
+    assert_eq!(near, [(ChunkKey(0, 0), 0), (ChunkKey(0, 0), 1), (ChunkKey(0, 0), 3)]);
+
+    let border = cm.buildings_in_radius(Vector3::new(edge, 50.0, 0.0), 8.0);
+    assert_eq!(border, [(ChunkKey(1, 0), 0)], "height is ignored, neighbours are searched");
+}
+
+#[test]
+fn empty_world_has_no_buildings() {
+    let cm = ChunkManager::new(params(3), 1, (-2, 2, -2, 2), false, "unused");
+    assert!(cm.buildings_in_radius(Vector3::new(0.0, 0.0, 0.0), 1.0e4).is_empty());
+}
+
+/// Deterministic scatter of design-space points over `[0, size)²`.
+fn scatter(n: u32, size: f32) -> Vec<(f32, f32)> {
+    let mut s = 0x9E37_79B9u32;
+    let mut next = || { s ^= s << 13; s ^= s >> 17; s ^= s << 5; (s % 10_000) as f32 / 10_000.0 * size };
+    (0..n).map(|_| (next(), next())).collect()
+}
+
+#[test]
+fn grid_rect_query_is_a_superset_of_the_rect() {
+    let pts = scatter(2_000, 100.0);
+    let grid = ChunkGrid::build((0.0, 0.0), (100.0, 100.0), 3.4, pts.iter().copied());
+    assert_eq!(grid.len(), pts.len());
+    for (min, max) in [((10.0, 10.0), (20.0, 35.0)), ((-50.0, 90.0), (5.0, 150.0)), ((40.0, 40.0), (40.0, 40.0))] {
+        let mut hits: Vec<usize> = grid.query_rect(min, max).collect();
+        hits.sort_unstable();
+        hits.dedup();
+        for (i, &(x, z)) in pts.iter().enumerate() {
+            if x >= min.0 && x <= max.0 && z >= min.1 && z <= max.1 {
+                assert!(hits.binary_search(&i).is_ok(), "point {i} at ({x}, {z}) missed by {min:?}..{max:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn gridded_chunks_match_a_linear_scan() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = hello_wgpu::assets::AssetLibrary::new(&device);
+    let mut cm = ChunkManager::new(params(3), 1, (-2, 2, -2, 2), false, "unused");
+    cm.set_viewer(0, 0.0, 0.0);
+    cm.ensure_for_viewers(&assets);
+
+    for (c, r) in [(Vector3::new(0.0, 0.0, 0.0), 25.0), (Vector3::new(40.0, 0.0, -70.0), 60.0), (Vector3::new(-5.0, 0.0, 3.0), 2.0)] {
+        let mut linear: Vec<(ChunkKey, usize)> = cm.loaded.iter().flat_map(|(k, l)| {
+            l.iter().enumerate().filter(|(_, p)| (p.center.x - c.x).hypot(p.center.z - c.z) <= r).map(|(i, _)| (*k, i))
+        }).collect();
+        linear.sort_by_key(|(k, i)| (k.0, k.1, *i));
+        assert_eq!(cm.buildings_in_radius(c, r), linear);
+    }
+}
@@ -0,0 +1,56 @@
+//! `Archetype::weight`: weighted picks within a category, deterministic.
+
+mod common;
+
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, RuleDesigner};
+
+fn id_of(assets: &AssetLibrary, name: &str) -> usize {
+    assets.archetypes.iter().position(|a| a.name == name).expect(name)
+}
+
+/// Archetype ids of every building in a 5×5 block of chunks.
+fn design_ids(assets: &AssetLibrary) -> Vec<u16> {
+    let mut d = RuleDesigner::new(common::params(0xC0FFEE));
+    let mut ids = Vec::new();
+    for cz in -2..=2 {
+        for cx in -2..=2 {
+            let ctx = DesignContext::new(cx, cz, d.params.seed);
+            ids.extend(d.design_chunk(&ctx, assets).iter().map(|p| p.archetype_id));
+        }
+    }
+    ids
+}
+
+#[test]
+fn ten_to_one_weights_give_ten_to_one_counts() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut assets = AssetLibrary::new(&device);
+    let (common_id, rare_id, off_id) =
+        (id_of(&assets, "timber_house_a"), id_of(&assets, "workshop_neon"), id_of(&assets, "timber_house_b"));
+    assets.archetypes[common_id].weight = 10.0;
+    assets.archetypes[rare_id].weight = 1.0;
+    assets.archetypes[off_id].weight = 0.0;
+
+    let ids = design_ids(&assets);
+    let count = |id: usize| ids.iter().filter(|&&a| a as usize == id).count() as f32;
+    assert_eq!(count(off_id), 0.0, "zero weight never spawns");
+    let ratio = count(common_id) / count(rare_id).max(1.0);
+    assert!(count(rare_id) > 100.0, "too few samples: {}", count(rare_id));
+    assert!((8.0..12.5).contains(&ratio), "ratio {ratio}");
+
+    assert_eq!(design_ids(&assets), ids, "same seed, same city");
+}
+
+#[test]
+fn equal_weights_stay_uniform() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let ids = design_ids(&assets);
+    let low = ["timber_house_a", "timber_house_b", "workshop_neon"].map(|n| {
+        let id = id_of(&assets, n);
+        ids.iter().filter(|&&a| a as usize == id).count() as f32
+    });
+    let mean = low.iter().sum::<f32>() / 3.0;
+    assert!(low.iter().all(|&c| (c - mean).abs() < 0.1 * mean), "{low:?}");
+}
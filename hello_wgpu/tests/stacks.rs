@@ -0,0 +1,121 @@
+//! Stacked placements: extra boxes are bucketed / baked as their own
+//! instances, picked and highlighted with the building, persisted with the
+//! chunk, and bounded by `MAX_STACK`.
+
+mod common;
+
+use cgmath::{Deg, Matrix4, Point3, Vector3, perspective};
+use common::params;
+use hello_wgpu::assets::{AssetLibrary, BuildingCategory};
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement, SubBox, MAX_STACK};
+use hello_wgpu::city_store::StoreBackend;
+use hello_wgpu::culling::{bucket_instances, frustum_from_vp};
+use hello_wgpu::designer_ml::{CityDesigner, DesignContext, Placement};
+use hello_wgpu::mesh::bake_chunk_data;
+
+/// One landmark per chunk: a wide podium with `parts` towers on top.
+struct Towers { parts: usize }
+
+impl CityDesigner for Towers {
+    fn design_chunk(&mut self, _ctx: &DesignContext, assets: &AssetLibrary) -> Vec<Placement> {
+        let podium = assets.indices_by_category(BuildingCategory::Landmark)[0];
+        let tower = assets.indices_by_category(BuildingCategory::Highrise)[0] as u16;
+        let scale = Vector3::new(4.0, 0.5, 4.0);
+        let stack: Vec<SubBox> = (0..self.parts).map(|i| SubBox {
+            offset: Vector3::new(i as f32 * 2.0 - 1.0, 3.0, 0.0),
+            scale: Vector3::new(0.8, 1.5, 0.8),
+            archetype_id: tower,
+        }).collect();
+        vec![Placement {
+            center: Vector3::new(0.0, assets.ground_y_for(podium, scale), 20.0),
+            scale,
+            archetype_id: podium as u16,
+            stack: Some(stack.into()),
+        }]
+    }
+}
+
+fn manager(dir: &str) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(3), 0, (0, 0, 0, 0), false, dir);
+    cm.set_viewer(0, 0.0, 0.0);
+    cm
+}
+
+#[test]
+fn every_box_is_drawn_and_baked() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager("unused");
+    cm.store = StoreBackend::None;
+    cm.set_designer(Box::new(Towers { parts: 2 }));
+    cm.ensure_for_viewers(&assets);
+    let list = &cm.loaded[&ChunkKey(0, 0)];
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].boxes().count(), 3);
+
+    let eye = Point3::new(0.0, 5.0, 0.0);
+    let view = Matrix4::look_at_rh(eye, Point3::new(0.0, 5.0, 1.0), Vector3::unit_y());
+    let fr = frustum_from_vp(&(perspective(Deg(60.0), 1.0, 0.1, 1000.0) * view));
+    let b = bucket_instances(list, Vector3::new(0.0, 5.0, 0.0), &fr, 90.0, 190.0, 380.0, &assets);
+    assert_eq!((b.v0_land.len(), b.v0_high.len()), (1, 2));
+    let towers: Vec<f32> = b.v0_high.iter().map(|i| i.pos[0]).collect();
+    assert_eq!(towers, [-1.0, 1.0]);
+
+    let verts = |id: u16| assets.data_of(id as usize).vertices.len();
+    let want: usize = list[0].boxes().map(|(_, _, id)| verts(id)).sum();
+    assert_eq!(bake_chunk_data(list, &assets).vertices.len(), want);
+}
+
+#[test]
+fn the_upper_boxes_pick_and_highlight_the_building() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut cm = manager("unused");
+    cm.store = StoreBackend::None;
+    cm.set_designer(Box::new(Towers { parts: 2 }));
+    cm.ensure_for_viewers(&assets);
+    let key = ChunkKey(0, 0);
+    let p = &cm.loaded[&key][0];
+    let podium_half = assets.base_half(p.archetype_id as usize);
+    let (tower, _, _) = p.boxes().nth(2).unwrap();
+
+    // level with the tower's middle, above the podium's roof
+    assert!(tower.y > p.center.y + podium_half.y * p.scale.y);
+    let (pk, pi, t) = cm.pick_ray(Vector3::new(-50.0, tower.y, tower.z), Vector3::new(1.0, 0.0, 0.0), 1000.0, &assets)
+        .expect("the tower is hit");
+    assert_eq!((pk, pi), (key, 0));
+    assert!(t < 50.0, "through the first tower at x = -1, not the podium ({t})");
+
+    let mut engine = hello_wgpu::render::Engine::new_headless(device, queue, 64, 64);
+    engine.set_selection(key, 0);
+    engine.update_selection(&cm);
+    let ids: Vec<usize> = p.boxes().map(|(_, _, id)| id as usize).collect();
+    assert_eq!(engine.selection_boxes(), &ids[..], "a shell for every box");
+}
+
+#[test]
+fn stacks_are_bounded_and_saved() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_stacks_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut cm = manager(&dir);
+    cm.set_designer(Box::new(Towers { parts: MAX_STACK + 3 }));
+    cm.ensure_for_viewers(&assets);
+    let key = ChunkKey(0, 0);
+    let saved: Vec<SubBox> = cm.loaded[&key][0].stack.as_deref().unwrap().to_vec();
+    assert_eq!(saved.len(), MAX_STACK);
+    // the ordinary single box stays unallocated
+    let idx = cm.insert_building(key, RuntimePlacement::single(Vector3::new(8.0, 1.0, 8.0), Vector3::new(1.0, 1.0, 1.0), 0)).unwrap();
+    cm.flush().expect("flush");
+
+    let mut again = manager(&dir);
+    again.set_designer(Box::new(Towers { parts: 0 }));
+    again.ensure_for_viewers(&assets);
+    let list = &again.loaded[&key];
+    assert_eq!(list[0].stack.as_deref(), Some(&saved[..]), "loaded from the store, not redesigned");
+    assert!(list[idx].stack.is_none());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,27 @@
+//! Oversize offscreen targets are clamped to the adapter's
+//! `max_texture_dimension_2d` instead of failing validation.
+
+mod common;
+
+use hello_wgpu::render::{clamp_texture_dim, Engine};
+
+#[test]
+fn clamp_keeps_sizes_in_range() {
+    assert_eq!(clamp_texture_dim("t", 512, 2048), 512);
+    assert_eq!(clamp_texture_dim("t", 8192, 2048), 2048);
+    assert_eq!(clamp_texture_dim("t", 0, 2048), 1);
+}
+
+#[test]
+fn oversize_targets_clamp_rather_than_panic() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let max = device.limits().max_texture_dimension_2d;
+    let mut engine = Engine::new_headless(device, queue, max + 1000, 16);
+    assert_eq!(engine.max_texture_dim(), max);
+    assert_eq!((engine.config.width, engine.config.height), (max, 16));
+    engine.render().expect("frame");
+
+    engine.resize(winit::dpi::PhysicalSize::new(16, max * 2));
+    assert_eq!((engine.config.width, engine.config.height), (16, max));
+    engine.render().expect("frame after resize");
+}
@@ -0,0 +1,50 @@
+//! Per-archetype tints: `AssetLibrary::set_tint` and the archetype id baked
+//! into chunk meshes for `vs_baked`.
+
+mod common;
+
+use cgmath::Vector3;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::RuntimePlacement;
+use hello_wgpu::facade::LAYER_COUNT;
+use hello_wgpu::mesh::bake_chunk_data;
+use hello_wgpu::render::Engine;
+use hello_wgpu::types::InstanceRaw;
+
+#[test]
+fn set_tint_updates_the_archetype_and_revision() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut assets = AssetLibrary::new(&device);
+    assert!(assets.archetypes.iter().all(|a| a.tint == [1.0; 4]), "white by default");
+    let rev = assets.tint_revision();
+    assets.set_tint(2, [0.8, 0.2, 0.2, 1.0]);
+    assert_eq!(assets.archetypes[2].tint, [0.8, 0.2, 0.2, 1.0]);
+    assert_ne!(assets.tint_revision(), rev);
+}
+
+#[test]
+fn baked_vertices_carry_the_archetype() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let id = assets.archetypes.len() - 1;
+    let p = RuntimePlacement::single(Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 1.0, 1.0), id as u16);
+    let data = bake_chunk_data(&[p], &assets);
+    let stride = 4.0 * LAYER_COUNT as f32;
+    for v in &data.vertices {
+        let tag = v.color[3];
+        assert_eq!((tag / stride).floor() as usize, id);
+        assert_eq!(((tag % stride) / 4.0).floor() as u32, assets.texture_of(id));
+    }
+}
+
+#[test]
+fn tinted_frame_renders() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    for id in 0..engine.assets.archetypes.len() { engine.assets.set_tint(id, [0.5, 0.7, 0.9, 1.0]); }
+    let inst = InstanceRaw { pos: [0.0; 4], scale: [1.0, 1.0, 1.0, 0.0], misc: [0.0, 1.0, 0.0, 0.0] };
+    engine.update_instances(&[inst], &[], &[], &[], &[], &[], &[], &[], &[], &inst);
+    engine.render().expect("frame");
+    engine.assets.set_tint(0, [1.0; 4]);
+    engine.render().expect("frame after a tint change");
+}
@@ -0,0 +1,53 @@
+//! Procedural windows: the per-building seed carried in `misc.w` (baked
+//! chunks: vertex `color.z`) and the density / lit-probability uniforms.
+
+mod common;
+
+use cgmath::Vector3;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::RuntimePlacement;
+use hello_wgpu::culling::window_seed;
+use hello_wgpu::mesh::bake_chunk_data;
+use hello_wgpu::render::Engine;
+use hello_wgpu::types::InstanceRaw;
+
+#[test]
+fn seeds_are_exact_and_spread() {
+    let mut seeds: Vec<f32> = (0..200).map(|i| {
+        let t = i as f32 * 0.0017;
+        window_seed(Vector3::new(0.85 + t, 1.2 + 0.5 * t, 1.2 - t))
+    }).collect();
+    assert!(seeds.iter().all(|&s| s >= 0.0 && s < (1u32 << 24) as f32 && s.fract() == 0.0));
+    assert_eq!(window_seed(Vector3::new(1.0, 2.0, 3.0)), window_seed(Vector3::new(1.0, 2.0, 3.0)));
+    seeds.sort_by(f32::total_cmp);
+    seeds.dedup();
+    assert!(seeds.len() > 190, "{} distinct", seeds.len());
+}
+
+#[test]
+fn baked_vertices_carry_the_instanced_window_inputs() {
+    let assets = AssetLibrary::data_only();
+    let scale = Vector3::new(1.1, 1.7, 0.9);
+    let at = |x: f32| bake_chunk_data(&[RuntimePlacement::single(Vector3::new(x, 3.0, -40.0), scale, 0)], &assets);
+    let (near, far) = (at(5.0), at(905.0));
+    let local = &assets.data_of(0).vertices;
+    for ((a, b), v) in near.vertices.iter().zip(&far.vertices).zip(local) {
+        assert_eq!(a.color[2], window_seed(scale));
+        assert_eq!(a.color[..3], b.color[..3], "independent of where the chunk sits");
+        let up = v.position[1] * scale.y;
+        assert!((a.color[1] - up).abs() < 1e-4, "{} vs {up}", a.color[1]);
+    }
+}
+
+#[test]
+fn window_uniforms_are_clamped_and_render() {
+    let Some((device, queue)) = common::gpu() else { eprintln!("no GPU adapter; skipping"); return };
+    let mut engine = Engine::new_headless(device, queue, 64, 64);
+    assert!(engine.windows().0 > 0.0, "on by default");
+    engine.set_windows(-1.0, 2.0);
+    assert_eq!(engine.windows(), (0.0, 1.0));
+    engine.set_windows(0.5, 0.25);
+    let inst = InstanceRaw { pos: [0.0, 0.0, 4.0, 0.0], scale: [1.0, 3.0, 1.0, 0.0], misc: [1.0, 0.0, 0.0, 1234.0] };
+    engine.update_instances(&[inst], &[], &[], &[], &[], &[], &[], &[], &[inst], &inst);
+    engine.render().expect("frame with windows");
+}
@@ -0,0 +1,103 @@
+//! Join sync: `mutation_snapshot` carries only the mutated buildings and
+//! `apply_snapshot` brings a freshly generated city in line with it.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+
+/// (key, [(archetype, scale bits, y bits)]) per loaded chunk, sorted.
+type City = Vec<(ChunkKey, Vec<(u16, [u32; 4])>)>;
+
+fn manager(seed: u64) -> ChunkManager {
+    let mut cm = ChunkManager::new(params(seed), 1, (-3, 3, -3, 3), false, "unused");
+    cm.store = hello_wgpu::city_store::StoreBackend::None;
+    cm.set_viewer(0, 0.0, 0.0);
+    cm
+}
+
+fn city(cm: &ChunkManager) -> City {
+    let mut out: City = cm.loaded.iter().map(|(k, l)| (*k, l.iter().map(|p| {
+        (p.archetype_id, [p.scale.x.to_bits(), p.scale.y.to_bits(), p.scale.z.to_bits(), p.center.y.to_bits()])
+    }).collect())).collect();
+    out.sort_by_key(|c| (c.0.0, c.0.1));
+    out
+}
+
+#[test]
+fn snapshot_round_trips_onto_a_fresh_city() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+
+    let mut host = manager(0xD1FF);
+    host.ensure_for_viewers(&assets);
+    let fresh = host.mutation_snapshot(&assets);
+    host.mutate_near(&assets, 0.5, 1.0, 1, 7);
+    let snap = host.mutation_snapshot(&assets);
+    let per_delta = 30;
+    assert!(snap.len() > fresh.len() && (snap.len() - fresh.len()).is_multiple_of(per_delta));
+    let total: usize = host.loaded.values().map(Vec::len).sum();
+    assert!((snap.len() - fresh.len()) / per_delta < total, "only changed buildings are sent");
+
+    // joined with the window loaded: applied at once
+    let mut peer = manager(0xD1FF);
+    peer.ensure_for_viewers(&assets);
+    assert_ne!(city(&peer), city(&host));
+    let n = peer.apply_snapshot(&assets, &snap).expect("valid snapshot");
+    assert_eq!(n, (snap.len() - fresh.len()) / per_delta);
+    assert_eq!(city(&peer), city(&host));
+    assert!(host.loaded.keys().any(|k| peer.is_dirty(*k)), "synced chunks are saved by the next flush");
+
+    // applied before anything loaded: held until the chunks arrive
+    let mut late = manager(0xD1FF);
+    assert_eq!(late.apply_snapshot(&assets, &snap), Ok(0));
+    late.ensure_for_viewers(&assets);
+    assert_eq!(city(&late), city(&host));
+}
+
+#[test]
+fn added_and_removed_buildings_reach_late_joiners() {
+    let assets = AssetLibrary::data_only();
+    let mut host = manager(0xD1FF);
+    host.ensure_for_viewers(&assets);
+    host.mutate_near(&assets, 0.5, 1.0, 1, 7);
+    let gone = host.remove_building(ChunkKey(0, 0), 2).expect("chunk (0, 0) has buildings");
+    let moved = hello_wgpu::chunking::RuntimePlacement { center: gone.center + cgmath::Vector3::new(0.0, 0.0, 60.0), ..gone };
+    let (added, _) = host.place_building(moved, &assets).expect("inside the world");
+    let snap = host.mutation_snapshot(&assets);
+
+    let mut peer = manager(0xD1FF);
+    peer.ensure_for_viewers(&assets);
+    assert!(peer.apply_snapshot(&assets, &snap).unwrap() > 0);
+    assert_eq!(city(&peer), city(&host));
+    for k in [ChunkKey(0, 0), added] {
+        assert_eq!(peer.loaded[&k].len(), host.loaded[&k].len(), "{k:?}");
+    }
+    assert_eq!(peer.loaded[&added].last().unwrap().center, host.loaded[&added].last().unwrap().center);
+
+    let mut late = manager(0xD1FF);
+    assert_eq!(late.apply_snapshot(&assets, &snap), Ok(0));
+    late.ensure_for_viewers(&assets);
+    assert_eq!(city(&late), city(&host));
+}
+
+#[test]
+fn foreign_or_damaged_snapshots_are_rejected() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+    let mut host = manager(0xD1FF);
+    host.ensure_for_viewers(&assets);
+    host.mutate_near(&assets, 0.5, 1.0, 1, 7);
+    let snap = host.mutation_snapshot(&assets);
+
+    assert!(manager(0xD1FF + 1).apply_snapshot(&assets, &snap).is_err(), "other seed");
+    let mut peer = manager(0xD1FF);
+    assert!(peer.apply_snapshot(&assets, &snap[..snap.len() - 1]).is_err());
+    let mut huge = snap[..16].to_vec();
+    huge[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(peer.apply_snapshot(&assets, &huge).is_err(), "delta count past the buffer");
+    assert!(peer.apply_snapshot(&assets, b"junk").is_err());
+    peer.ensure_for_viewers(&assets);
+    assert!(peer.loaded.keys().all(|k| !peer.is_dirty(*k)), "nothing was held from the bad ones");
+}
@@ -0,0 +1,37 @@
+//! `ChunkManager::stats` agrees with what is actually loaded.
+
+mod common;
+
+use cgmath::Vector3;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager, RuntimePlacement};
+
+fn manager() -> ChunkManager {
+    ChunkManager::new(common::params(1), 1, (-2, 2, -2, 2), false, "unused")
+}
+
+fn building(x: f32) -> RuntimePlacement {
+    RuntimePlacement::single(Vector3::new(x, 1.0, 0.0), Vector3::new(1.0, 1.0, 1.0), 0)
+}
+
+#[test]
+fn empty_world_has_no_placements() {
+    let s = manager().stats();
+    assert_eq!((s.loaded_chunks, s.placements), (0, 0));
+}
+
+#[test]
+fn placement_count_matches_loaded() {
+    let mut cm = manager();
+    for (i, n) in [3usize, 0, 17, 5].into_iter().enumerate() {
+        cm.loaded.insert(ChunkKey(i as i32, 0), (0..n).map(|k| building(k as f32)).collect());
+    }
+    let s = cm.stats();
+    assert_eq!(s.loaded_chunks, cm.loaded.len());
+    assert_eq!(s.placements, cm.loaded.values().map(Vec::len).sum::<usize>());
+    assert!(s.estimated_bytes >= s.placements * std::mem::size_of::<RuntimePlacement>());
+
+    assert!(cm.evict(ChunkKey(2, 0)));
+    let after = cm.stats();
+    assert_eq!(after.placements, s.placements - 17);
+    assert!(after.estimated_bytes < s.estimated_bytes);
+}
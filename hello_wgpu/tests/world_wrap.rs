@@ -0,0 +1,59 @@
+//! Finite worlds: with `wrap` off nothing past `bounds` is loaded or edited.
+
+mod common;
+
+use common::params;
+use hello_wgpu::assets::AssetLibrary;
+use hello_wgpu::chunking::{ChunkKey, ChunkManager};
+
+const BOUNDS: (i32, i32, i32, i32) = (-3, 3, -3, 3);
+
+/// Keys loaded by a viewer that stops in the corner chunk (3, 3), then
+/// flies two chunks past it.
+fn loaded_at_corner(assets: &AssetLibrary, wrap: bool) -> (Vec<ChunkKey>, Vec<ChunkKey>) {
+    let dir = std::env::temp_dir().join(format!("hello_wgpu_wrap_{}_{wrap}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut cm = ChunkManager::new(params(0x5EED), 1, BOUNDS, false, dir.to_str().unwrap());
+    cm.wrap = wrap;
+    let (x0, x1, z0, z1) = cm.world_rect();
+    let (cw, cd) = ((x1 - x0) / 7.0, (z1 - z0) / 7.0);
+    let visit = |cm: &mut ChunkManager, c: f32| {
+        cm.set_viewer(0, c * cw, c * cd);
+        cm.ensure_for_viewers(assets);
+        cm.mutate_near(assets, 1.0, 1.0, 0, 7);
+        let mut keys: Vec<ChunkKey> = cm.loaded.keys().copied().collect();
+        keys.sort_by_key(|k| (k.0, k.1));
+        keys
+    };
+    let corner = visit(&mut cm, 3.0);
+    let beyond = visit(&mut cm, 5.0);
+    let _ = std::fs::remove_dir_all(&dir);
+    (corner, beyond)
+}
+
+#[test]
+fn finite_world_stops_at_bounds() {
+    let Some(device) = common::device() else { eprintln!("no GPU adapter; skipping"); return };
+    let assets = AssetLibrary::new(&device);
+
+    let (corner, beyond) = loaded_at_corner(&assets, false);
+    let expect: Vec<ChunkKey> = [(2, 2), (2, 3), (3, 2), (3, 3)].into_iter().map(|(x, z)| ChunkKey(x, z)).collect();
+    assert_eq!(corner, expect);
+    assert!(beyond.is_empty(), "past the edge the window holds no chunks: {beyond:?}");
+
+    // the torus fills the same 3×3 window with chunks from the far edges
+    let (corner, beyond) = loaded_at_corner(&assets, true);
+    assert_eq!(corner.len(), 9);
+    assert!(corner.contains(&ChunkKey(-3, -3)));
+    assert_eq!(beyond.len(), 9);
+    assert!(beyond.contains(&ChunkKey(-2, -2)), "wrapped past the far edge");
+}
+
+#[test]
+fn world_rect_covers_bounds() {
+    let cm = ChunkManager::new(params(1), 1, BOUNDS, false, "unused");
+    let (x0, x1, z0, z1) = cm.world_rect();
+    assert!((x0 + x1).abs() < 1e-3 && (z0 + z1).abs() < 1e-3, "symmetric bounds give a centred rect");
+    let (sx, sz) = cm.world_span();
+    assert!((x1 - x0 - sx).abs() < 1e-2 && (z1 - z0 - sz).abs() < 1e-2);
+}